@@ -1,6 +1,33 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+// Grammar sources come from git submodules (see .gitmodules); fail with the fix instead of a
+// cc "file not found" error when a checkout is missing them
+fn check_grammar_sources() {
+    let parsers = [
+        "tree-sitter-javascript/src/parser.c",
+        "tree-sitter-typescript/typescript/src/parser.c",
+        "tree-sitter-typescript/tsx/src/parser.c",
+        "tree-sitter-rust/src/parser.c",
+        "tree-sitter-go/src/parser.c",
+        "tree-sitter-php/php/src/parser.c",
+        "tree-sitter-kotlin/src/parser.c",
+        "tree-sitter-c-sharp/src/parser.c",
+        "tree-sitter-ruby/src/parser.c",
+        "tree-sitter-bash/src/parser.c",
+        "tree-sitter-elixir/src/parser.c",
+    ];
+    let missing: Vec<&str> = parsers.iter().copied().filter(|parser| !Path::new(parser).is_file()).collect();
+    if !missing.is_empty() {
+        panic!(
+            "missing tree-sitter grammar sources:\n  {}\nrun `git submodule update --init --recursive` (or clone with --recursive)",
+            missing.join("\n  ")
+        );
+    }
+}
 
 fn main() {
+    check_grammar_sources();
+
     let js_dir: PathBuf = ["tree-sitter-javascript", "src"].iter().collect();
     cc::Build::new()
         .include(&js_dir)
@@ -27,4 +54,74 @@ fn main() {
         .compile("tree-sitter-tsx");
     println!("cargo:rerun-if-changed=tree-sitter-typescript/tsx/src/parser.c");
     println!("cargo:rerun-if-changed=tree-sitter-typescript/tsx/src/scanner.c");
-} 
\ No newline at end of file
+
+    let rust_dir: PathBuf = ["tree-sitter-rust", "src"].iter().collect();
+    cc::Build::new()
+        .include(&rust_dir)
+        .file(rust_dir.join("parser.c"))
+        .file(rust_dir.join("scanner.c"))
+        .compile("tree-sitter-rust");
+    println!("cargo:rerun-if-changed=tree-sitter-rust/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-rust/src/scanner.c");
+
+    let go_dir: PathBuf = ["tree-sitter-go", "src"].iter().collect();
+    cc::Build::new()
+        .include(&go_dir)
+        .file(go_dir.join("parser.c"))
+        .compile("tree-sitter-go");
+    println!("cargo:rerun-if-changed=tree-sitter-go/src/parser.c");
+
+    let php_dir: PathBuf = ["tree-sitter-php", "php", "src"].iter().collect();
+    cc::Build::new()
+        .include(&php_dir)
+        .file(php_dir.join("parser.c"))
+        .file(php_dir.join("scanner.c"))
+        .compile("tree-sitter-php");
+    println!("cargo:rerun-if-changed=tree-sitter-php/php/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-php/php/src/scanner.c");
+
+    let kotlin_dir: PathBuf = ["tree-sitter-kotlin", "src"].iter().collect();
+    cc::Build::new()
+        .include(&kotlin_dir)
+        .file(kotlin_dir.join("parser.c"))
+        .file(kotlin_dir.join("scanner.c"))
+        .compile("tree-sitter-kotlin");
+    println!("cargo:rerun-if-changed=tree-sitter-kotlin/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-kotlin/src/scanner.c");
+
+    let c_sharp_dir: PathBuf = ["tree-sitter-c-sharp", "src"].iter().collect();
+    cc::Build::new()
+        .include(&c_sharp_dir)
+        .file(c_sharp_dir.join("parser.c"))
+        .file(c_sharp_dir.join("scanner.c"))
+        .compile("tree-sitter-c-sharp");
+    println!("cargo:rerun-if-changed=tree-sitter-c-sharp/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-c-sharp/src/scanner.c");
+
+    let ruby_dir: PathBuf = ["tree-sitter-ruby", "src"].iter().collect();
+    cc::Build::new()
+        .include(&ruby_dir)
+        .file(ruby_dir.join("parser.c"))
+        .file(ruby_dir.join("scanner.c"))
+        .compile("tree-sitter-ruby");
+    println!("cargo:rerun-if-changed=tree-sitter-ruby/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-ruby/src/scanner.c");
+
+    let bash_dir: PathBuf = ["tree-sitter-bash", "src"].iter().collect();
+    cc::Build::new()
+        .include(&bash_dir)
+        .file(bash_dir.join("parser.c"))
+        .file(bash_dir.join("scanner.c"))
+        .compile("tree-sitter-bash");
+    println!("cargo:rerun-if-changed=tree-sitter-bash/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-bash/src/scanner.c");
+
+    let elixir_dir: PathBuf = ["tree-sitter-elixir", "src"].iter().collect();
+    cc::Build::new()
+        .include(&elixir_dir)
+        .file(elixir_dir.join("parser.c"))
+        .file(elixir_dir.join("scanner.c"))
+        .compile("tree-sitter-elixir");
+    println!("cargo:rerun-if-changed=tree-sitter-elixir/src/parser.c");
+    println!("cargo:rerun-if-changed=tree-sitter-elixir/src/scanner.c");
+}
@@ -1,151 +1,1748 @@
+use std::any::Any;
 use std::fs;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
-use walkdir::{DirEntry, WalkDir};
-use std::collections::{HashSet};
+use std::time::{Duration, Instant, SystemTime};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use walkdir::WalkDir;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use rayon::prelude::*;
-use tree_sitter::{Parser, Language, Query, QueryCursor, Node};
+use tree_sitter::{Parser, Language, Query, QueryCursor, QueryError, Node};
 use path_clean::PathClean;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{self, ExtractedArchive};
+use crate::bundler::{self, BundlerAliases};
+use crate::cache::{self, CacheMode, CachedFile};
+use crate::languages;
+use crate::monorepo::{self, MonorepoInfo};
+use crate::packages::{self, PackageVersions};
+use crate::search::SearchKeys;
+use crate::tsconfig::{self, PathAliases, TsProject};
 
 
 
 const IGNORED_DIRS: &[&str] = &["node_modules", ".git", ".next", ".cursor", "target"];
 const IGNORED_FILES: &[&str] = &["pnpm-lock.yaml", "yarn.lock", "package-lock.json"];
+// Tamaño máximo por defecto de un archivo analizado o volcado en Contenidos (0 = sin límite)
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 1024 * 1024;
+// Un byte NUL al principio delata un binario (como hace git)
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
 
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DetectedConnection {
     pub source_file: PathBuf,
     pub imported_string: String,
+    pub dynamic_pattern: bool, // Template string con `${}`: imported_string es un patrón con `*`
+    pub import_attribute: Option<String>, // `with { type: 'json' }` / `assert { type: 'json' }`
+    pub glob: Option<GlobImport>, // require.context / import.meta.glob: dependencia de un subárbol
+    pub pattern: CapturePattern, // Patrón de la consulta que produjo la conexión
+    pub line_number: usize, // Línea de la sentencia (clave de deduplicación junto al especificador)
+    pub imported_symbols: Vec<String>, // `formatDate`, `a as b`, `default as Button`, `* as ns`, `*`
+    pub type_only: bool, // `import type` / `export type ... from`: desaparece en tiempo de ejecución
+}
 
+// Qué forma sintáctica produjo una conexión (las consultas se solapan: import + export from)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapturePattern {
+    Import,        // import ... from '...'
+    SideEffect,    // import '...' (sin nada importado: polyfills, estilos, registros)
+    ExportFrom,    // export { x } from '...' / export * from '...'
+    Require,       // require('...')
+    DynamicImport, // import('...')
+    Glob,          // require.context / import.meta.glob
+    ModDeclaration, // Rust: `mod foo;` (archivo del submódulo, no un `use`)
+    AssetUrl,      // CSS: url(...) a imágenes, fuentes, etc.
 }
 
-#[derive(Clone, Debug)]
+impl CapturePattern {
+    /// Etiqueta del tipo de conexión en el informe; el filtro de conexiones también la acepta.
+    pub fn label(self) -> &'static str {
+        match self {
+            CapturePattern::Import => "import",
+            CapturePattern::SideEffect => "side-effect",
+            CapturePattern::ExportFrom => "re-export",
+            CapturePattern::Require => "require",
+            CapturePattern::DynamicImport => "dynamic",
+            CapturePattern::Glob => "glob",
+            CapturePattern::ModDeclaration => "mod",
+            CapturePattern::AssetUrl => "asset",
+        }
+    }
+}
+
+// Imports implícitos de un subárbol completo; `imported_string` guarda el directorio o el patrón
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GlobImport {
+    // Vite: `import.meta.glob('./pages/**/*.tsx')` o una lista de patrones (`!` excluye)
+    ImportMetaGlob { patterns: Vec<String> },
+    // webpack: `require.context('./modules', true, /\.js$/)`
+    RequireContext { directory: String, recursive: bool, filter: Option<String> },
+}
+
+impl GlobImport {
+    pub fn describe(&self) -> String {
+        match self {
+            GlobImport::ImportMetaGlob { .. } => "import.meta.glob".to_string(),
+            GlobImport::RequireContext { recursive, filter, .. } => {
+                let mut text = "require.context".to_string();
+                if *recursive {
+                    text.push_str(", recursivo");
+                }
+                if let Some(filter) = filter {
+                    text.push_str(&format!(", {}", filter));
+                }
+                text
+            }
+        }
+    }
+}
+
+// Orden en que se prueban extensiones al resolver un import relativo o por alias. En ambos
+// estilos gana el archivo exacto, luego las extensiones y por último `carpeta/index.*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolutionStyle {
+    NodeStyle,    // require() de Node: `.js`/`.json`/`.cjs`/`.mjs` antes que los fuentes TS
+    BundlerStyle, // tsc/Vite/webpack: `.ts`/`.tsx` (y `.d.ts` detrás) antes que `.js`/`.jsx`
+}
+
+impl ResolutionStyle {
+    // `.json` incluido para `import data from './data'`; las de estilos al final, para
+    // `./styles.module` -> `styles.module.css`. `.d.ts` va detrás de `.ts`/`.tsx` para que un
+    // fuente gane a su declaración generada.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ResolutionStyle::NodeStyle => &[
+                ".js", ".json", ".cjs", ".mjs", ".jsx", ".ts", ".tsx", ".d.ts", ".vue", ".astro", ".wasm", ".css", ".scss", ".sass", ".less",
+            ],
+            ResolutionStyle::BundlerStyle => &[
+                ".ts", ".tsx", ".d.ts", ".js", ".jsx", ".mjs", ".cjs", ".vue", ".astro", ".json", ".wasm", ".css", ".scss", ".sass", ".less",
+            ],
+        }
+    }
+
+    /// BundlerStyle si el proyecto tiene tsconfig/jsconfig o config de Vite/webpack; si no, es
+    /// JavaScript que ejecuta Node directamente.
+    pub fn for_project(project_files: &HashSet<PathBuf>) -> Self {
+        let uses_bundler = project_files.iter().any(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name == "tsconfig.json" || name == "jsconfig.json" || name.starts_with("vite.config.") || name.starts_with("webpack.config.")
+        });
+        if uses_bundler { ResolutionStyle::BundlerStyle } else { ResolutionStyle::NodeStyle }
+    }
+}
+
+// Qué clase de import es, para distinguir imports rotos de paquetes externos
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportClass {
+    Local,       // Resuelto a un archivo/carpeta del proyecto (o glob, patrón dinámico, módulo ambiental)
+    NpmPackage,  // Nombre desnudo encontrado en package.json / node_modules / lockfile
+    NodeBuiltin, // `fs`, `node:path`, ...
+    Missing,     // Ruta relativa o absoluta que no existe en el proyecto
+    OutsideRoot, // Ruta que sale de la carpeta analizada (`../../shared/lib` en un monorepo)
+    External,    // Cualquier otro import sin resolver (crates, gems, paquetes no declarados, ...)
+}
+
+impl ImportClass {
+    /// Etiqueta corta que también acepta el filtro de conexiones (`missing`, `npm`, ...).
+    pub fn label(self) -> &'static str {
+        match self {
+            ImportClass::Local => "local",
+            ImportClass::NpmPackage => "npm",
+            ImportClass::NodeBuiltin => "builtin",
+            ImportClass::Missing => "missing",
+            ImportClass::OutsideRoot => "outside",
+            ImportClass::External => "external",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ResolvedConnection {
     pub source_file: PathBuf,
     pub imported_string: String,
-    pub resolved_target: Option<PathBuf>,
+    pub line_number: usize, // Línea de la sentencia en `source_file`
+    pub resolved_target: Option<PathBuf>, // Siempre dentro de la raíz analizada
+    pub outside_target: Option<PathBuf>,  // Destino absoluto fuera de la raíz (no es un archivo del proyecto)
+    pub ambient_module: Option<AmbientModule>, // `declare module '...'` que cubre un import no resuelto
+    pub dynamic_pattern: bool,
+    pub pattern_matches: Vec<PathBuf>, // Archivos del proyecto que encajan con un patrón dinámico o glob
+    pub import_attribute: Option<String>,
+    pub glob: Option<GlobImport>,
+    pub barrel_targets: Vec<PathBuf>, // Si el destino es un barrel: archivos finales tras sus re-exports
+    pub import_class: ImportClass, // Se clasifica en el paso 4, cuando ya se conocen los paquetes
+    pub kind: CapturePattern,      // import, re-export, side-effect, dynamic, require, ...
+    pub imported_symbols: Vec<String>, // Mismo formato que en DetectedConnection
+    pub type_only: bool, // Ver DetectedConnection::type_only
+    pub occurrences: usize, // Sentencias fusionadas en esta conexión (ver merge_repeated_connections)
 }
 
-#[derive(Clone, Debug)]
+// Módulo ambiental declarado en un .d.ts (`declare module '@legacy/*'`)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AmbientModule {
+    pub source_file: PathBuf,
+    pub pattern: String,
+    pub line_number: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DetectedDefinition {
     pub source_file: PathBuf,
     pub symbol_name: String,
     pub kind: String, // e.g., "Function", "Class", "Const", "Let", "Var", "Export"
     pub line_number: usize, // Line number where the definition starts
+    pub end_line_number: usize, // Última línea de la declaración (span completo)
+    pub signature: String, // Primera línea de la declaración (o parámetros de la arrow function)
+    pub exported_as: Option<String>, // JS/TS: nombre con el que otro archivo lo importa (el propio o `default`)
+    pub parent: Option<String>, // Clase que contiene el miembro (Method/Property)
+    pub doc_summary: Option<String>, // JS/TS: comentario /** ... */ (primera línea o completo)
+    pub decorators: Vec<String>, // TS: `@Controller('users')`, `@Injectable` (argumento solo si es una cadena)
+    pub handler: Option<String>, // Route: identificador del manejador (`getUser`, `users.create`)
 }
 
+// Comentario con marcador TODO/FIXME/HACK/XXX (solo nodos `comment` del árbol, no cadenas)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TodoComment {
+    pub source_file: PathBuf,
+    pub line_number: usize,
+    pub marker: String, // TODO, FIXME, HACK o XXX
+    pub text: String,   // Resto de la línea tras el marcador (sin `*/`)
+}
 
-pub type AnalysisResult = Result<(PathBuf, Vec<PathBuf>, Vec<ResolvedConnection>, Vec<DetectedDefinition>), String>;
+// Opciones que cambian lo que se recoge en el análisis (requieren re-analizar)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AnalysisOptions {
+    pub include_class_members: bool, // Métodos y propiedades de las clases JS/TS
+    pub full_doc_comments: bool,     // doc_summary con todo el comentario en vez de su primera línea
+    pub respect_gitignore: bool,     // .gitignore (anidados y global) y .ignore del proyecto
+    pub max_file_bytes: u64,         // Archivos más grandes no se leen (0 = sin límite)
+}
+
+// Patrones glob de la ejecución, sobre rutas relativas a la raíz con `/` (`src/**`, `**/*.test.ts`).
+// `*` no cruza carpetas; `**` sí. Un archivo entra si no coincide con ningún exclude y, si hay
+// includes, coincide con alguno.
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    pub exclude_globs: GlobSet,
+    pub include_globs: Option<GlobSet>, // None = sin restricción
+}
+
+impl ScanOptions {
+    /// Un patrón por línea; las líneas vacías se ignoran. Error con el primer patrón inválido.
+    pub fn from_patterns(exclude: &str, include: &str) -> Result<Self, String> {
+        let include_globs = compile_globs(include)?;
+        Ok(Self {
+            exclude_globs: compile_globs(exclude)?,
+            include_globs: (!include_globs.is_empty()).then_some(include_globs),
+        })
+    }
+
+    fn allows(&self, root_path: &Path, file: &Path) -> bool {
+        let relative = file.strip_prefix(root_path).unwrap_or(file);
+        // Separador `/` también en Windows
+        let relative: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        let relative = relative.join("/");
+        !self.exclude_globs.is_match(&relative) && self.include_globs.as_ref().is_none_or(|globs| globs.is_match(&relative))
+    }
+}
+
+fn compile_globs(patterns: &str) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.lines().map(str::trim).filter(|p| !p.is_empty()) {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("Patrón inválido `{}`: {}", pattern, e.kind()))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ProjectAnalysis {
+    pub root_path: PathBuf,
+    pub root_label: String, // Nombre mostrado para la raíz (carpeta o archivo comprimido)
+    pub files: Vec<PathBuf>,
+    pub connections: Vec<ResolvedConnection>,
+    pub definitions: Vec<DetectedDefinition>,
+    pub todos: Arc<Vec<TodoComment>>, // Marcadores TODO/FIXME en comentarios
+    pub ts_projects: Vec<TsProject>, // tsconfig*.json descubiertos (monorepos / project references)
+    pub archive: Option<Arc<ExtractedArchive>>, // Mantiene vivo el directorio temporal extraído
+    pub search_keys: Arc<SearchKeys>, // Claves de filtrado precalculadas; reconstruir si cambian los datos
+    pub file_stamps: Arc<HashMap<PathBuf, FileStamp>>, // Tamaño/mtime de cada archivo al analizar
+    pub file_metrics: Arc<HashMap<PathBuf, FileMetrics>>, // Líneas (contadas al analizar) y tamaño de cada archivo
+    pub package_versions: Arc<PackageVersions>, // Versión instalada/declarada de cada paquete externo importado
+    pub monorepo: Arc<MonorepoInfo>, // Paquetes del workspace y pipelines de turbo.json / nx.json
+    pub warnings: Arc<Vec<AnalysisWarning>>, // Archivos que no se pudieron analizar (p. ej. pánico del parser)
+    pub skipped_files: Arc<Vec<SkippedFile>>, // Archivos no leídos: demasiado grandes, binarios, ...
+}
+
+#[derive(Clone, Debug)]
+pub struct AnalysisWarning {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+// Por qué no se leyó un archivo: sigue en la estructura, pero sin conexiones ni definiciones
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkipReason {
+    TooLarge { bytes: u64, limit: u64 },
+    Binary,
+    NotUtf8,
+    Unreadable(String),
+}
+
+impl SkipReason {
+    pub fn describe(&self) -> String {
+        match self {
+            SkipReason::TooLarge { bytes, limit } => format!("{} KB, supera el límite de {} KB", bytes / 1024, limit / 1024),
+            SkipReason::Binary => "binario".to_string(),
+            SkipReason::NotUtf8 => "no es texto UTF-8".to_string(),
+            SkipReason::Unreadable(error) => format!("no se pudo leer: {}", error),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SkippedFile {
+    pub file: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Lee un archivo de texto con las salvaguardas del análisis: tamaño (por metadatos, antes de
+/// leer) y binarios. Lo usan también Contenidos y el visor para no volcar archivos enormes.
+pub fn read_text_file(path: &Path, max_file_bytes: u64) -> Result<String, SkipReason> {
+    let bytes = fs::metadata(path).map_err(|e| SkipReason::Unreadable(e.to_string()))?.len();
+    if max_file_bytes > 0 && bytes > max_file_bytes {
+        return Err(SkipReason::TooLarge { bytes, limit: max_file_bytes });
+    }
+    let content = fs::read(path).map_err(|e| SkipReason::Unreadable(e.to_string()))?;
+    if content[..content.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Err(SkipReason::Binary);
+    }
+    String::from_utf8(content).map_err(|_| SkipReason::NotUtf8)
+}
+
+// Texto de un payload de pánico (`panic!("...")` produce &str o String)
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "pánico sin mensaje".to_string()
+    }
+}
+
+// Gancho de los tests: un archivo (o la raíz analizada) con este nombre hace entrar en pánico
+// al análisis, para comprobar que siempre llega un resultado final
+#[cfg(test)]
+pub(crate) const INJECTED_PANIC_NAME: &str = "__inyectar_panico__";
+
+#[cfg(test)]
+fn injected_panic(path: &Path) {
+    if path.file_stem().is_some_and(|stem| stem == INJECTED_PANIC_NAME) {
+        panic!("pánico inyectado en {}", path.display());
+    }
+}
+
+// Metadatos baratos para detectar archivos modificados después del análisis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self { len: metadata.len(), modified: metadata.modified().ok() })
+    }
+}
+
+// Tamaño de un archivo: las líneas salen del contenido que ya se lee para analizarlo y los
+// bytes de los metadatos, así que también los archivos binarios o sin analizar tienen tamaño
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileMetrics {
+    pub lines: Option<usize>, // None si el archivo no es texto UTF-8
+    pub bytes: u64,
+}
+
+fn root_label(root_path: &Path) -> String {
+    root_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("ROOT")
+        .to_string()
+}
+
+impl ProjectAnalysis {
+    /// Análisis provisional durante el escaneo: solo la estructura, sin conexiones ni metadatos.
+    pub fn partial(root_path: PathBuf, files: Vec<PathBuf>) -> Self {
+        let search_keys = Arc::new(SearchKeys::build(&root_path, &files, &[], &[], &[]));
+        Self {
+            root_label: root_label(&root_path),
+            root_path,
+            files,
+            connections: Vec::new(),
+            definitions: Vec::new(),
+            todos: Arc::default(),
+            ts_projects: Vec::new(),
+            archive: None,
+            search_keys,
+            file_stamps: Arc::default(),
+            file_metrics: Arc::default(),
+            package_versions: Arc::default(),
+            monorepo: Arc::default(),
+            warnings: Arc::default(),
+            skipped_files: Arc::default(),
+        }
+    }
+
+    /// Añade resultados parciales; las claves de filtrado solo se calculan para el lote nuevo.
+    pub fn merge_partial(&mut self, connections: Vec<ResolvedConnection>, definitions: Vec<DetectedDefinition>) {
+        Arc::make_mut(&mut self.search_keys).extend(&self.root_path, &connections, &definitions);
+        self.connections.extend(connections);
+        self.definitions.extend(definitions);
+    }
+
+    /// Archivos de `files` borrados o con tamaño/mtime distinto al del análisis.
+    pub fn files_changed_since_scan(&self, files: &[PathBuf]) -> Vec<PathBuf> {
+        files
+            .par_iter()
+            .filter(|f| self.file_stamps.get(*f).is_some_and(|stamp| FileStamp::of(f) != Some(*stamp)))
+            .cloned()
+            .collect()
+    }
+}
+
+// Resultado del análisis de un único archivo
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    pub connections: Vec<DetectedConnection>,
+    pub definitions: Vec<DetectedDefinition>,
+    pub ambient_modules: Vec<AmbientModule>,
+    pub todos: Vec<TodoComment>,
+    pub line_count: Option<usize>, // None si el archivo no es texto UTF-8
+    pub is_barrel: bool, // El archivo consiste (casi) solo en re-exports (`export * from './x'`)
+    pub skipped: Option<SkipReason>, // No se leyó el contenido
+}
+
+pub type AnalysisResult = Result<ProjectAnalysis, String>;
+
+// Mensajes del hilo de análisis; el último siempre es `Finished`.
+// Los `Partial*` son provisionales (sin `declare module`, barrels ni clase de import):
+// `Finished` trae el análisis completo y los sustituye.
+pub enum ScanProgress {
+    FilesDiscovered(usize),
+    PartialFiles { root_path: PathBuf, files: Vec<PathBuf> }, // Archivos limpios y ordenados, al terminar el recorrido
+    FileAnalyzed { done: usize, total: usize, current_path: PathBuf },
+    PartialConnections(Vec<ResolvedConnection>),
+    PartialDefinitions(Vec<DetectedDefinition>),
+    Finished(AnalysisResult),
+}
+
+// Un mensaje cada N archivos analizados (más el último) para no saturar el canal
+const PROGRESS_EVERY: usize = 25;
+// Los lotes de progreso despiertan a quien escucha como mucho con esta frecuencia
+const PROGRESS_WAKE_INTERVAL: Duration = Duration::from_millis(100);
+
+// Canal de progreso más un aviso (p. ej. repintar la interfaz) para que el receptor no tenga que
+// sondear: los hitos (recorrido terminado, último archivo, resultado) avisan siempre y los lotes
+// de archivos analizados, como mucho cada PROGRESS_WAKE_INTERVAL.
+struct ProgressSender {
+    tx: Sender<ScanProgress>,
+    on_progress: Box<dyn Fn() + Send + Sync>,
+    last_wake: Mutex<Option<Instant>>,
+}
+
+impl ProgressSender {
+    fn new(tx: Sender<ScanProgress>, on_progress: impl Fn() + Send + Sync + 'static) -> Self {
+        Self { tx, on_progress: Box::new(on_progress), last_wake: Mutex::new(None) }
+    }
+
+    fn send(&self, message: ScanProgress) {
+        self.tx.send(message).ok(); // Ignorar error si el receptor ya no existe
+    }
+
+    fn wake(&self, milestone: bool) {
+        let Ok(mut last_wake) = self.last_wake.lock() else { return };
+        if milestone || last_wake.is_none_or(|at| at.elapsed() >= PROGRESS_WAKE_INTERVAL) {
+            *last_wake = Some(Instant::now());
+            (self.on_progress)();
+        }
+    }
+
+    fn finish(&self, result: AnalysisResult) {
+        self.send(ScanProgress::Finished(result));
+        self.wake(true);
+    }
+}
 
 // --- Tree-sitter Languages (Extern declarations) ---
 unsafe extern "C" { fn tree_sitter_javascript() -> Language; }
 unsafe extern "C" { fn tree_sitter_typescript() -> Language; }
 unsafe extern "C" { fn tree_sitter_tsx() -> Language; }
 
+fn language_for_path(path: &Path) -> Option<Language> {
+    script_language(path.extension()?.to_str()?)
+}
+
+pub(crate) fn script_language(ext: &str) -> Option<Language> {
+    match ext {
+        "js" | "jsx" | "mjs" | "cjs" => Some(unsafe { tree_sitter_javascript() }),
+        "ts" => Some(unsafe { tree_sitter_typescript() }),
+        "tsx" => Some(unsafe { tree_sitter_tsx() }),
+        _ => None,
+    }
+}
+
+lazy_static! {
+    static ref VUE_SCRIPT_BLOCK: Regex = Regex::new(r"(?s)<script\b([^>]*)>(.*?)</script\s*>").unwrap();
+    static ref VUE_SCRIPT_LANG: Regex = Regex::new(r#"\blang\s*=\s*["']?([A-Za-z]+)"#).unwrap();
+    // `TODO: x`, `FIXME(ana) x`, `HACK - x`: marcador en mayúsculas como palabra completa
+    static ref TODO_MARKER: Regex = Regex::new(r"\b(TODO|FIXME|HACK|XXX)\b(?:\([^)]*\))?[:\s-]*(.*)").unwrap();
+}
+
+// Vue SFC: los bloques `<script>` y `<script setup>` con todo lo demás reemplazado por espacios,
+// de modo que líneas y rangos coinciden con el archivo original. Devuelve también la extensión
+// cuya gramática corresponde (`lang="ts"` en cualquiera de los bloques -> TypeScript).
+fn vue_script_source(content: &str) -> Option<(String, &'static str)> {
+    let mut blocks = Vec::new();
+    let mut script_ext = "js";
+    for caps in VUE_SCRIPT_BLOCK.captures_iter(content) {
+        let lang = caps.get(1).and_then(|attrs| VUE_SCRIPT_LANG.captures(attrs.as_str()));
+        match lang.and_then(|l| l.get(1)).map(|l| l.as_str()) {
+            Some("tsx") => script_ext = "tsx",
+            Some("ts") if script_ext != "tsx" => script_ext = "ts",
+            Some("jsx") if script_ext == "js" => script_ext = "jsx",
+            _ => {}
+        }
+        blocks.extend(caps.get(2).map(|body| body.range()));
+    }
+    if blocks.is_empty() {
+        return None;
+    }
+
+    Some((mask_outside(content, &blocks), script_ext))
+}
+
+// Reemplaza por espacios todo lo que queda fuera de `blocks`, conservando los saltos de línea
+fn mask_outside(content: &str, blocks: &[std::ops::Range<usize>]) -> String {
+    content
+        .char_indices()
+        .map(|(i, c)| if c == '\n' || blocks.iter().any(|block| block.contains(&i)) { c } else { ' ' })
+        .collect()
+}
+
+// Astro: el frontmatter entre las vallas `---` del inicio es TypeScript
+fn astro_frontmatter_source(content: &str) -> Option<String> {
+    let start = content.len() - content.trim_start().len();
+    let after_open = content[start..].strip_prefix("---")?;
+    let body_start = content.len() - after_open.len();
+    let body_len = after_open.find("\n---")?;
+    let frontmatter = body_start..body_start + body_len + 1;
+    Some(mask_outside(content, std::slice::from_ref(&frontmatter)))
+}
+
+// Un componente .astro se importa siempre como default export con el nombre del archivo
+fn astro_component_definition(path: &Path, content: &str) -> DetectedDefinition {
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    DetectedDefinition {
+        source_file: path.to_path_buf(),
+        symbol_name: name.clone(),
+        kind: "Component".to_string(),
+        line_number: 1,
+        end_line_number: content.lines().count().max(1),
+        signature: format!("export default {} (componente Astro)", name),
+        exported_as: Some("default".to_string()),
+        parent: None,
+        doc_summary: None,
+        decorators: Vec::new(),
+        handler: None,
+    }
+}
+
+
+// --- Helper Functions (Internal) ---
+
+// Convierte un template string con interpolaciones (`./locales/${lang}.json`) en un patrón
+// con comodines (`./locales/*.json`). Devuelve None si no hay `${}`.
+fn template_pattern(node: Node, source: &str) -> Option<String> {
+    let mut cursor = node.walk();
+    let substitutions: Vec<Node> = node
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "template_substitution")
+        .collect();
+    if substitutions.is_empty() {
+        return None;
+    }
+
+    // Cuerpo sin los backticks, sustituyendo cada `${...}` por `*`
+    let body_start = node.start_byte() + 1;
+    let body_end = node.end_byte().saturating_sub(1).max(body_start);
+    let mut pattern = String::new();
+    let mut pos = body_start;
+    for sub in substitutions {
+        pattern.push_str(source.get(pos..sub.start_byte()).unwrap_or(""));
+        if !pattern.ends_with('*') {
+            pattern.push('*');
+        }
+        pos = sub.end_byte();
+    }
+    pattern.push_str(source.get(pos..body_end).unwrap_or(""));
+    Some(pattern)
+}
+
+// Tipo declarado en la cláusula de atributos de un import estático:
+// `import cfg from './config.json' with { type: 'json' }` (o la sintaxis antigua `assert`).
+// La consulta ya captura `source` en estos import_statement; aquí se lee el atributo.
+fn import_attribute_type(source_node: Node, source: &str) -> Option<String> {
+    let statement = source_node.parent()?;
+    if statement.kind() != "import_statement" && statement.kind() != "export_statement" {
+        return None;
+    }
+    let mut cursor = statement.walk();
+    let attribute = statement
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "import_attribute")?;
+    let object = attribute.named_child(0)?;
+
+    let mut object_cursor = object.walk();
+    object.named_children(&mut object_cursor).find_map(|pair| {
+        let key = pair.child_by_field_name("key")?;
+        let key_text = source.get(key.byte_range())?.trim_matches(|c| c == '\'' || c == '"');
+        if key_text != "type" {
+            return None;
+        }
+        let value = pair.child_by_field_name("value")?;
+        Some(source.get(value.byte_range())?.trim_matches(|c| c == '\'' || c == '"').to_string())
+    })
+}
+
+// Comodín simple: `*` equivale a cualquier secuencia sin separadores de ruta
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text.ends_with(last) || text.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) if !rest[..idx].contains('/') => rest = &rest[idx + part.len()..],
+            _ => return false,
+        }
+    }
+    !rest.contains('/')
+}
+
+// Archivos del proyecto que encajan con un patrón dinámico relativo (`./locales/*.json`)
+fn expand_dynamic_pattern(source_file: &Path, pattern: &str, project_files: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    if !pattern.starts_with('.') {
+        return Vec::new();
+    }
+    let Some(source_dir) = source_file.parent() else { return Vec::new() };
+    let full_pattern = source_dir.join(pattern).clean().to_string_lossy().replace('\\', "/");
+    let mut matches: Vec<PathBuf> = project_files
+        .iter()
+        .filter(|f| wildcard_match(&full_pattern, &f.to_string_lossy().replace('\\', "/")))
+        .cloned()
+        .collect();
+    matches.sort();
+    matches
+}
+
+// Sentencia que contiene el especificador capturado: import/export o la llamada require()/import()
+fn connection_statement(path_node: Node) -> (Node, CapturePattern) {
+    let Some(parent) = path_node.parent() else { return (path_node, CapturePattern::Import) };
+    match parent.kind() {
+        "import_statement" if parent.children(&mut parent.walk()).all(|c| c.kind() != "import_clause") => {
+            (parent, CapturePattern::SideEffect)
+        }
+        "import_statement" => (parent, CapturePattern::Import),
+        "export_statement" => (parent, CapturePattern::ExportFrom),
+        _ => match parent.parent().filter(|call| call.kind() == "call_expression") {
+            Some(call) if call.child_by_field_name("function").is_some_and(|f| f.kind() == "import") => {
+                (call, CapturePattern::DynamicImport)
+            }
+            Some(call) => (call, CapturePattern::Require),
+            None => (parent, CapturePattern::Import),
+        },
+    }
+}
+
+// `import type { A } from`, `export type { A } from` o `import { type A, type B } from` (todos
+// los especificadores con `type`): solo tipos, el import se borra al compilar. Un import mixto
+// (`{ type A, b }`) sigue siendo una dependencia en tiempo de ejecución.
+fn type_only_statement(statement: Node) -> bool {
+    let mut cursor = statement.walk();
+    if statement.children(&mut cursor).any(|c| matches!(c.kind(), "type" | "typeof") && !c.is_named()) {
+        return true;
+    }
+    let specifiers: Vec<Node> = match statement.kind() {
+        "import_statement" => {
+            let Some(clause) = statement.named_children(&mut cursor).find(|c| c.kind() == "import_clause") else { return false };
+            let mut clause_cursor = clause.walk();
+            let bindings: Vec<Node> = clause.named_children(&mut clause_cursor).collect();
+            // Un default o un namespace junto a las llaves siempre es un valor
+            let [named_imports] = bindings.as_slice() else { return false };
+            if named_imports.kind() != "named_imports" {
+                return false;
+            }
+            let mut spec_cursor = named_imports.walk();
+            named_imports.named_children(&mut spec_cursor).filter(|n| n.kind() == "import_specifier").collect()
+        }
+        "export_statement" => {
+            let Some(clause) = statement.named_children(&mut cursor).find(|c| c.kind() == "export_clause") else { return false };
+            let mut spec_cursor = clause.walk();
+            clause.named_children(&mut spec_cursor).filter(|n| n.kind() == "export_specifier").collect()
+        }
+        _ => return false,
+    };
+    !specifiers.is_empty()
+        && specifiers.iter().all(|spec| {
+            let mut spec_cursor = spec.walk();
+            spec.children(&mut spec_cursor).any(|c| c.kind() == "type" && !c.is_named())
+        })
+}
+
+// Símbolos que trae una sentencia, por su nombre exportado: `{ a, b as c }` -> `a`, `b as c`;
+// default -> `default as X`; `* as ns`; `export * from` -> `*`. `const { a } = require('x')`
+// cuenta como import con nombre y `const x = require('x')` como `* as x`.
+fn imported_symbols(statement: Node, source: &str) -> Vec<String> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("").to_string();
+    let specifier = |spec: Node| {
+        let name = spec.child_by_field_name("name").map(text).unwrap_or_default();
+        match spec.child_by_field_name("alias").map(text) {
+            Some(alias) if alias != name => format!("{} as {}", name, alias),
+            _ => name,
+        }
+    };
+    let mut symbols = Vec::new();
+    let mut cursor = statement.walk();
+    match statement.kind() {
+        "import_statement" => {
+            let Some(clause) = statement.named_children(&mut cursor).find(|c| c.kind() == "import_clause") else { return symbols };
+            let mut clause_cursor = clause.walk();
+            for child in clause.named_children(&mut clause_cursor) {
+                match child.kind() {
+                    "identifier" => symbols.push(format!("default as {}", text(child))),
+                    "namespace_import" => {
+                        let mut ns_cursor = child.walk();
+                        let name = child.named_children(&mut ns_cursor).find(|n| n.kind() == "identifier");
+                        symbols.extend(name.map(|n| format!("* as {}", text(n))));
+                    }
+                    "named_imports" => {
+                        let mut spec_cursor = child.walk();
+                        symbols.extend(child.named_children(&mut spec_cursor).filter(|n| n.kind() == "import_specifier").map(specifier));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        "export_statement" => {
+            for child in statement.named_children(&mut cursor) {
+                match child.kind() {
+                    "export_clause" => {
+                        let mut spec_cursor = child.walk();
+                        symbols.extend(child.named_children(&mut spec_cursor).filter(|n| n.kind() == "export_specifier").map(specifier));
+                    }
+                    "namespace_export" => {
+                        let mut ns_cursor = child.walk();
+                        let name = child.named_children(&mut ns_cursor).next();
+                        symbols.extend(name.map(|n| format!("* as {}", text(n))));
+                    }
+                    _ => {}
+                }
+            }
+            if symbols.is_empty() && statement.child_by_field_name("source").is_some() {
+                symbols.push("*".to_string()); // `export * from './x'`
+            }
+        }
+        "call_expression" => {
+            let Some(declarator) = statement.parent().filter(|p| p.kind() == "variable_declarator") else { return symbols };
+            let Some(name) = declarator.child_by_field_name("name") else { return symbols };
+            match name.kind() {
+                "identifier" => symbols.push(format!("* as {}", text(name))),
+                "object_pattern" => {
+                    let mut pattern_cursor = name.walk();
+                    for property in name.named_children(&mut pattern_cursor) {
+                        match property.kind() {
+                            "shorthand_property_identifier_pattern" => symbols.push(text(property)),
+                            "pair_pattern" => {
+                                let key = property.child_by_field_name("key").map(text).unwrap_or_default();
+                                match property.child_by_field_name("value").filter(|v| v.kind() == "identifier").map(text) {
+                                    Some(local) if local != key => symbols.push(format!("{} as {}", key, local)),
+                                    _ => symbols.push(key),
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    symbols
+}
+
+fn string_literal(node: Node, source: &str) -> Option<String> {
+    if node.kind() != "string" {
+        return None;
+    }
+    let text = source.get(node.byte_range())?.trim_matches(|c| c == '\'' || c == '"');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// `require.context(directorio, recursivo = true, /filtro/)`
+fn require_context_args(args: Node, source: &str) -> Option<(String, GlobImport)> {
+    let mut cursor = args.walk();
+    let arguments: Vec<Node> = args.named_children(&mut cursor).collect();
+    let directory = string_literal(*arguments.first()?, source)?;
+    let recursive = arguments.get(1).is_none_or(|arg| arg.kind() != "false");
+    let filter = arguments
+        .get(2)
+        .filter(|arg| arg.kind() == "regex")
+        .and_then(|arg| source.get(arg.byte_range()))
+        .map(str::to_string);
+    Some((directory.clone(), GlobImport::RequireContext { directory, recursive, filter }))
+}
+
+// `import.meta.glob('./a/*.ts')` o `import.meta.glob(['./a/*.ts', '!./a/skip.ts'])`
+fn import_meta_glob_args(args: Node, source: &str) -> Option<(String, GlobImport)> {
+    let first = args.named_child(0)?;
+    let patterns: Vec<String> = if first.kind() == "array" {
+        let mut cursor = first.walk();
+        first.named_children(&mut cursor).filter_map(|item| string_literal(item, source)).collect()
+    } else {
+        vec![string_literal(first, source)?]
+    };
+    if patterns.is_empty() {
+        return None;
+    }
+    Some((patterns.join(", "), GlobImport::ImportMetaGlob { patterns }))
+}
+
+// `{a,b}` -> una variante por alternativa (admite varias llaves y anidamiento)
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else { return vec![pattern.to_string()] };
+    let mut depth = 0;
+    let mut close = None;
+    let mut splits = Vec::new();
+    for (i, c) in pattern[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open + i);
+                    break;
+                }
+            }
+            ',' if depth == 1 => splits.push(open + i),
+            _ => {}
+        }
+    }
+    let Some(close) = close else { return vec![pattern.to_string()] };
+    let mut bounds = vec![open];
+    bounds.extend(splits);
+    bounds.push(close);
+    let (prefix, suffix) = (&pattern[..open], &pattern[close + 1..]);
+    bounds
+        .windows(2)
+        .flat_map(|w| expand_braces(&format!("{}{}{}", prefix, &pattern[w[0] + 1..w[1]], suffix)))
+        .collect()
+}
+
+// Glob por segmentos: `**` cubre cero o más carpetas; el resto usa `wildcard_match`
+fn glob_segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|skip| glob_segments_match(&pattern[1..], &path[skip..])),
+        Some(segment) => {
+            !path.is_empty() && wildcard_match(segment, path[0]) && glob_segments_match(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+// Expansión de un import por glob contra los archivos del proyecto
+fn expand_glob_import(source_file: &Path, root_path: &Path, glob: &GlobImport, project_files: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let Some(source_dir) = source_file.parent() else { return Vec::new() };
+    // Relativo al archivo, o a la raíz del proyecto si empieza por `/` (convención de Vite)
+    let absolute = |pattern: &str| match pattern.strip_prefix('/') {
+        Some(from_root) => root_path.join(from_root).clean(),
+        None => source_dir.join(pattern).clean(),
+    };
+
+    let mut matches: Vec<PathBuf> = match glob {
+        GlobImport::ImportMetaGlob { patterns } => {
+            let compile = |patterns: &mut dyn Iterator<Item = &str>| -> Vec<String> {
+                patterns
+                    .filter(|p| p.starts_with('.') || p.starts_with('/'))
+                    .flat_map(expand_braces)
+                    .map(|p| slash_path(&absolute(&p)))
+                    .collect()
+            };
+            let included = compile(&mut patterns.iter().map(String::as_str).filter(|p| !p.starts_with('!')));
+            let excluded = compile(&mut patterns.iter().filter_map(|p| p.strip_prefix('!')));
+            let matches_any = |compiled: &[String], file: &str| {
+                let file_segments: Vec<&str> = file.split('/').collect();
+                compiled.iter().any(|p| glob_segments_match(&p.split('/').collect::<Vec<_>>(), &file_segments))
+            };
+            project_files
+                .iter()
+                .filter(|f| {
+                    let file = slash_path(f);
+                    matches_any(&included, &file) && !matches_any(&excluded, &file)
+                })
+                .cloned()
+                .collect()
+        }
+        GlobImport::RequireContext { directory, recursive, filter } => {
+            let base = absolute(directory);
+            // El filtro de webpack se aplica a claves del tipo `./sub/archivo.js`
+            let regex = match filter {
+                Some(literal) => {
+                    let (body, flags) = literal.trim_start_matches('/').rsplit_once('/').unwrap_or((literal, ""));
+                    let case_insensitive = if flags.contains('i') { "(?i)" } else { "" };
+                    match regex::Regex::new(&format!("{}{}", case_insensitive, body)) {
+                        Ok(regex) => Some(regex),
+                        Err(_) => return Vec::new(), // Filtro no soportado: mejor no inventar dependencias
+                    }
+                }
+                None => None,
+            };
+            project_files
+                .iter()
+                .filter(|f| {
+                    let Ok(relative) = f.strip_prefix(&base) else { return false };
+                    if !*recursive && relative.components().count() > 1 {
+                        return false;
+                    }
+                    let key = format!("./{}", slash_path(relative));
+                    regex.as_ref().is_none_or(|r| r.is_match(&key))
+                })
+                .cloned()
+                .collect()
+        }
+    };
+    matches.sort();
+    matches
+}
+
+const MAX_SIGNATURE_CHARS: usize = 120;
+
+// Colapsa espacios/saltos de línea y limita la longitud (respetando límites UTF-8)
+pub(crate) fn compact_signature(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= MAX_SIGNATURE_CHARS {
+        return collapsed;
+    }
+    let mut truncated: String = collapsed.chars().take(MAX_SIGNATURE_CHARS - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+// Nodos con lista de parámetros: su firma se reconstruye como `nombre<T>(params): Retorno`
+const CALLABLE_KINDS: [&str; 8] = [
+    "function_declaration",
+    "generator_function_declaration",
+    "function_signature", // Sobrecargas y `declare function` en .d.ts
+    "function_expression",
+    "arrow_function",
+    "method_definition",
+    "method_signature",
+    "abstract_method_signature",
+];
+
+// `getUser(id: string): Promise<User>` a partir de los campos del nodo, aunque los parámetros
+// ocupen varias líneas. None si el nodo no es invocable.
+fn callable_signature(name: &str, callable: Node, source: &str) -> Option<String> {
+    if !CALLABLE_KINDS.contains(&callable.kind()) {
+        return None;
+    }
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("");
+    let params = match (callable.child_by_field_name("parameters"), callable.child_by_field_name("parameter")) {
+        (Some(list), _) => text(list).to_string(),
+        (None, Some(single)) => format!("({})", text(single)), // `x => ...`
+        (None, None) => "()".to_string(),
+    };
+    let type_params = callable.child_by_field_name("type_parameters").map(text).unwrap_or("");
+    let return_type = callable.child_by_field_name("return_type").map(text).unwrap_or("");
+    let signature = compact_signature(&format!("{}{}{}{}", name, type_params, params, return_type));
+    // Listas multilínea: `( id: string, )` -> `(id: string)`
+    Some(signature.replace("( ", "(").replace(", )", ")").replace(" )", ")"))
+}
+
+// Firma de la declaración: `nombre(params): Retorno` para funciones, arrow functions y
+// métodos; para el resto, desde el inicio del nodo hasta el primer salto de línea o llave.
+fn extract_signature(decl_node: Node, name_node: Option<Node>, source: &str) -> String {
+    let name_text = name_node.and_then(|n| source.get(n.byte_range())).unwrap_or("");
+    // `const f = (...) => ...`: la firma está en el valor del declarador
+    let value_node = name_node
+        .and_then(|n| n.parent())
+        .filter(|p| p.kind() == "variable_declarator")
+        .and_then(|declarator| declarator.child_by_field_name("value"));
+    let declaration = match decl_node.kind() {
+        "export_statement" => decl_node.child_by_field_name("declaration"),
+        _ => Some(decl_node),
+    };
+    if let Some(signature) = [value_node, declaration].into_iter().flatten().find_map(|node| callable_signature(name_text, node, source)) {
+        return signature;
+    }
+
+    let text = undecorated_text(decl_node, source);
+    let end = text.find(['\n', '{']).unwrap_or(text.len());
+    compact_signature(text[..end].trim())
+}
+
+// Texto del nodo sin los decoradores iniciales (`@Injectable() export class S` -> `export class S`),
+// que van aparte en `decorators`
+fn undecorated_text<'a>(node: Node, source: &'a str) -> &'a str {
+    let mut start = node.start_byte();
+    for holder in [Some(node), node.child_by_field_name("declaration")].into_iter().flatten() {
+        let mut cursor = holder.walk();
+        for decorator in holder.children(&mut cursor).filter(|c| c.kind() == "decorator") {
+            start = start.max(decorator.end_byte());
+        }
+    }
+    source.get(start..node.end_byte()).unwrap_or("").trim_start()
+}
+
+// Decoradores de NestJS que registran una ruta HTTP en un método del controlador
+const HTTP_ROUTE_DECORATORS: [&str; 8] = ["Get", "Post", "Put", "Patch", "Delete", "All", "Options", "Head"];
+
+// `@Controller('users')` -> `@Controller('users')`, `@Injectable()` -> `@Injectable`,
+// `@Component({ ... })` -> `@Component`: el argumento solo se conserva si es una cadena
+// (rutas, tokens), así la sección de definiciones sirve de tabla de rutas.
+fn decorator_label(decorator: Node, source: &str) -> Option<String> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("");
+    let expression = decorator.named_child(0)?;
+    if expression.kind() != "call_expression" {
+        return Some(format!("@{}", text(expression)));
+    }
+    let name = text(expression.child_by_field_name("function")?);
+    let first_argument = expression.child_by_field_name("arguments").and_then(|args| args.named_child(0));
+    Some(match first_argument.filter(|arg| matches!(arg.kind(), "string" | "template_string")) {
+        Some(route) => format!("@{}({})", name, text(route)),
+        None => format!("@{}", name),
+    })
+}
+
+// Decoradores de una declaración: hijos de la clase o de su `export` (`@Dec export class`),
+// o hermanos anteriores en el cuerpo de la clase para métodos y propiedades.
+fn decorator_labels(node: Node, source: &str) -> Vec<String> {
+    let mut decorators = Vec::new();
+    if matches!(node.kind(), "method_definition" | "method_signature" | "abstract_method_signature" | "field_definition" | "public_field_definition") {
+        let mut sibling = node.prev_named_sibling();
+        while let Some(decorator) = sibling.filter(|s| s.kind() == "decorator") {
+            decorators.push(decorator);
+            sibling = decorator.prev_named_sibling();
+        }
+        decorators.reverse();
+    } else {
+        let holders = [Some(node), node.child_by_field_name("declaration"), node.parent().filter(|p| p.kind() == "export_statement")];
+        for holder in holders.into_iter().flatten() {
+            let mut cursor = holder.walk();
+            decorators.extend(holder.children(&mut cursor).filter(|c| c.kind() == "decorator"));
+        }
+        decorators.sort_by_key(|d| d.start_byte());
+        decorators.dedup();
+    }
+    decorators.into_iter().filter_map(|d| decorator_label(d, source)).collect()
+}
+
+fn is_route_decorator(label: &str) -> bool {
+    let name = label.trim_start_matches('@').split('(').next().unwrap_or("");
+    HTTP_ROUTE_DECORATORS.contains(&name)
+}
+
+// `export function f` / `export const f = ...` -> `f`; `export default function f` -> `default`.
+// La consulta captura la declaración o la propia sentencia export: se sube como mucho dos niveles.
+fn export_name(node: Node, name: &str) -> Option<String> {
+    let statement = std::iter::successors(Some(node), |n| n.parent())
+        .take(3)
+        .find(|n| n.kind() == "export_statement")?;
+    let is_default = statement.children(&mut statement.walk()).any(|c| c.kind() == "default");
+    Some(if is_default { "default".to_string() } else { name.to_string() })
+}
+
+// Comentario JSDoc/TSDoc (`/** ... */`) de una declaración: su hermano anterior, saltando
+// decoradores, y sin líneas en blanco de por medio (un comentario suelto de cabecera no
+// documenta lo que venga después). Devuelve la primera línea con texto o, con `full`, todo.
+fn doc_comment(node: Node, source: &str, full: bool) -> Option<String> {
+    // `export function f`: el comentario precede a la sentencia export, no a la declaración
+    let node = match node.parent() {
+        Some(parent) if parent.kind() == "export_statement" && node.prev_named_sibling().is_none_or(|s| s.kind() == "decorator") => parent,
+        _ => node,
+    };
+    let mut attached_to = node;
+    let mut sibling = node.prev_named_sibling();
+    while let Some(decorator) = sibling.filter(|s| s.kind() == "decorator") {
+        attached_to = decorator;
+        sibling = decorator.prev_named_sibling();
+    }
+    let comment = sibling.filter(|s| s.kind() == "comment")?;
+    if comment.end_position().row + 1 < attached_to.start_position().row {
+        return None;
+    }
+    let body = source.get(comment.byte_range())?.strip_prefix("/**")?.strip_suffix("*/")?;
+    let mut lines = body.lines().map(|line| line.trim().trim_start_matches('*').trim()).filter(|line| !line.is_empty());
+    let summary = if full { lines.collect::<Vec<_>>().join(" ") } else { lines.next()?.to_string() };
+    (!summary.is_empty()).then_some(summary)
+}
+
+// Envoltorios que convierten una constante en componente React
+const REACT_COMPONENT_WRAPPERS: [&str; 4] = ["memo", "React.memo", "forwardRef", "React.forwardRef"];
+
+// Componentes y hooks de React: `useX` es un Hook; una función PascalCase con JSX en el cuerpo
+// o una constante exportada `= memo(...)` / `= forwardRef(...)` es un Component.
+fn react_kind(node: Node, name_node: Option<Node>, name: &str, kind: &str, source: &str) -> Option<&'static str> {
+    let is_hook = name.strip_prefix("use").and_then(|rest| rest.chars().next()).is_some_and(|c| c.is_ascii_uppercase());
+    let is_pascal_case = name.chars().next().is_some_and(|c| c.is_ascii_uppercase());
+    match kind {
+        "Function" if is_hook => Some("Hook"),
+        "Function" if is_pascal_case && contains_jsx(node) => Some("Component"),
+        "Variable" => {
+            let callee = name_node
+                .and_then(|n| n.parent())
+                .and_then(|declarator| declarator.child_by_field_name("value"))
+                .filter(|value| value.kind() == "call_expression")
+                .and_then(|call| call.child_by_field_name("function"))
+                .and_then(|function| source.get(function.byte_range()))?;
+            REACT_COMPONENT_WRAPPERS.contains(&callee).then_some("Component")
+        }
+        _ => None,
+    }
+}
+
+fn contains_jsx(node: Node) -> bool {
+    let mut cursor = node.walk();
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        if matches!(current.kind(), "jsx_element" | "jsx_self_closing_element") {
+            return true;
+        }
+        stack.extend(current.named_children(&mut cursor));
+    }
+    false
+}
+
+// Exports que las consultas de declaraciones no ven: `export default ...` (kind DefaultExport,
+// con el identificador local o `<default>` si es anónimo; `export default function f` sale
+// además como Function) y los miembros de `export { a, b as c }` (NamedExport) o
+// `export { a } from './x'` (ReExport), que se registran con el nombre exportado.
+fn export_definitions(root: Node, path: &Path, source: &str, full_docs: bool) -> Vec<DetectedDefinition> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("").to_string();
+    let definition = |node: Node, name: String, kind: &str, exported_as: String, signature: String| DetectedDefinition {
+        source_file: path.to_path_buf(),
+        symbol_name: name,
+        kind: kind.to_string(),
+        line_number: node.start_position().row + 1,
+        end_line_number: node.end_position().row + 1,
+        signature: compact_signature(&signature),
+        exported_as: Some(exported_as),
+        parent: None,
+        doc_summary: doc_comment(node, source, full_docs),
+        decorators: decorator_labels(node, source),
+        handler: None,
+    };
+
+    let mut definitions = Vec::new();
+    let mut cursor = root.walk();
+    for statement in root.named_children(&mut cursor).filter(|n| n.kind() == "export_statement") {
+        let is_default = statement.children(&mut statement.walk()).any(|c| c.kind() == "default");
+        let default_node = statement.child_by_field_name("value").or_else(|| statement.child_by_field_name("declaration").filter(|_| is_default));
+        if let Some(value) = default_node {
+            let name = match value.kind() {
+                "identifier" => Some(text(value)),
+                _ => value.child_by_field_name("name").map(text),
+            };
+            let signature = undecorated_text(statement, source);
+            // Funciones y clases: hasta el cuerpo, para que las llaves de un parámetro
+            // desestructurado (`({ page = 1 }) {`) no corten la firma
+            let end = match value.child_by_field_name("body").filter(|b| matches!(b.kind(), "statement_block" | "class_body")) {
+                Some(body) => signature.len() - (statement.end_byte() - body.start_byte()),
+                None => signature.find(['\n', '{']).unwrap_or(signature.len()),
+            };
+            let name = name.unwrap_or_else(|| "<default>".to_string());
+            let signature = signature[..end].trim().trim_end_matches("=>").trim_end().to_string();
+            definitions.push(definition(statement, name, "DefaultExport", "default".to_string(), signature));
+            continue;
+        }
+
+        let mut statement_cursor = statement.walk();
+        let Some(clause) = statement.named_children(&mut statement_cursor).find(|n| n.kind() == "export_clause") else { continue };
+        let from = statement.child_by_field_name("source").map(|s| format!(" from {}", text(s)));
+        let kind = if from.is_some() { "ReExport" } else { "NamedExport" };
+        let mut clause_cursor = clause.walk();
+        for specifier in clause.named_children(&mut clause_cursor).filter(|n| n.kind() == "export_specifier") {
+            let Some(local) = specifier.child_by_field_name("name").map(text) else { continue };
+            let exported = specifier.child_by_field_name("alias").map(text).unwrap_or_else(|| local.clone());
+            let signature = format!("export {{ {} }}{}", text(specifier), from.as_deref().unwrap_or(""));
+            definitions.push(definition(specifier, exported.clone(), kind, exported, signature));
+        }
+    }
+    definitions
+}
+
+// Marcadores TODO/FIXME/HACK/XXX en los comentarios del archivo, uno por línea marcada
+fn todo_comments(root: Node, path: &Path, source: &str) -> Vec<TodoComment> {
+    let mut todos = Vec::new();
+    let mut cursor = root.walk();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.kind() != "comment" {
+            stack.extend(node.named_children(&mut cursor));
+            continue;
+        }
+        let Some(comment) = source.get(node.byte_range()) else { continue };
+        for (offset, line) in comment.lines().enumerate() {
+            let Some(caps) = TODO_MARKER.captures(line) else { continue };
+            todos.push(TodoComment {
+                source_file: path.to_path_buf(),
+                line_number: node.start_position().row + offset + 1,
+                marker: caps[1].to_string(),
+                text: caps[2].trim().trim_end_matches("*/").trim_end().to_string(),
+            });
+        }
+    }
+    todos.sort_by_key(|todo| todo.line_number);
+    todos
+}
+
+// Métodos HTTP de `app.get(...)`, `router.post(...)`, `fastify.put(...)`
+const HTTP_ROUTE_METHODS: [&str; 8] = ["get", "post", "put", "patch", "delete", "options", "head", "all"];
+
+// Contenido de un literal de cadena sin interpolaciones (`'/users'`, `` `/a` ``)
+fn string_literal_value<'a>(node: Node, source: &'a str) -> Option<&'a str> {
+    match node.kind() {
+        "string" => {}
+        "template_string" if node.named_child_count() <= 1 && node.named_child(0).is_none_or(|c| c.kind() == "string_fragment") => {}
+        _ => return None,
+    }
+    let text = source.get(node.byte_range())?;
+    text.get(1..text.len().checked_sub(1)?)
+}
+
+// `router.route('/items').get(list).put(update)`: ruta del `.route()` al inicio de la cadena
+fn chained_route_path<'a>(object: Node, source: &'a str) -> Option<&'a str> {
+    let mut call = object;
+    while call.kind() == "call_expression" {
+        let function = call.child_by_field_name("function").filter(|f| f.kind() == "member_expression")?;
+        let property = function.child_by_field_name("property").and_then(|p| source.get(p.byte_range()))?;
+        if property == "route" {
+            let path = call.child_by_field_name("arguments")?.named_child(0)?;
+            return string_literal_value(path, source);
+        }
+        call = function.child_by_field_name("object")?;
+    }
+    None
+}
+
+// Rutas de servidor (kind Route, `GET /users/:id`): `app.get('/users/:id', handler)`,
+// `router.route('/items').get(list)` y `fastify.route({ method, url, handler })`. Para no
+// confundirlas con `map.get(key)`, la ruta debe ser un literal que empiece por `/` o `*`
+// y tener al menos un manejador detrás.
+fn route_definitions(root: Node, path: &Path, source: &str) -> Vec<DetectedDefinition> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("");
+    // Manejador referenciado por nombre; las funciones inline no apuntan a otro archivo
+    let handler_name = |node: Node| matches!(node.kind(), "identifier" | "member_expression").then(|| text(node).to_string());
+    let mut definitions = Vec::new();
+    let mut cursor = root.walk();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "call_expression" {
+            continue;
+        }
+        let Some(function) = node.child_by_field_name("function").filter(|f| f.kind() == "member_expression") else { continue };
+        let (Some(object), Some(property)) = (function.child_by_field_name("object"), function.child_by_field_name("property")) else { continue };
+        let Some(arguments) = node.child_by_field_name("arguments") else { continue };
+        let mut arguments_cursor = arguments.walk();
+        let args: Vec<Node> = arguments.named_children(&mut arguments_cursor).filter(|a| a.kind() != "comment").collect();
+        let method = text(property);
+
+        let route = if HTTP_ROUTE_METHODS.contains(&method) {
+            match (chained_route_path(object, source), args.first().and_then(|a| string_literal_value(*a, source))) {
+                (Some(route_path), _) if !args.is_empty() => Some((method.to_uppercase(), route_path.to_string(), args.last().and_then(|a| handler_name(*a)))),
+                (None, Some(route_path)) if args.len() >= 2 => Some((method.to_uppercase(), route_path.to_string(), args.last().and_then(|a| handler_name(*a)))),
+                _ => None,
+            }
+        } else if method == "route" && args.len() == 1 && args[0].kind() == "object" {
+            // Fastify: `{ method: 'GET' | ['GET', 'HEAD'], url: '/x', handler }`
+            let mut route_method = None;
+            let mut route_path = None;
+            let mut handler = None;
+            let mut pair_cursor = args[0].walk();
+            for pair in args[0].named_children(&mut pair_cursor).filter(|p| p.kind() == "pair") {
+                let (Some(key), Some(value)) = (pair.child_by_field_name("key"), pair.child_by_field_name("value")) else { continue };
+                match text(key) {
+                    "method" => {
+                        route_method = match value.kind() {
+                            "array" => {
+                                let mut array_cursor = value.walk();
+                                let methods: Vec<&str> = value.named_children(&mut array_cursor).filter_map(|m| string_literal_value(m, source)).collect();
+                                Some(methods.join("|").to_uppercase())
+                            }
+                            _ => string_literal_value(value, source).map(str::to_uppercase),
+                        }
+                    }
+                    "url" | "path" => route_path = string_literal_value(value, source),
+                    "handler" => handler = handler_name(value),
+                    _ => {}
+                }
+            }
+            route_method.zip(route_path).map(|(m, p)| (m, p.to_string(), handler))
+        } else {
+            None
+        };
+
+        let Some((route_method, route_path, handler)) = route else { continue };
+        if !route_path.starts_with(['/', '*']) {
+            continue;
+        }
+        // El objeto de Fastify es la propia definición; de un manejador inline basta la cabecera
+        let call_text = text(node);
+        let end = match method {
+            "route" => call_text.len(),
+            _ => call_text.find(['\n', '{']).unwrap_or(call_text.len()),
+        };
+        definitions.push(DetectedDefinition {
+            source_file: path.to_path_buf(),
+            symbol_name: format!("{} {}", route_method, route_path),
+            kind: "Route".to_string(),
+            line_number: node.start_position().row + 1,
+            end_line_number: node.end_position().row + 1,
+            signature: compact_signature(call_text[..end].trim()),
+            exported_as: None,
+            parent: None,
+            doc_summary: None,
+            decorators: Vec::new(),
+            handler,
+        });
+    }
+    definitions
+}
+
+// Exports CommonJS de nivel superior (kind CJSExport): `exports.a = ...` y
+// `module.exports.a = ...` -> `a`; `module.exports = { a, b: f, c() {} }` -> una entrada por
+// clave; cualquier otro `module.exports = x` -> `<module>`, importable como default.
+fn commonjs_export_definitions(root: Node, path: &Path, source: &str, full_docs: bool) -> Vec<DetectedDefinition> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("");
+    let definition = |node: Node, name: &str, exported_as: &str| {
+        let node_text = text(node);
+        let end = node_text.find(['\n', '{']).unwrap_or(node_text.len());
+        DetectedDefinition {
+            source_file: path.to_path_buf(),
+            symbol_name: name.to_string(),
+            kind: "CJSExport".to_string(),
+            line_number: node.start_position().row + 1,
+            end_line_number: node.end_position().row + 1,
+            signature: compact_signature(node_text[..end].trim()),
+            exported_as: Some(exported_as.to_string()),
+            parent: None,
+            doc_summary: doc_comment(node, source, full_docs),
+            decorators: Vec::new(),
+            handler: None,
+        }
+    };
+
+    let mut definitions = Vec::new();
+    let mut cursor = root.walk();
+    for statement in root.named_children(&mut cursor).filter(|n| n.kind() == "expression_statement") {
+        let Some(assignment) = statement.named_child(0).filter(|n| n.kind() == "assignment_expression") else { continue };
+        let (Some(left), Some(right)) = (assignment.child_by_field_name("left"), assignment.child_by_field_name("right")) else { continue };
+        if left.kind() != "member_expression" {
+            continue;
+        }
+
+        if text(left) != "module.exports" {
+            // exports.a = ... / module.exports.a = ...
+            let on_exports = left.child_by_field_name("object").is_some_and(|o| matches!(text(o), "exports" | "module.exports"));
+            if let (true, Some(property)) = (on_exports, left.child_by_field_name("property")) {
+                definitions.push(definition(statement, text(property), text(property)));
+            }
+            continue;
+        }
+
+        if right.kind() != "object" {
+            definitions.push(definition(statement, "<module>", "default"));
+            continue;
+        }
+        let mut object_cursor = right.walk();
+        for member in right.named_children(&mut object_cursor) {
+            let key = match member.kind() {
+                "shorthand_property_identifier" => Some(member),
+                "pair" => member.child_by_field_name("key"),
+                "method_definition" => member.child_by_field_name("name"),
+                _ => None, // ...spread: claves desconocidas
+            };
+            let Some(key) = key else { continue };
+            let name = match key.kind() {
+                "string" => text(key).trim_matches(['"', '\'']),
+                "computed_property_name" => continue, // `[nombre]: ...`: se sabe en ejecución
+                _ => text(key),
+            };
+            definitions.push(definition(member, name, name));
+        }
+    }
+    definitions
+}
+
+// Miembros de cada clase (kind Method/Property, con `parent` = nombre de la clase):
+// métodos, firmas de métodos abstractos y campos (`field_definition` en JS,
+// `public_field_definition` en TS). Solo con AnalysisOptions::include_class_members; sin ella
+// (`routes_only`) se listan únicamente los métodos con decorador de ruta (`@Get(':id')`).
+fn class_member_definitions(root: Node, path: &Path, source: &str, full_docs: bool, routes_only: bool) -> Vec<DetectedDefinition> {
+    let text = |node: Node| source.get(node.byte_range()).unwrap_or("");
+    let mut definitions = Vec::new();
+    let mut cursor = root.walk();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        stack.extend(node.named_children(&mut cursor));
+        if !matches!(node.kind(), "class_declaration" | "abstract_class_declaration") {
+            continue;
+        }
+        let (Some(class_name), Some(body)) = (node.child_by_field_name("name"), node.child_by_field_name("body")) else { continue };
+        let mut body_cursor = body.walk();
+        for member in body.named_children(&mut body_cursor) {
+            let (kind, name) = match member.kind() {
+                "method_definition" | "method_signature" | "abstract_method_signature" => ("Method", member.child_by_field_name("name")),
+                "field_definition" => ("Property", member.child_by_field_name("property")),
+                "public_field_definition" => ("Property", member.child_by_field_name("name")),
+                _ => continue,
+            };
+            let Some(name) = name else { continue };
+            let decorators = decorator_labels(member, source);
+            if routes_only && !decorators.iter().any(|d| is_route_decorator(d)) {
+                continue;
+            }
+            let member_text = text(member);
+            let end = member_text.find(['\n', '{']).unwrap_or(member_text.len());
+            let signature = callable_signature(text(name), member, source).unwrap_or_else(|| compact_signature(member_text[..end].trim()));
+            definitions.push(DetectedDefinition {
+                source_file: path.to_path_buf(),
+                symbol_name: text(name).to_string(),
+                kind: kind.to_string(),
+                line_number: member.start_position().row + 1,
+                end_line_number: member.end_position().row + 1,
+                signature,
+                exported_as: None,
+                parent: Some(text(class_name).to_string()),
+                doc_summary: doc_comment(member, source, full_docs),
+                decorators,
+                handler: None,
+            });
+        }
+    }
+    definitions
+}
+
+/// true si el escáner recorrería `path`: está bajo `root_path`, ninguna carpeta intermedia está
+/// ignorada (node_modules, .git, ...) y pasa los patrones de la ejecución. No consulta .gitignore.
+pub fn scanner_would_visit(root_path: &Path, path: &Path, scan: &ScanOptions) -> bool {
+    let Ok(relative) = path.strip_prefix(root_path) else { return false };
+    let mut current = root_path.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        current.push(component);
+        let is_dir = components.peek().is_some() || current.is_dir();
+        if is_ignored(&current, is_dir) {
+            return false;
+        }
+    }
+    scan.allows(root_path, path)
+}
+
+fn is_ignored(path: &Path, is_dir: bool) -> bool {
+    if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
+        if is_dir {
+            IGNORED_DIRS.contains(&filename)
+        } else {
+            IGNORED_FILES.contains(&filename)
+        }
+    } else {
+        false
+    }
+}
+
+
+// Una misma sentencia solo cuenta una vez: deduplicar por (especificador, línea), conservando
+// sentencias distintas en líneas distintas. Las métricas (fan-in, resúmenes) parten de aquí.
+// Un glob es otra dependencia (el subárbol), aunque comparta texto y línea con un require().
+fn dedup_connections(connections: &mut Vec<DetectedConnection>) {
+    let mut seen = HashSet::new();
+    connections.retain(|conn| seen.insert((conn.imported_string.clone(), conn.line_number, conn.pattern == CapturePattern::Glob)));
+}
+
+// --- Consultas precompiladas de JS/TS ---
+// Compilar una consulta cuesta más que ejecutarla sobre un archivo típico: se compilan una vez
+// por gramática y se comparten entre los hilos de rayon (`Query` es Sync).
+
+// Imports, re-exports, require() e import() dinámicos
+const IMPORT_QUERY: &str = r#"
+    [
+      ; Static ES6 Imports & Exports from '...'
+      (import_statement source: (string) @import_path)
+      (export_statement source: (string) @import_path)
+
+      ; CommonJS Requires: require('...') or require`...`
+      (call_expression
+        function: (identifier) @require_func (#eq? @require_func "require")
+        arguments: (arguments (string) @import_path))
+      (call_expression
+        function: (identifier) @require_func (#eq? @require_func "require")
+        arguments: (arguments (template_string) @import_path))
+        
+      ; Dynamic Imports: import('...') or import`...`
+      (call_expression
+        function: (import) @import_func
+        arguments: (arguments (string) @import_path))
+       (call_expression
+        function: (import) @import_func
+        arguments: (arguments (template_string) @import_path))
+        
+       ; Removed: Handle potential 'import_declaration'...
+       ; (import_declaration source: (string) @import_path) 
+    ]
+"#;
+
+// Imports por glob: require.context (webpack) e import.meta.glob (Vite)
+// Dos patrones separados y no una alternancia: los predicados se aplican a todo el patrón, y
+// dentro de `[...]` el `#eq?` de una rama descartaría las coincidencias de la otra
+const GLOB_IMPORT_QUERY: &str = r#"
+    (call_expression
+      function: (member_expression
+        object: (identifier) @glob.object (#eq? @glob.object "require")
+        property: (property_identifier) @glob.method (#eq? @glob.method "context"))
+      arguments: (arguments) @glob.args)
+    (call_expression
+      function: (member_expression
+        object: (meta_property)
+        property: (property_identifier) @glob.method (#match? @glob.method "^glob(Eager)?$"))
+      arguments: (arguments) @glob.args)
+"#;
+
+// JavaScript (js, jsx, mjs, cjs) usa 'identifier' para clases
+const JS_DEFINITION_QUERY: &str = r#"
+    [
+      ; Funciones
+      (function_declaration name: (identifier) @def.name) @def.function
+      (lexical_declaration
+        (variable_declarator name: (identifier) @def.name value: [
+          (arrow_function)
+          (function_expression)
+        ])
+      ) @def.function.lexical
+      (export_statement declaration: (function_declaration name: (identifier) @def.name)) @def.function.exported.decl
+
+      ; Clases (JS usa identifier)
+      (class_declaration name: (identifier) @def.name) @def.class 
+      (export_statement declaration: (class_declaration name: (identifier) @def.name)) @def.class.exported.decl
+
+      ; Variables/Constantes
+      (export_statement declaration: (lexical_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl
+      (export_statement (variable_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl.var
+
+      ; Componentes React envueltos: const Button = memo(...) / React.forwardRef(...)
+      (lexical_declaration
+        (variable_declarator name: (identifier) @def.name value: (call_expression
+          function: (_) @wrapper (#match? @wrapper "^(React\\.)?(memo|forwardRef)$")))
+      ) @def.component.wrapped
+    ]
+"#;
+
+// TypeScript (ts, tsx) usa 'type_identifier' para clases
+const TS_DEFINITION_QUERY: &str = r#"
+    [
+      ; Funciones (function_signature: sobrecargas y `declare function`, una entrada cada una)
+      (function_declaration name: (identifier) @def.name) @def.function
+      (function_signature name: (identifier) @def.name) @def.function
+      (lexical_declaration
+        (variable_declarator name: (identifier) @def.name value: [
+          (arrow_function)
+          (function_expression)
+        ])
+      ) @def.function.lexical
+      (export_statement declaration: (function_declaration name: (identifier) @def.name)) @def.function.exported.decl
 
-// --- Helper Functions (Internal) ---
+      ; Clases (TS/TSX usa type_identifier)
+      (class_declaration name: (type_identifier) @def.name) @def.class 
+      (export_statement declaration: (class_declaration name: (type_identifier) @def.name)) @def.class.exported.decl
 
+      ; Variables/Constantes
+      (export_statement declaration: (lexical_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl
+      (export_statement (variable_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl.var
 
-fn is_ignored(entry: &DirEntry) -> bool {
-    let path = entry.path();
-    if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-        if entry.file_type().is_dir() {
-            IGNORED_DIRS.contains(&filename)
-        } else {
-            IGNORED_FILES.contains(&filename)
+      ; Componentes React envueltos: const Button = memo(...) / React.forwardRef(...)
+      (lexical_declaration
+        (variable_declarator name: (identifier) @def.name value: (call_expression
+          function: (_) @wrapper (#match? @wrapper "^(React\\.)?(memo|forwardRef)$")))
+      ) @def.component.wrapped
+    ]
+"#;
+
+const AMBIENT_MODULE_QUERY: &str = r#"(ambient_declaration (module name: (string) @ambient.name))"#;
+
+// Los errores se guardan para reportarlos por archivo, igual que al compilar en cada uno
+struct ScriptQueries {
+    language: Language,
+    imports: Result<Query, QueryError>,
+    globs: Result<Query, QueryError>,
+    definitions: Result<Query, QueryError>,
+    ambient_modules: Result<Query, QueryError>, // Solo existe en la gramática de TypeScript
+}
+
+impl ScriptQueries {
+    fn compile(language: Language, definition_query: &str) -> Self {
+        Self {
+            imports: Query::new(&language, IMPORT_QUERY),
+            globs: Query::new(&language, GLOB_IMPORT_QUERY),
+            definitions: Query::new(&language, definition_query),
+            ambient_modules: Query::new(&language, AMBIENT_MODULE_QUERY),
+            language,
         }
-    } else {
-        false
     }
 }
 
+lazy_static! {
+    static ref JAVASCRIPT_QUERIES: ScriptQueries = ScriptQueries::compile(unsafe { tree_sitter_javascript() }, JS_DEFINITION_QUERY);
+    static ref TYPESCRIPT_QUERIES: ScriptQueries = ScriptQueries::compile(unsafe { tree_sitter_typescript() }, TS_DEFINITION_QUERY);
+    static ref TSX_QUERIES: ScriptQueries = ScriptQueries::compile(unsafe { tree_sitter_tsx() }, TS_DEFINITION_QUERY);
+}
+
+// Mismas extensiones que `script_language`
+fn script_queries(ext: &str) -> Option<&'static ScriptQueries> {
+    match ext {
+        "js" | "jsx" | "mjs" | "cjs" => Some(&JAVASCRIPT_QUERIES),
+        "ts" => Some(&TYPESCRIPT_QUERIES),
+        "tsx" => Some(&TSX_QUERIES),
+        _ => None,
+    }
+}
 
-fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDefinition>) {
-    let mut connections = Vec::new();
-    let mut definitions = Vec::new();
-    let file_content = match fs::read_to_string(path) {
+fn analyze_file_content(path: &Path, options: AnalysisOptions) -> FileAnalysis {
+    #[cfg(test)]
+    injected_panic(path);
+    let mut output = FileAnalysis::default();
+    let file_content = match read_text_file(path, options.max_file_bytes) {
         Ok(content) => content,
-        Err(_) => return (connections, definitions),
+        Err(reason) => {
+            output.skipped = Some(reason);
+            return output;
+        }
     };
 
-    let language_ref = match path.extension().and_then(|ext| ext.to_str()) {
-        Some("js") | Some("jsx") | Some("mjs") | Some("cjs") => unsafe { &tree_sitter_javascript() },
-        Some("ts") => unsafe { &tree_sitter_typescript() },
-        Some("tsx") => unsafe { &tree_sitter_tsx() },
-        _ => return (connections, definitions),
-    };
+    output.line_count = Some(file_content.lines().count());
 
-    let mut parser = Parser::new();
-    if parser.set_language(language_ref).is_err() {
-        eprintln!("Error setting language for file: {}", path.display());
-        return (connections, definitions);
+    // Lenguajes con analizador propio (Rust, ...)
+    if let Some(mut language_analysis) = languages::analyze_file(path, &file_content) {
+        dedup_connections(&mut language_analysis.connections);
+        language_analysis.line_count = output.line_count;
+        return language_analysis;
     }
 
-    let tree = match parser.parse(&file_content, None) {
-        Some(tree) => tree,
-        None => {
-            eprintln!("Error parsing file: {}", path.display());
-            return (connections, definitions);
+    // Extensión que decide gramática y consultas; en un .vue, la del bloque <script>
+    let (file_content, script_ext) = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("vue") => match vue_script_source(&file_content) {
+            Some(script) => script,
+            None => return output,
+        },
+        Some("astro") => {
+            output.definitions.push(astro_component_definition(path, &file_content));
+            match astro_frontmatter_source(&file_content) {
+                Some(frontmatter) => (frontmatter, "ts"),
+                None => return output,
+            }
         }
+        Some(ext) => (file_content, ext),
+        None => return output,
+    };
+
+    let queries = match script_queries(script_ext) {
+        Some(queries) => queries,
+        None => return output,
+    };
+
+    let tree = match languages::parse(&queries.language, path, &file_content) {
+        Some(tree) => tree,
+        None => return output,
     };
 
-    // Define tree-sitter queries for different import types
-    // Updated query for TS/TSX compatibility - Removed import_declaration attempt
-    let import_query_str = r#"
-        [
-          ; Static ES6 Imports & Exports from '...'
-          (import_statement source: (string) @import_path)
-          (export_statement source: (string) @import_path)
-
-          ; CommonJS Requires: require('...') or require`...`
-          (call_expression
-            function: (identifier) @require_func (#eq? @require_func "require")
-            arguments: (arguments (string) @import_path))
-          (call_expression
-            function: (identifier) @require_func (#eq? @require_func "require")
-            arguments: (arguments (template_string) @import_path))
-            
-          ; Dynamic Imports: import('...') or import`...`
-          (call_expression
-            function: (import) @import_func
-            arguments: (arguments (string) @import_path))
-           (call_expression
-            function: (import) @import_func
-            arguments: (arguments (template_string) @import_path))
-            
-           ; Removed: Handle potential 'import_declaration'...
-           ; (import_declaration source: (string) @import_path) 
-        ]
-    "#;
-
-
-    let query = match Query::new(language_ref, import_query_str) {
+    let query = match &queries.imports {
         Ok(q) => q,
         Err(e) => {
             // Print error with file path for better debugging
             eprintln!("Error creating query for {}: {:?}", path.display(), e);
-            return (connections, definitions);
+            return output;
         }
     };
 
     let mut query_cursor = QueryCursor::new();
-    let matches = query_cursor.matches(&query, tree.root_node(), file_content.as_bytes());
+    let matches = query_cursor.matches(query, tree.root_node(), file_content.as_bytes());
 
     for mat in matches {
         // Find the capture named "import_path"
         for cap in mat.captures {
              if query.capture_names()[cap.index as usize] == "import_path" {
                 let node = cap.node;
+                let (statement, capture_pattern) = connection_statement(node);
+                let line_number = statement.start_position().row + 1;
+                // Template strings con interpolación: registrar como patrón dinámico
+                if node.kind() == "template_string" {
+                    if let Some(pattern) = template_pattern(node, &file_content) {
+                        // Totalmente dinámico (`${x}`, `${dir}/${name}`): no hay nada literal que reportar
+                        if pattern.chars().any(|c| c != '*' && c != '/') {
+                            output.connections.push(DetectedConnection {
+                                source_file: path.to_path_buf(),
+                                imported_string: pattern,
+                                dynamic_pattern: true,
+                                import_attribute: None,
+                                glob: None,
+                                pattern: capture_pattern,
+                                line_number,
+                                imported_symbols: Vec::new(),
+                                type_only: false,
+                            });
+                        }
+                        break;
+                    }
+                }
                 if let Some(import_path_raw) = file_content.get(node.byte_range()) {
                      // Remove quotes (single, double) or backticks
                      let import_path = import_path_raw.trim_matches(|c| c == '\'' || c == '"' || c == '`').to_string();
                      if !import_path.is_empty() {
-                         connections.push(DetectedConnection {
+                         output.connections.push(DetectedConnection {
                             source_file: path.to_path_buf(),
                             imported_string: import_path,
+                            dynamic_pattern: false,
+                            import_attribute: import_attribute_type(node, &file_content),
+                            glob: None,
+                            pattern: capture_pattern,
+                            line_number,
+                            imported_symbols: imported_symbols(statement, &file_content),
+                            type_only: type_only_statement(statement),
                         });
                      }
                  }
@@ -154,70 +1751,48 @@ fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDe
          }
     }
 
-    // --- Consulta de Definiciones (Adaptada por lenguaje) ---
-    let definition_query_str = match path.extension().and_then(|ext| ext.to_str()) {
-        // JavaScript (js, jsx, mjs, cjs) usa 'identifier' para clases
-        Some("js") | Some("jsx") | Some("mjs") | Some("cjs") => r#"
-            [
-              ; Funciones
-              (function_declaration name: (identifier) @def.name) @def.function
-              (lexical_declaration
-                (variable_declarator name: (identifier) @def.name value: [
-                  (arrow_function)
-                  (function_expression)
-                ])
-              ) @def.function.lexical
-              (export_statement declaration: (function_declaration name: (identifier) @def.name)) @def.function.exported.decl
-    
-              ; Clases (JS usa identifier)
-              (class_declaration name: (identifier) @def.name) @def.class 
-              (export_statement declaration: (class_declaration name: (identifier) @def.name)) @def.class.exported.decl
-    
-              ; Variables/Constantes
-              (export_statement declaration: (lexical_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl
-              (export_statement (variable_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl.var
-            ]
-        "#,
-        // TypeScript (ts, tsx) usa 'type_identifier' para clases
-        Some("ts") | Some("tsx") => r#"
-            [
-              ; Funciones
-              (function_declaration name: (identifier) @def.name) @def.function
-              (lexical_declaration
-                (variable_declarator name: (identifier) @def.name value: [
-                  (arrow_function)
-                  (function_expression)
-                ])
-              ) @def.function.lexical
-              (export_statement declaration: (function_declaration name: (identifier) @def.name)) @def.function.exported.decl
-    
-              ; Clases (TS/TSX usa type_identifier)
-              (class_declaration name: (type_identifier) @def.name) @def.class 
-              (export_statement declaration: (class_declaration name: (type_identifier) @def.name)) @def.class.exported.decl
-    
-              ; Variables/Constantes
-              (export_statement declaration: (lexical_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl
-              (export_statement (variable_declaration (variable_declarator name: (identifier) @def.name))) @def.var.exported.decl.var
-            ]
-        "#,
-        // Fallback: Si no es un lenguaje soportado, no intentar consulta de definiciones
-        _ => {
-             // Ya hemos devuelto (connections, definitions) vacíos antes si el lenguaje no es soportado,
-            // pero por seguridad, retornamos de nuevo aquí si llegamos inesperadamente.
-            return (connections, definitions);
+    // --- Imports por glob: require.context (webpack) e import.meta.glob (Vite) ---
+    match &queries.globs {
+        Ok(glob_query) => {
+            let args_index = glob_query.capture_index_for_name("glob.args");
+            let mut glob_cursor = QueryCursor::new();
+            for mat in glob_cursor.matches(glob_query, tree.root_node(), file_content.as_bytes()) {
+                let Some(args) = mat.captures.iter().find(|c| Some(c.index) == args_index).map(|c| c.node) else { continue };
+                let is_context = mat.captures.iter().any(|c| file_content.get(c.node.byte_range()) == Some("context"));
+                let glob = if is_context { require_context_args(args, &file_content) } else { import_meta_glob_args(args, &file_content) };
+                if let Some((imported_string, glob)) = glob {
+                    output.connections.push(DetectedConnection {
+                        source_file: path.to_path_buf(),
+                        imported_string,
+                        dynamic_pattern: false,
+                        import_attribute: None,
+                        glob: Some(glob),
+                        pattern: CapturePattern::Glob,
+                        line_number: args.start_position().row + 1,
+                        imported_symbols: Vec::new(),
+                        type_only: false,
+                    });
+                }
+            }
         }
-    };
+        Err(e) => eprintln!("Error creating glob import query for {}: {:?}", path.display(), e),
+    }
 
-    let def_query = match Query::new(language_ref, definition_query_str) {
+    dedup_connections(&mut output.connections);
+    output.is_barrel = is_barrel_module(tree.root_node());
+
+    // --- Consulta de Definiciones (Adaptada por lenguaje) ---
+
+    let def_query = match &queries.definitions {
         Ok(q) => q,
         Err(e) => {
             eprintln!("Error creating definition query for {}: {:?}", path.display(), e);
-            return (connections, definitions); // Retornar definiciones vacías también
+            return output; // Retornar definiciones vacías también
         }
     };
 
     let mut def_query_cursor = QueryCursor::new();
-    let def_matches = def_query_cursor.matches(&def_query, tree.root_node(), file_content.as_bytes());
+    let def_matches = def_query_cursor.matches(def_query, tree.root_node(), file_content.as_bytes());
 
     // Indices para capturas específicas (más eficiente que buscar por nombre en el bucle)
     let name_capture_index = def_query.capture_index_for_name("def.name");
@@ -227,6 +1802,7 @@ fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDe
         let mut definition_name : Option<String> = None;
         let mut kind_str : Option<String> = None;
         let mut node_for_line : Option<Node> = None; // Nodo para obtener la línea inicial
+        let mut name_node : Option<Node> = None;
 
         // Iterar sobre las capturas del match actual
         for cap in mat.captures {
@@ -238,6 +1814,7 @@ fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDe
                 if let Some(name_str) = file_content.get(cap.node.byte_range()) {
                     definition_name = Some(name_str.to_string());
                 }
+                name_node = Some(cap.node);
             }
             // Es una captura que define el tipo? (empieza con "def.")
             else if capture_name.starts_with("def.") {
@@ -245,6 +1822,7 @@ fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDe
                      "def.function" | "def.function.lexical" | "def.function.exported" | "def.function.exported.decl" => "Function",
                      "def.class" | "def.class.exported.decl" => "Class",
                      "def.var.exported.decl" | "def.var.exported.decl.var" | "def.var.toplevel" => "Variable",
+                     "def.component.wrapped" => "Component",
                      _ => "Definition" // Fallback
                  }.to_string());
                  // Usar el nodo de esta captura para la línea, ya que representa el constructo principal
@@ -263,86 +1841,257 @@ fn analyze_file_content(path: &Path) -> (Vec<DetectedConnection>, Vec<DetectedDe
         // Si tenemos toda la información necesaria, la añadimos
         if let (Some(name), Some(kind), Some(node)) = (definition_name, kind_str, node_for_line) {
             if !name.is_empty() { // Asegurarnos de que el nombre no esté vacío
-                definitions.push(DetectedDefinition {
+                let exported_as = export_name(node, &name);
+                let kind = react_kind(node, name_node, &name, &kind, &file_content).map(str::to_string).unwrap_or(kind);
+                output.definitions.push(DetectedDefinition {
                     source_file: path.to_path_buf(),
                     symbol_name: name,
                     kind: kind,
                     line_number: node.start_position().row + 1, // tree-sitter es 0-indexed
+                    end_line_number: node.end_position().row + 1,
+                    signature: extract_signature(node, name_node, &file_content),
+                    exported_as,
+                    parent: None,
+                    doc_summary: doc_comment(node, &file_content, options.full_doc_comments),
+                    decorators: decorator_labels(node, &file_content),
+                    handler: None,
                 });
             }
         }
     }
+    output.definitions.extend(export_definitions(tree.root_node(), path, &file_content, options.full_doc_comments));
+    output.definitions.extend(commonjs_export_definitions(tree.root_node(), path, &file_content, options.full_doc_comments));
+    output.definitions.extend(class_member_definitions(tree.root_node(), path, &file_content, options.full_doc_comments, !options.include_class_members));
+    output.definitions.extend(route_definitions(tree.root_node(), path, &file_content));
+    output.todos = todo_comments(tree.root_node(), path, &file_content);
     // --- Fin de la consulta de Definiciones ---
 
-    (connections, definitions) // Devolver ambos vectores
+    // --- Módulos ambientales (`declare module '...'`) en archivos de declaración ---
+    if path.to_string_lossy().ends_with(".d.ts") {
+        match &queries.ambient_modules {
+            Ok(ambient_query) => {
+                let mut ambient_cursor = QueryCursor::new();
+                for mat in ambient_cursor.matches(ambient_query, tree.root_node(), file_content.as_bytes()) {
+                    for cap in mat.captures {
+                        if let Some(raw_name) = file_content.get(cap.node.byte_range()) {
+                            let pattern = raw_name.trim_matches(|c| c == '\'' || c == '"').to_string();
+                            if !pattern.is_empty() {
+                                output.ambient_modules.push(AmbientModule {
+                                    source_file: path.to_path_buf(),
+                                    pattern,
+                                    line_number: cap.node.start_position().row + 1,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error creating ambient module query for {}: {:?}", path.display(), e),
+        }
+    }
+
+    output
+}
+
+// --- Barrels (`index.ts` que solo re-exporta) ---
+
+// Proporción mínima de sentencias que deben ser re-exports para tratar el archivo como barrel
+const BARREL_REEXPORT_RATIO: f32 = 0.8;
+
+// Re-exports: `export * from`, `export { a } from` y listas `export { a, b }` de lo importado.
+// Los imports y comentarios no cuentan ni a favor ni en contra.
+fn is_barrel_module(root: Node) -> bool {
+    let mut cursor = root.walk();
+    let (mut statements, mut reexports) = (0, 0);
+    for child in root.named_children(&mut cursor) {
+        match child.kind() {
+            "comment" | "import_statement" => continue,
+            "export_statement" if child.child_by_field_name("source").is_some()
+                || (child.child_by_field_name("declaration").is_none() && child.child_by_field_name("value").is_none()) =>
+            {
+                reexports += 1;
+            }
+            _ => {}
+        }
+        statements += 1;
+    }
+    reexports > 0 && reexports as f32 >= statements as f32 * BARREL_REEXPORT_RATIO
+}
+
+// Archivos finales (no barrels) alcanzables desde `barrel` siguiendo sus re-exports.
+// El conjunto de visitados corta los ciclos entre barrels.
+fn barrel_sources(barrel: &Path, reexports: &HashMap<PathBuf, Vec<PathBuf>>, barrel_files: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut sources = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![barrel.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        if !visited.insert(current.clone()) {
+            continue;
+        }
+        for target in reexports.get(&current).into_iter().flatten() {
+            if barrel_files.contains(target) {
+                pending.push(target.clone());
+            } else {
+                sources.insert(target.clone());
+            }
+        }
+    }
+    sources.into_iter().collect()
+}
+
+// Segundo paso tras la resolución: rellena `barrel_targets` en las conexiones que apuntan a un
+// barrel. Sin los símbolos importados no se sabe cuál de las fuentes se usa: se listan todas.
+// Un archivo que importa el mismo módulo en varias sentencias (import estático, import() y
+// require) depende de él una sola vez: se fusionan por (origen, especificador, destino), con la
+// primera línea, la unión de símbolos y el número de sentencias. Solo es `type` si todas lo son.
+// Un glob sigue siendo otra dependencia aunque comparta especificador con un require(), y dos
+// require.context de la misma carpeta con otra recursión o filtro no se fusionan.
+fn merge_repeated_connections(connections: Vec<ResolvedConnection>) -> Vec<ResolvedConnection> {
+    let mut merged: Vec<ResolvedConnection> = Vec::with_capacity(connections.len());
+    let mut index: HashMap<(PathBuf, String, Option<PathBuf>, Option<String>), usize> = HashMap::new();
+    for conn in connections {
+        let key = (conn.source_file.clone(), conn.imported_string.clone(), conn.resolved_target.clone(), conn.glob.as_ref().map(GlobImport::describe));
+        match index.get(&key) {
+            Some(&i) => {
+                let kept = &mut merged[i];
+                kept.occurrences += conn.occurrences;
+                kept.line_number = kept.line_number.min(conn.line_number);
+                kept.type_only &= conn.type_only;
+                for symbol in conn.imported_symbols {
+                    if !kept.imported_symbols.contains(&symbol) {
+                        kept.imported_symbols.push(symbol);
+                    }
+                }
+            }
+            None => {
+                index.insert(key, merged.len());
+                merged.push(conn);
+            }
+        }
+    }
+    merged
+}
+
+fn flatten_barrels(connections: &mut [ResolvedConnection], barrel_files: &HashSet<PathBuf>) {
+    if barrel_files.is_empty() {
+        return;
+    }
+    let mut reexports: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for conn in connections.iter().filter(|c| barrel_files.contains(&c.source_file)) {
+        if let Some(target) = &conn.resolved_target {
+            reexports.entry(conn.source_file.clone()).or_default().push(target.clone());
+        }
+    }
+    let mut flattened: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for conn in connections.iter_mut() {
+        let Some(target) = conn.resolved_target.as_ref().filter(|t| barrel_files.contains(*t)) else { continue };
+        conn.barrel_targets = flattened
+            .entry(target.clone())
+            .or_insert_with(|| barrel_sources(target, &reexports, barrel_files))
+            .clone();
+    }
+}
+
+// Coincidencia de un especificador con un patrón de módulo ambiental: exacto o con un único
+// comodín `*` (p. ej. `@legacy/*`, `*.svg`).
+fn matches_ambient_pattern(import_str: &str, pattern: &str) -> bool {
+    match pattern.split_once('*') {
+        None => import_str == pattern,
+        Some((prefix, suffix)) => {
+            import_str.len() >= prefix.len() + suffix.len()
+                && import_str.starts_with(prefix)
+                && import_str.ends_with(suffix)
+        }
+    }
+}
+
+fn find_ambient_module(import_str: &str, ambient_modules: &[AmbientModule]) -> Option<AmbientModule> {
+    if import_str.starts_with('.') {
+        return None; // Solo especificadores "bare"
+    }
+    ambient_modules
+        .iter()
+        .find(|m| matches_ambient_pattern(import_str, &m.pattern))
+        .cloned()
 }
 
 
+// Vite/webpack: `./shader.glsl?raw`, `./worker.ts?worker`, `./icon.svg#sprite` apuntan al archivo
+// sin el sufijo. Un `#` inicial no se toca: son los subpath imports de Node (`#internal/db`).
+fn strip_query_suffix(import_str: &str) -> &str {
+    let end = import_str
+        .char_indices()
+        .find(|&(i, c)| c == '?' || (c == '#' && i > 0))
+        .map_or(import_str.len(), |(i, _)| i);
+    &import_str[..end]
+}
+
 // NUEVA: Función auxiliar para resolver rutas de importación
+// (el `imported_string` de la conexión conserva el texto original, con su `?query`)
 fn resolve_import_path(
     source_file: &Path,
     import_str: &str,
-    project_files: &HashSet<PathBuf> // Conjunto de todos los archivos válidos del proyecto
+    project_files: &HashSet<PathBuf>, // Conjunto de todos los archivos válidos del proyecto
+    path_aliases: &[PathAliases],     // `baseUrl`/`paths` de los tsconfig del proyecto
+    bundler_aliases: &[BundlerAliases], // `resolve.alias` de vite.config.* / webpack.config.*
+    style: ResolutionStyle,
 ) -> Option<PathBuf> {
-    // Ignorar paquetes (sin ./) y URLs/absolutos por ahora
-    if !import_str.starts_with('.') || import_str.contains(':') {
+    // Ignorar URLs/absolutos por ahora
+    if import_str.contains(':') {
         return None;
     }
+    let import_str = strip_query_suffix(import_str);
+
+    // Especificadores "bare": alias del tsconfig y luego del bundler que aplican al archivo;
+    // si ninguno existe en el proyecto, es un paquete
+    if !import_str.starts_with('.') {
+        let source_file = source_file.clean();
+        let ts_candidates = tsconfig::aliases_for_file(&source_file, path_aliases)
+            .map(|aliases| aliases.candidates(import_str))
+            .unwrap_or_default();
+        let bundler_candidates = bundler::aliases_for_file(&source_file, bundler_aliases)
+            .map(|aliases| aliases.candidates(import_str))
+            .unwrap_or_default();
+        return ts_candidates
+            .into_iter()
+            .chain(bundler_candidates)
+            .find_map(|base| probe_module_path(base, style, project_files));
+    }
 
     let source_dir = source_file.parent()?;
 
     // Construir ruta base y limpiarla/normalizarla
     let base_path = source_dir.join(import_str);
-    let cleaned_base_path = base_path.clean(); // Usa path_clean
-
-    // Extensiones a probar
-    let extensions = ["", ".js", ".jsx", ".ts", ".tsx", ".mjs", ".cjs"];
-    // Archivos índice a probar si es un directorio
-    let index_files = ["index.js", "index.jsx", "index.ts", "index.tsx", "index.mjs", "index.cjs"];
-
-    // 1. Probar como archivo con/sin extensión
-    for ext in extensions {
-        let mut potential_path = cleaned_base_path.clone();
-        // set_extension requiere la extensión sin el punto inicial, pero sí para la comparación
-        // Manejar el caso sin extensión explícitamente
-        if ext.is_empty() {
-             // Ya es cleaned_base_path, no hacer nada
-        } else {
-            // Construir el nombre de archivo con extensión
-             let current_filename = potential_path.file_name().unwrap_or_default();
-             let mut new_filename = current_filename.to_os_string();
-            // Evitar doble extensión si ya la tiene
-            if potential_path.extension().is_none() || potential_path.extension().unwrap_or_default() != ext.trim_start_matches('.') {
-                 new_filename.push(ext);
-                 potential_path.set_file_name(new_filename);
-            }
-        }
-
-        // Normalizar DE NUEVO después de añadir/modificar extensión
-        let final_path = potential_path.clean();
+    probe_module_path(base_path.clean(), style, project_files)
+}
 
-        if project_files.contains(&final_path) {
-            return Some(final_path);
-        }
+// Prueba una ruta base con una precedencia fija: 0) el archivo exacto, 1) añadiendo extensiones
+// en el orden del estilo, 2) como carpeta con índice. Así `./Button` elige `Button.tsx` antes
+// que `Button/index.tsx`, y `./utils` elige `utils.ts` antes que `utils.js` (BundlerStyle).
+fn probe_module_path(cleaned_base_path: PathBuf, style: ResolutionStyle, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    // 0. El import tal cual existe (`./config.json`, `./icon.svg`, `./styles.css`): cualquier
+    // extensión vale, no solo las de la familia JS
+    if project_files.contains(&cleaned_base_path) {
+        return Some(cleaned_base_path);
+    }
 
-        // Caso especial: si el import no tiene extensión, probar añadiéndola
-        if import_str.ends_with('/') || Path::new(import_str).extension().is_none() {
-            if !ext.is_empty() {
-                 let mut path_with_ext = cleaned_base_path.clone();
-                path_with_ext.set_extension(ext.trim_start_matches('.'));
-                let final_path_with_ext = path_with_ext.clean();
-                 if project_files.contains(&final_path_with_ext) {
-                    return Some(final_path_with_ext);
-                }
+    // 1. Probar como archivo añadiendo la extensión al nombre completo: lo que haya tras el
+    // último punto es parte del nombre (`./config.prod` -> `config.prod.ts`, `./foo.test` ->
+    // `foo.test.ts`), nunca una extensión a reemplazar
+    if let Some(file_name) = cleaned_base_path.file_name() {
+        for ext in style.extensions() {
+            let mut candidate_name = file_name.to_os_string();
+            candidate_name.push(ext);
+            let candidate = cleaned_base_path.with_file_name(candidate_name);
+            if project_files.contains(&candidate) {
+                return Some(candidate);
             }
         }
-
     }
 
-    // 2. Probar como directorio buscando archivo index
-    // (No necesitamos verificar is_dir explícitamente, path_clean maneja la base)
-    for index_file in index_files {
-        let potential_path = cleaned_base_path.join(index_file).clean();
+    // 2. Probar como directorio buscando archivo index, con el mismo orden de extensiones
+    for ext in style.extensions() {
+        let potential_path = cleaned_base_path.join(format!("index{}", ext));
         if project_files.contains(&potential_path) {
             return Some(potential_path);
         }
@@ -352,68 +2101,930 @@ fn resolve_import_path(
 }
 
 
+// Import relativo que sale de `root` (`../../shared/lib` con la raíz en `packages/app`): ruta
+// absoluta del archivo en disco si se encuentra con las extensiones/índices habituales, o la
+// ruta base tal cual. None si el import queda dentro de la raíz.
+fn outside_root_target(source_file: &Path, import_str: &str, root: &Path, style: ResolutionStyle) -> Option<PathBuf> {
+    if !import_str.starts_with('.') {
+        return None;
+    }
+    let base = source_file.clean().parent()?.join(strip_query_suffix(import_str)).clean();
+    if base.starts_with(root) {
+        return None;
+    }
+    let on_disk = std::iter::once(base.clone())
+        .filter(|p| p.is_file())
+        .chain(style.extensions().iter().map(|ext| PathBuf::from(format!("{}{}", base.display(), ext))))
+        .chain(style.extensions().iter().map(|ext| base.join(format!("index{}", ext))))
+        .find(|p| p.is_file());
+    Some(on_disk.unwrap_or(base))
+}
+
+
 // --- Funciones Públicas Principales ---
 
 
-pub fn start_analysis(path_to_scan: PathBuf) -> Receiver<AnalysisResult> {
-    let (tx, rx) = mpsc::channel();
+// --- Referencias dentro de un rango de líneas (contexto mínimo de una definición) ---
 
-    thread::spawn(move || {
-        let root_path = path_to_scan;
-        let walker_entries: Vec<_> = WalkDir::new(&root_path)
+#[derive(Clone, Debug)]
+pub struct ImportBinding {
+    pub local_name: String, // Nombre con el que el archivo usa lo importado
+    pub statement: String,  // Sentencia completa (`import { a as b } from './x'`)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SpanReferences {
+    pub identifiers: BTreeSet<String>,  // Identificadores usados dentro del rango
+    pub imports: Vec<ImportBinding>,    // Todos los bindings importados por el archivo, en orden
+}
+
+// Nombres locales que introduce un `import_clause` (default, `* as ns`, `{ a as b }`)
+fn import_clause_bindings(clause: Node, source: &str, bindings: &mut Vec<String>) {
+    let mut cursor = clause.walk();
+    for child in clause.named_children(&mut cursor) {
+        match child.kind() {
+            "identifier" => bindings.extend(source.get(child.byte_range()).map(str::to_string)),
+            "namespace_import" => {
+                let mut ns_cursor = child.walk();
+                let name = child.named_children(&mut ns_cursor).find(|n| n.kind() == "identifier");
+                bindings.extend(name.and_then(|n| source.get(n.byte_range())).map(str::to_string));
+            }
+            "named_imports" => {
+                let mut spec_cursor = child.walk();
+                for spec in child.named_children(&mut spec_cursor).filter(|n| n.kind() == "import_specifier") {
+                    let local = spec.child_by_field_name("alias").or_else(|| spec.child_by_field_name("name"));
+                    bindings.extend(local.and_then(|n| source.get(n.byte_range())).map(str::to_string));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Identificadores usados entre `start_line` y `end_line` (1-indexed, inclusivo) y los
+/// bindings de import del archivo. Heurístico: no distingue ámbitos ni sombreado.
+pub fn span_references(path: &Path, start_line: usize, end_line: usize) -> Option<SpanReferences> {
+    let source = fs::read_to_string(path).ok()?;
+    let language = language_for_path(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(&source, None)?;
+
+    let identifier_query_str = match path.extension().and_then(|e| e.to_str()) {
+        Some("ts") | Some("tsx") => "[(identifier) (type_identifier) (shorthand_property_identifier)] @id",
+        _ => "[(identifier) (shorthand_property_identifier)] @id",
+    };
+    let identifier_query = Query::new(&language, identifier_query_str).ok()?;
+    let mut cursor = QueryCursor::new();
+    cursor.set_point_range(
+        tree_sitter::Point::new(start_line.saturating_sub(1), 0)..tree_sitter::Point::new(end_line, 0),
+    );
+    let mut references = SpanReferences::default();
+    for mat in cursor.matches(&identifier_query, tree.root_node(), source.as_bytes()) {
+        for cap in mat.captures {
+            if let Some(name) = source.get(cap.node.byte_range()) {
+                references.identifiers.insert(name.to_string());
+            }
+        }
+    }
+
+    let import_query = Query::new(&language, "(import_statement (import_clause) @clause) @statement").ok()?;
+    let clause_index = import_query.capture_index_for_name("clause")?;
+    let statement_index = import_query.capture_index_for_name("statement")?;
+    let mut import_cursor = QueryCursor::new();
+    for mat in import_cursor.matches(&import_query, tree.root_node(), source.as_bytes()) {
+        let statement = mat.captures.iter().find(|c| c.index == statement_index).and_then(|c| source.get(c.node.byte_range()));
+        let clause = mat.captures.iter().find(|c| c.index == clause_index).map(|c| c.node);
+        if let (Some(statement), Some(clause)) = (statement, clause) {
+            let mut local_names = Vec::new();
+            import_clause_bindings(clause, &source, &mut local_names);
+            references.imports.extend(local_names.into_iter().map(|local_name| ImportBinding {
+                local_name,
+                statement: statement.to_string(),
+            }));
+        }
+    }
+    Some(references)
+}
+
+// Nodos cuyo cuerpo (`statement_block`) se sustituye por `{ ... }` en el esqueleto
+const SKELETON_BODY_OWNERS: [&str; 5] = [
+    "function_declaration",
+    "generator_function_declaration",
+    "function_expression",
+    "arrow_function",
+    "method_definition",
+];
+
+/// Esqueleto de un archivo JS/TS: imports, firmas, tipos/interfaces y miembros de clase se
+/// conservan y los cuerpos de funciones y métodos se reemplazan por `{ ... }`. Cada línea va
+/// con su número en el archivo original. None si el archivo no es JS/TS o no se puede leer.
+pub fn file_skeleton(path: &Path, max_file_bytes: u64) -> Option<Vec<(usize, String)>> {
+    let source = read_text_file(path, max_file_bytes).ok()?;
+    let language = language_for_path(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(&source, None)?;
+
+    // Rangos de los cuerpos más externos (los anidados desaparecen con su contenedor)
+    let mut bodies = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let is_body = node.kind() == "statement_block"
+            && node.parent().is_some_and(|p| SKELETON_BODY_OWNERS.contains(&p.kind()) && p.child_by_field_name("body") == Some(node));
+        if is_body {
+            bodies.push(node.byte_range());
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    bodies.sort_by_key(|range| range.start);
+
+    let mut lines = vec![(1, String::new())];
+    let mut line_number = 1;
+    let mut offset = 0;
+    for body in bodies.iter().map(Some).chain([None]) {
+        let kept_end = body.map_or(source.len(), |b| b.start);
+        for (i, piece) in source[offset..kept_end].split('\n').enumerate() {
+            if i > 0 {
+                line_number += 1;
+                lines.push((line_number, String::new()));
+            }
+            lines.last_mut()?.1.push_str(piece.trim_end_matches('\r'));
+        }
+        if let Some(body) = body {
+            lines.last_mut()?.1.push_str("{ ... }");
+            line_number += source[body.clone()].matches('\n').count();
+            offset = body.end;
+        }
+    }
+    // El salto de línea final no abre una línea más
+    if lines.len() > 1 && lines.last().is_some_and(|(_, text)| text.is_empty()) {
+        lines.pop();
+    }
+    Some(lines)
+}
+
+// Archivos a analizar. Con `respect_gitignore` se aplican las reglas de git (.gitignore de cada
+// carpeta, .git/info/exclude y el gitignore global) y los .ignore; las de git solo dentro de un
+// repositorio. IGNORED_DIRS/IGNORED_FILES se aplican siempre: cubren los proyectos sin git.
+// Después, los patrones de `scan` (ScanOptions).
+pub(crate) fn project_files(root_path: &Path, respect_gitignore: bool, scan: &ScanOptions) -> Vec<PathBuf> {
+    let files: Vec<PathBuf> = if respect_gitignore {
+        WalkBuilder::new(root_path)
+            .hidden(false) // Como el recorrido sin gitignore: .github/, .eslintrc.js, ...
+            .filter_entry(|e| !is_ignored(e.path(), e.file_type().is_some_and(|t| t.is_dir())))
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.path().is_file() && !is_ignored(entry.path(), false))
+            .map(|entry| entry.into_path())
+            .collect()
+    } else {
+        WalkDir::new(root_path)
             .into_iter()
-            .filter_entry(|e| !is_ignored(e))
+            .filter_entry(|e| !is_ignored(e.path(), e.file_type().is_dir()))
             .filter_map(|e| e.ok())
-            .filter(|entry| entry.path().is_file() && !is_ignored(entry))
-            .collect();
+            .filter(|entry| entry.path().is_file() && !is_ignored(entry.path(), false))
+            .map(|entry| entry.into_path())
+            .collect()
+    };
+    files.into_iter().filter(|file| scan.allows(root_path, file)).collect()
+}
 
-        // Crear HashSet de todos los archivos encontrados para búsqueda eficiente
-        let project_files_set: HashSet<PathBuf> = walker_entries
-            .par_iter()
-            .map(|entry| entry.path().to_path_buf().clean()) // Limpiar/normalizar aquí también
-            .collect();
+// Ruta, metadatos, hash del contenido (para la caché) y resultado del análisis de un archivo
+type FileResult = (PathBuf, Option<FileStamp>, Option<u64>, Result<FileAnalysis, String>);
 
-        // Paso 1: Análisis inicial para obtener conexiones crudas y definiciones
-        let initial_results: Vec<(PathBuf, Vec<DetectedConnection>, Vec<DetectedDefinition>)> = walker_entries
-            .par_iter()
-            .map(|entry| {
-                let path = entry.path().to_path_buf();
-                let (connections, definitions) = analyze_file_content(&path);
-                (path, connections, definitions)
-            })
-            .collect();
+fn run_analysis(
+    root_path: PathBuf,
+    options: AnalysisOptions,
+    scan: &ScanOptions,
+    cache_mode: CacheMode,
+    progress: &ProgressSender,
+) -> ProjectAnalysis {
+    #[cfg(test)]
+    injected_panic(&root_path);
+    let walker_entries = project_files(&root_path, options.respect_gitignore, scan);
+    let previous_cache = match cache_mode {
+        CacheMode::Reuse => cache::load(&root_path, options),
+        CacheMode::Refresh | CacheMode::Disabled => None,
+    };
+    let total = walker_entries.len();
+    progress.send(ScanProgress::FilesDiscovered(total));
+    progress.wake(true);
 
-        let mut files = Vec::with_capacity(initial_results.len());
-        let mut raw_connections = Vec::new();
-        let mut definitions = Vec::new();
-        for (path, file_connections, file_definitions) in initial_results {
-            files.push(path.clean()); // Almacenar rutas limpias
-            raw_connections.extend(file_connections);
-            definitions.extend(file_definitions);
-        }
-
-        // Paso 2: Resolver las conexiones
-        let resolved_connections: Vec<ResolvedConnection> = raw_connections
-            .par_iter() // Paralelizar resolución si es posible/seguro
-            .map(|conn| {
-                let resolved = resolve_import_path(&conn.source_file, &conn.imported_string, &project_files_set);
-                ResolvedConnection {
-                    source_file: conn.source_file.clone().clean(), // Guardar ruta limpia
-                    imported_string: conn.imported_string.clone(),
-                    resolved_target: resolved, // Puede ser None
+    // Crear HashSet de todos los archivos encontrados para búsqueda eficiente
+    let project_files_set: HashSet<PathBuf> = walker_entries
+        .par_iter()
+        .map(|path| path.clone().clean()) // Limpiar/normalizar aquí también
+        .collect();
+
+    // La estructura ya se puede mostrar mientras se analizan los archivos
+    let mut discovered: Vec<PathBuf> = project_files_set.iter().cloned().collect();
+    discovered.sort();
+    progress.send(ScanProgress::PartialFiles { root_path: root_path.clone(), files: discovered });
+    progress.wake(true);
+
+    // Los alias (`paths`/`baseUrl` de los tsconfig y `resolve.alias` de Vite/webpack) solo
+    // dependen de la lista de archivos: así se pueden resolver conexiones provisionales
+    let clean_root = root_path.clean();
+    let path_aliases = tsconfig::discover_path_aliases(&project_files_set);
+    let bundler_aliases = bundler::discover_bundler_aliases(&project_files_set);
+    let resolution_style = ResolutionStyle::for_project(&project_files_set);
+    let resolve_connection = |conn: &DetectedConnection, ambient_modules: &[AmbientModule]| -> ResolvedConnection {
+        if conn.dynamic_pattern {
+            return ResolvedConnection {
+                source_file: conn.source_file.clone().clean(),
+                imported_string: conn.imported_string.clone(),
+                line_number: conn.line_number,
+                resolved_target: None,
+                outside_target: None,
+                ambient_module: None,
+                dynamic_pattern: true,
+                pattern_matches: expand_dynamic_pattern(&conn.source_file, &conn.imported_string, &project_files_set),
+                import_attribute: None,
+                glob: None,
+                barrel_targets: Vec::new(),
+                import_class: ImportClass::Local,
+                kind: conn.pattern,
+                imported_symbols: conn.imported_symbols.clone(),
+                type_only: conn.type_only,
+                occurrences: 1,
+            };
+        }
+        if let Some(glob) = &conn.glob {
+            return ResolvedConnection {
+                source_file: conn.source_file.clone().clean(),
+                imported_string: conn.imported_string.clone(),
+                line_number: conn.line_number,
+                resolved_target: None,
+                outside_target: None,
+                ambient_module: None,
+                dynamic_pattern: false,
+                pattern_matches: expand_glob_import(&conn.source_file, &clean_root, glob, &project_files_set),
+                import_attribute: None,
+                glob: Some(glob.clone()),
+                barrel_targets: Vec::new(),
+                import_class: ImportClass::Local,
+                kind: conn.pattern,
+                imported_symbols: conn.imported_symbols.clone(),
+                type_only: conn.type_only,
+                occurrences: 1,
+            };
+        }
+        let mut resolved = languages::resolve_import(conn, &project_files_set)
+            .unwrap_or_else(|| resolve_import_path(&conn.source_file, &conn.imported_string, &project_files_set, &path_aliases, &bundler_aliases, resolution_style));
+        // Destinos fuera de la raíz (carpetas de Docker, `../../shared` desde un paquete de un
+        // monorepo): no son archivos del proyecto y no cuentan para los usos inversos
+        let outside_target = match resolved.take_if(|target| !target.starts_with(&clean_root)) {
+            Some(target) => Some(target),
+            None if resolved.is_none() => outside_root_target(&conn.source_file, &conn.imported_string, &clean_root, resolution_style),
+            None => None,
+        };
+        // Si no se resolvió, comprobar si lo cubre un `declare module`
+        let ambient_module = match resolved {
+            Some(_) => None,
+            None if outside_target.is_some() => None,
+            None => find_ambient_module(&conn.imported_string, ambient_modules),
+        };
+        ResolvedConnection {
+            source_file: conn.source_file.clone().clean(), // Guardar ruta limpia
+            imported_string: conn.imported_string.clone(),
+            line_number: conn.line_number,
+            resolved_target: resolved, // Puede ser None
+            outside_target,
+            ambient_module,
+            dynamic_pattern: false,
+            pattern_matches: Vec::new(),
+            import_attribute: conn.import_attribute.clone(),
+            glob: None,
+            barrel_targets: Vec::new(),
+            import_class: ImportClass::Local,
+            kind: conn.pattern,
+            imported_symbols: conn.imported_symbols.clone(),
+            type_only: conn.type_only,
+            occurrences: 1,
+        }
+    };
+
+    let analyzed = AtomicUsize::new(0);
+    let reused = AtomicUsize::new(0);
+    let analysis_started = Instant::now();
+    // Resultados provisionales pendientes de enviar con el siguiente `FileAnalyzed`
+    let pending: Mutex<(Vec<ResolvedConnection>, Vec<DetectedDefinition>)> = Mutex::new((Vec::new(), Vec::new()));
+
+    // Paso 1: Análisis inicial para obtener conexiones crudas y definiciones
+    // Un pánico en un archivo (bindings de tree-sitter, consultas) no debe tumbar todo el análisis
+    // Los archivos sin cambios desde el último análisis salen de la caché sin parsearse
+    let initial_results: Vec<FileResult> = walker_entries
+        .par_iter()
+        .map(|path| {
+            let path = path.clone();
+            // Capturar metadatos antes de leer para no ocultar cambios hechos durante el análisis
+            let stamp = FileStamp::of(&path);
+            let cached = previous_cache.as_ref().and_then(|cache| cache.lookup(&path, stamp));
+            let (file_analysis, hash) = match cached {
+                Some((file_analysis, hash)) => {
+                    reused.fetch_add(1, Ordering::Relaxed);
+                    (Ok(file_analysis), Some(hash))
                 }
+                None => {
+                    let file_analysis = panic::catch_unwind(AssertUnwindSafe(|| analyze_file_content(&path, options)))
+                        .map_err(|payload| panic_message(payload.as_ref()));
+                    let hash = if cache_mode == CacheMode::Disabled { None } else { cache::content_hash(&path) };
+                    (file_analysis, hash)
+                }
+            };
+            if let Ok(file_analysis) = &file_analysis {
+                // Sin `declare module` todavía: se conocen al terminar todos los archivos
+                let connections = file_analysis.connections.iter().map(|conn| resolve_connection(conn, &[])).collect();
+                let connections = merge_repeated_connections(connections);
+                if let Ok(mut pending) = pending.lock() {
+                    pending.0.extend(connections);
+                    pending.1.extend(file_analysis.definitions.iter().cloned());
+                }
+            }
+            let done = analyzed.fetch_add(1, Ordering::AcqRel) + 1;
+            if done.is_multiple_of(PROGRESS_EVERY) || done == total {
+                progress.send(ScanProgress::FileAnalyzed { done, total, current_path: path.clone() });
+                if let Ok(mut pending) = pending.lock() {
+                    let (connections, definitions) = std::mem::take(&mut *pending);
+                    if !connections.is_empty() {
+                        progress.send(ScanProgress::PartialConnections(connections));
+                    }
+                    if !definitions.is_empty() {
+                        progress.send(ScanProgress::PartialDefinitions(definitions));
+                    }
+                }
+                progress.wake(done == total);
+            }
+            (path, stamp, hash, file_analysis)
+        })
+        .collect();
+    // Tiempo del paso 1 (parseo y consultas), para comparar cambios de rendimiento del análisis
+    eprintln!(
+        "Analizados {} archivos ({} desde la caché) en {:.2?}",
+        total,
+        reused.load(Ordering::Relaxed),
+        analysis_started.elapsed()
+    );
+
+    // Los archivos que entraron en pánico no se guardan: se reintentan en el próximo análisis
+    if cache_mode != CacheMode::Disabled {
+        let entries = initial_results
+            .iter()
+            .filter_map(|(path, stamp, hash, file_analysis)| {
+                let analysis = file_analysis.as_ref().ok()?.clone();
+                Some((path.clone(), CachedFile { stamp: (*stamp)?, hash: (*hash)?, analysis }))
             })
             .collect();
+        if let Err(e) = cache::save(&root_path, options, entries) {
+            eprintln!("{}", e);
+        }
+    }
 
-        // Ordenar archivos para consistencia
-        files.sort();
-        // Podríamos ordenar definiciones y conexiones si es necesario
+    let mut files = Vec::with_capacity(initial_results.len());
+    let mut file_stamps = HashMap::with_capacity(initial_results.len());
+    let mut file_metrics = HashMap::with_capacity(initial_results.len());
+    let mut raw_connections = Vec::new();
+    let mut definitions = Vec::new();
+    let mut ambient_modules = Vec::new();
+    let mut todos = Vec::new();
+    let mut barrel_files = HashSet::new();
+    let mut warnings = Vec::new();
+    let mut skipped_files = Vec::new();
+    for (path, stamp, _, file_analysis) in initial_results {
+        let path = path.clean(); // Almacenar rutas limpias
+        if let Some(stamp) = stamp {
+            file_stamps.insert(path.clone(), stamp);
+            let lines = file_analysis.as_ref().ok().and_then(|analysis| analysis.line_count);
+            file_metrics.insert(path.clone(), FileMetrics { lines, bytes: stamp.len });
+        }
+        files.push(path.clone());
+        let mut file_analysis = match file_analysis {
+            Ok(file_analysis) => file_analysis,
+            Err(message) => {
+                // El archivo sigue en la estructura, pero sin conexiones ni definiciones
+                warnings.push(AnalysisWarning { file: path, message: format!("pánico al analizar: {}", message) });
+                continue;
+            }
+        };
+        if let Some(reason) = file_analysis.skipped.take() {
+            skipped_files.push(SkippedFile { file: path.clone(), reason });
+        }
+        if file_analysis.is_barrel {
+            barrel_files.insert(path.clone());
+        }
+        raw_connections.extend(file_analysis.connections);
+        definitions.extend(file_analysis.definitions);
+        ambient_modules.extend(file_analysis.ambient_modules);
+        todos.extend(file_analysis.todos);
+    }
+
+    // Paso 2: Resolver las conexiones, ahora con los `declare module` de todo el proyecto
+    let resolved_connections: Vec<ResolvedConnection> = raw_connections
+        .par_iter() // Paralelizar resolución si es posible/seguro
+        .map(|conn| resolve_connection(conn, &ambient_modules))
+        .collect();
+    let mut resolved_connections = merge_repeated_connections(resolved_connections);
+    flatten_barrels(&mut resolved_connections, &barrel_files);
+
+    // Paso 3: Descubrir tsconfigs y sus project references
+    let ts_projects = tsconfig::discover_ts_projects(&project_files_set);
+
+    // Paso 4: Versiones de los paquetes externos importados y clase de cada import
+    let package_versions = packages::resolve_package_versions(&root_path, &resolved_connections);
+    for conn in &mut resolved_connections {
+        conn.import_class = packages::classify_import(conn, &package_versions);
+    }
+    let package_versions = Arc::new(package_versions);
+
+    // Paso 5: Paquetes del workspace y pipeline de tareas (Turborepo / Nx)
+    let monorepo = Arc::new(monorepo::discover_monorepo(&clean_root, &project_files_set));
+
+    // Ordenar archivos para consistencia
+    files.sort();
+    // Podríamos ordenar definiciones y conexiones si es necesario
+
+    let root_label = root_label(&root_path);
+
+    let search_keys = Arc::new(SearchKeys::build(&root_path, &files, &resolved_connections, &definitions, &todos));
+
+    ProjectAnalysis {
+        root_path,
+        root_label,
+        files,
+        connections: resolved_connections,
+        definitions,
+        todos: Arc::new(todos),
+        ts_projects,
+        archive: None,
+        search_keys,
+        file_stamps: Arc::new(file_stamps),
+        file_metrics: Arc::new(file_metrics),
+        package_versions,
+        monorepo,
+        warnings: Arc::new(warnings),
+        skipped_files: Arc::new(skipped_files),
+    }
+}
+
+/// Analiza en segundo plano; `on_progress` se llama (desde el hilo del análisis) cuando llegan
+/// mensajes nuevos al receptor, p. ej. para repintar la interfaz.
+pub fn start_analysis(
+    path_to_scan: PathBuf,
+    options: AnalysisOptions,
+    scan: ScanOptions,
+    cache_mode: CacheMode,
+    on_progress: impl Fn() + Send + Sync + 'static,
+) -> Receiver<ScanProgress> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let progress = ProgressSender::new(tx, on_progress);
+        // Siempre enviar un resultado final, aunque el propio análisis entre en pánico
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run_analysis(path_to_scan, options, &scan, cache_mode, &progress)))
+            .map_err(|payload| format!("El análisis falló: {}", panic_message(payload.as_ref())));
+        progress.finish(result);
+    });
+
+    rx
+}
+
+// Extrae un .zip/.tar.gz a un directorio temporal y lo analiza como un proyecto normal
+pub fn start_archive_analysis(
+    archive_path: PathBuf,
+    options: AnalysisOptions,
+    scan: ScanOptions,
+    on_progress: impl Fn() + Send + Sync + 'static,
+) -> Receiver<ScanProgress> {
+    let (tx, rx) = mpsc::channel();
 
-        // Enviar el resultado con conexiones resueltas
-        let result = Ok((root_path, files, resolved_connections, definitions));
-        tx.send(result).ok(); // Ignorar error si el receptor ya no existe
+    thread::spawn(move || {
+        let progress = ProgressSender::new(tx, on_progress);
+        let result = archive::extract_archive(&archive_path).and_then(|extracted| {
+            let analysis = panic::catch_unwind(AssertUnwindSafe(|| run_analysis(extracted.project_root.clone(), options, &scan, CacheMode::Disabled, &progress)))
+                .map_err(|payload| format!("El análisis falló: {}", panic_message(payload.as_ref())))?;
+            Ok(ProjectAnalysis {
+                root_label: extracted.label.clone(),
+                archive: Some(Arc::new(extracted)),
+                ..analysis
+            })
+        });
+        progress.finish(result);
     });
 
     rx
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{analyze, connection, definition, TempTree};
+
+    #[test]
+    fn partial_batches_extend_the_search_keys() {
+        let root = PathBuf::from("/proyecto");
+        let (a, b, c) = (root.join("src/A.ts"), root.join("src/b.ts"), root.join("src/c.ts"));
+        let mut analysis = ProjectAnalysis::partial(root.clone(), vec![a.clone(), b.clone(), c.clone()]);
+        analysis.merge_partial(vec![connection(&a, &b)], vec![definition(&a, "Alpha", "Function", 1, true)]);
+        analysis.merge_partial(vec![connection(&b, &c), connection(&c, &a)], vec![definition(&c, "gamma", "Const", 2, false)]);
+
+        let keys = &analysis.search_keys;
+        let full = SearchKeys::build(&root, &analysis.files, &analysis.connections, &analysis.definitions, &analysis.todos);
+        assert_eq!(keys.files, full.files);
+        let sources = |keys: &SearchKeys| keys.connections.iter().map(|k| (k.source.clone(), k.target.clone())).collect::<Vec<_>>();
+        assert_eq!(sources(keys), sources(&full));
+        assert_eq!(sources(keys)[2], ("src/c.ts".to_string(), Some("src/a.ts".to_string())));
+        let symbols = |keys: &SearchKeys| keys.definitions.iter().map(|k| k.symbol.clone()).collect::<Vec<_>>();
+        assert_eq!(symbols(keys), ["alpha", "gamma"]);
+        assert_eq!(symbols(keys), symbols(&full));
+    }
+
+    fn file_set(root: &Path, relative: &[&str]) -> HashSet<PathBuf> {
+        relative.iter().map(|r| root.join(r)).collect()
+    }
+
+    fn resolve(source: &Path, import_str: &str, files: &HashSet<PathBuf>, style: ResolutionStyle) -> Option<PathBuf> {
+        resolve_import_path(source, import_str, files, &[], &[], style)
+    }
+
+    #[test]
+    fn dotted_names_get_the_extension_appended() {
+        let root = PathBuf::from("/proyecto");
+        let files = file_set(&root, &["src/foo.module.css", "src/foo.test.ts", "src/foo.ts", "src/config.prod.js"]);
+        let source = root.join("src/main.ts");
+        for style in [ResolutionStyle::NodeStyle, ResolutionStyle::BundlerStyle] {
+            assert_eq!(resolve(&source, "./foo.module", &files, style), Some(root.join("src/foo.module.css")));
+            assert_eq!(resolve(&source, "./foo.test", &files, style), Some(root.join("src/foo.test.ts")));
+            assert_eq!(resolve(&source, "./foo", &files, style), Some(root.join("src/foo.ts")));
+            assert_eq!(resolve(&source, "./config.prod", &files, style), Some(root.join("src/config.prod.js")));
+        }
+    }
+
+    #[test]
+    fn resolution_prefers_exact_then_extensions_then_index() {
+        let root = PathBuf::from("/proyecto");
+        let files = file_set(&root, &[
+            "src/Button.tsx", "src/Button/index.tsx",
+            "src/utils.js", "src/utils.ts",
+            "src/api.ts", "src/api.d.ts",
+            "src/data", "src/data.json",
+            "src/widgets/index.js", "src/widgets/index.ts",
+        ]);
+        let source = root.join("src/main.ts");
+        let resolved = |import_str: &str, style| resolve(&source, import_str, &files, style).map(|p| p.strip_prefix(&root).unwrap().to_path_buf());
+        for style in [ResolutionStyle::NodeStyle, ResolutionStyle::BundlerStyle] {
+            // El archivo exacto gana siempre; un archivo con extensión, a la carpeta con índice
+            assert_eq!(resolved("./data", style), Some(PathBuf::from("src/data")));
+            assert_eq!(resolved("./Button", style), Some(PathBuf::from("src/Button.tsx")));
+            assert_eq!(resolved("./Button/index", style), Some(PathBuf::from("src/Button/index.tsx")));
+            assert_eq!(resolved("./api", style), Some(PathBuf::from("src/api.ts")));
+            assert_eq!(resolved("./missing", style), None);
+        }
+        assert_eq!(resolved("./utils", ResolutionStyle::NodeStyle), Some(PathBuf::from("src/utils.js")));
+        assert_eq!(resolved("./utils", ResolutionStyle::BundlerStyle), Some(PathBuf::from("src/utils.ts")));
+        assert_eq!(resolved("./widgets", ResolutionStyle::NodeStyle), Some(PathBuf::from("src/widgets/index.js")));
+        assert_eq!(resolved("./widgets", ResolutionStyle::BundlerStyle), Some(PathBuf::from("src/widgets/index.ts")));
+    }
+
+    #[test]
+    fn resolution_style_follows_the_project_config() {
+        let root = PathBuf::from("/proyecto");
+        assert_eq!(ResolutionStyle::for_project(&file_set(&root, &["index.js", "package.json"])), ResolutionStyle::NodeStyle);
+        assert_eq!(ResolutionStyle::for_project(&file_set(&root, &["index.js", "tsconfig.json"])), ResolutionStyle::BundlerStyle);
+        assert_eq!(ResolutionStyle::for_project(&file_set(&root, &["src/main.js", "vite.config.js"])), ResolutionStyle::BundlerStyle);
+    }
+
+    fn connection_from<'a>(analysis: &'a ProjectAnalysis, imported_string: &str) -> &'a ResolvedConnection {
+        analysis.connections.iter()
+            .find(|c| c.imported_string == imported_string)
+            .unwrap_or_else(|| panic!("sin conexión para `{}`", imported_string))
+    }
+
+    #[test]
+    fn query_and_anchor_suffixes_are_stripped() {
+        assert_eq!(strip_query_suffix("./shader.glsl?raw"), "./shader.glsl");
+        assert_eq!(strip_query_suffix("./logo.png?url"), "./logo.png");
+        assert_eq!(strip_query_suffix("./worker.ts?worker&inline"), "./worker.ts");
+        assert_eq!(strip_query_suffix("./icons.svg#sprite"), "./icons.svg");
+        assert_eq!(strip_query_suffix("./plain"), "./plain");
+        // Subpath imports de Node: el `#` inicial es parte del especificador
+        assert_eq!(strip_query_suffix("#internal/db"), "#internal/db");
+    }
+
+    #[test]
+    fn asset_modifiers_resolve_but_keep_the_original_string() {
+        let tree = TempTree::new();
+        tree.file("src/shader.glsl", "void main() {}\n");
+        tree.file("src/logo.png", "");
+        tree.file("src/worker.ts", "export const run = () => 1;\n");
+        tree.file("src/icons.svg", "<svg/>\n");
+        tree.file("src/main.ts", concat!(
+            "import shader from './shader.glsl?raw';\n",
+            "import logo from './logo.png?url';\n",
+            "import Worker from './worker?worker';\n",
+            "import icons from './icons.svg#sprite';\n",
+        ));
+        let analysis = analyze(&tree);
+        for (import_str, target) in [
+            ("./shader.glsl?raw", "src/shader.glsl"),
+            ("./logo.png?url", "src/logo.png"),
+            ("./worker?worker", "src/worker.ts"),
+            ("./icons.svg#sprite", "src/icons.svg"),
+        ] {
+            let conn = connection_from(&analysis, import_str);
+            assert_eq!(conn.resolved_target.as_deref(), Some(tree.path(target).as_path()), "{}", import_str);
+            assert_eq!(conn.import_class, ImportClass::Local);
+        }
+    }
+
+    #[test]
+    fn signatures_keep_generics_and_default_parameters() {
+        let tree = TempTree::new();
+        tree.file("src/users.ts", concat!(
+            "export async function fetchUser<T extends User = User>(id: string, retries = 3): Promise<T> {\n",
+            "  return load(id, retries);\n",
+            "}\n",
+            "export const formatName = <U,>(user: U, separator: string = ' '): string =>\n",
+            "  String(user) + separator;\n",
+            "export class Cache<K, V = unknown> extends Map<K, V> {}\n",
+            "export default function ({ page = 1 }: Props = {}) {}\n",
+        ));
+        tree.file("src/page.ts", "export default async ({ id }: { id: string }) => {\n  return id;\n};\n");
+        let analysis = analyze(&tree);
+        let signatures = |name: &str| analysis.definitions.iter()
+            .filter(|d| d.symbol_name == name)
+            .map(|d| d.signature.as_str())
+            .collect::<Vec<_>>();
+        assert!(signatures("fetchUser").contains(&"fetchUser<T extends User = User>(id: string, retries = 3): Promise<T>"));
+        // Arrow function: los parámetros salen del nodo de la función, no de la primera línea
+        assert!(signatures("formatName").contains(&"formatName<U,>(user: U, separator: string = ' '): string"));
+        assert!(signatures("Cache").contains(&"export class Cache<K, V = unknown> extends Map<K, V>"));
+        let mut defaults = signatures("<default>");
+        defaults.sort();
+        assert_eq!(defaults, ["export default async ({ id }: { id: string })", "export default function ({ page = 1 }: Props = {})"]);
+    }
+
+    #[test]
+    fn ambient_patterns_match_exactly_or_around_one_wildcard() {
+        assert!(matches_ambient_pattern("legacy-lib", "legacy-lib"));
+        assert!(!matches_ambient_pattern("legacy-lib/extra", "legacy-lib"));
+        assert!(matches_ambient_pattern("@legacy/forms", "@legacy/*"));
+        assert!(matches_ambient_pattern("./icons/logo.svg", "*.svg"));
+        assert!(!matches_ambient_pattern("@legacy", "@legacy/*"));
+        // El prefijo y el sufijo no pueden solaparse: `a*a` no cubre `a`
+        assert!(!matches_ambient_pattern("a", "a*a"));
+    }
+
+    #[test]
+    fn unresolved_bare_imports_are_annotated_with_their_ambient_module() {
+        let tree = TempTree::new();
+        tree.file("types/shims.d.ts", concat!(
+            "declare module 'legacy-lib';\n",
+            "declare module '@legacy/*' {\n  const value: unknown;\n  export default value;\n}\n",
+        ));
+        tree.file("src/main.ts", concat!(
+            "import lib from 'legacy-lib';\n",
+            "import forms from '@legacy/forms';\n",
+            "import other from 'other-lib';\n",
+            "import missing from './missing';\n",
+        ));
+        let analysis = analyze(&tree);
+        let ambient = |import_str: &str| connection_from(&analysis, import_str).ambient_module.as_ref().map(|m| (m.pattern.clone(), m.line_number));
+        assert_eq!(ambient("legacy-lib"), Some(("legacy-lib".to_string(), 1)));
+        assert_eq!(ambient("@legacy/forms"), Some(("@legacy/*".to_string(), 2)));
+        assert_eq!(ambient("other-lib"), None);
+        // Solo especificadores "bare": un relativo roto sigue siendo un import roto
+        assert_eq!(ambient("./missing"), None);
+        let declaring = connection_from(&analysis, "legacy-lib").ambient_module.as_ref().unwrap();
+        assert_eq!(declaring.source_file, tree.path("types/shims.d.ts"));
+    }
+
+    #[test]
+    fn template_literal_imports_are_literal_patterns_or_skipped() {
+        let tree = TempTree::new();
+        tree.file("src/pages/home.js", "export default 1;\n");
+        tree.file("src/locales/en.json", "{}");
+        tree.file("src/locales/es.json", "{}");
+        tree.file("src/locales/nested/fr.json", "{}");
+        tree.file("src/locales/notes.txt", "");
+        let main = tree.file("src/main.js", concat!(
+            "const home = import(`./pages/home.js`);\n",
+            "const messages = import(`./locales/${lang}.json`);\n",
+            "const anything = import(`${base}`);\n",
+            "const plugin = require(`${dir}/${name}`);\n",
+        ));
+        let analysis = analyze(&tree);
+        let from_main: Vec<&ResolvedConnection> = analysis.connections.iter().filter(|c| c.source_file == main).collect();
+        assert_eq!(from_main.len(), 2, "las expresiones totalmente dinámicas no generan conexión");
+
+        let home = connection_from(&analysis, "./pages/home.js");
+        assert!(!home.dynamic_pattern);
+        assert_eq!(home.resolved_target, Some(tree.path("src/pages/home.js")));
+
+        let locales = connection_from(&analysis, "./locales/*.json");
+        assert!(locales.dynamic_pattern);
+        assert_eq!(locales.line_number, 2);
+        // `*` no cruza carpetas ni cambia la extensión
+        assert_eq!(locales.pattern_matches, [tree.path("src/locales/en.json"), tree.path("src/locales/es.json")]);
+    }
+
+    #[test]
+    fn import_attributes_are_recorded_for_with_and_assert() {
+        let tree = TempTree::new();
+        tree.file("src/config.json", "{ \"debug\": true }\n");
+        tree.file("src/legacy.json", "{}\n");
+        tree.file("src/module.wasm", "");
+        tree.file("src/main.ts", concat!(
+            "import config from './config.json' with { type: 'json' };\n",
+            "import legacy from './legacy.json' assert { type: \"json\" };\n",
+            "import init from './module';\n",
+        ));
+        let analysis = analyze(&tree);
+        for (import_str, target, attribute) in [
+            ("./config.json", "src/config.json", Some("json")),
+            ("./legacy.json", "src/legacy.json", Some("json")),
+            ("./module", "src/module.wasm", None),
+        ] {
+            let conn = connection_from(&analysis, import_str);
+            assert_eq!(conn.resolved_target, Some(tree.path(target)), "{}", import_str);
+            assert_eq!(conn.import_attribute.as_deref(), attribute, "{}", import_str);
+        }
+    }
+
+    #[test]
+    fn commonjs_object_exports_define_one_entry_per_key() {
+        let tree = TempTree::new();
+        let file = tree.file("lib/service.js", concat!(
+            "const helper = require('./helper');\n",
+            "function start() {}\n",
+            "module.exports = {\n",
+            "  start,\n",
+            "  stop: () => {},\n",
+            "  'with-dash': 1,\n",
+            "  restart() { return start(); },\n",
+            "  nested: { inner: 1 },\n",
+            "  [computedName]: 2,\n",
+            "  ...helper,\n",
+            "};\n",
+            "module.exports.extra = 1;\n",
+            "exports.legacy = function () {};\n",
+        ));
+        let definitions = analyze_file_content(&file, AnalysisOptions::default()).definitions;
+        let exports: Vec<(&str, usize)> = definitions.iter()
+            .filter(|d| d.kind == "CJSExport")
+            .map(|d| (d.symbol_name.as_str(), d.line_number))
+            .collect();
+        // Ni la clave calculada ni el spread tienen un nombre conocido; `inner` no es de primer nivel
+        assert_eq!(exports, [("start", 4), ("stop", 5), ("with-dash", 6), ("restart", 7), ("nested", 8), ("extra", 12), ("legacy", 13)]);
+        assert!(definitions.iter().filter(|d| d.kind == "CJSExport").all(|d| d.exported_as.as_deref() == Some(d.symbol_name.as_str())));
+
+        let whole = tree.file("lib/index.js", "module.exports = require('./service');\n");
+        let definitions = analyze_file_content(&whole, AnalysisOptions::default()).definitions;
+        assert_eq!(definitions.len(), 1);
+        assert_eq!((definitions[0].symbol_name.as_str(), definitions[0].exported_as.as_deref()), ("<module>", Some("default")));
+    }
+
+    #[test]
+    fn connections_record_the_line_of_their_statement() {
+        let tree = crate::test_support::import_lines_tree();
+        let analysis = analyze(&tree);
+        let mut lines: Vec<(&str, usize, CapturePattern)> = analysis.connections.iter()
+            .map(|c| (c.imported_string.as_str(), c.line_number, c.kind))
+            .collect();
+        lines.sort_by_key(|(import_str, line, _)| (*import_str, *line));
+        assert_eq!(lines, [
+            ("./a", 3, CapturePattern::Import),
+            ("./b", 4, CapturePattern::Import),
+            ("./c", 8, CapturePattern::ExportFrom),
+            ("./d", 10, CapturePattern::DynamicImport),
+            ("./e", 12, CapturePattern::Require),
+            ("./f", 12, CapturePattern::SideEffect),
+        ]);
+    }
+
+    #[test]
+    fn import_and_re_exports_of_one_module_count_once_per_statement() {
+        let tree = TempTree::new();
+        let shared = tree.file("src/shared.ts", "export const x = 1;\n");
+        let main = tree.file("src/main.ts", concat!(
+            "import { x } from './shared';\n",
+            "export { x } from './shared';\n",
+            "export * as shared from './shared';\n",
+            "export const y = x;\n",
+        ));
+        // Cada sentencia se detecta una vez, aunque las consultas de imports y exports se solapen
+        let detected = analyze_file_content(&main, AnalysisOptions::default()).connections;
+        let statements: Vec<(CapturePattern, usize)> = detected.iter().map(|c| (c.pattern, c.line_number)).collect();
+        assert_eq!(statements, [(CapturePattern::Import, 1), (CapturePattern::ExportFrom, 2), (CapturePattern::ExportFrom, 3)]);
+
+        // Tras resolver queda una sola dependencia con sus tres sentencias
+        let analysis = analyze(&tree);
+        assert_eq!(analysis.connections.len(), 1);
+        let conn = &analysis.connections[0];
+        assert_eq!((conn.line_number, conn.occurrences), (1, 3));
+        assert_eq!(conn.resolved_target.as_ref(), Some(&shared));
+        assert_eq!(crate::reporting::compute_fan_in(&analysis.connections).get(&shared), Some(&1));
+    }
+
+    fn finished(progress: Receiver<ScanProgress>) -> Option<AnalysisResult> {
+        progress.iter().find_map(|message| match message {
+            ScanProgress::Finished(result) => Some(result),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn a_panicking_file_becomes_a_warning() {
+        let tree = TempTree::new();
+        let good = tree.file("src/good.ts", "export function ok() {}\n");
+        let bad = tree.file(&format!("src/{}.ts", INJECTED_PANIC_NAME), "export const x = 1;\n");
+        let progress = start_analysis(tree.root.clone(), AnalysisOptions::default(), ScanOptions::default(), CacheMode::Disabled, || {});
+        let analysis = finished(progress).expect("sin resultado final").expect("el análisis no debe fallar");
+        assert_eq!(analysis.warnings.len(), 1);
+        assert_eq!(analysis.warnings[0].file, bad);
+        assert!(analysis.warnings[0].message.starts_with("pánico al analizar: pánico inyectado en"));
+        assert!(analysis.definitions.iter().any(|d| d.source_file == good && d.symbol_name == "ok"));
+    }
+
+    #[test]
+    fn a_panic_in_the_scan_itself_still_sends_a_result() {
+        let tree = TempTree::new();
+        let root = tree.path(INJECTED_PANIC_NAME);
+        std::fs::create_dir_all(&root).unwrap();
+        let progress = start_analysis(root, AnalysisOptions::default(), ScanOptions::default(), CacheMode::Disabled, || {});
+        let error = finished(progress).expect("sin resultado final").unwrap_err();
+        assert!(error.starts_with("El análisis falló: pánico inyectado en"), "{}", error);
+    }
+
+    #[test]
+    fn require_context_and_import_meta_glob_expand_against_the_project() {
+        let tree = crate::test_support::glob_import_tree();
+        let analysis = analyze(&tree);
+        let matches = |line: usize| {
+            let conn = analysis.connections.iter().find(|c| c.glob.is_some() && c.line_number == line).unwrap();
+            assert_eq!(conn.kind, CapturePattern::Glob);
+            conn.pattern_matches.iter().map(|p| slash_path(p.strip_prefix(&tree.root).unwrap())).collect::<Vec<_>>()
+        };
+        // Recursivo y con la bandera `i` del filtro
+        assert_eq!(matches(1), ["src/modules/E.JS", "src/modules/a.js", "src/modules/nested/b.js", "src/modules/nested/deep/c.js"]);
+        assert_eq!(matches(2), ["src/modules/a.js"]);
+        // `**` incluye la propia carpeta
+        assert_eq!(matches(3), ["src/pages/blog/[slug].tsx", "src/pages/blog/skip.tsx", "src/pages/index.tsx"]);
+        assert_eq!(matches(4), ["src/pages/about.vue", "src/pages/blog/[slug].tsx", "src/pages/index.tsx"]);
+        let views = analysis.connections.iter().find(|c| c.line_number == 4).unwrap();
+        assert_eq!(views.imported_string, "./pages/**/*.{tsx,vue}, !./pages/blog/skip.tsx");
+    }
+
+    #[test]
+    fn precompiled_script_queries_all_compile() {
+        for ext in ["js", "ts", "tsx"] {
+            let queries = script_queries(ext).unwrap();
+            for (name, query) in [("imports", &queries.imports), ("globs", &queries.globs), ("definitions", &queries.definitions)] {
+                assert!(query.is_ok(), "{} {}: {:?}", ext, name, query.as_ref().err());
+            }
+            // `declare module` solo existe en la gramática de TypeScript
+            assert_eq!(queries.ambient_modules.is_ok(), ext != "js", "{}", ext);
+        }
+    }
+
+    // Coste de compilar las consultas y crear el parser en cada archivo (como antes) frente a las
+    // consultas precompiladas y el parser por hilo. Depende de la máquina: solo bajo demanda,
+    // `cargo test --release -- --ignored precompiled_queries_beat_per_file_compilation --nocapture`
+    #[test]
+    #[ignore]
+    fn precompiled_queries_beat_per_file_compilation() {
+        let tree = TempTree::new();
+        let files: Vec<PathBuf> = (0..200)
+            .map(|i| tree.file(&format!("src/module_{}.ts", i), &format!(
+                "import {{ helper }} from './module_{}';\nexport function run{}(value: number): number {{ return helper(value) + {}; }}\nexport class Service{} {{}}\n",
+                (i + 1) % 200, i, i, i,
+            )))
+            .collect();
+        let language = script_language("ts").unwrap();
+
+        let started = Instant::now();
+        for path in &files {
+            let content = fs::read_to_string(path).unwrap();
+            let mut parser = Parser::new();
+            parser.set_language(&language).unwrap();
+            let syntax = parser.parse(&content, None).unwrap();
+            for source in [IMPORT_QUERY, GLOB_IMPORT_QUERY, TS_DEFINITION_QUERY, AMBIENT_MODULE_QUERY] {
+                let query = Query::new(&language, source).unwrap();
+                QueryCursor::new().matches(&query, syntax.root_node(), content.as_bytes()).count();
+            }
+        }
+        let per_file = started.elapsed();
+
+        // Hace más trabajo que el bucle anterior (firmas, docs, TODOs...): la comparación es prudente
+        let started = Instant::now();
+        for path in &files {
+            analyze_file_content(path, AnalysisOptions::default());
+        }
+        let precompiled = started.elapsed();
+
+        println!("{} archivos: {:?} compilando por archivo, {:?} con consultas precompiladas", files.len(), per_file, precompiled);
+        assert!(precompiled < per_file, "{:?} >= {:?}", precompiled, per_file);
+    }
+}
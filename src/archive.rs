@@ -0,0 +1,330 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::read::GzDecoder;
+
+// --- Extracción de archivos comprimidos (zip, tar.gz) a un directorio temporal ---
+
+// Límite de bytes descomprimidos para evitar bombas de compresión
+pub const MAX_EXTRACTED_BYTES: u64 = 512 * 1024 * 1024;
+
+static EXTRACTION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+pub fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Directorio temporal gestionado: se borra al soltarse (nuevo análisis o cierre de la app).
+#[derive(Debug)]
+pub struct ExtractedArchive {
+    pub temp_dir: PathBuf,
+    pub project_root: PathBuf, // temp_dir o su única carpeta de primer nivel
+    pub label: String,         // Nombre del archivo comprimido, usado como etiqueta de la raíz
+}
+
+impl Drop for ExtractedArchive {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.temp_dir) {
+            eprintln!("Error al borrar el directorio temporal {}: {}", self.temp_dir.display(), e);
+        }
+    }
+}
+
+// Ruta relativa segura dentro del destino (protección contra zip-slip)
+fn sanitize_entry_path(entry_path: &Path) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in entry_path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!safe.as_os_str().is_empty()).then_some(safe)
+}
+
+// Copia como mucho `remaining` bytes; error si la entrada excede el límite
+fn copy_limited(reader: &mut impl Read, dest: &Path, remaining: &mut u64, limit: u64) -> Result<(), String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("No se pudo crear {}: {}", parent.display(), e))?;
+    }
+    let mut out = File::create(dest).map_err(|e| format!("No se pudo crear {}: {}", dest.display(), e))?;
+    let written = io::copy(&mut reader.take(*remaining + 1), &mut out)
+        .map_err(|e| format!("Error al extraer {}: {}", dest.display(), e))?;
+    if written > *remaining {
+        return Err(format!(
+            "El archivo supera el límite de {} MB descomprimidos",
+            limit / (1024 * 1024)
+        ));
+    }
+    *remaining -= written;
+    Ok(())
+}
+
+// Modo unix de un enlace simbólico (S_IFLNK)
+const SYMLINK_MODE: u32 = 0o120000;
+
+fn extract_zip(archive_path: &Path, dest: &Path, limit: u64) -> Result<(), String> {
+    let file = File::open(archive_path).map_err(|e| format!("No se pudo abrir el zip: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| format!("Zip inválido: {}", e))?;
+    let mut remaining = limit;
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| format!("Entrada de zip inválida: {}", e))?;
+        let relative = entry
+            .enclosed_name()
+            .and_then(sanitize_entry_path)
+            .ok_or_else(|| format!("Ruta insegura en el zip: {}", entry.name()))?;
+        let target = dest.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&target).map_err(|e| format!("No se pudo crear {}: {}", target.display(), e))?;
+        } else if entry.unix_mode().is_some_and(|mode| mode & 0o170000 == SYMLINK_MODE) {
+            continue; // Como en tar: los enlaces se ignoran (su contenido es la ruta de destino)
+        } else {
+            copy_limited(&mut entry, &target, &mut remaining, limit)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar_gz(archive_path: &Path, dest: &Path, limit: u64) -> Result<(), String> {
+    let file = File::open(archive_path).map_err(|e| format!("No se pudo abrir el tar.gz: {}", e))?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+    let mut remaining = limit;
+
+    let entries = tar.entries().map_err(|e| format!("tar.gz inválido: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Entrada de tar inválida: {}", e))?;
+        let entry_path = entry.path().map_err(|e| format!("Ruta inválida en el tar: {}", e))?.into_owned();
+        let relative = sanitize_entry_path(&entry_path)
+            .ok_or_else(|| format!("Ruta insegura en el tar: {}", entry_path.display()))?;
+        let target = dest.join(relative);
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => {
+                fs::create_dir_all(&target).map_err(|e| format!("No se pudo crear {}: {}", target.display(), e))?;
+            }
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                copy_limited(&mut entry, &target, &mut remaining, limit)?;
+            }
+            _ => {} // Enlaces y entradas especiales se ignoran
+        }
+    }
+    Ok(())
+}
+
+// Si el archivo contiene una única carpeta de primer nivel, analizar desde ella
+fn single_top_level_dir(dir: &Path) -> Option<PathBuf> {
+    let mut entries = fs::read_dir(dir).ok()?.filter_map(|e| e.ok());
+    let first = entries.next()?;
+    if entries.next().is_some() || !first.path().is_dir() {
+        return None;
+    }
+    Some(first.path())
+}
+
+pub fn extract_archive(archive_path: &Path) -> Result<ExtractedArchive, String> {
+    let temp_dir = std::env::temp_dir().join(format!(
+        "context-lens-{}-{}",
+        std::process::id(),
+        EXTRACTION_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    extract_archive_into(archive_path, temp_dir, MAX_EXTRACTED_BYTES)
+}
+
+fn extract_archive_into(archive_path: &Path, temp_dir: PathBuf, limit: u64) -> Result<ExtractedArchive, String> {
+    let kind = archive_kind(archive_path)
+        .ok_or_else(|| format!("Formato de archivo no soportado: {}", archive_path.display()))?;
+    let label = archive_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("archivo")
+        .to_string();
+
+    fs::create_dir_all(&temp_dir).map_err(|e| format!("No se pudo crear el directorio temporal: {}", e))?;
+
+    // A partir de aquí el guard borra el directorio también en caso de error
+    let mut extracted = ExtractedArchive { temp_dir: temp_dir.clone(), project_root: temp_dir.clone(), label };
+    match kind {
+        ArchiveKind::Zip => extract_zip(archive_path, &temp_dir, limit)?,
+        ArchiveKind::TarGz => extract_tar_gz(archive_path, &temp_dir, limit)?,
+    }
+    if let Some(inner) = single_top_level_dir(&temp_dir) {
+        extracted.project_root = inner;
+    }
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use zip::write::FileOptions;
+
+    use super::*;
+    use crate::test_support::TempTree;
+
+    enum TarEntry<'a> {
+        File(&'a str, &'a [u8]),
+        Symlink(&'a str, &'a str),
+    }
+
+    // tar::Builder rechaza `..` y rutas absolutas: el nombre se escribe a mano en la cabecera
+    fn write_tar_gz(path: &Path, entries: &[TarEntry]) {
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(path).unwrap(), Compression::default()));
+        for entry in entries {
+            let mut header = tar::Header::new_gnu();
+            let (name, data, kind): (&str, &[u8], _) = match entry {
+                TarEntry::File(name, data) => (name, data, tar::EntryType::Regular),
+                TarEntry::Symlink(name, target) => {
+                    header.set_link_name(target).unwrap();
+                    (name, &[], tar::EntryType::Symlink)
+                }
+            };
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(kind);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])], symlinks: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        for (name, target) in symlinks {
+            zip.add_symlink(*name, *target, FileOptions::default()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn entry_paths_must_stay_inside_the_destination() {
+        assert_eq!(sanitize_entry_path(Path::new("./src/a.ts")), Some(PathBuf::from("src/a.ts")));
+        assert_eq!(sanitize_entry_path(Path::new("../evil.sh")), None);
+        assert_eq!(sanitize_entry_path(Path::new("src/../../evil.sh")), None);
+        assert_eq!(sanitize_entry_path(Path::new("/etc/passwd")), None);
+        assert_eq!(sanitize_entry_path(Path::new(".")), None);
+    }
+
+    #[test]
+    fn copy_limited_rejects_entries_over_the_cap() {
+        let tree = TempTree::new();
+        let mut remaining = 10;
+        copy_limited(&mut &b"0123456789"[..], &tree.path("exact.txt"), &mut remaining, 10).unwrap();
+        assert_eq!(remaining, 0);
+        let mut remaining = 10;
+        assert!(copy_limited(&mut &b"0123456789X"[..], &tree.path("over.txt"), &mut remaining, 10).is_err());
+    }
+
+    #[test]
+    fn tar_gz_extracts_and_descends_into_single_folder() {
+        let tree = TempTree::new();
+        let archive = tree.path("snapshot.tar.gz");
+        write_tar_gz(&archive, &[TarEntry::File("proyecto/src/a.ts", b"export const a = 1;\n")]);
+        let extracted = extract_archive_into(&archive, tree.path("out"), 1024).unwrap();
+        assert_eq!(extracted.label, "snapshot.tar.gz");
+        assert_eq!(extracted.project_root, tree.path("out/proyecto"));
+        assert_eq!(fs::read_to_string(extracted.project_root.join("src/a.ts")).unwrap(), "export const a = 1;\n");
+        drop(extracted);
+        assert!(!tree.path("out").exists(), "Drop borra el directorio temporal");
+    }
+
+    #[test]
+    fn tar_gz_with_parent_or_absolute_paths_is_rejected_and_cleaned_up() {
+        let tree = TempTree::new();
+        for (i, name) in ["../escape.txt", "/tmp/absolute.txt"].iter().enumerate() {
+            let archive = tree.path(&format!("evil{}.tgz", i));
+            write_tar_gz(&archive, &[TarEntry::File("ok.txt", b"ok"), TarEntry::File(name, b"pwned")]);
+            let out = tree.path(&format!("out{}", i));
+            let error = extract_archive_into(&archive, out.clone(), 1024).unwrap_err();
+            assert!(error.contains("Ruta insegura"), "{}", error);
+            assert!(!out.exists());
+        }
+        assert!(!tree.root.parent().unwrap().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn tar_gz_symlinks_are_skipped() {
+        let tree = TempTree::new();
+        let archive = tree.path("links.tar.gz");
+        write_tar_gz(&archive, &[TarEntry::File("a.txt", b"a"), TarEntry::Symlink("passwd", "/etc/passwd")]);
+        let extracted = extract_archive_into(&archive, tree.path("out"), 1024).unwrap();
+        assert!(extracted.project_root.join("a.txt").is_file());
+        assert!(fs::symlink_metadata(extracted.project_root.join("passwd")).is_err());
+    }
+
+    #[test]
+    fn over_cap_archive_fails_and_removes_the_temp_dir() {
+        let tree = TempTree::new();
+        let archive = tree.path("big.tar.gz");
+        write_tar_gz(&archive, &[TarEntry::File("a.txt", &[b'x'; 600]), TarEntry::File("b.txt", &[b'y'; 600])]);
+        let out = tree.path("out");
+        let error = extract_archive_into(&archive, out.clone(), 1000).unwrap_err();
+        assert!(error.contains("supera el límite"), "{}", error);
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn zip_extracts_files_and_rejects_slip_paths() {
+        let tree = TempTree::new();
+        let archive = tree.path("ok.zip");
+        write_zip(&archive, &[("src/a.ts", b"a"), ("README.md", b"# r")], &[]);
+        let extracted = extract_archive_into(&archive, tree.path("out"), 1024).unwrap();
+        assert_eq!(extracted.project_root, tree.path("out")); // Dos entradas de primer nivel
+        assert!(extracted.project_root.join("src/a.ts").is_file());
+
+        for (i, name) in ["../escape.txt", "/abs.txt", "a/../../escape.txt"].iter().enumerate() {
+            let archive = tree.path(&format!("evil{}.zip", i));
+            write_zip(&archive, &[(name, b"pwned")], &[]);
+            let out = tree.path(&format!("evil_out{}", i));
+            let error = extract_archive_into(&archive, out.clone(), 1024).unwrap_err();
+            assert!(error.contains("Ruta insegura"), "{}", error);
+            assert!(!out.exists());
+        }
+    }
+
+    #[test]
+    fn zip_symlinks_are_skipped_and_cap_applies() {
+        let tree = TempTree::new();
+        let archive = tree.path("links.zip");
+        write_zip(&archive, &[("a.txt", b"a")], &[("passwd", "/etc/passwd")]);
+        let extracted = extract_archive_into(&archive, tree.path("out"), 1024).unwrap();
+        assert!(extracted.project_root.join("a.txt").is_file());
+        assert!(!extracted.project_root.join("passwd").exists());
+
+        let archive = tree.path("big.zip");
+        write_zip(&archive, &[("a.txt", &[b'x'; 2000])], &[]);
+        assert!(extract_archive_into(&archive, tree.path("big_out"), 1000).is_err());
+        assert!(!tree.path("big_out").exists());
+    }
+
+    #[test]
+    fn archive_kind_by_extension() {
+        assert_eq!(archive_kind(Path::new("a/B.ZIP")), Some(ArchiveKind::Zip));
+        assert_eq!(archive_kind(Path::new("x.tgz")), Some(ArchiveKind::TarGz));
+        assert_eq!(archive_kind(Path::new("x.tar.gz")), Some(ArchiveKind::TarGz));
+        assert_eq!(archive_kind(Path::new("x.tar")), None);
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Node;
+
+use crate::analysis;
+use crate::languages::{for_each_match, node_text, parse};
+
+// --- Alias de bundlers: `resolve.alias` de vite.config.* y webpack.config.* ---
+// Lectura best-effort: solo alias cuyo destino es un literal o `path.resolve(__dirname, '...')`.
+
+#[derive(Clone, Debug)]
+pub struct BundlerAlias {
+    pub find: String,     // `@`, `utils`, `~/` (webpack `xyz$` = solo coincidencia exacta)
+    pub exact: bool,
+    pub replacement: PathBuf, // Absoluto, relativo a la carpeta del config
+}
+
+#[derive(Clone, Debug)]
+pub struct BundlerAliases {
+    pub config_path: PathBuf,
+    pub aliases: Vec<BundlerAlias>,
+}
+
+const ALIAS_QUERY: &str = r#"
+    (pair key: [(property_identifier) (string)] @key value: [(object) (array)] @value)
+"#;
+
+fn is_bundler_config(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else { return false };
+    let Some((stem, ext)) = name.rsplit_once('.') else { return false };
+    matches!(stem, "vite.config" | "webpack.config") && matches!(ext, "js" | "mjs" | "cjs" | "ts" | "mts" | "cts")
+}
+
+fn string_value(node: Node, content: &str) -> Option<String> {
+    match node.kind() {
+        "string" => Some(node_text(node, content).trim_matches(['"', '\'']).to_string()),
+        // Plantilla sin interpolaciones
+        "template_string" if !node_text(node, content).contains("${") => Some(node_text(node, content).trim_matches('`').to_string()),
+        _ => None,
+    }
+}
+
+// `'./src'`, `path.resolve(__dirname, 'src')`, `join(process.cwd(), 'src')`,
+// `fileURLToPath(new URL('./src', import.meta.url))`. Cualquier otra expresión: None.
+fn replacement_path(node: Node, content: &str, config_dir: &Path) -> Option<PathBuf> {
+    if let Some(value) = string_value(node, content) {
+        // En Vite `/src` es relativo a la raíz del proyecto (la carpeta del config)
+        return Some(config_dir.join(value.trim_start_matches('/')).clean());
+    }
+    match node.kind() {
+        "call_expression" => {
+            let callee = node_text(node.child_by_field_name("function")?, content);
+            let arguments = node.child_by_field_name("arguments")?;
+            let mut cursor = arguments.walk();
+            let arguments: Vec<Node> = arguments.named_children(&mut cursor).collect();
+            match callee.rsplit('.').next()? {
+                "resolve" | "join" => {
+                    let mut path = config_dir.to_path_buf();
+                    for argument in arguments {
+                        match node_text(argument, content) {
+                            "__dirname" | "process.cwd()" | "import.meta.dirname" => {}
+                            _ => path.push(string_value(argument, content)?.as_str()),
+                        }
+                    }
+                    Some(path.clean())
+                }
+                "fileURLToPath" => replacement_path(*arguments.first()?, content, config_dir),
+                _ => None,
+            }
+        }
+        // `new URL('./src', import.meta.url)`
+        "new_expression" => {
+            let arguments = node.child_by_field_name("arguments")?;
+            let first = arguments.named_child(0)?;
+            replacement_path(first, content, config_dir)
+        }
+        _ => None,
+    }
+}
+
+fn alias(find: String, replacement: PathBuf) -> BundlerAlias {
+    match find.strip_suffix('$') {
+        Some(exact) => BundlerAlias { find: exact.to_string(), exact: true, replacement },
+        None => BundlerAlias { find, exact: false, replacement },
+    }
+}
+
+fn pair_parts<'t>(pair: Node<'t>, content: &str) -> Option<(String, Node<'t>)> {
+    let key = pair.child_by_field_name("key")?;
+    let key = match key.kind() {
+        "property_identifier" => node_text(key, content).to_string(),
+        _ => string_value(key, content)?,
+    };
+    Some((key, pair.child_by_field_name("value")?))
+}
+
+fn parse_bundler_aliases(config_path: &Path) -> Option<BundlerAliases> {
+    let content = fs::read_to_string(config_path).ok()?;
+    let ext = config_path.extension()?.to_str()?;
+    let language = analysis::script_language(if matches!(ext, "ts" | "mts" | "cts") { "ts" } else { "js" })?;
+    let tree = parse(&language, config_path, &content)?;
+    let config_dir = config_path.parent()?;
+
+    let mut aliases = Vec::new();
+    for_each_match(&language, ALIAS_QUERY, &tree, config_path, &content, |capture| {
+        let (Some(key), Some(value)) = (capture("key"), capture("value")) else { return };
+        if node_text(key, &content).trim_matches(['"', '\'']) != "alias" {
+            return;
+        }
+        let mut cursor = value.walk();
+        for entry in value.named_children(&mut cursor) {
+            match entry.kind() {
+                // Webpack y Vite: `{ '@': path.resolve(__dirname, 'src') }`
+                "pair" => {
+                    if let Some((find, target)) = pair_parts(entry, &content)
+                        && let Some(replacement) = replacement_path(target, &content, config_dir)
+                    {
+                        aliases.push(alias(find, replacement));
+                    }
+                }
+                // Vite: `[{ find: '@', replacement: '/src' }]` (los `find` con regex se ignoran)
+                "object" => {
+                    let mut inner = entry.walk();
+                    let pairs: Vec<(String, Node)> = entry.named_children(&mut inner).filter_map(|p| pair_parts(p, &content)).collect();
+                    let field = |name: &str| pairs.iter().find(|(key, _)| key == name).map(|(_, node)| *node);
+                    if let (Some(find), Some(target)) = (field("find"), field("replacement"))
+                        && let Some(find) = string_value(find, &content)
+                        && let Some(replacement) = replacement_path(target, &content, config_dir)
+                    {
+                        aliases.push(BundlerAlias { find, exact: false, replacement });
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    (!aliases.is_empty()).then(|| BundlerAliases { config_path: config_path.to_path_buf(), aliases })
+}
+
+/// Alias estáticos de cada vite.config.* / webpack.config.* del proyecto.
+pub fn discover_bundler_aliases(project_files: &HashSet<PathBuf>) -> Vec<BundlerAliases> {
+    let mut aliases: Vec<BundlerAliases> = project_files
+        .iter()
+        .filter(|p| is_bundler_config(p))
+        .filter_map(|p| parse_bundler_aliases(p))
+        .collect();
+    aliases.sort_by(|a, b| a.config_path.cmp(&b.config_path));
+    aliases
+}
+
+/// Configuración que aplica a `file`: la del config de bundler más profundo que lo contiene.
+pub fn aliases_for_file<'a>(file: &Path, aliases: &'a [BundlerAliases]) -> Option<&'a BundlerAliases> {
+    aliases
+        .iter()
+        .filter(|a| a.config_path.parent().is_some_and(|dir| file.starts_with(dir)))
+        .max_by_key(|a| a.config_path.components().count())
+}
+
+impl BundlerAliases {
+    /// Rutas base candidatas para un especificador "bare", en el orden en que se declararon los
+    /// alias. `@` cubre `@` y `@/x`, pero no `@scope/pkg`. Sin extensión, como `PathAliases`.
+    pub fn candidates(&self, import_str: &str) -> Vec<PathBuf> {
+        self.aliases
+            .iter()
+            .filter_map(|alias| {
+                if import_str == alias.find {
+                    return Some(alias.replacement.clone());
+                }
+                if alias.exact {
+                    return None;
+                }
+                let rest = import_str.strip_prefix(&alias.find)?;
+                let rest = if alias.find.ends_with('/') { rest } else { rest.strip_prefix('/')? };
+                Some(alias.replacement.join(rest).clean())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::ResolvedConnection;
+    use crate::test_support::{analyze, TempTree};
+
+    fn target_of<'a>(connections: &'a [ResolvedConnection], source: &Path, import_str: &str) -> Option<&'a PathBuf> {
+        connections.iter()
+            .find(|c| c.source_file == source && c.imported_string == import_str)
+            .and_then(|c| c.resolved_target.as_ref())
+    }
+
+    #[test]
+    fn vite_and_webpack_aliases_resolve_their_imports() {
+        let tree = TempTree::new();
+        tree.file("web/vite.config.ts", concat!(
+            "import { fileURLToPath } from 'node:url';\n",
+            "import path from 'node:path';\n",
+            "export default defineConfig({\n",
+            "  resolve: {\n",
+            "    alias: {\n",
+            "      '@': fileURLToPath(new URL('./src', import.meta.url)),\n",
+            "      utils: path.resolve(__dirname, 'src/utils'),\n",
+            "      [dynamicName]: path.resolve(__dirname, 'ignored'),\n",
+            "    },\n",
+            "  },\n",
+            "});\n",
+        ));
+        let button = tree.file("web/src/components/Button.tsx", "export const Button = 1;\n");
+        let format = tree.file("web/src/utils/format.ts", "export const format = 1;\n");
+        let web_main = tree.file("web/src/main.ts", "import { Button } from '@/components/Button';\nimport { format } from 'utils/format';\n");
+
+        tree.file("legacy/webpack.config.js", concat!(
+            "const path = require('path');\n",
+            "module.exports = {\n",
+            "  resolve: {\n",
+            "    alias: {\n",
+            "      'lib$': path.resolve(__dirname, 'lib/index.js'),\n",
+            "      '~': path.join(__dirname, 'assets'),\n",
+            "    },\n",
+            "  },\n",
+            "};\n",
+        ));
+        let lib = tree.file("legacy/lib/index.js", "module.exports = {};\n");
+        tree.file("legacy/lib/extra.js", "module.exports = {};\n");
+        let logo = tree.file("legacy/assets/logo.js", "module.exports = {};\n");
+        let legacy_main = tree.file("legacy/app.js", "require('lib');\nrequire('lib/extra');\nrequire('~/logo');\n");
+
+        let analysis = analyze(&tree);
+        let connections = &analysis.connections;
+        assert_eq!(target_of(connections, &web_main, "@/components/Button"), Some(&button));
+        assert_eq!(target_of(connections, &web_main, "utils/format"), Some(&format));
+        assert_eq!(target_of(connections, &legacy_main, "lib"), Some(&lib));
+        assert_eq!(target_of(connections, &legacy_main, "~/logo"), Some(&logo));
+        // `lib$` solo coincide exactamente
+        assert_eq!(target_of(connections, &legacy_main, "lib/extra"), None);
+    }
+
+    #[test]
+    fn vite_alias_arrays_keep_string_finds_and_skip_computed_entries() {
+        let tree = TempTree::new();
+        let config = tree.file("vite.config.js", concat!(
+            "export default {\n",
+            "  resolve: {\n",
+            "    alias: [\n",
+            "      { find: '@', replacement: '/src' },\n",
+            "      { find: /^~(.*)$/, replacement: '/assets/$1' },\n",
+            "      { find: 'shared', replacement: sharedDir },\n",
+            "    ],\n",
+            "  },\n",
+            "};\n",
+        ));
+        let files: HashSet<PathBuf> = [config.clone()].into_iter().collect();
+        let discovered = discover_bundler_aliases(&files);
+        assert_eq!(discovered.len(), 1);
+        let aliases = &discovered[0];
+        assert_eq!(aliases.aliases.len(), 1);
+        assert_eq!(aliases.candidates("@/pages/home"), [tree.path("src/pages/home")]);
+        assert!(aliases.candidates("@scope/pkg").is_empty());
+        assert_eq!(aliases_for_file(&tree.path("src/main.ts"), &discovered).map(|a| &a.config_path), Some(&config));
+    }
+}
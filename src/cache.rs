@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{AnalysisOptions, FileAnalysis, FileStamp};
+use crate::output;
+
+// --- Caché en disco del análisis por archivo (re-análisis incremental) ---
+// Un JSON por raíz analizada en la carpeta de caché de la plataforma. Cada archivo guarda su
+// huella (tamaño, mtime y hash del contenido) junto con lo extraído de él; al re-analizar solo
+// se parsean los archivos cuya huella cambió. La resolución de imports se repite siempre: es
+// barata y depende del conjunto completo de archivos.
+
+// Subir al cambiar lo que extraen los analizadores o la forma de `FileAnalysis`
+const CACHE_FORMAT: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    Reuse,    // Leer la caché y actualizarla
+    Refresh,  // Parsear todo y reescribir la caché ("Reescanear (completo)")
+    Disabled, // Sin caché: archivos comprimidos extraídos a un directorio temporal
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub stamp: FileStamp,
+    pub hash: u64,
+    pub analysis: FileAnalysis,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnalysisCache {
+    format: u32,
+    version: String,
+    options: (bool, bool, u64), // Opciones que cambian lo extraído de cada archivo
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+// `respect_gitignore` solo decide qué archivos entran, no lo que se extrae de cada uno
+fn options_key(options: AnalysisOptions) -> (bool, bool, u64) {
+    (options.include_class_members, options.full_doc_comments, options.max_file_bytes)
+}
+
+// DefaultHasher no es estable entre versiones de Rust: en el peor caso, un fallo de caché
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn cache_path(root_path: &Path) -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("context-lens").join(format!("{:016x}.json", hash_of(root_path))))
+}
+
+/// Hash del contenido: distingue un archivo tocado (mtime nuevo) de uno modificado.
+pub fn content_hash(path: &Path) -> Option<u64> {
+    fs::read(path).ok().map(hash_of)
+}
+
+/// Caché de `root_path` si existe y se generó con el mismo formato, versión y opciones.
+pub fn load(root_path: &Path, options: AnalysisOptions) -> Option<AnalysisCache> {
+    let bytes = fs::read(cache_path(root_path)?).ok()?;
+    let cache: AnalysisCache = serde_json::from_slice(&bytes).ok()?;
+    let compatible = cache.format == CACHE_FORMAT
+        && cache.version == env!("CARGO_PKG_VERSION")
+        && cache.options == options_key(options);
+    compatible.then_some(cache)
+}
+
+/// Sustituye la caché de `root_path`. Solo se guardan los archivos de este análisis, así que
+/// los borrados desaparecen de la caché.
+pub fn save(root_path: &Path, options: AnalysisOptions, files: HashMap<PathBuf, CachedFile>) -> Result<(), String> {
+    let path = cache_path(root_path).ok_or("No hay carpeta de caché en esta plataforma")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Error al crear {}: {}", dir.display(), e))?;
+    }
+    let cache = AnalysisCache {
+        format: CACHE_FORMAT,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        options: options_key(options),
+        files,
+    };
+    let json = serde_json::to_vec(&cache).map_err(|e| format!("Error al serializar la caché: {}", e))?;
+    output::write_atomically(&path, &json).map_err(|e| format!("Error al escribir {}: {}", path.display(), e))
+}
+
+impl AnalysisCache {
+    /// Análisis guardado de `path` (y su hash) si el archivo no cambió: mismo tamaño y mtime,
+    /// o mismo contenido aunque el mtime sea otro (checkout, `touch`).
+    pub fn lookup(&self, path: &Path, stamp: Option<FileStamp>) -> Option<(FileAnalysis, u64)> {
+        let cached = self.files.get(path)?;
+        let stamp = stamp?;
+        let unchanged = stamp == cached.stamp
+            || (stamp.len == cached.stamp.len && content_hash(path) == Some(cached.hash));
+        unchanged.then(|| (cached.analysis.clone(), cached.hash))
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+use crate::analysis;
+
+// --- Búsqueda de texto en el contenido de los archivos analizados (tipo grep) ---
+// Relee los archivos del análisis en paralelo y envía las coincidencias de cada archivo en
+// cuanto se encuentran. Se respeta el límite de tamaño del escaneo y se saltan los binarios.
+
+pub const MAX_MATCHES: usize = 2000;
+const MAX_LINE_CHARS: usize = 200;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    pub regex: bool,            // false = texto literal
+    pub case_insensitive: bool,
+    pub whole_word: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContentMatch {
+    pub path: PathBuf,
+    pub line: usize, // 1-based
+    pub text: String, // Línea sin sangría, recortada a MAX_LINE_CHARS
+}
+
+pub enum SearchProgress {
+    Matches(Vec<ContentMatch>),
+    Finished { files_searched: usize, truncated: bool }, // truncated: se llegó a MAX_MATCHES
+}
+
+/// Compila la consulta; el error (regex inválida) se muestra tal cual en la interfaz.
+pub fn build_pattern(query: &str, options: SearchOptions) -> Result<Regex, String> {
+    let mut pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .map_err(|e| format!("Expresión no válida: {}", e))
+}
+
+fn shorten(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// Busca `pattern` línea a línea en segundo plano. Soltar el receptor cancela la búsqueda.
+pub fn start_content_search(files: Vec<PathBuf>, pattern: Regex, max_file_bytes: u64) -> Receiver<SearchProgress> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let found = AtomicUsize::new(0);
+        let searched = AtomicUsize::new(0);
+        let cancelled = AtomicBool::new(false);
+        files.par_iter().for_each_with(tx.clone(), |tx, path| {
+            if cancelled.load(Ordering::Relaxed) || found.load(Ordering::Relaxed) >= MAX_MATCHES {
+                return;
+            }
+            let Ok(content) = analysis::read_text_file(path, max_file_bytes) else { return };
+            searched.fetch_add(1, Ordering::Relaxed);
+            let matches: Vec<ContentMatch> = content.lines()
+                .enumerate()
+                .filter(|(_, line)| pattern.is_match(line))
+                .map(|(i, line)| ContentMatch { path: path.clone(), line: i + 1, text: shorten(line) })
+                .collect();
+            if matches.is_empty() {
+                return;
+            }
+            // Varios hilos pueden pasarse del límite a la vez: se recorta lo que sobra
+            let previous = found.fetch_add(matches.len(), Ordering::Relaxed);
+            let room = MAX_MATCHES.saturating_sub(previous);
+            let matches: Vec<ContentMatch> = matches.into_iter().take(room).collect();
+            if !matches.is_empty() && tx.send(SearchProgress::Matches(matches)).is_err() {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        });
+        tx.send(SearchProgress::Finished {
+            files_searched: searched.into_inner(),
+            truncated: found.into_inner() >= MAX_MATCHES,
+        })
+        .ok();
+    });
+    rx
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::analysis::{self, DetectedDefinition, ResolvedConnection};
+use crate::output;
+use crate::reporting;
+
+// --- Exportación de un documento por archivo (corpus para embeddings/RAG) ---
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorpusFormat {
+    Markdown,
+    Json,
+}
+
+impl CorpusFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            CorpusFormat::Markdown => "md",
+            CorpusFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CorpusOptions {
+    pub format: CorpusFormat,
+    pub include_content: bool, // false = solo metadatos, imports y firmas
+    pub max_file_bytes: u64,   // Contenido de los archivos más grandes (o binarios) omitido
+}
+
+pub enum CorpusProgress {
+    Written { done: usize, total: usize },
+    Finished(Result<usize, String>), // Nº de documentos generados
+}
+
+// Espejo del árbol fuente: `src/a.ts` -> `<out>/src/a.ts.md`. Conservar la extensión original
+// evita colisiones entre `a.ts` y `a.tsx` en la misma carpeta.
+fn document_path(out_dir: &Path, root_path: &Path, file: &Path, format: CorpusFormat) -> PathBuf {
+    let relative = file.strip_prefix(root_path).unwrap_or(file);
+    let mut name = relative.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".");
+    name.push(format.extension());
+    out_dir.join(relative).with_file_name(name)
+}
+
+fn json_document(
+    root_path: &Path,
+    file: &Path,
+    connections: &[&ResolvedConnection],
+    definitions: &[&DetectedDefinition],
+    include_content: bool,
+    max_file_bytes: u64,
+) -> Value {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mut document = json!({
+        "path": relative(file),
+        "language": reporting::language_for_extension(ext),
+        "size_bytes": fs::metadata(file).map(|m| m.len()).ok(),
+        "imports": connections.iter().map(|c| json!({
+            "specifier": c.imported_string,
+            "resolved": c.resolved_target.as_deref().map(relative),
+            "kind": c.kind.label(),
+            "symbols": c.imported_symbols,
+            "type_only": c.type_only,
+        })).collect::<Vec<_>>(),
+        "definitions": definitions.iter().map(|d| json!({
+            "name": d.symbol_name,
+            "kind": d.kind,
+            "line": d.line_number,
+            "signature": d.signature,
+            "doc": d.doc_summary,
+            "decorators": d.decorators,
+            "handler": d.handler,
+        })).collect::<Vec<_>>(),
+    });
+    if include_content {
+        match analysis::read_text_file(file, max_file_bytes) {
+            Ok(content) => document["content"] = Value::String(content),
+            Err(reason) => {
+                document["content"] = Value::Null;
+                document["skipped"] = Value::String(reason.describe());
+            }
+        }
+    }
+    document
+}
+
+fn write_manifest(out_dir: &Path, root_path: &Path, written: &[(PathBuf, PathBuf)], format: CorpusFormat) -> Result<(), String> {
+    let relative_to = |base: &Path, p: &Path| p.strip_prefix(base).unwrap_or(p).display().to_string();
+    let (name, contents) = match format {
+        CorpusFormat::Markdown => {
+            let mut manifest = format!("# Corpus manifest\n\n{} documents\n\n", written.len());
+            for (source, document) in written {
+                manifest.push_str(&format!("- `{}` -> `{}`\n", relative_to(root_path, source), relative_to(out_dir, document)));
+            }
+            ("manifest.md", manifest)
+        }
+        CorpusFormat::Json => {
+            let entries: Vec<Value> = written
+                .iter()
+                .map(|(source, document)| json!({
+                    "source": relative_to(root_path, source),
+                    "document": relative_to(out_dir, document),
+                }))
+                .collect();
+            let manifest = json!({ "count": written.len(), "documents": entries });
+            ("manifest.json", serde_json::to_string_pretty(&manifest).unwrap_or_default())
+        }
+    };
+    output::write_atomically(&out_dir.join(name), contents.as_bytes())
+        .map_err(|e| format!("No se pudo escribir el manifiesto: {}", e))
+}
+
+fn export_corpus(
+    tx: &Sender<CorpusProgress>,
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    definitions: &[DetectedDefinition],
+    out_dir: &Path,
+    options: CorpusOptions,
+) -> Result<usize, String> {
+    let mut connections_by_file: HashMap<&Path, Vec<&ResolvedConnection>> = HashMap::new();
+    for conn in connections {
+        connections_by_file.entry(conn.source_file.as_path()).or_default().push(conn);
+    }
+    let mut definitions_by_file: HashMap<&Path, Vec<&DetectedDefinition>> = HashMap::new();
+    for def in definitions {
+        definitions_by_file.entry(def.source_file.as_path()).or_default().push(def);
+    }
+
+    let mut written = Vec::with_capacity(files.len());
+    for (i, file) in files.iter().enumerate() {
+        let mut file_connections = connections_by_file.remove(file.as_path()).unwrap_or_default();
+        file_connections.sort_by(|a, b| a.imported_string.cmp(&b.imported_string));
+        let mut file_definitions = definitions_by_file.remove(file.as_path()).unwrap_or_default();
+        file_definitions.sort_by_key(|d| d.line_number);
+
+        let contents = match options.format {
+            CorpusFormat::Markdown => reporting::generate_file_document(
+                root_path, file, &file_connections, &file_definitions, options.include_content, options.max_file_bytes,
+            ),
+            CorpusFormat::Json => serde_json::to_string_pretty(&json_document(
+                root_path, file, &file_connections, &file_definitions, options.include_content, options.max_file_bytes,
+            ))
+            .unwrap_or_default(),
+        };
+
+        let document = document_path(out_dir, root_path, file, options.format);
+        output::write_atomically(&document, contents.as_bytes())
+            .map_err(|e| format!("No se pudo escribir {}: {}", document.display(), e))?;
+        written.push((file.clone(), document));
+        tx.send(CorpusProgress::Written { done: i + 1, total: files.len() }).ok();
+    }
+
+    write_manifest(out_dir, root_path, &written, options.format)?;
+    Ok(written.len())
+}
+
+pub fn start_corpus_export(
+    root_path: PathBuf,
+    files: Vec<PathBuf>,
+    connections: Vec<ResolvedConnection>,
+    definitions: Vec<DetectedDefinition>,
+    out_dir: PathBuf,
+    options: CorpusOptions,
+) -> Receiver<CorpusProgress> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = export_corpus(&tx, &root_path, &files, &connections, &definitions, &out_dir, options);
+        tx.send(CorpusProgress::Finished(result)).ok();
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{analyze, TempTree};
+
+    fn export(tree: &TempTree, out: &TempTree, format: CorpusFormat, include_content: bool) -> usize {
+        let analysis = analyze(tree);
+        let options = CorpusOptions { format, include_content, max_file_bytes: 1 << 20 };
+        let progress = start_corpus_export(
+            tree.root.clone(), analysis.files.to_vec(), analysis.connections.to_vec(), analysis.definitions.to_vec(), out.root.clone(), options,
+        );
+        progress.iter()
+            .find_map(|message| match message {
+                CorpusProgress::Finished(result) => Some(result.unwrap()),
+                CorpusProgress::Written { .. } => None,
+            })
+            .unwrap()
+    }
+
+    fn fixture_project() -> TempTree {
+        let tree = TempTree::new();
+        tree.file("src/Button.ts", "export const size = 2;\n");
+        // Mismo nombre base que Button.ts: el documento conserva la extensión original
+        tree.file("src/Button.tsx", "import { size } from './Button.ts';\nimport React from 'react';\nexport function Button() { return size; }\n");
+        tree.file("src/lib/deep/util.js", "module.exports = {};\n");
+        tree
+    }
+
+    #[test]
+    fn markdown_corpus_mirrors_the_tree_with_one_document_per_file() {
+        let (tree, out) = (fixture_project(), TempTree::new());
+        assert_eq!(export(&tree, &out, CorpusFormat::Markdown, true), 3);
+        for document in ["src/Button.ts.md", "src/Button.tsx.md", "src/lib/deep/util.js.md"] {
+            assert!(out.path(document).is_file(), "{}", document);
+        }
+        let manifest = fs::read_to_string(out.path("manifest.md")).unwrap();
+        assert!(manifest.starts_with("# Corpus manifest\n\n3 documents\n\n"));
+        assert!(manifest.contains("- `src/lib/deep/util.js` -> `src/lib/deep/util.js.md`\n"));
+
+        let document = fs::read_to_string(out.path("src/Button.tsx.md")).unwrap();
+        assert!(document.starts_with("# `src/Button.tsx`\n\n- Language: TypeScript\n"));
+        assert!(document.contains("## Imports\n\n- `./Button.ts` -> `src/Button.ts`\n- `react` (external or unresolved)\n"));
+        assert!(document.contains("## Definitions\n\n```\nL3 Function Button()\n"));
+        assert!(document.contains("## Content\n\n### `src/Button.tsx`\n\n```tsx\n1 | import { size }"));
+    }
+
+    #[test]
+    fn json_corpus_documents_can_omit_content() {
+        let (tree, out) = (fixture_project(), TempTree::new());
+        assert_eq!(export(&tree, &out, CorpusFormat::Json, false), 3);
+        let manifest: Value = serde_json::from_str(&fs::read_to_string(out.path("manifest.json")).unwrap()).unwrap();
+        assert_eq!(manifest["count"], 3);
+
+        let document: Value = serde_json::from_str(&fs::read_to_string(out.path("src/Button.tsx.json")).unwrap()).unwrap();
+        assert_eq!(document["path"], "src/Button.tsx");
+        assert_eq!(document["imports"][0]["resolved"], "src/Button.ts");
+        assert_eq!(document["definitions"][0]["signature"], "Button()");
+        assert!(document.get("content").is_none());
+    }
+}
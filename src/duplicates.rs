@@ -0,0 +1,246 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use rayon::prelude::*;
+
+use crate::analysis::DetectedDefinition;
+
+// --- Detección de carpetas con módulos duplicados (bajo demanda) ---
+// Dos carpetas se consideran posibles duplicados si comparten nombres de archivo y esos
+// archivos tienen contenido idéntico (hash normalizado) o exportan casi los mismos símbolos.
+
+const MIN_SHARED_NAMES: usize = 2;
+const MIN_SIMILARITY: f32 = 0.6;
+// Nombres demasiado comunes (index.ts en cientos de carpetas) no generan candidatos
+const MAX_DIRS_PER_NAME: usize = 50;
+pub const MAX_REPORTED_GROUPS: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct DuplicatePair {
+    pub file_a: PathBuf,
+    pub file_b: PathBuf,
+    pub identical: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    pub dir_a: PathBuf,
+    pub dir_b: PathBuf,
+    pub similarity: f32, // 0.0 - 1.0
+    pub pairs: Vec<DuplicatePair>,
+}
+
+struct FileFingerprint {
+    content_hash: Option<u64>,
+    symbols: HashSet<String>,
+}
+
+// Hash del contenido ignorando indentación y líneas vacías
+fn normalized_content_hash(path: &Path) -> Option<u64> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        line.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+fn jaccard<T: Eq + Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+// Similitud de un par de archivos homónimos: 1.0 si el contenido coincide, si no la de sus símbolos
+fn file_similarity(a: &FileFingerprint, b: &FileFingerprint) -> (f32, bool) {
+    if a.content_hash.is_some() && a.content_hash == b.content_hash {
+        return (1.0, true);
+    }
+    (jaccard(&a.symbols, &b.symbols), false)
+}
+
+pub fn find_duplicate_directories(files: &[PathBuf], definitions: &[DetectedDefinition]) -> Vec<DuplicateGroup> {
+    let mut symbols_by_file: HashMap<&Path, HashSet<String>> = HashMap::new();
+    for def in definitions {
+        symbols_by_file.entry(def.source_file.as_path()).or_default().insert(def.symbol_name.clone());
+    }
+
+    // carpeta -> (nombre de archivo -> ruta)
+    let mut dirs: HashMap<&Path, HashMap<&str, &PathBuf>> = HashMap::new();
+    for file in files {
+        if let (Some(parent), Some(name)) = (file.parent(), file.file_name().and_then(|n| n.to_str())) {
+            dirs.entry(parent).or_default().insert(name, file);
+        }
+    }
+
+    // Candidatos: pares de carpetas que comparten suficientes nombres
+    let mut dirs_by_name: HashMap<&str, Vec<&Path>> = HashMap::new();
+    for (dir, names) in &dirs {
+        for name in names.keys() {
+            dirs_by_name.entry(name).or_default().push(dir);
+        }
+    }
+    let mut shared_counts: HashMap<(&Path, &Path), usize> = HashMap::new();
+    for dirs_with_name in dirs_by_name.values_mut() {
+        if dirs_with_name.len() < 2 || dirs_with_name.len() > MAX_DIRS_PER_NAME {
+            continue;
+        }
+        dirs_with_name.sort();
+        for i in 0..dirs_with_name.len() {
+            for j in i + 1..dirs_with_name.len() {
+                *shared_counts.entry((dirs_with_name[i], dirs_with_name[j])).or_default() += 1;
+            }
+        }
+    }
+    let candidates: Vec<(&Path, &Path)> = shared_counts
+        .into_iter()
+        .filter(|(_, shared)| *shared >= MIN_SHARED_NAMES)
+        .map(|(pair, _)| pair)
+        .collect();
+
+    // Huella solo de los archivos implicados en algún candidato (lectura en paralelo)
+    let involved: HashSet<&PathBuf> = candidates
+        .iter()
+        .flat_map(|(a, b)| {
+            let (names_a, names_b) = (&dirs[a], &dirs[b]);
+            names_a
+                .iter()
+                .filter(|(name, _)| names_b.contains_key(*name))
+                .flat_map(|(name, file)| [*file, names_b[name]])
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let fingerprints: HashMap<&PathBuf, FileFingerprint> = involved
+        .into_par_iter()
+        .map(|file| {
+            let fingerprint = FileFingerprint {
+                content_hash: normalized_content_hash(file),
+                symbols: symbols_by_file.get(file.as_path()).cloned().unwrap_or_default(),
+            };
+            (file, fingerprint)
+        })
+        .collect();
+
+    let mut groups: Vec<DuplicateGroup> = candidates
+        .into_iter()
+        .filter_map(|(dir_a, dir_b)| {
+            let (names_a, names_b) = (&dirs[dir_a], &dirs[dir_b]);
+            let all_names: HashSet<&str> = names_a.keys().chain(names_b.keys()).copied().collect();
+            let mut shared_names: Vec<&str> = names_a.keys().filter(|n| names_b.contains_key(*n)).copied().collect();
+            shared_names.sort();
+
+            let mut content_total = 0.0;
+            let mut pairs = Vec::new();
+            for name in &shared_names {
+                let (file_a, file_b) = (names_a[name], names_b[name]);
+                let (similarity, identical) = file_similarity(&fingerprints[file_a], &fingerprints[file_b]);
+                content_total += similarity;
+                if identical || similarity >= MIN_SIMILARITY {
+                    pairs.push(DuplicatePair { file_a: file_a.clone(), file_b: file_b.clone(), identical });
+                }
+            }
+
+            // Mitad solapamiento de nombres, mitad parecido del contenido de los homónimos
+            let name_overlap = shared_names.len() as f32 / all_names.len() as f32;
+            let content_similarity = content_total / shared_names.len() as f32;
+            let similarity = 0.5 * name_overlap + 0.5 * content_similarity;
+
+            (similarity >= MIN_SIMILARITY && !pairs.is_empty()).then(|| DuplicateGroup {
+                dir_a: dir_a.to_path_buf(),
+                dir_b: dir_b.to_path_buf(),
+                similarity,
+                pairs,
+            })
+        })
+        .collect();
+
+    groups.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.dir_a.cmp(&b.dir_a))
+            .then_with(|| a.dir_b.cmp(&b.dir_b))
+    });
+    groups
+}
+
+// Ejecuta la búsqueda en segundo plano; es costosa (lee todos los archivos candidatos)
+pub fn start_duplicate_search(files: Vec<PathBuf>, definitions: Vec<DetectedDefinition>) -> Receiver<Vec<DuplicateGroup>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        tx.send(find_duplicate_directories(&files, &definitions)).ok();
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{definition, TempTree};
+
+    #[test]
+    fn identical_helpers_in_two_folders_form_a_group() {
+        let tree = TempTree::new();
+        let files = vec![
+            tree.file("features/orders/format.ts", "export function money(x) {\n  return x;\n}\n"),
+            // Misma lógica con otra sangría y líneas vacías: cuenta como idéntico
+            tree.file("features/billing/format.ts", "export function money(x) {\n\n    return x;\n}\n"),
+            tree.file("features/orders/dates.ts", "export const today = () => new Date();\n"),
+            tree.file("features/billing/dates.ts", "export const today = () => new Date();\n"),
+            tree.file("features/orders/Orders.tsx", "export const Orders = 1;\n"),
+            tree.file("features/billing/Invoices.tsx", "export const Invoices = 1;\n"),
+        ];
+        let groups = find_duplicate_directories(&files, &[]);
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!((group.dir_a.clone(), group.dir_b.clone()), (tree.path("features/billing"), tree.path("features/orders")));
+        // 2 de 4 nombres compartidos (0.5) y contenido idéntico (1.0)
+        assert!((group.similarity - 0.75).abs() < 1e-6);
+        let pairs: Vec<(&Path, bool)> = group.pairs.iter().map(|p| (p.file_a.as_path(), p.identical)).collect();
+        assert_eq!(pairs, [(tree.path("features/billing/dates.ts").as_path(), true), (tree.path("features/billing/format.ts").as_path(), true)]);
+    }
+
+    #[test]
+    fn near_identical_files_match_on_their_exported_symbols() {
+        let tree = TempTree::new();
+        let a = tree.file("a/utils.ts", "export const pad = 1;\nexport const trim = 2;\n");
+        let b = tree.file("b/utils.ts", "export const pad = 10;\nexport const trim = 20;\n");
+        let a_index = tree.file("a/index.ts", "export * from './utils';\n");
+        let b_index = tree.file("b/index.ts", "export * from './utils';\n");
+        let definitions = vec![
+            definition(&a, "pad", "Variable", 1, true),
+            definition(&a, "trim", "Variable", 2, true),
+            definition(&b, "pad", "Variable", 1, true),
+            definition(&b, "trim", "Variable", 2, true),
+        ];
+        let groups = find_duplicate_directories(&[a.clone(), b, a_index, b_index], &definitions);
+        assert_eq!(groups.len(), 1);
+        assert!((groups[0].similarity - 1.0).abs() < 1e-6);
+        let utils_pair = groups[0].pairs.iter().find(|p| p.file_a == a).unwrap();
+        assert!(!utils_pair.identical);
+    }
+
+    #[test]
+    fn shared_names_with_different_contents_are_not_duplicates() {
+        let tree = TempTree::new();
+        let files = vec![
+            tree.file("users/index.ts", "export const users = [];\n"),
+            tree.file("users/types.ts", "export type User = { id: string };\n"),
+            tree.file("posts/index.ts", "export const posts = [];\n"),
+            tree.file("posts/types.ts", "export type Post = { title: string };\n"),
+        ];
+        let definitions = vec![
+            definition(&files[0], "users", "Variable", 1, true),
+            definition(&files[2], "posts", "Variable", 1, true),
+        ];
+        assert!(find_duplicate_directories(&files, &definitions).is_empty());
+        // Un solo nombre compartido no basta para ser candidato
+        assert!(find_duplicate_directories(&files[..3], &[]).is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+// --- Abrir archivos fuera de la aplicación (editor externo, explorador de archivos) ---
+// El editor se configura con una plantilla de comando (`code -g {path}:{line}`); se parte en
+// argumentos antes de sustituir los marcadores, así una ruta con espacios sigue siendo un
+// solo argumento y no pasa por ningún shell.
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(windows) { &["exe", "cmd", "bat"] } else { &[""] };
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).find_map(|dir| {
+        extensions.iter()
+            .map(|ext| dir.join(program).with_extension(ext))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Plantilla por defecto: VS Code si está en el PATH; si no, el editor o visor del sistema.
+pub fn default_command() -> String {
+    if let Some(code) = find_on_path("code") {
+        // En Windows `code` es un .cmd: se llama por su ruta completa y no a través de `cmd /C`,
+        // que interpretaría `&`, `|`... dentro de la ruta del archivo
+        return if cfg!(windows) {
+            format!("\"{}\" -g {{path}}:{{line}}", code.display())
+        } else {
+            "code -g {path}:{line}".to_string()
+        };
+    }
+    let command = if cfg!(target_os = "macos") {
+        "open -t {path}"
+    } else if cfg!(windows) {
+        "notepad {path}"
+    } else {
+        "xdg-open {path}"
+    };
+    command.to_string()
+}
+
+// Argumentos separados por espacios; las comillas dobles agrupan ("C:\Program Files\...")
+fn split_arguments(template: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in template.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    arguments.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        arguments.push(current);
+    }
+    arguments
+}
+
+// cmd.exe (y los .cmd/.bat, que se ejecutan con él) reinterpreta estos caracteres aunque vayan
+// en un argumento propio
+const CMD_METACHARACTERS: &[char] = &['&', '|', '<', '>', '^', '%', '!', '"'];
+
+fn runs_through_cmd(program: &str) -> bool {
+    let program = Path::new(program);
+    let is_cmd = program.file_stem().is_some_and(|stem| stem.eq_ignore_ascii_case("cmd"))
+        && program.extension().is_none_or(|ext| ext.eq_ignore_ascii_case("exe"));
+    let is_script = program.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cmd") || ext.eq_ignore_ascii_case("bat"));
+    is_cmd || is_script
+}
+
+// Lanza el proceso sin esperarlo; un hilo lo recoge al terminar para no dejar zombis
+fn spawn(program: &str, arguments: &[String]) -> Result<(), String> {
+    let mut child = Command::new(program)
+        .args(arguments)
+        .spawn()
+        .map_err(|e| format!("No se pudo ejecutar `{}`: {}", program, e))?;
+    thread::spawn(move || child.wait().ok());
+    Ok(())
+}
+
+/// Abre `path` con la plantilla `template`; `{path}` y `{line}` (1 si no hay línea) se sustituyen.
+pub fn open_in_editor(template: &str, path: &Path, line: Option<usize>) -> Result<(), String> {
+    let (program, arguments) = editor_arguments(template, path, line)?;
+    spawn(&program, &arguments)
+}
+
+fn editor_arguments(template: &str, path: &Path, line: Option<usize>) -> Result<(String, Vec<String>), String> {
+    let path = path.display().to_string();
+    let line = line.unwrap_or(1).to_string();
+    let mut arguments: Vec<String> = split_arguments(template)
+        .into_iter()
+        .map(|arg| arg.replace("{path}", &path).replace("{line}", &line))
+        .collect();
+    if arguments.is_empty() {
+        return Err("No hay comando de editor configurado (Vista > Editor)".to_string());
+    }
+    let program = arguments.remove(0);
+    if runs_through_cmd(&program) && path.contains(CMD_METACHARACTERS) {
+        return Err(format!("La ruta `{}` contiene caracteres que cmd.exe interpretaría; configura el editor sin `cmd`", path));
+    }
+    Ok((program, arguments))
+}
+
+/// Muestra `path` en el explorador de archivos (seleccionado donde el sistema lo permite).
+pub fn reveal(path: &Path) -> Result<(), String> {
+    let path_arg = path.display().to_string();
+    if cfg!(target_os = "macos") {
+        spawn("open", &["-R".to_string(), path_arg])
+    } else if cfg!(windows) {
+        spawn("explorer", &[format!("/select,{}", path_arg)])
+    } else {
+        // xdg-open no sabe seleccionar: se abre la carpeta que lo contiene
+        let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+        spawn("xdg-open", &[folder.display().to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_arguments_splits_on_whitespace() {
+        assert_eq!(split_arguments("code  -g\t{path}:{line} "), ["code", "-g", "{path}:{line}"]);
+        assert!(split_arguments("   ").is_empty());
+    }
+
+    #[test]
+    fn split_arguments_groups_quoted_text() {
+        assert_eq!(
+            split_arguments(r#""C:\Program Files\Editor\editor.exe" "{path}" -n{line}"#),
+            [r"C:\Program Files\Editor\editor.exe", "{path}", "-n{line}"]
+        );
+        // Las comillas en mitad de un argumento solo agrupan; una comilla sin cerrar llega al final
+        assert_eq!(split_arguments(r#"--file="a b" c"#), ["--file=a b", "c"]);
+        assert_eq!(split_arguments(r#"vim "a b"#), ["vim", "a b"]);
+    }
+
+    #[test]
+    fn placeholders_are_replaced_inside_single_arguments() {
+        let (program, arguments) = editor_arguments("code -g {path}:{line}", Path::new("/tmp/my file.rs"), Some(12)).unwrap();
+        assert_eq!(program, "code");
+        assert_eq!(arguments, ["-g", "/tmp/my file.rs:12"]);
+        let (_, arguments) = editor_arguments("subl {path}:{line}", Path::new("a.rs"), None).unwrap();
+        assert_eq!(arguments, ["a.rs:1"]);
+        assert!(editor_arguments("  ", Path::new("a.rs"), None).is_err());
+    }
+
+    #[test]
+    fn cmd_metacharacters_are_rejected_only_through_cmd() {
+        let path = Path::new("a&calc.js");
+        assert!(editor_arguments("cmd /C code -g {path}:{line}", path, None).is_err());
+        assert!(editor_arguments("CMD.EXE /C code {path}", path, None).is_err());
+        assert!(editor_arguments(r#""C:\bin\code.cmd" -g {path}"#, path, None).is_err());
+        assert!(editor_arguments("editor.bat {path}", path, None).is_err());
+        assert!(editor_arguments("code -g {path}", path, None).is_ok());
+        assert!(editor_arguments("cmd /C code {path}", Path::new("src/main.rs"), None).is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use path_clean::PathClean;
+
+// --- Acceso a git (aislado tras un trait para poder sustituirlo) ---
+
+pub trait GitSource {
+    /// Archivos modificados respecto a `base_ref` (rutas absolutas).
+    fn changed_files(&self, base_ref: &str) -> Result<Vec<PathBuf>, String>;
+    /// Diff unificado de un archivo respecto a `base_ref`.
+    fn file_diff(&self, base_ref: &str, file: &Path) -> Result<String, String>;
+}
+
+/// Rechaza referencias que git leería como opción (`--output=...`): vienen de texto del usuario.
+pub fn validate_ref(base_ref: &str) -> Result<&str, String> {
+    let base_ref = base_ref.trim();
+    if base_ref.is_empty() {
+        return Err("Indica una referencia base (rama, tag o commit)".to_string());
+    }
+    if base_ref.starts_with('-') {
+        return Err(format!("'{}' no es una referencia válida: no puede empezar por '-'", base_ref));
+    }
+    Ok(base_ref)
+}
+
+/// Implementación real: ejecuta el binario `git` dentro de `repo_dir`.
+pub struct GitCli {
+    pub repo_dir: PathBuf,
+}
+
+impl GitCli {
+    pub fn new(repo_dir: &Path) -> Self {
+        Self { repo_dir: repo_dir.to_path_buf() }
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo_dir)
+            .args(args)
+            .output()
+            .map_err(|e| format!("No se pudo ejecutar git: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "git {} falló: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl GitSource for GitCli {
+    fn changed_files(&self, base_ref: &str) -> Result<Vec<PathBuf>, String> {
+        // --relative: rutas relativas a repo_dir, ignorando cambios fuera de él
+        let base_ref = validate_ref(base_ref)?;
+        let stdout = self.run(&["diff", "--name-only", "--relative", base_ref, "--"])?;
+        Ok(stdout
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| self.repo_dir.join(line.trim()).clean())
+            .collect())
+    }
+
+    fn file_diff(&self, base_ref: &str, file: &Path) -> Result<String, String> {
+        let base_ref = validate_ref(base_ref)?;
+        let file_arg = file.to_string_lossy();
+        self.run(&["diff", base_ref, "--", &file_arg])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refs_that_look_like_options_are_rejected() {
+        assert!(validate_ref("--output=/tmp/pwned").is_err());
+        assert!(validate_ref("-p").is_err());
+        assert!(validate_ref(" --no-index").is_err());
+        assert!(validate_ref("   ").is_err());
+    }
+
+    #[test]
+    fn ordinary_refs_are_accepted() {
+        for base_ref in ["main", "origin/main", "HEAD~3", "v1.2.0", "a1b2c3d", "feature/x-y"] {
+            assert_eq!(validate_ref(base_ref), Ok(base_ref));
+        }
+        assert_eq!(validate_ref(" main "), Ok("main"));
+    }
+
+    #[test]
+    fn cli_rejects_option_refs_before_running_git() {
+        let git = GitCli::new(Path::new("/nonexistent"));
+        let error = git.changed_files("--output=/tmp/x").unwrap_err();
+        assert!(error.contains("no puede empezar por '-'"), "{}", error);
+        assert!(git.file_diff("-R", Path::new("a.rs")).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+// --- Utilidades de grafos dirigidos (referencias de proyecto, imports, ...) ---
+
+/// Componentes fuertemente conexos (Tarjan, iterativo para no desbordar la pila
+/// con grafos grandes). Devuelve todas las componentes, incluidas las de un solo nodo.
+pub fn strongly_connected_components<N>(adjacency: &HashMap<N, Vec<N>>) -> Vec<Vec<N>>
+where
+    N: Clone + Eq + Hash + Ord,
+{
+    // Indexar nodos de forma determinista
+    let mut nodes: Vec<N> = adjacency.keys().cloned().collect();
+    for targets in adjacency.values() {
+        nodes.extend(targets.iter().cloned());
+    }
+    nodes.sort();
+    nodes.dedup();
+    let index_of: HashMap<&N, usize> = nodes.iter().enumerate().map(|(i, n)| (n, i)).collect();
+    let edges: Vec<Vec<usize>> = nodes
+        .iter()
+        .map(|n| {
+            adjacency
+                .get(n)
+                .map(|ts| ts.iter().map(|t| index_of[t]).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let n = nodes.len();
+    let mut index = vec![usize::MAX; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for start in 0..n {
+        if index[start] != usize::MAX {
+            continue;
+        }
+        // Pila de llamadas simulada: (nodo, siguiente arista a visitar)
+        let mut call_stack: Vec<(usize, usize)> = vec![(start, 0)];
+        index[start] = next_index;
+        lowlink[start] = next_index;
+        next_index += 1;
+        stack.push(start);
+        on_stack[start] = true;
+
+        while let Some(frame) = call_stack.last_mut() {
+            let v = frame.0;
+            if frame.1 < edges[v].len() {
+                let w = edges[v][frame.1];
+                frame.1 += 1;
+                if index[w] == usize::MAX {
+                    index[w] = next_index;
+                    lowlink[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+            } else {
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[v]);
+                }
+                if lowlink[v] == index[v] {
+                    let mut component = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        component.push(nodes[w].clone());
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    components
+}
+
+/// Nodos que forman parte de algún ciclo (componente de más de un nodo o autorreferencia).
+pub fn nodes_in_cycles<N>(adjacency: &HashMap<N, Vec<N>>) -> Vec<Vec<N>>
+where
+    N: Clone + Eq + Hash + Ord,
+{
+    strongly_connected_components(adjacency)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1
+                || adjacency.get(&component[0]).is_some_and(|ts| ts.contains(&component[0]))
+        })
+        .collect()
+}
+
+/// Ciclo más corto que sale de `start` y vuelve a él sin salir de `within` (BFS).
+/// Devuelve los nodos en orden de recorrido, empezando por `start` y sin repetirlo al final.
+pub fn shortest_cycle_through<N>(adjacency: &HashMap<N, Vec<N>>, start: &N, within: &HashSet<N>) -> Vec<N>
+where
+    N: Clone + Eq + Hash,
+{
+    let mut parent: HashMap<&N, &N> = HashMap::new();
+    let mut queue: VecDeque<&N> = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        for next in adjacency.get(current).into_iter().flatten() {
+            if !within.contains(next) {
+                continue;
+            }
+            if next == start {
+                // Reconstruir el camino start -> ... -> current
+                let mut path = vec![current.clone()];
+                let mut node = current;
+                while node != start {
+                    node = parent[node];
+                    path.push(node.clone());
+                }
+                path.reverse();
+                return path;
+            }
+            if !parent.contains_key(next) {
+                parent.insert(next, current);
+                queue.push_back(next);
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Nodos a `max_hops` aristas o menos de `start`, siguiendo las aristas en ambos sentidos
+/// (lo que `start` usa y lo que lo usa, y así sucesivamente). Incluye `start`.
+pub fn within_hops<N>(adjacency: &HashMap<N, Vec<N>>, start: &N, max_hops: usize) -> HashSet<N>
+where
+    N: Clone + Eq + Hash,
+{
+    let mut neighbours: HashMap<&N, Vec<&N>> = HashMap::new();
+    for (source, targets) in adjacency {
+        for target in targets {
+            neighbours.entry(source).or_default().push(target);
+            neighbours.entry(target).or_default().push(source);
+        }
+    }
+
+    let mut reached: HashSet<&N> = HashSet::from([start]);
+    let mut queue: VecDeque<(&N, usize)> = VecDeque::from([(start, 0)]);
+    while let Some((current, hops)) = queue.pop_front() {
+        if hops == max_hops {
+            continue;
+        }
+        for next in neighbours.get(current).into_iter().flatten() {
+            if reached.insert(next) {
+                queue.push_back((next, hops + 1));
+            }
+        }
+    }
+    reached.into_iter().cloned().collect()
+}
+
+/// PageRank clásico (`damping` típico 0.85) sobre el grafo dirigido: un nodo importa más si lo
+/// apuntan nodos que a su vez importan. Los nodos sin aristas salientes reparten su peso entre
+/// todos. Orden de suma determinista para que los empates sean estables entre ejecuciones.
+pub fn pagerank<N>(adjacency: &HashMap<N, Vec<N>>, damping: f64, iterations: usize) -> HashMap<N, f64>
+where
+    N: Clone + Eq + Hash + Ord,
+{
+    let mut nodes: Vec<N> = adjacency.keys().cloned().collect();
+    for targets in adjacency.values() {
+        nodes.extend(targets.iter().cloned());
+    }
+    nodes.sort();
+    nodes.dedup();
+    let n = nodes.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+    let index_of: HashMap<&N, usize> = nodes.iter().enumerate().map(|(i, node)| (node, i)).collect();
+    let edges: Vec<Vec<usize>> = nodes
+        .iter()
+        .map(|node| {
+            let mut targets: Vec<usize> = adjacency.get(node).map(|ts| ts.iter().map(|t| index_of[t]).collect()).unwrap_or_default();
+            targets.sort_unstable();
+            targets.dedup();
+            targets
+        })
+        .collect();
+
+    let base = (1.0 - damping) / n as f64;
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..iterations {
+        let dangling: f64 = (0..n).filter(|&v| edges[v].is_empty()).map(|v| rank[v]).sum();
+        let mut next = vec![base + damping * dangling / n as f64; n];
+        for (v, targets) in edges.iter().enumerate() {
+            let share = damping * rank[v] / targets.len().max(1) as f64;
+            for &w in targets {
+                next[w] += share;
+            }
+        }
+        rank = next;
+    }
+    nodes.into_iter().zip(rank).collect()
+}
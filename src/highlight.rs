@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use egui::text::{LayoutJob, LayoutSection};
+use egui::{Color32, FontId, TextFormat};
+use egui_extras::syntax_highlighting::{self, CodeTheme};
+
+// --- Resaltado de sintaxis del modal y de la sección de contenidos ---
+// El coloreado lo hace egui_extras (syntect), que guarda cada LayoutJob en la caché de egui por
+// texto, tema y lenguaje. Aquí solo queda lo propio del informe: las vallas y etiquetas `<file>`
+// que cambian de lenguaje, los números de línea y los títulos.
+
+// Extensión (o lenguaje de una valla ```ts) -> extensión que entiende syntect; sin gramática
+// conocida queda en texto plano. syntect no trae TypeScript: TS, JSX y los componentes
+// .vue/.svelte usan la gramática de JavaScript
+fn language_for(extension: &str) -> String {
+    let extension = extension.to_ascii_lowercase();
+    match extension.as_str() {
+        "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" | "vue" | "svelte" => "js".to_string(),
+        "scss" | "less" => "css".to_string(),
+        _ => extension,
+    }
+}
+
+/// Qué se resalta: un archivo (por su extensión) o la sección de contenidos, que mezcla
+/// vallas o etiquetas `<file>` de varios lenguajes con números de línea.
+#[derive(Clone, Copy, Hash)]
+pub enum Source<'a> {
+    File(&'a str),
+    Report,
+}
+
+// Colores del informe fuera de los bloques de código
+#[derive(Clone, Copy)]
+struct Palette {
+    plain: Color32,
+    dim: Color32, // Números de línea, vallas y etiquetas
+    heading: Color32,
+}
+
+impl Palette {
+    fn new(dark_mode: bool) -> Self {
+        if dark_mode {
+            Palette { plain: Color32::from_gray(210), dim: Color32::from_gray(120), heading: Color32::from_rgb(97, 175, 239) }
+        } else {
+            Palette { plain: Color32::from_gray(40), dim: Color32::from_gray(150), heading: Color32::from_rgb(64, 120, 242) }
+        }
+    }
+}
+
+struct JobBuilder {
+    job: LayoutJob,
+    font: FontId,
+}
+
+impl JobBuilder {
+    // Tramos seguidos del mismo color se fusionan: menos secciones que maquetar
+    fn push(&mut self, text: &str, color: Color32) {
+        if text.is_empty() {
+            return;
+        }
+        let start = self.job.text.len();
+        self.job.text.push_str(text);
+        let end = self.job.text.len();
+        if let Some(last) = self.job.sections.last_mut()
+            && last.format.color == color
+            && last.byte_range.end == start
+        {
+            last.byte_range.end = end;
+            return;
+        }
+        self.job.sections.push(LayoutSection {
+            leading_space: 0.0,
+            byte_range: start..end,
+            format: TextFormat::simple(self.font.clone(), color),
+        });
+    }
+}
+
+// `12 | código` de la sección de contenidos: (número con separador, código)
+fn split_line_number(line: &str) -> Option<(&str, &str)> {
+    let separator = line.find(" | ")?;
+    let number = &line[..separator];
+    (!number.trim().is_empty() && number.trim().chars().all(|c| c.is_ascii_digit()))
+        .then(|| line.split_at(separator + 3))
+}
+
+// Bloque de código del informe: el código sin números se colorea entero (los comentarios y
+// cadenas de varias líneas siguen bien) y luego se intercalan los números de cada línea
+struct Block<'a> {
+    language: String,
+    numbers: Vec<&'a str>,
+    code: String,
+}
+
+impl Block<'_> {
+    fn flush(self, ctx: &egui::Context, theme: &CodeTheme, out: &mut JobBuilder, palette: &Palette) {
+        let job = syntax_highlighting::highlight(ctx, theme, &self.code, &self.language);
+        let mut numbers = self.numbers.into_iter();
+        let mut line_start = true;
+        for section in &job.sections {
+            for piece in self.code[section.byte_range.clone()].split_inclusive('\n') {
+                if line_start {
+                    out.push(numbers.next().unwrap_or_default(), palette.dim);
+                }
+                out.push(piece, section.format.color);
+                line_start = piece.ends_with('\n');
+            }
+        }
+        // Líneas con número pero sin código al final del bloque
+        for number in numbers {
+            out.push(number, palette.dim);
+        }
+    }
+}
+
+// Sección de contenidos: cada valla ```ext o `<file path="...">` elige el lenguaje de su bloque
+fn highlight_report(ctx: &egui::Context, theme: &CodeTheme, out: &mut JobBuilder, text: &str) {
+    let palette = Palette::new(ctx.style().visuals.dark_mode);
+    let mut block: Option<Block> = None;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        // Some(lenguaje del bloque que abre la línea, None si lo cierra); None si no es una etiqueta
+        let tag = if let Some(info) = trimmed.strip_prefix("```") {
+            Some(block.is_none().then(|| language_for(info.trim())))
+        } else if let Some(path) = trimmed.strip_prefix("<file path=\"") {
+            Some(Some(language_for(path.trim_end_matches("\">").rsplit('.').next().unwrap_or_default())))
+        } else if trimmed.starts_with("</file>") {
+            Some(None)
+        } else {
+            None
+        };
+        if let Some(language) = tag {
+            if let Some(done) = block.take() {
+                done.flush(ctx, theme, out, &palette);
+            }
+            block = language.map(|language| Block { language, numbers: Vec::new(), code: String::new() });
+            out.push(line, palette.dim);
+        } else if let Some(block) = &mut block {
+            let (number, code) = split_line_number(line).unwrap_or(("", line));
+            block.numbers.push(number);
+            block.code.push_str(code);
+        } else {
+            out.push(line, if trimmed.starts_with('#') { palette.heading } else { palette.plain });
+        }
+    }
+    if let Some(done) = block {
+        done.flush(ctx, theme, out, &palette);
+    }
+}
+
+// El informe junta muchos bloques: se guarda ya montado y solo se recompone si cambian el
+// texto, el tema o el tamaño de letra
+fn report_job(ctx: &egui::Context, theme: &CodeTheme, font_size: f32, text: &str) -> LayoutJob {
+    let id = egui::Id::new("report_highlight");
+    let key = egui::util::hash((theme, font_size.to_bits(), text));
+    if let Some((cached_key, job)) = ctx.data(|data| data.get_temp::<(u64, Arc<LayoutJob>)>(id))
+        && cached_key == key
+    {
+        return (*job).clone();
+    }
+    let mut out = JobBuilder { job: LayoutJob::default(), font: FontId::monospace(font_size) };
+    highlight_report(ctx, theme, &mut out, text);
+    ctx.data_mut(|data| data.insert_temp(id, (key, Arc::new(out.job.clone()))));
+    out.job
+}
+
+/// LayoutJob coloreado de `text`, para el `layouter` de un TextEdit. Se recalcula solo si
+/// cambian el texto, el origen, el tema o el tamaño de letra.
+pub fn highlight(ctx: &egui::Context, font_size: f32, source: Source<'_>, text: &str) -> LayoutJob {
+    let theme = CodeTheme::from_style(&ctx.style());
+    match source {
+        Source::File(extension) => {
+            let mut job = syntax_highlighting::highlight(ctx, &theme, text, &language_for(extension));
+            // egui_extras maqueta siempre a 12 px
+            for section in &mut job.sections {
+                section.format.font_id = FontId::monospace(font_size);
+            }
+            job
+        }
+        Source::Report => report_job(ctx, &theme, font_size, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_blocks_keep_their_text_and_dim_line_numbers() {
+        let ctx = egui::Context::default();
+        let report = "# Contenido\n<file path=\"src/a.ts\">\n 1 | const a = `x\n 2 | y`;\n</file>\n```rs\nfn main() {}\n```\n";
+        let job = highlight(&ctx, 14.0, Source::Report, report);
+        assert_eq!(job.text, report);
+
+        let palette = Palette::new(ctx.style().visuals.dark_mode);
+        let color_at = |needle: &str| {
+            let offset = report.find(needle).unwrap();
+            job.sections.iter().find(|s| s.byte_range.contains(&offset)).unwrap().format.color
+        };
+        assert_eq!(color_at(" 2 | "), palette.dim);
+        assert_eq!(color_at("# Contenido"), palette.heading);
+        // La cadena de plantilla sigue en la segunda línea pese al número intercalado
+        assert_eq!(color_at("y`"), color_at("x\n"));
+        assert_ne!(color_at("const"), palette.plain);
+        assert!(job.sections.iter().all(|s| s.format.font_id == FontId::monospace(14.0)));
+    }
+}
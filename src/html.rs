@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::analysis;
+use crate::output;
+use crate::reporting::{ReportItem, ReportSpan};
+
+// --- Informe HTML autocontenido (para adjuntar a tickets) ---
+// Recorre los mismos `ReportItem` que la UI: cada `FilePath` (o tramo `FileLink`) enlaza al ancla del contenido
+// de su archivo, así estructura, conexiones y usos inversos quedan enlazados entre sí.
+// Sin recursos externos: el CSS va en línea.
+
+const STYLE: &str = "
+body { margin: 0; font-family: system-ui, sans-serif; color: #1f2328; }
+nav { position: fixed; top: 0; bottom: 0; left: 0; width: 220px; overflow-y: auto; padding: 16px; background: #f6f8fa; border-right: 1px solid #d0d7de; box-sizing: border-box; }
+nav a { display: block; padding: 2px 0; color: #0969da; text-decoration: none; }
+main { margin-left: 220px; padding: 16px 24px; }
+pre { font-family: ui-monospace, monospace; font-size: 12px; line-height: 1.45; background: #f6f8fa; padding: 12px; overflow-x: auto; }
+pre a { color: #0969da; text-decoration: none; }
+pre a:hover, nav a:hover { text-decoration: underline; }
+.ln { display: inline-block; min-width: 3em; padding-right: 1em; text-align: right; color: #8c959f; user-select: none; }
+.back { font-size: 12px; }
+.ln:target { background: #fff8c5; }
+";
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Las vallas ``` y los títulos markdown de las secciones sobran dentro de un <pre>
+fn push_plain_lines(html: &mut String, text: &str) {
+    for line in text.lines() {
+        if line.starts_with("```") {
+            continue;
+        }
+        match line.strip_prefix('#') {
+            Some(heading) => html.push_str(&format!("<strong>{}</strong>\n", escape_html(heading.trim_start_matches('#').trim()))),
+            None => html.push_str(&format!("{}\n", escape_html(line))),
+        }
+    }
+}
+
+// Los archivos sin contenido en el informe (excluidos, fuera del foco) quedan como texto
+fn file_link(display: &str, path: &Path, anchors: &HashMap<&Path, String>) -> String {
+    match anchors.get(path) {
+        Some(anchor) => format!("<a href=\"#{}\">{}</a>", anchor, escape_html(display)),
+        None => escape_html(display),
+    }
+}
+
+// Enlace a una línea concreta dentro del contenido del archivo
+fn line_link(display: &str, path: &Path, line: usize, anchors: &HashMap<&Path, String>) -> String {
+    match anchors.get(path) {
+        Some(anchor) => format!("<a href=\"#{}-L{}\">{}</a>", anchor, line, escape_html(display)),
+        None => escape_html(display),
+    }
+}
+
+fn push_report_items(html: &mut String, items: &[ReportItem], anchors: &HashMap<&Path, String>) {
+    html.push_str("<pre>");
+    for item in items {
+        match item {
+            ReportItem::PlainText(text) => push_plain_lines(html, text),
+            ReportItem::FilePath { display, path } => html.push_str(&format!("{}\n", file_link(display, path, anchors))),
+            ReportItem::Directory { display, .. } => html.push_str(&format!("<strong>{}</strong>\n", escape_html(display))),
+            ReportItem::DefinitionLink { display, file, line } => html.push_str(&format!("{}\n", line_link(display, file, *line, anchors))),
+            ReportItem::Line(spans) => {
+                for span in spans {
+                    match span {
+                        ReportSpan::Text(text) => html.push_str(&escape_html(text)),
+                        ReportSpan::FileLink { display, path } => html.push_str(&file_link(display, path, anchors)),
+                        ReportSpan::DirectoryLink { display, .. } => html.push_str(&format!("<strong>{}</strong>", escape_html(display))),
+                        ReportSpan::FileLineLink { display, path, line } => html.push_str(&line_link(display, path, *line, anchors)),
+                    }
+                }
+                html.push('\n');
+            }
+        }
+    }
+    html.push_str("</pre>\n");
+}
+
+// Contenido con un <span> por número de línea (no seleccionable al copiar), con ancla `file-N-L12`
+fn push_file_content(html: &mut String, file: &Path, anchor: &str, max_file_bytes: u64) {
+    html.push_str("<pre>");
+    match analysis::read_text_file(file, max_file_bytes) {
+        Ok(content) => {
+            for (i, line) in content.lines().enumerate() {
+                html.push_str(&format!("<span class=\"ln\" id=\"{}-L{}\">{}</span>{}\n", anchor, i + 1, i + 1, escape_html(line)));
+            }
+        }
+        Err(reason) => html.push_str(&escape_html(&format!("[Omitido: {}]", reason.describe()))),
+    }
+    html.push_str("</pre>\n");
+}
+
+/// Documento HTML completo: índice lateral, una sección por `sections` y el contenido de `files`.
+pub fn render_html_report(
+    title: &str,
+    root_path: &Path,
+    sections: &[(&str, Vec<ReportItem>)],
+    files: &[PathBuf],
+    max_file_bytes: u64,
+) -> String {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let anchors: HashMap<&Path, String> = files.iter().enumerate().map(|(i, f)| (f.as_path(), format!("file-{}", i))).collect();
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", escape_html(title), STYLE));
+
+    html.push_str(&format!("<nav>\n<strong>{}</strong>\n", escape_html(title)));
+    for (i, (name, _)) in sections.iter().enumerate() {
+        html.push_str(&format!("<a href=\"#section-{}\">{}</a>\n", i, escape_html(name)));
+    }
+    if !files.is_empty() {
+        html.push_str("<a href=\"#contents\">Contenidos</a>\n");
+    }
+    html.push_str("</nav>\n<main>\n");
+
+    for (i, (name, items)) in sections.iter().enumerate() {
+        html.push_str(&format!("<h2 id=\"section-{}\">{}</h2>\n", i, escape_html(name)));
+        push_report_items(&mut html, items, &anchors);
+    }
+
+    if !files.is_empty() {
+        html.push_str("<h2 id=\"contents\">Contenidos</h2>\n");
+        for file in files {
+            let anchor = &anchors[file.as_path()];
+            html.push_str(&format!(
+                "<h3 id=\"{}\">{} <a class=\"back\" href=\"#\">↑</a></h3>\n",
+                anchor,
+                escape_html(&relative(file))
+            ));
+            push_file_content(&mut html, file, anchor, max_file_bytes);
+        }
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// Genera y escribe el informe en segundo plano (lee el contenido de todos los archivos).
+pub fn start_html_export(
+    title: String,
+    root_path: PathBuf,
+    sections: Vec<(&'static str, Vec<ReportItem>)>,
+    files: Vec<PathBuf>,
+    path: PathBuf,
+    max_file_bytes: u64,
+) -> Receiver<Result<PathBuf, String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let html = render_html_report(&title, &root_path, &sections, &files, max_file_bytes);
+        let result = output::write_atomically(&path, html.as_bytes())
+            .map(|()| path.clone())
+            .map_err(|e| format!("Error al escribir {}: {}", path.display(), e));
+        tx.send(result).ok();
+    });
+    rx
+}
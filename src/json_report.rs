@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{DetectedDefinition, ResolvedConnection};
+use crate::output;
+
+// --- Exportación JSON del análisis completo (para scripts, y como formato de caché) ---
+// Las rutas van relativas a `root`; solo `root` y los destinos fuera de la raíz
+// (`outside_target`) quedan absolutos.
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalysisReport {
+    pub root: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub connections: Vec<ResolvedConnection>,
+    pub definitions: Vec<DetectedDefinition>,
+}
+
+fn relative(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+fn relative_connection(root: &Path, conn: &ResolvedConnection) -> ResolvedConnection {
+    let mut conn = conn.clone();
+    conn.source_file = relative(root, &conn.source_file);
+    conn.resolved_target = conn.resolved_target.map(|t| relative(root, &t));
+    if let Some(ambient) = conn.ambient_module.as_mut() {
+        ambient.source_file = relative(root, &ambient.source_file);
+    }
+    for path in conn.pattern_matches.iter_mut().chain(conn.barrel_targets.iter_mut()) {
+        *path = relative(root, path);
+    }
+    conn
+}
+
+impl AnalysisReport {
+    pub fn new(root: &Path, files: &[PathBuf], connections: &[ResolvedConnection], definitions: &[DetectedDefinition]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files: files.iter().map(|f| relative(root, f)).collect(),
+            connections: connections.iter().map(|c| relative_connection(root, c)).collect(),
+            definitions: definitions
+                .iter()
+                .map(|d| DetectedDefinition { source_file: relative(root, &d.source_file), ..d.clone() })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| format!("Error al serializar el análisis: {}", e))?;
+        output::write_atomically(path, &json).map_err(|e| format!("Error al escribir {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::analysis::{CapturePattern, ImportClass};
+    use crate::test_support::{analyze, TempTree};
+
+    #[test]
+    fn written_reports_load_back_with_relative_paths() {
+        let tree = TempTree::new();
+        tree.file("package.json", "{ \"dependencies\": { \"react\": \"^18.0.0\" } }");
+        tree.file("src/util.ts", "export function helper() {}\n");
+        tree.file("src/main.ts", concat!(
+            "import React from 'react';\n",
+            "import { helper } from './util';\n",
+            "export { helper } from './util';\n",
+            "import './missing';\n",
+        ));
+        let analysis = analyze(&tree);
+        let report = AnalysisReport::new(&analysis.root_path, &analysis.files, &analysis.connections, &analysis.definitions);
+        let out = tree.path("report.json");
+        report.write(&out).unwrap();
+
+        let loaded: AnalysisReport = serde_json::from_slice(&fs::read(&out).unwrap()).unwrap();
+        assert_eq!(loaded.root, tree.root);
+        assert!(loaded.files.contains(&PathBuf::from("src/main.ts")));
+        assert!(loaded.files.iter().all(|f| f.is_relative()));
+
+        let conn = |import_str: &str| loaded.connections.iter().find(|c| c.imported_string == import_str).unwrap();
+        let util = conn("./util");
+        assert_eq!(util.source_file, PathBuf::from("src/main.ts"));
+        assert_eq!(util.resolved_target, Some(PathBuf::from("src/util.ts")));
+        assert_eq!((util.line_number, util.occurrences, util.kind), (2, 2, CapturePattern::Import));
+        assert_eq!(conn("react").import_class, ImportClass::NpmPackage);
+        let missing = conn("./missing");
+        assert_eq!((missing.resolved_target.as_ref(), missing.import_class, missing.kind), (None, ImportClass::Missing, CapturePattern::SideEffect));
+
+        let helper = loaded.definitions.iter().find(|d| d.symbol_name == "helper").unwrap();
+        assert_eq!((helper.source_file.as_path(), helper.line_number), (Path::new("src/util.ts"), 1));
+
+        // Volver a serializar lo cargado da exactamente el mismo documento
+        assert_eq!(serde_json::to_vec_pretty(&loaded).unwrap(), fs::read(&out).unwrap());
+    }
+}
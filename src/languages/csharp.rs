@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, is_project_dir, node_text, parse, qualified_name_candidates, resolve_qualified_name};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- C#: `using` y namespace/class/struct/interface/enum/record/método ---
+
+unsafe extern "C" { fn tree_sitter_c_sharp() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (using_directive) @using
+"#;
+
+// Cada `partial class` aporta su propia entrada: no se fusionan entre archivos
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (namespace_declaration name: (_) @def.name) @def.namespace
+      (file_scoped_namespace_declaration name: (_) @def.name) @def.namespace
+      (class_declaration name: (identifier) @def.name) @def.class
+      (struct_declaration name: (identifier) @def.name) @def.struct
+      (interface_declaration name: (identifier) @def.name) @def.interface
+      (enum_declaration name: (identifier) @def.name) @def.enum
+      (record_declaration name: (identifier) @def.name) @def.record
+      (method_declaration name: (identifier) @def.name) @def.method
+    ]
+"#;
+
+// `global using static Foo.Bar;` -> (`Foo.Bar`, static); `using Alias = Foo.Bar;` -> `Foo.Bar`
+fn using_target(text: &str) -> Option<(String, bool)> {
+    let mut body = text.trim().trim_end_matches(';').trim();
+    body = body.strip_prefix("global").map(str::trim_start).unwrap_or(body);
+    body = body.strip_prefix("using")?.trim_start();
+    let is_static = body.starts_with("static ");
+    if is_static {
+        body = body["static ".len()..].trim_start();
+    }
+    if let Some((_, target)) = body.split_once('=') {
+        body = target.trim();
+    }
+    (!body.is_empty() && !body.contains('(')).then(|| (body.to_string(), is_static))
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_c_sharp() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let Some(statement) = capture("using") else { return };
+        let Some((target, is_static)) = using_target(node_text(statement, content)) else { return };
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            // `using static` importa un tipo (archivo), no un namespace (carpeta)
+            imported_string: if is_static { format!("static {}", target) } else { target },
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            pattern: CapturePattern::Import,
+            line_number: statement.start_position().row + 1,
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let kinds = [
+            ("def.namespace", "Namespace"),
+            ("def.class", "Class"),
+            ("def.struct", "Struct"),
+            ("def.interface", "Interface"),
+            ("def.enum", "Enum"),
+            ("def.record", "Record"),
+            ("def.method", "Method"),
+        ];
+        let Some((node, kind)) = kinds.iter().find_map(|(name, kind)| capture(name).map(|n| (n, *kind))) else { return };
+        let Some(name_node) = capture("def.name") else { return };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+/// Namespaces -> carpeta con la misma estructura bajo algún ancestro del archivo, con o sin el
+/// namespace raíz (suele ser el nombre del proyecto: `MyApp.Services` -> `Services/`).
+/// `using static Foo.Bar` -> `Foo/Bar.cs`. `System.*` y paquetes NuGet quedan sin resolver.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    if let Some(type_name) = conn.imported_string.strip_prefix("static ") {
+        let segments: Vec<&str> = type_name.split('.').collect();
+        return resolve_qualified_name(&source, &segments, &["cs"], project_files);
+    }
+    let segments: Vec<&str> = conn.imported_string.split('.').collect();
+    let without_root = segments.get(1..).filter(|rest| !rest.is_empty());
+    qualified_name_candidates(&source, &segments)
+        .into_iter()
+        .chain(without_root.map(|rest| qualified_name_candidates(&source, rest)).unwrap_or_default())
+        .find(|dir| is_project_dir(dir, project_files))
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+
+use super::LineIndex;
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- CSS/SCSS/Sass/Less: `@import`, `@use`, `@forward` y `url(...)` ---
+// Sin gramática tree-sitter: las reglas son lo bastante regulares para expresiones regulares.
+
+lazy_static! {
+    static ref BLOCK_COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    static ref AT_RULE: Regex = Regex::new(r"@(import|use|forward)\s+([^;\n]+)").unwrap();
+    static ref QUOTED: Regex = Regex::new(r#"["']([^"']+)["']"#).unwrap();
+    static ref URL: Regex = Regex::new(r#"url\(\s*["']?([^"')\s]+)["']?\s*\)"#).unwrap();
+}
+
+const STYLE_EXTENSIONS: &[&str] = &["scss", "sass", "css", "less"];
+
+// Comentarios reemplazados por espacios para no alterar los números de línea
+fn strip_comments(content: &str) -> String {
+    BLOCK_COMMENT
+        .replace_all(content, |caps: &regex::Captures| {
+            caps[0].chars().map(|c| if c == '\n' { '\n' } else { ' ' }).collect::<String>()
+        })
+        .into_owned()
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let content = strip_comments(content);
+    let lines = LineIndex::new(&content);
+    let mut push = |imported_string: &str, pattern: CapturePattern, offset: usize| {
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            imported_string: imported_string.to_string(),
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            pattern,
+            line_number: lines.line_at(offset),
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    };
+
+    for caps in AT_RULE.captures_iter(&content) {
+        let (Some(rule), Some(args)) = (caps.get(1), caps.get(2)) else { continue };
+        // `@import url(...)` lo recoge la búsqueda de url()
+        if args.as_str().trim_start().starts_with("url(") {
+            continue;
+        }
+        let mut targets: Vec<&str> = QUOTED.captures_iter(args.as_str()).filter_map(|q| q.get(1)).map(|q| q.as_str()).collect();
+        // `@use`/`@forward` solo tienen un módulo (`@use "x" as y with (...)`)
+        if rule.as_str() != "import" {
+            targets.truncate(1);
+        }
+        // Sintaxis indentada de Sass: `@import variables` sin comillas
+        if targets.is_empty() {
+            targets.extend(args.as_str().split([',', ' ']).map(str::trim).filter(|t| !t.is_empty()).take(1));
+        }
+        for target in targets {
+            push(target, CapturePattern::Import, caps.get(0).map_or(0, |m| m.start()));
+        }
+    }
+
+    for caps in URL.captures_iter(&content) {
+        if let (Some(whole), Some(target)) = (caps.get(0), caps.get(1)) {
+            push(target.as_str(), CapturePattern::AssetUrl, whole.start());
+        }
+    }
+
+    output
+}
+
+/// Rutas relativas al archivo (también sin `./`, como hace Sass), con parciales `_nombre.scss`,
+/// extensiones implícitas e `_index`. URLs, `data:`, módulos `sass:` y `~paquete` quedan fuera.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let import_str = conn.imported_string.split(['?', '#']).next()?;
+    if import_str.is_empty() || import_str.contains(':') || import_str.starts_with(['/', '~']) {
+        return None;
+    }
+
+    let base = conn.source_file.parent()?.join(import_str).clean();
+    let file_name = base.file_name()?.to_str()?.to_string();
+    let has_style_extension = base
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| STYLE_EXTENSIONS.contains(&e));
+
+    let mut candidates = vec![base.clone()];
+    if conn.pattern == CapturePattern::Import {
+        let extensions: Vec<String> = if has_style_extension {
+            vec![String::new()]
+        } else {
+            STYLE_EXTENSIONS.iter().map(|ext| format!(".{}", ext)).collect()
+        };
+        for ext in &extensions {
+            candidates.push(base.with_file_name(format!("{}{}", file_name, ext)));
+            candidates.push(base.with_file_name(format!("_{}{}", file_name, ext)));
+        }
+        for ext in &extensions {
+            candidates.push(base.join(format!("_index{}", ext)));
+            candidates.push(base.join(format!("index{}", ext)));
+        }
+    }
+    candidates.into_iter().find(|candidate| project_files.contains(candidate))
+}
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+
+use crate::analysis::{self, CapturePattern, DetectedConnection, DetectedDefinition, FileAnalysis};
+
+// --- Docker: Dockerfile (FROM/COPY/ADD) y docker-compose (servicios, build, volumes, env_file) ---
+// Sin gramática: ambos formatos se recorren línea a línea.
+
+/// `Dockerfile`, `Dockerfile.dev`, `api.Dockerfile`, `build.dockerfile`
+pub fn is_dockerfile(file_name: &str) -> bool {
+    let lower = file_name.to_ascii_lowercase();
+    lower == "dockerfile" || lower.starts_with("dockerfile.") || lower.ends_with(".dockerfile")
+}
+
+/// `docker-compose.yml`, `docker-compose.prod.yaml`, `compose.yml`
+pub fn is_compose_file(file_name: &str) -> bool {
+    let lower = file_name.to_ascii_lowercase();
+    (lower.starts_with("docker-compose") || lower.starts_with("compose."))
+        && (lower.ends_with(".yml") || lower.ends_with(".yaml"))
+}
+
+fn connection(path: &Path, imported_string: &str, line_number: usize) -> DetectedConnection {
+    DetectedConnection {
+        source_file: path.to_path_buf(),
+        imported_string: imported_string.to_string(),
+        dynamic_pattern: false,
+        import_attribute: None,
+        glob: None,
+        pattern: CapturePattern::Import,
+        line_number,
+        imported_symbols: Vec::new(),
+        type_only: false,
+    }
+}
+
+fn definition(path: &Path, name: &str, kind: &str, line_number: usize, end_line_number: usize, signature: &str) -> DetectedDefinition {
+    DetectedDefinition {
+        source_file: path.to_path_buf(),
+        symbol_name: name.to_string(),
+        kind: kind.to_string(),
+        line_number,
+        end_line_number,
+        signature: analysis::compact_signature(signature.trim()),
+        exported_as: None,
+        parent: None,
+        doc_summary: None,
+        decorators: Vec::new(),
+        handler: None,
+    }
+}
+
+// Instrucciones con sus líneas de continuación (`\`) unidas: (línea inicial, texto)
+fn dockerfile_instructions(content: &str) -> Vec<(usize, String)> {
+    let mut instructions = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || (current.is_none() && trimmed.is_empty()) {
+            continue;
+        }
+        let (start, mut text) = current.take().unwrap_or((index + 1, String::new()));
+        match trimmed.strip_suffix('\\') {
+            Some(continued) => {
+                text.push_str(continued);
+                text.push(' ');
+                current = Some((start, text));
+            }
+            None => {
+                text.push_str(trimmed);
+                instructions.push((start, text));
+            }
+        }
+    }
+    instructions.extend(current);
+    instructions
+}
+
+// Argumentos de COPY/ADD: forma JSON (`["a", "b"]`) o separada por espacios, sin flags
+fn copy_arguments(args: &str) -> Vec<String> {
+    let args = args.trim();
+    if let Some(Ok(serde_json::Value::Array(items))) = args.starts_with('[').then(|| serde_json::from_str(args)) {
+        return items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect();
+    }
+    args.split_whitespace().filter(|arg| !arg.starts_with("--")).map(str::to_string).collect()
+}
+
+pub fn analyze_dockerfile(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let instructions = dockerfile_instructions(content);
+    let total_lines = content.lines().count().max(1);
+
+    // Cada FROM abre una etapa que dura hasta el siguiente FROM
+    let stage_starts: Vec<usize> = instructions
+        .iter()
+        .filter(|(_, text)| text.split_whitespace().next().is_some_and(|i| i.eq_ignore_ascii_case("FROM")))
+        .map(|(line, _)| *line)
+        .collect();
+
+    for (line_number, text) in &instructions {
+        let (instruction, args) = text.split_once(char::is_whitespace).unwrap_or((text.as_str(), ""));
+        match instruction.to_ascii_uppercase().as_str() {
+            "FROM" => {
+                let words: Vec<&str> = args.split_whitespace().filter(|w| !w.starts_with("--")).collect();
+                // `FROM node:20 AS build` -> etapa `build`; sin alias, la imagen
+                let name = match words.iter().position(|w| w.eq_ignore_ascii_case("AS")) {
+                    Some(i) => words.get(i + 1).copied(),
+                    None => words.first().copied(),
+                };
+                let end = stage_starts.iter().find(|start| *start > line_number).map_or(total_lines, |next| next - 1);
+                if let Some(name) = name {
+                    output.definitions.push(definition(path, name, "Stage", *line_number, end, text));
+                }
+            }
+            // `COPY --from=build ...` copia de otra etapa, no del proyecto
+            "COPY" | "ADD" if !args.contains("--from") => {
+                let arguments = copy_arguments(args);
+                let sources = &arguments[..arguments.len().saturating_sub(1)];
+                for source in sources.iter().filter(|s| !s.contains("://")) {
+                    output.connections.push(connection(path, source, *line_number));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    output
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn yaml_scalar(value: &str) -> &str {
+    let value = value.split(" #").next().unwrap_or(value).trim();
+    value.trim_matches(['"', '\''])
+}
+
+// Parte del host en `- ./data:/var/lib/data:ro`; los volúmenes con nombre no son rutas
+fn volume_host_path(entry: &str) -> Option<&str> {
+    let host = entry.split(':').next()?;
+    (host.starts_with('.') || host.starts_with('/') || host.starts_with('~')).then_some(host)
+}
+
+pub fn analyze_compose(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut in_services = false;
+    let mut service_indent: Option<usize> = None;
+    let mut current_key: Option<(String, usize)> = None; // clave con lista/mapa anidado e indentación
+    let mut services: Vec<(String, usize)> = Vec::new();
+
+    for (index, raw_line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = indentation(raw_line);
+        if indent == 0 {
+            in_services = trimmed.starts_with("services:");
+            service_indent = None;
+            current_key = None;
+            continue;
+        }
+        if !in_services {
+            continue;
+        }
+        let indent_of_services = *service_indent.get_or_insert(indent);
+        if indent == indent_of_services {
+            if let Some(name) = trimmed.strip_suffix(':') {
+                services.push((yaml_scalar(name).to_string(), line_number));
+            }
+            current_key = None;
+            continue;
+        }
+
+        if let Some((key, key_indent)) = &current_key {
+            if indent > *key_indent {
+                let entry = trimmed.trim_start_matches('-').trim();
+                match key.as_str() {
+                    "build" => {
+                        if let Some(("context", value)) = entry.split_once(':').map(|(k, v)| (k.trim(), v)) {
+                            output.connections.push(connection(path, yaml_scalar(value), line_number));
+                        }
+                    }
+                    "volumes" if trimmed.starts_with('-') => {
+                        if let Some(host) = volume_host_path(yaml_scalar(entry)) {
+                            output.connections.push(connection(path, host, line_number));
+                        }
+                    }
+                    // Sintaxis larga: `- type: bind\n  source: ./data`
+                    "volumes" => {
+                        if let Some(("source", value)) = entry.split_once(':').map(|(k, v)| (k.trim(), v))
+                            && let Some(host) = volume_host_path(yaml_scalar(value))
+                        {
+                            output.connections.push(connection(path, host, line_number));
+                        }
+                    }
+                    "env_file" if trimmed.starts_with('-') => {
+                        output.connections.push(connection(path, yaml_scalar(entry), line_number));
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            current_key = None;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else { continue };
+        let key = key.trim();
+        let value = yaml_scalar(value);
+        match key {
+            "build" | "volumes" | "env_file" if value.is_empty() => current_key = Some((key.to_string(), indent)),
+            "build" | "env_file" => output.connections.push(connection(path, value, line_number)),
+            _ => {}
+        }
+    }
+
+    let total_lines = lines.len().max(1);
+    for (i, (name, line_number)) in services.iter().enumerate() {
+        let end = services.get(i + 1).map_or(total_lines, |(_, next)| next - 1);
+        output.definitions.push(definition(path, name, "Service", *line_number, end, &format!("service {}", name)));
+    }
+
+    output
+}
+
+/// Rutas del host relativas al Dockerfile/compose: archivo del proyecto o carpeta existente.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let target = conn.imported_string.as_str();
+    if target.contains(['*', '?', '$']) || target.starts_with('~') {
+        return None; // Globs y variables no se expanden
+    }
+    let candidate = conn.source_file.clean().parent()?.join(target).clean();
+    (project_files.contains(&candidate) || candidate.is_dir()).then_some(candidate)
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Elixir (.ex/.exs): alias/import/use/require y defmodule/def/defp/defmacro ---
+
+unsafe extern "C" { fn tree_sitter_elixir() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (call
+      target: (identifier) @directive (#match? @directive "^(alias|import|use|require)$")
+      (arguments . (_) @module)) @call
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (call
+        target: (identifier) @def.keyword (#eq? @def.keyword "defmodule")
+        (arguments . (alias) @def.name)) @def.node
+      (call
+        target: (identifier) @def.keyword (#match? @def.keyword "^(def|defp|defmacro|defmacrop)$")
+        (arguments . [
+          (identifier) @def.name
+          (call target: (identifier) @def.name)
+          (binary_operator left: (call target: (identifier) @def.name))
+        ])) @def.node
+    ]
+"#;
+
+// `MyApp.Accounts.{User, Team}` -> [`MyApp.Accounts.User`, `MyApp.Accounts.Team`]
+fn expand_aliases(text: &str) -> Vec<String> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    match (text.find('{'), text.rfind('}')) {
+        (Some(open), Some(close)) if open < close => {
+            let prefix = &text[..open];
+            text[open + 1..close]
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| format!("{}{}", prefix, name))
+                .collect()
+        }
+        _ => vec![text],
+    }
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_elixir() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let (Some(call), Some(module)) = (capture("call"), capture("module")) else { return };
+        // Solo módulos con nombre (`alias Foo.Bar`); fuera `__MODULE__.X`, variables o átomos
+        let text = node_text(module, content);
+        if !text.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return;
+        }
+        for imported_string in expand_aliases(text) {
+            output.connections.push(DetectedConnection {
+                source_file: path.to_path_buf(),
+                imported_string,
+                dynamic_pattern: false,
+                import_attribute: None,
+                glob: None,
+                pattern: CapturePattern::Import,
+                line_number: call.start_position().row + 1,
+                imported_symbols: Vec::new(),
+                type_only: false,
+            });
+        }
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let (Some(node), Some(keyword), Some(name_node)) = (capture("def.node"), capture("def.keyword"), capture("def.name")) else { return };
+        let kind = match node_text(keyword, content) {
+            "defmodule" => "Module",
+            "defp" => "Private Function",
+            "defmacro" | "defmacrop" => "Macro",
+            _ => "Function",
+        };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+// Igual que `Macro.underscore/1`: `MyApp` -> `my_app`, `HTTPClient` -> `http_client`
+fn underscore(segment: &str) -> String {
+    let chars: Vec<char> = segment.chars().collect();
+    let mut result = String::with_capacity(segment.len() + 4);
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if previous.is_ascii_lowercase() || previous.is_ascii_digit() || (previous.is_ascii_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+/// `MyApp.Accounts.User` -> `lib/my_app/accounts/user.ex` bajo algún ancestro del archivo
+/// (apps de un umbrella incluidas). Si no existe, el módulo contenedor más largo que sí exista
+/// (`MyAppWeb.Router.Helpers` -> `my_app_web/router.ex`). Módulos de Elixir/deps: None.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let segments: Vec<String> = conn.imported_string.split('.').map(underscore).collect();
+    let source = conn.source_file.clean();
+    // Un solo segmento solo cuenta si es el nombre completo (`alias MyApp`)
+    let shortest = segments.len().min(2);
+    for length in (shortest..=segments.len()).rev() {
+        let relative: PathBuf = segments[..length].iter().collect();
+        let found = source.ancestors().skip(1).find_map(|dir| {
+            ["ex", "exs"]
+                .iter()
+                .map(|ext| dir.join("lib").join(&relative).with_extension(ext))
+                .find(|candidate| project_files.contains(candidate))
+        });
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Go: `import "..."` y declaraciones func/type/const/var ---
+
+unsafe extern "C" { fn tree_sitter_go() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (import_spec path: (interpreted_string_literal) @import.path) @import
+"#;
+
+// const/var solo a nivel de paquete; las locales no son parte de la API
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (function_declaration name: (identifier) @def.name) @def.function
+      (method_declaration name: (field_identifier) @def.name) @def.method
+      (type_spec name: (type_identifier) @def.name type: (struct_type)) @def.struct
+      (type_spec name: (type_identifier) @def.name type: (interface_type)) @def.interface
+      (type_spec name: (type_identifier) @def.name type: [
+        (type_identifier) (qualified_type) (pointer_type) (slice_type) (array_type)
+        (map_type) (channel_type) (function_type) (generic_type)
+      ]) @def.type
+      (source_file (const_declaration (const_spec name: (identifier) @def.name) @def.const))
+      (source_file (var_declaration (var_spec name: (identifier) @def.name) @def.var))
+    ]
+"#;
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_go() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let (Some(spec), Some(import_path)) = (capture("import"), capture("import.path")) else { return };
+        let import_path = node_text(import_path, content).trim_matches('"');
+        if !import_path.is_empty() {
+            output.connections.push(DetectedConnection {
+                source_file: path.to_path_buf(),
+                imported_string: import_path.to_string(),
+                dynamic_pattern: false,
+                import_attribute: None,
+                glob: None,
+                pattern: CapturePattern::Import,
+                line_number: spec.start_position().row + 1,
+                imported_symbols: Vec::new(),
+                type_only: false,
+            });
+        }
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let kinds = [
+            ("def.function", "Function"),
+            ("def.method", "Method"),
+            ("def.struct", "Struct"),
+            ("def.interface", "Interface"),
+            ("def.type", "Type"),
+            ("def.const", "Const"),
+            ("def.var", "Var"),
+        ];
+        let Some((node, kind)) = kinds.iter().find_map(|(name, kind)| capture(name).map(|n| (n, *kind))) else { return };
+        let Some(name_node) = capture("def.name") else { return };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() && name != "_" {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+// Ruta del módulo declarada en un go.mod (`module github.com/acme/api`)
+fn module_path(go_mod: &Path) -> Option<String> {
+    let content = fs::read_to_string(go_mod).ok()?;
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().trim_matches('"').to_string())
+}
+
+/// Imports del propio módulo (prefijo del `module` del go.mod más cercano) -> carpeta del paquete.
+/// La biblioteca estándar y los módulos externos quedan sin resolver.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    let go_mod = source
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("go.mod"))
+        .find(|candidate| project_files.contains(candidate))?;
+    let module = module_path(&go_mod)?;
+    let module_dir = go_mod.parent()?;
+
+    let package = if conn.imported_string == module {
+        module_dir.to_path_buf()
+    } else {
+        let relative = conn.imported_string.strip_prefix(&module)?.strip_prefix('/')?;
+        module_dir.join(relative).clean()
+    };
+    package.is_dir().then_some(package)
+}
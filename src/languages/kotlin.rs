@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, is_project_dir, node_text, parse, qualified_name_candidates, resolve_qualified_name};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Kotlin (.kt/.kts): imports y fun/class/object/interface/val/var de primer nivel ---
+
+unsafe extern "C" { fn tree_sitter_kotlin() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (import_header (identifier) @import.path) @import
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (function_declaration (simple_identifier) @def.name) @def.function
+      (class_declaration (type_identifier) @def.name) @def.class
+      (object_declaration (type_identifier) @def.name) @def.object
+      (source_file (property_declaration (variable_declaration (simple_identifier) @def.name)) @def.property)
+    ]
+"#;
+
+// Scripts de Gradle: bloques de primer nivel (`plugins { }`, `android { }`, `dependencies { }`)
+const GRADLE_BLOCKS_QUERY: &str = r#"
+    (source_file
+      (call_expression
+        (simple_identifier) @def.name
+        (call_suffix (annotated_lambda))) @def.block)
+"#;
+
+const SOURCE_EXTENSIONS: &[&str] = &["kt", "kts", "java"];
+
+// `class` cubre también interface, enum class y data class; el tipo sale de sus palabras clave
+fn class_kind(node: tree_sitter::Node, content: &str) -> &'static str {
+    let header = node_text(node, content).split(['{', '(']).next().unwrap_or("");
+    let words: Vec<&str> = header.split_whitespace().collect();
+    if words.contains(&"interface") {
+        "Interface"
+    } else if words.contains(&"data") {
+        "Data Class"
+    } else if words.contains(&"enum") {
+        "Enum"
+    } else {
+        "Class"
+    }
+}
+
+fn property_kind(node: tree_sitter::Node, content: &str) -> &'static str {
+    let header = node_text(node, content).split('=').next().unwrap_or("");
+    if header.split_whitespace().any(|word| word == "var") { "Var" } else { "Val" }
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_kotlin() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let (Some(statement), Some(import_path)) = (capture("import"), capture("import.path")) else { return };
+        let mut imported_string = node_text(import_path, content).to_string();
+        if node_text(statement, content).contains(".*") {
+            imported_string.push_str(".*");
+        }
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            imported_string,
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            pattern: CapturePattern::Import,
+            line_number: statement.start_position().row + 1,
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let Some(name_node) = capture("def.name") else { return };
+        let (node, kind) = if let Some(node) = capture("def.function") {
+            (node, "Function")
+        } else if let Some(node) = capture("def.class") {
+            (node, class_kind(node, content))
+        } else if let Some(node) = capture("def.object") {
+            (node, "Object")
+        } else if let Some(node) = capture("def.property") {
+            (node, property_kind(node, content))
+        } else {
+            return;
+        };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    if path.to_string_lossy().ends_with(".gradle.kts") {
+        for_each_match(&language, GRADLE_BLOCKS_QUERY, &tree, path, content, |capture| {
+            if let (Some(node), Some(name_node)) = (capture("def.block"), capture("def.name")) {
+                let name = node_text(name_node, content).to_string();
+                output.definitions.push(definition(path, node, name, "Gradle Block", content));
+            }
+        });
+    }
+
+    output
+}
+
+/// `com.acme.ui.Button` -> `.../com/acme/ui/Button.kt` (o .java) bajo algún ancestro del archivo;
+/// si no existe, el archivo del penúltimo segmento (miembros importados: `Foo.Companion`,
+/// funciones de primer nivel). `com.acme.ui.*` -> carpeta del paquete.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    if let Some(package) = conn.imported_string.strip_suffix(".*") {
+        let segments: Vec<&str> = package.split('.').collect();
+        return qualified_name_candidates(&source, &segments).into_iter().find(|dir| is_project_dir(dir, project_files));
+    }
+    let segments: Vec<&str> = conn.imported_string.split('.').collect();
+    resolve_qualified_name(&source, &segments, SOURCE_EXTENSIONS, project_files).or_else(|| {
+        let owner = &segments[..segments.len().saturating_sub(1)];
+        (owner.len() > 1).then(|| resolve_qualified_name(&source, owner, SOURCE_EXTENSIONS, project_files)).flatten()
+    })
+}
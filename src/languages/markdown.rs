@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+
+use super::LineIndex;
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Markdown/MDX: enlaces `[texto](./ruta.md)`, imágenes `![alt](./img.png)` y referencias ---
+
+lazy_static! {
+    static ref CODE_FENCE: Regex = Regex::new(r"(?ms)^\s*(```|~~~).*?^\s*(```|~~~)[^\n]*$").unwrap();
+    static ref INLINE_LINK: Regex = Regex::new(r#"(!?)\[[^\]]*\]\(\s*<?([^)\s>]+)>?(?:\s+["'(][^)]*)?\)"#).unwrap();
+    static ref REFERENCE_LINK: Regex = Regex::new(r"(?m)^\s{0,3}\[[^\]]+\]:\s*<?(\S+?)>?(?:\s|$)").unwrap();
+}
+
+// Solo enlaces locales: fuera URLs (`https:`, `mailto:`) y anclas de la propia página
+fn is_local_link(target: &str) -> bool {
+    !target.starts_with('#') && !target.contains(':') && !target.starts_with("//")
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    // Los bloques de código suelen mostrar enlaces de ejemplo: no son dependencias
+    let content = CODE_FENCE.replace_all(content, |caps: &regex::Captures| {
+        caps[0].chars().map(|c| if c == '\n' { '\n' } else { ' ' }).collect::<String>()
+    });
+    let lines = LineIndex::new(&content);
+
+    let links = INLINE_LINK.captures_iter(&content).filter_map(|caps| {
+        let pattern = if caps.get(1).is_some_and(|bang| !bang.as_str().is_empty()) { CapturePattern::AssetUrl } else { CapturePattern::Import };
+        caps.get(2).map(|target| (target, pattern))
+    });
+    let references = REFERENCE_LINK.captures_iter(&content).filter_map(|caps| caps.get(1).map(|target| (target, CapturePattern::Import)));
+
+    for (target, pattern) in links.chain(references) {
+        if is_local_link(target.as_str()) {
+            output.connections.push(DetectedConnection {
+                source_file: path.to_path_buf(),
+                imported_string: target.as_str().to_string(),
+                dynamic_pattern: false,
+                import_attribute: None,
+                glob: None,
+                pattern,
+                line_number: lines.line_at(target.start()),
+                imported_symbols: Vec::new(),
+                type_only: false,
+            });
+        }
+    }
+
+    output
+}
+
+/// Ruta relativa al documento sin `#ancla` ni `?query`; un enlace a carpeta apunta a su
+/// README/index.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let target = conn.imported_string.split(['#', '?']).next()?;
+    if target.is_empty() || target.starts_with('/') {
+        return None;
+    }
+    let base = conn.source_file.clean().parent()?.join(target).clean();
+    [base.clone(), base.join("README.md"), base.join("index.md"), base.join("index.mdx")]
+        .into_iter()
+        .find(|candidate| project_files.contains(candidate))
+}
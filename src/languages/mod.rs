@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, QueryError, Tree};
+
+use crate::analysis::{self, DetectedConnection, DetectedDefinition, FileAnalysis};
+
+pub mod csharp;
+pub mod css;
+pub mod docker;
+pub mod elixir;
+pub mod go;
+pub mod kotlin;
+pub mod markdown;
+pub mod php;
+pub mod protobuf;
+pub mod ruby;
+pub mod rust;
+pub mod shell;
+pub mod sql;
+
+// --- Analizadores de lenguajes distintos de JS/TS ---
+// Cada lenguaje expone `analyze` (conexiones y definiciones de un archivo) y, si sus imports
+// no siguen las reglas de Node, `resolve_import`. El núcleo JS/TS sigue en analysis.rs.
+
+// Claves con analizador en este módulo (deben coincidir con los `match` de abajo)
+const HANDLED_KEYS: &[&str] = &[
+    "rs", "go", "css", "scss", "sass", "less", "php", "kt", "kts", "cs", "rb", "sql", "md", "mdx",
+    "sh", "bash", "proto", "ex", "exs", "dockerfile", "compose",
+];
+
+// Archivos sin extensión propia (Dockerfile, docker-compose.yml) se reconocen por nombre;
+// el resto, por extensión
+fn analyzer_key(path: &Path) -> Option<&str> {
+    let file_name = path.file_name()?.to_str()?;
+    if docker::is_dockerfile(file_name) {
+        Some("dockerfile")
+    } else if docker::is_compose_file(file_name) {
+        Some("compose")
+    } else {
+        path.extension()?.to_str()
+    }
+}
+
+/// Analiza un archivo si su nombre o extensión corresponde a un lenguaje de este módulo.
+pub fn analyze_file(path: &Path, content: &str) -> Option<FileAnalysis> {
+    match analyzer_key(path)? {
+        "rs" => Some(rust::analyze(path, content)),
+        "go" => Some(go::analyze(path, content)),
+        "css" | "scss" | "sass" | "less" => Some(css::analyze(path, content)),
+        "php" => Some(php::analyze(path, content)),
+        "kt" | "kts" => Some(kotlin::analyze(path, content)),
+        "cs" => Some(csharp::analyze(path, content)),
+        "rb" => Some(ruby::analyze(path, content)),
+        "sql" => Some(sql::analyze(path, content)),
+        "md" | "mdx" => Some(markdown::analyze(path, content)),
+        "sh" | "bash" => Some(shell::analyze(path, content)),
+        "proto" => Some(protobuf::analyze(path, content)),
+        "ex" | "exs" => Some(elixir::analyze(path, content)),
+        "dockerfile" => Some(docker::analyze_dockerfile(path, content)),
+        "compose" => Some(docker::analyze_compose(path, content)),
+        _ => None,
+    }
+}
+
+/// Resolución según el lenguaje del archivo origen; None = usar la resolución de Node.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<Option<PathBuf>> {
+    match analyzer_key(&conn.source_file)? {
+        "rs" => Some(rust::resolve_import(conn, project_files)),
+        "go" => Some(go::resolve_import(conn, project_files)),
+        "css" | "scss" | "sass" | "less" => Some(css::resolve_import(conn, project_files)),
+        "php" => Some(php::resolve_import(conn, project_files)),
+        "kt" | "kts" => Some(kotlin::resolve_import(conn, project_files)),
+        "cs" => Some(csharp::resolve_import(conn, project_files)),
+        "rb" => Some(ruby::resolve_import(conn, project_files)),
+        "md" | "mdx" => Some(markdown::resolve_import(conn, project_files)),
+        "sh" | "bash" => Some(shell::resolve_import(conn, project_files)),
+        "proto" => Some(protobuf::resolve_import(conn, project_files)),
+        "ex" | "exs" => Some(elixir::resolve_import(conn, project_files)),
+        "dockerfile" | "compose" => Some(docker::resolve_import(conn, project_files)),
+        _ => None,
+    }
+}
+
+/// true si el archivo lo analiza este módulo (sus imports nunca son paquetes npm).
+pub fn handles(path: &Path) -> bool {
+    analyzer_key(path).is_some_and(|key| HANDLED_KEYS.contains(&key))
+}
+
+/// Rutas candidatas (sin extensión) para un nombre calificado (`App\Models\User`, `com.acme.Foo`):
+/// los segmentos bajo cada carpeta ancestro del archivo origen, primero completos, luego con el
+/// primer segmento en minúsculas (PSR-4: `App\` -> `app/`) y después sus sufijos (mínimo
+/// carpeta + nombre). Mejor esfuerzo: se busca en ancestros, no en todo el proyecto.
+pub(crate) fn qualified_name_candidates(source_file: &Path, segments: &[&str]) -> Vec<PathBuf> {
+    let mut relative_paths: Vec<PathBuf> = Vec::new();
+    if let Some((first, rest)) = segments.split_first() {
+        relative_paths.push(segments.iter().collect());
+        let lowered = first.to_lowercase();
+        if lowered != *first {
+            relative_paths.push(std::iter::once(lowered.as_str()).chain(rest.iter().copied()).collect());
+        }
+        for start in 1..segments.len().saturating_sub(1) {
+            relative_paths.push(segments[start..].iter().collect());
+        }
+    }
+    let mut candidates = Vec::new();
+    for dir in source_file.ancestors().skip(1) {
+        candidates.extend(relative_paths.iter().map(|relative| dir.join(relative)));
+    }
+    candidates
+}
+
+/// Primer candidato de `qualified_name_candidates` que existe con alguna de las extensiones.
+pub(crate) fn resolve_qualified_name(
+    source_file: &Path,
+    segments: &[&str],
+    extensions: &[&str],
+    project_files: &HashSet<PathBuf>,
+) -> Option<PathBuf> {
+    qualified_name_candidates(source_file, segments).into_iter().find_map(|base| {
+        extensions
+            .iter()
+            .map(|ext| base.with_extension(ext))
+            .find(|candidate| project_files.contains(candidate))
+    })
+}
+
+/// true si la carpeta contiene directamente algún archivo del proyecto (descarta carpetas fuera
+/// de la raíz o ignoradas que los candidatos por ancestros pueden alcanzar).
+pub(crate) fn is_project_dir(dir: &Path, project_files: &HashSet<PathBuf>) -> bool {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().any(|entry| project_files.contains(&entry.path().clean())))
+        .unwrap_or(false)
+}
+
+thread_local! {
+    // Un parser por hilo de rayon: crear uno por archivo es caro, cambiar de gramática no
+    static PARSER: RefCell<Parser> = RefCell::new(Parser::new());
+}
+
+lazy_static! {
+    // Consultas ya compiladas por gramática y texto (las de cada analizador son constantes)
+    static ref QUERY_CACHE: Mutex<HashMap<(Language, &'static str), Arc<Query>>> = Mutex::new(HashMap::new());
+}
+
+pub(crate) fn parse(language: &Language, path: &Path, content: &str) -> Option<Tree> {
+    PARSER.with(|parser| {
+        let mut parser = parser.borrow_mut();
+        // set_language también reinicia el estado de un análisis anterior
+        if parser.set_language(language).is_err() {
+            eprintln!("Error setting language for file: {}", path.display());
+            return None;
+        }
+        let tree = parser.parse(content, None);
+        if tree.is_none() {
+            eprintln!("Error parsing file: {}", path.display());
+        }
+        tree
+    })
+}
+
+/// Consulta compilada una sola vez por gramática; los errores no se guardan (se vuelven a
+/// reportar en cada archivo, como antes).
+pub(crate) fn cached_query(language: &Language, query_source: &'static str) -> Result<Arc<Query>, QueryError> {
+    let key = (language.clone(), query_source);
+    if let Some(query) = QUERY_CACHE.lock().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return Ok(query);
+    }
+    // Compilar fuera del candado; si dos hilos compilan a la vez, gana el último
+    let query = Arc::new(Query::new(language, query_source)?);
+    if let Ok(mut cache) = QUERY_CACHE.lock() {
+        cache.insert(key, query.clone());
+    }
+    Ok(query)
+}
+
+/// Ejecuta una consulta y llama a `on_match` con cada captura agrupada por nombre.
+pub(crate) fn for_each_match<'t>(
+    language: &Language,
+    query_source: &'static str,
+    tree: &'t Tree,
+    path: &Path,
+    content: &str,
+    mut on_match: impl FnMut(&dyn Fn(&str) -> Option<Node<'t>>),
+) {
+    let query = match cached_query(language, query_source) {
+        Ok(query) => query,
+        Err(e) => {
+            eprintln!("Error creating query for {}: {:?}", path.display(), e);
+            return;
+        }
+    };
+    let mut cursor = QueryCursor::new();
+    for mat in cursor.matches(&query, tree.root_node(), content.as_bytes()) {
+        let capture = |name: &str| {
+            let index = query.capture_index_for_name(name)?;
+            mat.captures.iter().find(|c| c.index == index).map(|c| c.node)
+        };
+        on_match(&capture);
+    }
+}
+
+/// Número de línea (1-based) de offsets en un mismo texto, para los analizadores por regex:
+/// los inicios de línea se calculan una vez y cada consulta es una búsqueda binaria.
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(content: &str) -> Self {
+        let starts = std::iter::once(0).chain(content.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        Self { starts }
+    }
+
+    pub(crate) fn line_at(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+}
+
+pub(crate) fn node_text<'s>(node: Node, content: &'s str) -> &'s str {
+    content.get(node.byte_range()).unwrap_or("")
+}
+
+// Primera línea de la declaración hasta `{` (mismo criterio que las firmas de JS/TS)
+pub(crate) fn signature(node: Node, content: &str) -> String {
+    let text = node_text(node, content);
+    let end = text.find(['\n', '{']).unwrap_or(text.len());
+    analysis::compact_signature(text[..end].trim())
+}
+
+pub(crate) fn definition(path: &Path, node: Node, name: String, kind: &str, content: &str) -> DetectedDefinition {
+    DetectedDefinition {
+        source_file: path.to_path_buf(),
+        symbol_name: name,
+        kind: kind.to_string(),
+        line_number: node.start_position().row + 1,
+        end_line_number: node.end_position().row + 1,
+        signature: signature(node, content),
+        exported_as: None,
+        parent: None,
+        doc_summary: None,
+        decorators: Vec::new(),
+        handler: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_index_matches_counting_newlines() {
+        let content = "a\nbc\n\nd\n";
+        let index = LineIndex::new(content);
+        for offset in 0..=content.len() {
+            assert_eq!(index.line_at(offset), content[..offset].matches('\n').count() + 1, "offset {}", offset);
+        }
+        assert_eq!(LineIndex::new("").line_at(0), 1);
+    }
+
+    #[test]
+    fn the_thread_parser_matches_a_fresh_one_when_switching_grammars() {
+        let path = Path::new("demo");
+        let sources = [
+            ("ts", "export function f<T>(x: T): T { return x; }\n"),
+            ("js", "const a = require('./a');\nexport default class B {}\n"),
+            ("tsx", "export const C = () => <div>{1}</div>;\n"),
+            ("ts", "import type { X } from './x';\n"),
+        ];
+        for (ext, content) in sources {
+            let language = analysis::script_language(ext).unwrap();
+            let reused = parse(&language, path, content).unwrap();
+            let mut fresh = Parser::new();
+            fresh.set_language(&language).unwrap();
+            let fresh = fresh.parse(content, None).unwrap();
+            assert_eq!(reused.root_node().to_sexp(), fresh.root_node().to_sexp(), "{}", ext);
+        }
+    }
+
+    #[test]
+    fn cached_queries_are_compiled_once_and_shared_between_threads() {
+        let language = analysis::script_language("js").unwrap();
+        const SOURCE: &str = "(identifier) @id";
+        let first = cached_query(&language, SOURCE).unwrap();
+        let other_thread = std::thread::spawn(move || cached_query(&analysis::script_language("js").unwrap(), SOURCE).unwrap());
+        assert!(Arc::ptr_eq(&first, &other_thread.join().unwrap()));
+        // Otra gramática, otra consulta; los errores no se guardan
+        let typescript = analysis::script_language("ts").unwrap();
+        assert!(!Arc::ptr_eq(&first, &cached_query(&typescript, SOURCE).unwrap()));
+        assert!(cached_query(&language, "(no_such_node) @x").is_err());
+        assert!(cached_query(&language, "(no_such_node) @x").is_err());
+    }
+}
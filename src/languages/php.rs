@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse, resolve_qualified_name};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- PHP: require/include, `use Namespace\Clase;` y function/class/trait/interface ---
+
+unsafe extern "C" { fn tree_sitter_php() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    [
+      (require_expression) @require
+      (require_once_expression) @require
+      (include_expression) @require
+      (include_once_expression) @require
+      (namespace_use_declaration) @use
+    ]
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (function_definition name: (name) @def.name) @def.function
+      (class_declaration name: (name) @def.name) @def.class
+      (trait_declaration name: (name) @def.name) @def.trait
+      (interface_declaration name: (name) @def.name) @def.interface
+    ]
+"#;
+
+lazy_static! {
+    static ref STRING_LITERAL: Regex = Regex::new(r#"['"]([^'"$]+)['"]"#).unwrap();
+    static ref USE_ALIAS: Regex = Regex::new(r"(?i)\s+as\s+\w+").unwrap();
+}
+
+// `use App\Models\{User, Post as P};` -> [`App\Models\User`, `App\Models\Post`]
+fn expand_use_declaration(text: &str) -> Vec<String> {
+    let body = text.trim().trim_end_matches(';').trim();
+    let body = body.strip_prefix("use").unwrap_or(body).trim_start();
+    // `use function ...` / `use const ...` importan símbolos sueltos, no clases
+    if body.starts_with("function ") || body.starts_with("const ") {
+        return Vec::new();
+    }
+    let body = USE_ALIAS.replace_all(body, "");
+    let (prefix, items) = match (body.find('{'), body.rfind('}')) {
+        (Some(open), Some(close)) if open < close => (&body[..open], &body[open + 1..close]),
+        _ => ("", &body[..]),
+    };
+    items
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| format!("{}{}", prefix.trim(), item).trim_start_matches('\\').to_string())
+        .collect()
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_php() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let (targets, pattern, statement) = if let Some(statement) = capture("require") {
+            // `require __DIR__ . '/config.php'`: el último literal es la parte de la ruta
+            let text = node_text(statement, content);
+            let literal = STRING_LITERAL.captures_iter(text).last().and_then(|c| c.get(1)).map(|m| m.as_str().to_string());
+            (literal.into_iter().collect(), CapturePattern::Require, statement)
+        } else if let Some(statement) = capture("use") {
+            (expand_use_declaration(node_text(statement, content)), CapturePattern::Import, statement)
+        } else {
+            return;
+        };
+        for imported_string in targets {
+            output.connections.push(DetectedConnection {
+                source_file: path.to_path_buf(),
+                imported_string,
+                dynamic_pattern: false,
+                import_attribute: None,
+                glob: None,
+                pattern,
+                line_number: statement.start_position().row + 1,
+                imported_symbols: Vec::new(),
+                type_only: false,
+            });
+        }
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let kinds = [("def.function", "Function"), ("def.class", "Class"), ("def.trait", "Trait"), ("def.interface", "Interface")];
+        let Some((node, kind)) = kinds.iter().find_map(|(name, kind)| capture(name).map(|n| (n, *kind))) else { return };
+        let Some(name_node) = capture("def.name") else { return };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+/// require/include: relativo al archivo (`__DIR__ . '/x.php'` y rutas simples).
+/// `use`: mapeo PSR-4 aproximado (`App\Models\User` -> `app/Models/User.php`).
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    if conn.pattern == CapturePattern::Require {
+        let candidate = source.parent()?.join(conn.imported_string.trim_start_matches('/')).clean();
+        return project_files.contains(&candidate).then_some(candidate);
+    }
+    let segments: Vec<&str> = conn.imported_string.split('\\').filter(|s| !s.is_empty()).collect();
+    resolve_qualified_name(&source, &segments, &["php"], project_files)
+}
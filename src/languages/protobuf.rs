@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+
+use super::LineIndex;
+use crate::analysis::{self, CapturePattern, DetectedConnection, DetectedDefinition, FileAnalysis};
+
+// --- Protobuf: `import "x.proto";` y message/service/rpc/enum ---
+// La sintaxis es lo bastante regular para expresiones regulares; no hace falta gramática.
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/|//[^\n]*").unwrap();
+    static ref IMPORT: Regex = Regex::new(r#"\bimport\s+(?:public\s+|weak\s+)?["']([^"']+)["']\s*;"#).unwrap();
+    static ref DECLARATION: Regex = Regex::new(r"\b(message|service|enum|rpc)\s+([A-Za-z_]\w*)").unwrap();
+}
+
+// Tipos bien conocidos que trae protoc: nunca son archivos del proyecto
+const WELL_KNOWN_PREFIXES: &[&str] = &["google/protobuf/", "google/api/", "google/rpc/", "google/type/"];
+
+// Fin de la declaración: su `}` de cierre, o el `;` si no tiene cuerpo (`rpc X(A) returns (B);`)
+fn declaration_end(content: &str, start: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in content[start..].char_indices() {
+        match c {
+            ';' if depth == 0 => return start + i,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth <= 0 {
+                    return start + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    content.len()
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let content = COMMENT.replace_all(content, |caps: &regex::Captures| {
+        caps[0].chars().map(|c| if c == '\n' { '\n' } else { ' ' }).collect::<String>()
+    });
+    let lines = LineIndex::new(&content);
+
+    for caps in IMPORT.captures_iter(&content) {
+        let (Some(statement), Some(target)) = (caps.get(0), caps.get(1)) else { continue };
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            imported_string: target.as_str().to_string(),
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            pattern: CapturePattern::Import,
+            line_number: lines.line_at(statement.start()),
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    }
+
+    for caps in DECLARATION.captures_iter(&content) {
+        let (Some(statement), Some(keyword), Some(name)) = (caps.get(0), caps.get(1), caps.get(2)) else { continue };
+        let kind = match keyword.as_str() {
+            "message" => "Message",
+            "service" => "Service",
+            "enum" => "Enum",
+            _ => "Rpc",
+        };
+        let end = declaration_end(&content, statement.start());
+        let header = content[statement.start()..end].split(['{', '\n']).next().unwrap_or("");
+        output.definitions.push(DetectedDefinition {
+            source_file: path.to_path_buf(),
+            symbol_name: name.as_str().to_string(),
+            kind: kind.to_string(),
+            line_number: lines.line_at(statement.start()),
+            end_line_number: lines.line_at(end),
+            signature: analysis::compact_signature(header.trim()),
+            exported_as: None,
+            parent: None,
+            doc_summary: None,
+            decorators: Vec::new(),
+            handler: None,
+        });
+    }
+
+    output
+}
+
+/// Los imports son relativos a una raíz de include (`protoc -I`): se prueba la carpeta del
+/// archivo y cada ancestro. Los tipos bien conocidos de Google quedan sin resolver.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let import_str = conn.imported_string.as_str();
+    if WELL_KNOWN_PREFIXES.iter().any(|prefix| import_str.starts_with(prefix)) {
+        return None;
+    }
+    conn.source_file
+        .clean()
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(import_str).clean())
+        .find(|candidate| project_files.contains(candidate))
+}
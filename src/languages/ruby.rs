@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Ruby: require/require_relative/autoload y def/class/module ---
+
+unsafe extern "C" { fn tree_sitter_ruby() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (call
+      method: (identifier) @call.method (#match? @call.method "^(require|require_relative|autoload)$")
+      arguments: (argument_list) @call.args) @call
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (method name: (_) @def.name) @def.method
+      (singleton_method name: (_) @def.name) @def.method
+      (class name: (_) @def.name) @def.class
+      (module name: (_) @def.name) @def.module
+    ]
+"#;
+
+lazy_static! {
+    static ref STRING_LITERAL: Regex = Regex::new(r##"['"]([^'"#]+)['"]"##).unwrap();
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_ruby() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let (Some(call), Some(method), Some(args)) = (capture("call"), capture("call.method"), capture("call.args")) else { return };
+        let method = node_text(method, content);
+        let mut literals = STRING_LITERAL.captures_iter(node_text(args, content)).filter_map(|c| c.get(1));
+        // `autoload :Foo, "foo/bar"`: la ruta es el último argumento
+        let target = if method == "autoload" { literals.last() } else { literals.next() };
+        let Some(target) = target else { return };
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            imported_string: target.as_str().to_string(),
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            // require_relative es relativo al archivo; require/autoload buscan en el load path
+            pattern: if method == "require_relative" { CapturePattern::Import } else { CapturePattern::Require },
+            line_number: call.start_position().row + 1,
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let kinds = [("def.method", "Method"), ("def.class", "Class"), ("def.module", "Module")];
+        let Some((node, kind)) = kinds.iter().find_map(|(name, kind)| capture(name).map(|n| (n, *kind))) else { return };
+        let Some(name_node) = capture("def.name") else { return };
+        let name = node_text(name_node, content).to_string();
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+fn with_rb_extension(path: PathBuf) -> PathBuf {
+    if path.extension().is_some_and(|ext| ext == "rb") { path } else { path.with_extension("rb") }
+}
+
+/// require_relative: ruta relativa al archivo + `.rb`. require/autoload: `lib/` de algún
+/// ancestro (la raíz del proyecto incluida) antes de darlo por gema externa.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    let import_str = conn.imported_string.as_str();
+    if conn.pattern == CapturePattern::Import || import_str.starts_with('.') {
+        let candidate = with_rb_extension(source.parent()?.join(import_str).clean());
+        return project_files.contains(&candidate).then_some(candidate);
+    }
+    source
+        .ancestors()
+        .skip(1)
+        .map(|dir| with_rb_extension(dir.join("lib").join(import_str).clean()))
+        .find(|candidate| project_files.contains(candidate))
+}
@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use path_clean::PathClean;
+use regex::Regex;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Rust: `mod foo;` / `use crate::...` y definiciones fn/struct/enum/trait/impl ---
+
+unsafe extern "C" { fn tree_sitter_rust() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    [
+      (mod_item name: (identifier) @mod.name !body) @mod
+      (use_declaration argument: (_) @use.tree) @use
+    ]
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    [
+      (function_item name: (identifier) @def.name) @def.function
+      (function_signature_item name: (identifier) @def.name) @def.function
+      (struct_item name: (type_identifier) @def.name) @def.struct
+      (enum_item name: (type_identifier) @def.name) @def.enum
+      (trait_item name: (type_identifier) @def.name) @def.trait
+      (impl_item type: (_) @def.name) @def.impl
+    ]
+"#;
+
+lazy_static! {
+    static ref USE_ALIAS: Regex = Regex::new(r"\s+as\s+[A-Za-z_][A-Za-z0-9_]*").unwrap();
+}
+
+// Archivos que definen el módulo de su propia carpeta
+const MODULE_ROOT_STEMS: &[&str] = &["mod", "main", "lib"];
+
+// `crate::{a::B, c::{self, d}}` -> [`crate::a::B`, `crate::c`, `crate::c::d`]
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let text: String = USE_ALIAS.replace_all(tree, "").chars().filter(|c| !c.is_whitespace()).collect();
+    expand_use_text(&text)
+}
+
+fn expand_use_text(text: &str) -> Vec<String> {
+    let Some(open) = text.find('{') else {
+        let path = text.trim_end_matches("::*").trim_end_matches("::self");
+        return if path.is_empty() { Vec::new() } else { vec![path.to_string()] };
+    };
+    let Some(close) = text.rfind('}') else { return Vec::new() };
+    let prefix = &text[..open];
+    let inner = &text[open + 1..close];
+
+    // Separar por comas de primer nivel
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&inner[start..]);
+
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| {
+            if part == "self" {
+                expand_use_text(prefix.trim_end_matches("::"))
+            } else {
+                expand_use_text(&format!("{}{}", prefix, part))
+            }
+        })
+        .collect()
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_rust() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        if let (Some(statement), Some(name)) = (capture("mod"), capture("mod.name")) {
+            output.connections.push(DetectedConnection {
+                source_file: path.to_path_buf(),
+                imported_string: node_text(name, content).to_string(),
+                dynamic_pattern: false,
+                import_attribute: None,
+                glob: None,
+                pattern: CapturePattern::ModDeclaration,
+                line_number: statement.start_position().row + 1,
+                imported_symbols: Vec::new(),
+                type_only: false,
+            });
+        } else if let (Some(statement), Some(use_tree)) = (capture("use"), capture("use.tree")) {
+            for use_path in expand_use_tree(node_text(use_tree, content)) {
+                output.connections.push(DetectedConnection {
+                    source_file: path.to_path_buf(),
+                    imported_string: use_path,
+                    dynamic_pattern: false,
+                    import_attribute: None,
+                    glob: None,
+                    pattern: CapturePattern::Import,
+                    line_number: statement.start_position().row + 1,
+                    imported_symbols: Vec::new(),
+                    type_only: false,
+                });
+            }
+        }
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        let kinds = [("def.function", "Function"), ("def.struct", "Struct"), ("def.enum", "Enum"), ("def.trait", "Trait"), ("def.impl", "Impl")];
+        let Some((node, kind)) = kinds.iter().find_map(|(name, kind)| capture(name).map(|n| (n, *kind))) else { return };
+        let Some(name_node) = capture("def.name") else { return };
+        // `impl Trait for Type` se lista con ambos nombres
+        let name = match node.child_by_field_name("trait").filter(|_| kind == "Impl") {
+            Some(trait_node) => format!("{} for {}", node_text(trait_node, content), node_text(name_node, content)),
+            None => node_text(name_node, content).to_string(),
+        };
+        if !name.is_empty() {
+            output.definitions.push(definition(path, node, name, kind, content));
+        }
+    });
+
+    output
+}
+
+// Carpeta donde viven los submódulos de un archivo: `src/a.rs` -> `src/a/`, `src/a/mod.rs` -> `src/a/`
+fn module_dir(file: &Path) -> Option<PathBuf> {
+    let parent = file.parent()?;
+    let stem = file.file_stem()?.to_str()?;
+    if MODULE_ROOT_STEMS.contains(&stem) {
+        Some(parent.to_path_buf())
+    } else {
+        Some(parent.join(stem))
+    }
+}
+
+// `mod name;` declarado en `dir`: `dir/name.rs` o `dir/name/mod.rs`
+fn submodule_file(dir: &Path, name: &str, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    [dir.join(format!("{}.rs", name)), dir.join(name).join("mod.rs")]
+        .into_iter()
+        .map(|p| p.clean())
+        .find(|p| project_files.contains(p))
+}
+
+// Archivo que define el módulo de una carpeta (`dir/mod.rs`, `dir.rs` o la raíz del crate)
+fn module_file_for_dir(dir: &Path, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    [dir.join("mod.rs"), dir.with_extension("rs"), dir.join("lib.rs"), dir.join("main.rs")]
+        .into_iter()
+        .map(|p| p.clean())
+        .find(|p| project_files.contains(p))
+}
+
+// Carpeta de la raíz del crate: el ancestro más cercano con lib.rs o main.rs
+fn crate_root_dir(file: &Path, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .find(|dir| project_files.contains(&dir.join("lib.rs")) || project_files.contains(&dir.join("main.rs")))
+        .map(Path::to_path_buf)
+}
+
+/// `mod foo;` -> archivo del submódulo; `use crate::a::b::Item` -> el módulo más profundo que
+/// exista (`a/b.rs`), o el propio módulo raíz si la ruta nombra un ítem. std y crates externos: None.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let source = conn.source_file.clean();
+    if conn.pattern == CapturePattern::ModDeclaration {
+        return submodule_file(&module_dir(&source)?, &conn.imported_string, project_files);
+    }
+
+    let mut segments = conn.imported_string.split("::").peekable();
+    let (mut dir, mut resolved) = match segments.next()? {
+        "crate" => {
+            let root = crate_root_dir(&source, project_files)?;
+            let root_file = module_file_for_dir(&root, project_files);
+            (root, root_file)
+        }
+        "self" => (module_dir(&source)?, None), // Ítems del propio archivo no son una dependencia
+        "super" => {
+            let parent = module_dir(&source)?.parent()?.to_path_buf();
+            let parent_file = module_file_for_dir(&parent, project_files);
+            (parent, parent_file)
+        }
+        _ => return None,
+    };
+    while segments.peek() == Some(&"super") {
+        segments.next();
+        dir = dir.parent()?.to_path_buf();
+        resolved = module_file_for_dir(&dir, project_files);
+    }
+    for segment in segments {
+        match submodule_file(&dir, segment, project_files) {
+            Some(file) => {
+                resolved = Some(file);
+                dir = dir.join(segment);
+            }
+            None => break,
+        }
+    }
+    resolved.filter(|file| *file != source)
+}
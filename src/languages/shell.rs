@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use tree_sitter::Language;
+
+use super::{definition, for_each_match, node_text, parse};
+use crate::analysis::{CapturePattern, DetectedConnection, FileAnalysis};
+
+// --- Shell (.sh/.bash): `source archivo` / `. archivo` y funciones ---
+
+unsafe extern "C" { fn tree_sitter_bash() -> Language; }
+
+const IMPORTS_QUERY: &str = r#"
+    (command name: (command_name (word) @command.name (#match? @command.name "^(source|\\.)$"))) @command
+"#;
+
+const DEFINITIONS_QUERY: &str = r#"
+    (function_definition name: (word) @def.name) @def.function
+"#;
+
+// `"$(dirname "$0")/lib/log.sh"` o `"$SCRIPT_DIR/lib/log.sh"` -> `./lib/log.sh`: el prefijo
+// variable casi siempre es la carpeta del propio script. Otras expansiones no se resuelven.
+fn source_argument(raw: &str) -> Option<String> {
+    let argument = raw.trim_matches(['"', '\'']);
+    if !argument.starts_with('$') {
+        return (!argument.contains('$')).then(|| argument.to_string());
+    }
+    // Fin del prefijo variable: primer `/` fuera de `$(...)`/`${...}`
+    let mut depth = 0;
+    for (i, c) in argument.char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            '/' if depth == 0 => {
+                let rest = &argument[i + 1..];
+                return (!rest.is_empty() && !rest.contains('$')).then(|| format!("./{}", rest));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    let language = unsafe { tree_sitter_bash() };
+    let Some(tree) = parse(&language, path, content) else { return output };
+
+    for_each_match(&language, IMPORTS_QUERY, &tree, path, content, |capture| {
+        let Some(command) = capture("command") else { return };
+        // Solo el primer argumento es el archivo; el resto se pasa como parámetros
+        let Some(argument) = command.child_by_field_name("argument") else { return };
+        let Some(imported_string) = source_argument(node_text(argument, content)) else { return };
+        output.connections.push(DetectedConnection {
+            source_file: path.to_path_buf(),
+            imported_string,
+            dynamic_pattern: false,
+            import_attribute: None,
+            glob: None,
+            pattern: CapturePattern::Import,
+            line_number: command.start_position().row + 1,
+            imported_symbols: Vec::new(),
+            type_only: false,
+        });
+    });
+
+    for_each_match(&language, DEFINITIONS_QUERY, &tree, path, content, |capture| {
+        if let (Some(node), Some(name_node)) = (capture("def.function"), capture("def.name")) {
+            let name = node_text(name_node, content).to_string();
+            output.definitions.push(definition(path, node, name, "Function", content));
+        }
+    });
+
+    output
+}
+
+/// Ruta relativa al script tal cual, sin probar extensiones.
+pub fn resolve_import(conn: &DetectedConnection, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    if conn.imported_string.starts_with(['/', '~']) {
+        return None;
+    }
+    let candidate = conn.source_file.clean().parent()?.join(&conn.imported_string).clean();
+    project_files.contains(&candidate).then_some(candidate)
+}
@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::LineIndex;
+use crate::analysis::{self, DetectedDefinition, FileAnalysis};
+
+// --- SQL: CREATE TABLE/VIEW/INDEX/FUNCTION ---
+// Pasada ligera por expresiones regulares: los dialectos varían demasiado para una gramática
+// y un archivo que no encaja simplemente no aporta definiciones.
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/|--[^\n]*").unwrap();
+    static ref CREATE_STATEMENT: Regex = Regex::new(
+        r#"(?i)\bCREATE\s+(?:OR\s+REPLACE\s+)?(?:(?:GLOBAL|LOCAL)\s+)?(?:TEMP(?:ORARY)?\s+|UNLOGGED\s+)?(?:MATERIALIZED\s+)?(TABLE|VIEW|(?:UNIQUE\s+)?INDEX|FUNCTION)\s+(?:CONCURRENTLY\s+)?(?:IF\s+NOT\s+EXISTS\s+)?([`"\[]?[\w$]+[`"\]]?(?:\.[`"\[]?[\w$]+[`"\]]?)*)"#
+    ).unwrap();
+}
+
+pub fn analyze(path: &Path, content: &str) -> FileAnalysis {
+    let mut output = FileAnalysis::default();
+    // Comentarios enmascarados (conservando saltos de línea) para no listar código comentado
+    let content = COMMENT.replace_all(content, |caps: &regex::Captures| {
+        caps[0].chars().map(|c| if c == '\n' { '\n' } else { ' ' }).collect::<String>()
+    });
+    let lines = LineIndex::new(&content);
+
+    for caps in CREATE_STATEMENT.captures_iter(&content) {
+        let (Some(statement), Some(object), Some(name)) = (caps.get(0), caps.get(1), caps.get(2)) else { continue };
+        let kind = match object.as_str().to_ascii_uppercase().as_str() {
+            "TABLE" => "Table",
+            "VIEW" => "View",
+            "FUNCTION" => "Function",
+            _ => "Index",
+        };
+        // La sentencia termina en el siguiente `;` (o al final del archivo)
+        let end = content[statement.start()..].find(';').map_or(content.len(), |i| statement.start() + i);
+        let first_line = content[statement.start()..end].lines().next().unwrap_or("");
+        output.definitions.push(DetectedDefinition {
+            source_file: path.to_path_buf(),
+            symbol_name: name.as_str().trim_matches(['`', '"', '[', ']']).to_string(),
+            kind: kind.to_string(),
+            line_number: lines.line_at(statement.start()),
+            end_line_number: lines.line_at(end),
+            signature: analysis::compact_signature(first_line.trim()),
+            exported_as: None,
+            parent: None,
+            doc_summary: None,
+            decorators: Vec::new(),
+            handler: None,
+        });
+    }
+
+    output
+}
@@ -1,20 +1,329 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Ocultar consola en Windows release
 
 mod analysis;
+mod archive;
+mod bundler;
+mod cache;
+mod content_search;
+mod corpus;
+mod duplicates;
+mod editor;
+mod git;
+mod graph;
+mod highlight;
+mod html;
+mod json_report;
+mod languages;
+mod monorepo;
+mod output;
+mod packages;
 mod reporting;
+mod search;
+#[cfg(test)]
+mod test_support;
+mod tokens;
+mod tsconfig;
+mod watcher;
 
-use std::path::{ PathBuf};
-use std::sync::mpsc::{ Receiver};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
 use std::time::{Duration, Instant};
 
-use analysis::{AnalysisResult, DetectedDefinition, ResolvedConnection};
-use arboard::Clipboard;
+use analysis::{ProjectAnalysis, ScanProgress};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Polling interval for the channels of background tasks (exports, searches, duplicates)
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// The scan worker wakes the UI itself; this is only a safety net in case a wake-up is missed
+const SCAN_FALLBACK_INTERVAL: Duration = Duration::from_millis(500);
+// Partial scan results are merged and the sections rebuilt at most this often
+const PARTIAL_REFRESH_INTERVAL: Duration = Duration::from_millis(400);
+// Quiet time after the last keystroke in a filter box before the sections are rebuilt
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(200);
+// Quiet time after the last watched file change before an automatic rescan
+const AUTO_RESCAN_DEBOUNCE: Duration = Duration::from_secs(1);
+// Repaint interval while a transient notification is waiting to expire
+const NOTIFICATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// How long the "Copiar Todo" size breakdown stays on screen
+const COPY_BREAKDOWN_DURATION: Duration = Duration::from_secs(6);
+// How long a transient notice (ignored dropped items, editor errors) stays on screen
+const NOTICE_DURATION: Duration = Duration::from_secs(4);
+// How long the target line stays highlighted after opening a definition
+const MODAL_HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+// Height of each link list (definitions, imports, importers) in the content modal header
+const MODAL_LINKS_MAX_HEIGHT: f32 = 120.0;
+
+// Report text size (monospace points) and the longest path shown before eliding its middle
+const DEFAULT_REPORT_FONT_SIZE: f32 = 12.0;
+const REPORT_FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 8.0..=24.0;
+const ELIDED_PATH_MAX_CHARS: usize = 48;
+const DEFAULT_DOC_SUMMARY_WIDTH: usize = 60;
+// Files listed in the hub files section
+const HUB_FILES_TOP_N: usize = 20;
+// Largest files listed in the statistics section
+const LARGEST_FILES_TOP_N: usize = 10;
+// Import hops around the focused file (either direction)
+const DEFAULT_FOCUS_HOPS: usize = 2;
+const FOCUS_HOPS_RANGE: std::ops::RangeInclusive<usize> = 1..=10;
+// Token budget of "Copiar Todo" before its count turns into a warning (a 128k context window)
+const DEFAULT_TOKEN_BUDGET: usize = 128_000;
+// Sections kept first when fitting "Copiar Todo" to the budget; file contents come next, then the rest
+const BUDGET_PRIORITY_SECTIONS: [&str; 3] = ["Estructura", "Conexiones", "Definiciones"];
+// Key of the file content section in the token counts (separate and inline layouts alike)
+const CONTENT_TOKENS_KEY: &str = "Contenidos";
+// Entry-point heuristics of the orphans section, in EntryPointRules builder order
+const ORPHAN_ENTRY_POINT_LABELS: [&str; 5] = ["index.*", "main.*", "*.config.*", "pages/ y app/", "Tests"];
+
+// Key of the persisted Settings in eframe storage
+const SETTINGS_KEY: &str = "settings";
+
+// Settings kept across runs (eframe storage, RON); scan results are never persisted.
+// `#[serde(default)]`: fields missing from older storage keep their defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    // Output
+    content_mode: ContentMode,
+    content_layout: ContentLayout,
+    content_order: reporting::ContentOrder,
+    output_format: output::OutputFormat,
+    relative_paths: bool,
+    anonymize_output: bool,
+    redact_secrets: bool,
+    token_budget: usize,
+    fit_to_budget: bool,
+    // Section visibility
+    show_stats: bool,
+    show_structure: bool,
+    show_connections: bool,
+    show_definitions: bool,
+    show_symbol_index: bool,
+    show_inverse_usage: bool,
+    show_symbol_usage: bool,
+    show_cycles: bool,
+    show_orphans: bool,
+    show_todos: bool,
+    show_hubs: bool,
+    show_project_references: bool,
+    show_task_pipeline: bool,
+    show_directory_summary: bool,
+    show_duplicates: bool,
+    show_file_content: bool,
+    // Section filters
+    filter_structure: String,
+    filter_connections: String,
+    filter_definitions: String,
+    filter_symbol_index: String,
+    filter_inverse_usage: String,
+    filter_symbol_usage: String,
+    filter_orphans: String,
+    filter_todos: String,
+    // Report view
+    report_font_size: f32,
+    elide_paths: bool,
+    syntax_highlighting: bool,
+    editor_command: String,
+    expand_glob_imports: bool,
+    flatten_barrels: bool,
+    runtime_only_usage: bool,
+    show_sizes: bool,
+    hubs_pagerank: bool,
+    orphan_entry_points: [bool; 5],
+    doc_summary_width: usize,
+    // Scan
+    include_class_members: bool,
+    full_doc_comments: bool,
+    respect_gitignore: bool,
+    exclude_patterns: String,
+    include_patterns: String,
+    max_file_bytes: u64,
+    auto_rescan: bool,
+    // Last project
+    last_project: Option<PathBuf>,
+    reopen_last_project: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::from_app(&MyApp::default())
+    }
+}
+
+impl Settings {
+    fn from_app(app: &MyApp) -> Self {
+        Self {
+            content_mode: app.content_mode,
+            content_layout: app.content_layout,
+            content_order: app.content_order,
+            output_format: app.output_format,
+            relative_paths: app.relative_paths,
+            anonymize_output: app.anonymize_output,
+            redact_secrets: app.redact_secrets,
+            token_budget: app.token_budget,
+            fit_to_budget: app.fit_to_budget,
+            show_stats: app.show_stats,
+            show_structure: app.show_structure,
+            show_connections: app.show_connections,
+            show_definitions: app.show_definitions,
+            show_symbol_index: app.show_symbol_index,
+            show_inverse_usage: app.show_inverse_usage,
+            show_symbol_usage: app.show_symbol_usage,
+            show_cycles: app.show_cycles,
+            show_orphans: app.show_orphans,
+            show_todos: app.show_todos,
+            show_hubs: app.show_hubs,
+            show_project_references: app.show_project_references,
+            show_task_pipeline: app.show_task_pipeline,
+            show_directory_summary: app.show_directory_summary,
+            show_duplicates: app.show_duplicates,
+            show_file_content: app.show_file_content,
+            filter_structure: app.filter_structure.clone(),
+            filter_connections: app.filter_connections.clone(),
+            filter_definitions: app.filter_definitions.clone(),
+            filter_symbol_index: app.filter_symbol_index.clone(),
+            filter_inverse_usage: app.filter_inverse_usage.clone(),
+            filter_symbol_usage: app.filter_symbol_usage.clone(),
+            filter_orphans: app.filter_orphans.clone(),
+            filter_todos: app.filter_todos.clone(),
+            report_font_size: app.report_font_size,
+            elide_paths: app.elide_paths,
+            syntax_highlighting: app.syntax_highlighting,
+            editor_command: app.editor_command.clone(),
+            expand_glob_imports: app.expand_glob_imports,
+            flatten_barrels: app.flatten_barrels,
+            runtime_only_usage: app.runtime_only_usage,
+            show_sizes: app.show_sizes,
+            hubs_pagerank: app.hubs_pagerank,
+            orphan_entry_points: app.orphan_entry_points,
+            doc_summary_width: app.doc_summary_width,
+            include_class_members: app.include_class_members,
+            full_doc_comments: app.full_doc_comments,
+            respect_gitignore: app.respect_gitignore,
+            exclude_patterns: app.exclude_patterns.clone(),
+            include_patterns: app.include_patterns.clone(),
+            max_file_bytes: app.max_file_bytes,
+            auto_rescan: app.auto_rescan,
+            last_project: app.last_project.clone(),
+            reopen_last_project: app.reopen_last_project,
+        }
+    }
+
+    fn apply(self, app: &mut MyApp) {
+        app.content_mode = self.content_mode;
+        app.content_layout = self.content_layout;
+        app.content_order = self.content_order;
+        app.output_format = self.output_format;
+        app.relative_paths = self.relative_paths;
+        app.anonymize_output = self.anonymize_output;
+        app.redact_secrets = self.redact_secrets;
+        app.token_budget = self.token_budget;
+        app.fit_to_budget = self.fit_to_budget;
+        app.show_stats = self.show_stats;
+        app.show_structure = self.show_structure;
+        app.show_connections = self.show_connections;
+        app.show_definitions = self.show_definitions;
+        app.show_symbol_index = self.show_symbol_index;
+        app.show_inverse_usage = self.show_inverse_usage;
+        app.show_symbol_usage = self.show_symbol_usage;
+        app.show_cycles = self.show_cycles;
+        app.show_orphans = self.show_orphans;
+        app.show_todos = self.show_todos;
+        app.show_hubs = self.show_hubs;
+        app.show_project_references = self.show_project_references;
+        app.show_task_pipeline = self.show_task_pipeline;
+        app.show_directory_summary = self.show_directory_summary;
+        app.show_duplicates = self.show_duplicates;
+        app.show_file_content = self.show_file_content;
+        app.filter_structure = self.filter_structure;
+        app.filter_connections = self.filter_connections;
+        app.filter_definitions = self.filter_definitions;
+        app.filter_symbol_index = self.filter_symbol_index;
+        app.filter_inverse_usage = self.filter_inverse_usage;
+        app.filter_symbol_usage = self.filter_symbol_usage;
+        app.filter_orphans = self.filter_orphans;
+        app.filter_todos = self.filter_todos;
+        app.report_font_size = self.report_font_size;
+        app.elide_paths = self.elide_paths;
+        app.syntax_highlighting = self.syntax_highlighting;
+        app.editor_command = self.editor_command;
+        app.expand_glob_imports = self.expand_glob_imports;
+        app.flatten_barrels = self.flatten_barrels;
+        app.runtime_only_usage = self.runtime_only_usage;
+        app.show_sizes = self.show_sizes;
+        app.hubs_pagerank = self.hubs_pagerank;
+        app.orphan_entry_points = self.orphan_entry_points;
+        app.doc_summary_width = self.doc_summary_width;
+        app.include_class_members = self.include_class_members;
+        app.full_doc_comments = self.full_doc_comments;
+        app.respect_gitignore = self.respect_gitignore;
+        app.exclude_patterns = self.exclude_patterns;
+        app.include_patterns = self.include_patterns;
+        app.max_file_bytes = self.max_file_bytes;
+        app.auto_rescan = self.auto_rescan;
+        app.last_project = self.last_project;
+        app.reopen_last_project = self.reopen_last_project;
+        app.report_font_size = app.report_font_size.clamp(*REPORT_FONT_SIZE_RANGE.start(), *REPORT_FONT_SIZE_RANGE.end());
+    }
+}
+
+// What the user clicked inside a report section
+#[derive(Clone, Debug)]
+enum ReportClick {
+    File(PathBuf, Option<usize>), // Line to jump to (definitions)
+    Directory(PathBuf),
+    Exclude(PathBuf),
+    Focus(PathBuf),
+    SelectContent(PathBuf, bool), // Structure checkbox: file or folder in/out of the content section
+    OpenInEditor(PathBuf, Option<usize>),
+    CopyPath(PathBuf),
+    Reveal(PathBuf), // Show in the system file manager
+}
+
+// How much of each file goes into the content section
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ContentMode {
+    None,
+    Skeleton, // Imports and signatures, function bodies elided as `{ ... }`
+    Full,
+}
+
+impl ContentMode {
+    fn label(self) -> &'static str {
+        match self {
+            ContentMode::None => "Sin contenido",
+            ContentMode::Skeleton => "Esqueleto (solo firmas)",
+            ContentMode::Full => "Contenido completo",
+        }
+    }
+
+    fn includes_content(self) -> bool {
+        self != ContentMode::None
+    }
+}
+
+// How file contents are laid out when included
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ContentLayout {
+    Separate,   // "## File Contents" section after the reports
+    InlineTree, // Each file's content right under its tree line
+}
+
+impl ContentLayout {
+    fn label(self) -> &'static str {
+        match self {
+            ContentLayout::Separate => "Sección aparte",
+            ContentLayout::InlineTree => "Árbol con contenidos",
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 enum ScanStatus {
     Idle,
     Scanning,
-    Completed(PathBuf, Vec<PathBuf>, Vec<ResolvedConnection>, Vec<DetectedDefinition>),
+    Completed(ProjectAnalysis),
     Error(String),
 }
 
@@ -25,6 +334,12 @@ impl Default for ScanStatus {
 }
 
 fn main() -> Result<(), eframe::Error> {
+    // `context-lens --stdout <folder>`: analyze without opening a window and print the context
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, folder] = args.as_slice() && flag == "--stdout" {
+        std::process::exit(print_context(Path::new(folder)));
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
         ..
@@ -34,43 +349,297 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Project Context Extractor (MVP)",
         options,
-        Box::new(|_cc| Box::<MyApp>::default()),
+        Box::new(|cc| Box::new(MyApp::new(cc))),
     )
 }
 
+type ReportSection<'a> = (&'static str, bool, &'a Option<Vec<reporting::ReportItem>>);
+
+// Only visible, generated sections count; with `inline_tree` the contents replace "Estructura"
+fn assemble_parts(report_sections: &[ReportSection], contents: Option<&str>, inline_tree: bool) -> Vec<output::OutputSection> {
+    let mut parts = Vec::new();
+    for &(name, visible, section) in report_sections {
+        if inline_tree && name == "Estructura" {
+            if let Some(fc) = contents {
+                parts.push(output::OutputSection { name: "Árbol con contenidos", text: fc.trim_end().to_string() });
+            }
+            continue;
+        }
+        if let (true, Some(items)) = (visible, section) {
+            parts.push(output::OutputSection { name, text: MyApp::report_items_to_string(items) });
+        }
+    }
+    if let (false, Some(fc)) = (inline_tree, contents) {
+        parts.push(output::OutputSection { name: "Contenidos", text: fc.trim_end().to_string() });
+    }
+    parts
+}
+
+// Headless mode: structure, connections and definitions through the same pipeline as "Copiar Todo"
+fn print_context(folder: &Path) -> i32 {
+    let receiver = analysis::start_analysis(folder.to_path_buf(), analysis::AnalysisOptions::default(), analysis::ScanOptions::default(), cache::CacheMode::Reuse, || {});
+    let result = loop {
+        match receiver.recv() {
+            Ok(ScanProgress::Finished(result)) => break result,
+            Ok(_) => continue,
+            Err(_) => break Err("El análisis terminó sin resultado".to_string()),
+        }
+    };
+    let analysis = match result {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let root = &analysis.root_path;
+    let sections = vec![
+        output::OutputSection {
+            name: "Estructura",
+            text: MyApp::report_items_to_string(&reporting::generate_structure_section(root, &analysis.root_label, &analysis.files, None)),
+        },
+        output::OutputSection {
+            name: "Conexiones",
+            text: MyApp::report_items_to_string(&reporting::generate_connections_section(root, &analysis.connections, &analysis.package_versions, false, false)),
+        },
+        output::OutputSection {
+            name: "Definiciones",
+            text: MyApp::report_items_to_string(&reporting::generate_definitions_section(root, &analysis.definitions, &analysis.connections, DEFAULT_DOC_SUMMARY_WIDTH)),
+        },
+    ];
+    let mut request = output::OutputRequest::new(sections);
+    request.options.path_root = Some(root.clone());
+    match output::render_and_deliver(&request, &mut output::StdoutSink) {
+        Ok(report) => {
+            eprintln!("{} caracteres, ~{} tokens", report.total_chars, report.estimated_tokens);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
 struct MyApp {
     scan_status: ScanStatus,
-    scan_receiver: Option<Receiver<AnalysisResult>>,
-    include_file_content: bool,
+    scan_receiver: Option<Receiver<ScanProgress>>,
+    repaint_ctx: Option<egui::Context>, // Lets the scan worker wake the UI when messages arrive
+    scanning_message: String,
+    scan_progress: Option<(usize, usize)>, // (analyzed, total)
+    scan_current_file: Option<String>,
+    // Partial results received since the last refresh, merged into the Completed state in batches
+    partial_connections: Vec<analysis::ResolvedConnection>,
+    partial_definitions: Vec<analysis::DetectedDefinition>,
+    partial_refreshed_at: Instant,
+    content_mode: ContentMode,
+    content_layout: ContentLayout,
+    content_order: reporting::ContentOrder,
+    output_format: output::OutputFormat, // Markdown or XML tags, for every copy and export
+    // Content transformations of the output pipeline, applied to every copy and export
+    relative_paths: bool,
+    anonymize_output: bool,
+    redact_secrets: bool,
     copy_notification: Option<Instant>,
+    copy_breakdown: Option<(Instant, output::DeliveryReport)>,
+    notice: Option<(Instant, String)>, // Transient message: extra dropped items, editor launch and watcher errors
+    token_estimator: Box<dyn tokens::TokenEstimator>,
+    section_tokens: HashMap<&'static str, usize>, // Estimated tokens per generated section, by output name
+    token_budget: usize, // "Copiar Todo" total above this is shown as a warning
+    fit_to_budget: bool, // "Copiar Todo" drops low-priority sections and files to stay within token_budget
+    export_receiver: Option<Receiver<Result<output::DeliveryReport, String>>>, // Background "Exportar a archivo..."
+    html_receiver: Option<Receiver<Result<PathBuf, String>>>, // Background "Exportar HTML..."
+    export_status: Option<Result<String, String>>, // Outcome of the last file export
 
     // --- Generated Section Content ---
     // Now storing structured data for interactivity
+    stats_section: Option<Vec<reporting::ReportItem>>,
     structure_section: Option<Vec<reporting::ReportItem>>,
     connections_section: Option<Vec<reporting::ReportItem>>,
     file_content_section: Option<String>, // Keep as String for now
     definitions_section: Option<Vec<reporting::ReportItem>>, // Updated to Vec<ReportItem>
+    symbol_index_section: Option<Vec<reporting::ReportItem>>,
     inverse_usage_section: Option<Vec<reporting::ReportItem>>, // Updated to Vec<ReportItem>
+    symbol_usage_section: Option<Vec<reporting::ReportItem>>,
+    cycles_section: Option<Vec<reporting::ReportItem>>,
+    orphans_section: Option<Vec<reporting::ReportItem>>,
+    todos_section: Option<Vec<reporting::ReportItem>>,
+    hubs_section: Option<Vec<reporting::ReportItem>>,
+    project_references_section: Option<Vec<reporting::ReportItem>>,
+    task_pipeline_section: Option<Vec<reporting::ReportItem>>,
+    directory_summary_section: Option<Vec<reporting::ReportItem>>,
+    duplicates_section: Option<Vec<reporting::ReportItem>>, // Only after "Buscar duplicaciones"
+    duplicates_receiver: Option<Receiver<Vec<duplicates::DuplicateGroup>>>,
+    // --- Content search ("Buscar en el contenido"), shown above the report ---
+    content_search_query: String,
+    content_search_options: content_search::SearchOptions,
+    content_search_receiver: Option<Receiver<content_search::SearchProgress>>,
+    content_search_pattern: String, // Query of the results on screen, which the text box may no longer match
+    content_search_matches: Vec<content_search::ContentMatch>,
+    content_search_section: Option<Vec<reporting::ReportItem>>,
+    content_search_status: Option<Result<String, String>>, // Match count, or an invalid pattern
 
     // --- UI State ---
+    show_stats: bool,
     show_structure: bool,
     show_connections: bool,
     show_definitions: bool,
+    show_symbol_index: bool,
     show_inverse_usage: bool,
+    show_symbol_usage: bool,
+    show_cycles: bool,
+    show_orphans: bool,
+    show_todos: bool,
+    show_hubs: bool,
+    show_project_references: bool,
+    show_task_pipeline: bool,
+    show_directory_summary: bool,
+    show_duplicates: bool,
     show_file_content: bool,
 
     // --- State for section filtering ---
     filter_structure: String,
     filter_connections: String,
     filter_definitions: String,
+    filter_symbol_index: String, // Prefix match on the symbol name
     filter_inverse_usage: String,
+    filter_symbol_usage: String, // Matches the defining file or the symbol name
+    filter_orphans: String,
+    filter_todos: String, // Matches the file, the marker or the comment text
+    filters_edited_at: Option<Instant>, // Pending filter edit; sections rebuild FILTER_DEBOUNCE after it
     // Note: Filtering file content directly might be too slow/complex for now
 
+    // --- Report view ---
+    report_font_size: f32,
+    elide_paths: bool, // Shorten the middle of long paths for display only
+    syntax_highlighting: bool, // Colored code in the file modal and the content section
+    editor_command: String, // "Abrir en editor" template with {path} and {line} placeholders
+    report_focus: HashMap<&'static str, (usize, egui::Id)>, // Last keyboard-focused link per section
+    expand_glob_imports: bool, // List every file matched by require.context / import.meta.glob
+    flatten_barrels: bool, // Follow index.ts re-exports to the files that define the symbols
+    runtime_only_usage: bool, // Leave `import type` edges out of Inverse Usage
+    show_sizes: bool, // Line count and size after each file and directory of the structure tree
+    hubs_pagerank: bool, // Rank hub files by PageRank instead of plain importer count
+    orphan_entry_points: [bool; 5], // Which ORPHAN_ENTRY_POINT_LABELS never count as orphans
+    include_class_members: bool, // Collected during analysis, so toggling it rescans
+    full_doc_comments: bool, // Whole /** */ comment instead of its first line (also rescans)
+    respect_gitignore: bool, // Skip what .gitignore/.ignore exclude (also rescans)
+    exclude_patterns: String, // One glob per line, root-relative; applied on the next scan
+    include_patterns: String,
+    scan_pattern_error: Option<String>,
+    active_scan: Option<analysis::ScanOptions>, // Patterns of the current scan, reused by the watcher
+    active_respect_gitignore: bool, // respect_gitignore of the current scan (the toggle may change meanwhile)
+    project_watcher: Option<watcher::ProjectWatcher>, // Watches the analyzed folder after a successful scan
+    auto_rescan: bool, // Rescan once watched files stop changing, instead of only flagging them
+    pending_change_at: Option<Instant>, // Last watched change not yet rescanned
+    last_project: Option<PathBuf>, // Folder of the last successful scan
+    reopen_last_project: bool, // Scan last_project again on startup
+    max_file_bytes: u64, // Larger files are skipped by the scan and left out of contents (0 = no limit)
+    doc_summary_width: usize, // Max chars of the doc comment shown in Definitions (0 = no limit)
+
+    // --- Session exclusions ("Excluir del contexto") ---
+    excluded_files: HashSet<PathBuf>,
+    // --- Files whose content goes into the content section (structure checkboxes) ---
+    content_selection: ContentSelection,
+    // --- Focus mode: only files within `focus_hops` imports of `focus_file` ---
+    focus_file: Option<PathBuf>,
+    focus_hops: usize,
+    regenerate_sections: bool, // Set when something other than the filters requires regeneration
+
+    // --- Files changed on disk since the scan ---
+    stale_files: HashSet<PathBuf>,
+    missing_file_notice: Option<PathBuf>, // Clicked file that no longer exists
+
+    // --- Corpus export (one document per file) ---
+    corpus_format: corpus::CorpusFormat,
+    corpus_include_content: bool,
+    corpus_receiver: Option<Receiver<corpus::CorpusProgress>>,
+    corpus_progress: Option<(usize, usize)>, // (written, total)
+    corpus_status: Option<String>, // Outcome of the last export
+
+    // --- Graphviz DOT export of the import graph ---
+    dot_direction: reporting::DotDirection,
+    dot_phantom_nodes: bool, // Dashed node per unresolved import
+    dot_clusters: bool, // One subgraph cluster per directory
+
+    // --- Code review packet ---
+    review_base_ref: String,
+    review_error: Option<String>,
+
     // --- Modal State ---
     show_modal: bool,
     modal_file_path: Option<PathBuf>,
     modal_file_content: Option<String>,
     modal_copy_include_path: bool,
+    modal_target: Option<ModalTarget>,
+    modal_history: Vec<PathBuf>, // Files left by following a link in the modal, for "Atrás"
+    file_links: HashMap<PathBuf, FileLinks>, // Built once per scan for the modal header
+}
+
+// Indices into the analysis vectors for one file: what it defines, imports and who imports it
+#[derive(Default)]
+struct FileLinks {
+    definitions: Vec<usize>, // By line, without repeats of the same symbol on a line
+    imports: Vec<usize>,
+    importers: Vec<usize>,
+}
+
+fn build_file_links(analysis: &ProjectAnalysis) -> HashMap<PathBuf, FileLinks> {
+    let mut links: HashMap<PathBuf, FileLinks> = HashMap::new();
+    for (i, def) in analysis.definitions.iter().enumerate() {
+        links.entry(def.source_file.clone()).or_default().definitions.push(i);
+    }
+    for (i, conn) in analysis.connections.iter().enumerate() {
+        links.entry(conn.source_file.clone()).or_default().imports.push(i);
+        if let Some(target) = &conn.resolved_target {
+            links.entry(target.clone()).or_default().importers.push(i);
+        }
+    }
+    for file in links.values_mut() {
+        let definitions = &analysis.definitions;
+        file.definitions.sort_by_key(|&i| definitions[i].line_number);
+        file.definitions.dedup_by(|a, b| definitions[*a].line_number == definitions[*b].line_number && definitions[*a].symbol_name == definitions[*b].symbol_name);
+        file.imports.sort_by_key(|&i| analysis.connections[i].line_number);
+        file.importers.sort_by(|&a, &b| analysis.connections[a].source_file.cmp(&analysis.connections[b].source_file));
+    }
+    links
+}
+
+// Where a link in the content modal's header leads
+#[derive(Clone)]
+enum ModalNav {
+    Line(usize),                  // Same file, scrolled to the line
+    File(PathBuf, Option<usize>), // Another file, pushing the current one onto the history
+    Back,
+}
+
+// Header groups of the content modal: (title, [(label, link)]); entries without a link are plain text
+type ModalLinkGroups = Vec<(&'static str, Vec<(String, Option<ModalNav>)>)>;
+
+fn modal_link_groups(analysis: &ProjectAnalysis, links: &FileLinks) -> ModalLinkGroups {
+    let relative = |p: &Path| p.strip_prefix(&analysis.root_path).unwrap_or(p).display().to_string();
+    let definitions = links.definitions.iter()
+        .map(|&i| &analysis.definitions[i])
+        .map(|d| (format!("L{} {} {}", d.line_number, d.kind, d.symbol_name), Some(ModalNav::Line(d.line_number))))
+        .collect();
+    let imports = links.imports.iter()
+        .map(|&i| &analysis.connections[i])
+        .map(|c| match &c.resolved_target {
+            Some(target) => (format!("L{} {} -> {}", c.line_number, c.imported_string, relative(target)), Some(ModalNav::File(target.clone(), None))),
+            None => (format!("L{} {}", c.line_number, c.imported_string), None),
+        })
+        .collect();
+    let importers = links.importers.iter()
+        .map(|&i| &analysis.connections[i])
+        .map(|c| (format!("{}:{}", relative(&c.source_file), c.line_number), Some(ModalNav::File(c.source_file.clone(), Some(c.line_number)))))
+        .collect();
+    vec![("Definiciones", definitions), ("Importa", imports), ("Importado por", importers)]
+}
+
+// Line the content modal scrolls to and highlights; the highlight starts once it is on screen
+struct ModalTarget {
+    line: usize,
+    shown_at: Option<Instant>,
 }
 
 impl Default for MyApp {
@@ -78,136 +647,894 @@ impl Default for MyApp {
         Self {
             scan_status: ScanStatus::Idle,
             scan_receiver: None,
-            include_file_content: false,
+            repaint_ctx: None,
+            scanning_message: String::new(),
+            scan_progress: None,
+            scan_current_file: None,
+            partial_connections: Vec::new(),
+            partial_definitions: Vec::new(),
+            partial_refreshed_at: Instant::now(),
+            content_mode: ContentMode::None,
+            content_layout: ContentLayout::Separate,
+            content_order: reporting::ContentOrder::Alphabetical,
+            output_format: output::OutputFormat::Markdown,
+            relative_paths: false,
+            anonymize_output: false,
+            redact_secrets: false,
             copy_notification: None,
+            copy_breakdown: None,
+            notice: None,
+            token_estimator: Box::new(tokens::CharHeuristic),
+            section_tokens: HashMap::new(),
+            token_budget: DEFAULT_TOKEN_BUDGET,
+            fit_to_budget: false,
+            export_receiver: None,
+            html_receiver: None,
+            export_status: None,
+            stats_section: None,
             structure_section: None,
             connections_section: None,
             file_content_section: None,
             definitions_section: None,
+            symbol_index_section: None,
             inverse_usage_section: None,
+            symbol_usage_section: None,
+            cycles_section: None,
+            orphans_section: None,
+            todos_section: None,
+            hubs_section: None,
+            project_references_section: None,
+            task_pipeline_section: None,
+            directory_summary_section: None,
+            duplicates_section: None,
+            duplicates_receiver: None,
+            content_search_query: String::new(),
+            content_search_options: content_search::SearchOptions::default(),
+            content_search_receiver: None,
+            content_search_pattern: String::new(),
+            content_search_matches: Vec::new(),
+            content_search_section: None,
+            content_search_status: None,
             // Initialize visibility flags
+            show_stats: true,
             show_structure: true,
             show_connections: true,
             show_definitions: true,
+            show_symbol_index: true,
             show_inverse_usage: true,
+            show_symbol_usage: true,
+            show_cycles: true,
+            show_orphans: true,
+            show_todos: true,
+            show_hubs: true,
+            show_project_references: true,
+            show_task_pipeline: true,
+            show_directory_summary: true,
+            show_duplicates: true,
             show_file_content: true, // Default to visible if generated
 
             // Initialize filter strings
             filter_structure: String::new(),
             filter_connections: String::new(),
             filter_definitions: String::new(),
+            filter_symbol_index: String::new(),
             filter_inverse_usage: String::new(),
+            filter_symbol_usage: String::new(),
+            filter_orphans: String::new(),
+            filter_todos: String::new(),
+            filters_edited_at: None,
+
+            report_font_size: DEFAULT_REPORT_FONT_SIZE,
+            elide_paths: false,
+            syntax_highlighting: true,
+            editor_command: editor::default_command(),
+            report_focus: HashMap::new(),
+            expand_glob_imports: false,
+            flatten_barrels: false,
+            runtime_only_usage: false,
+            show_sizes: false,
+            hubs_pagerank: false,
+            orphan_entry_points: [true; 5],
+            include_class_members: false,
+            full_doc_comments: false,
+            respect_gitignore: true,
+            exclude_patterns: String::new(),
+            include_patterns: String::new(),
+            scan_pattern_error: None,
+            active_scan: None,
+            active_respect_gitignore: true,
+            project_watcher: None,
+            auto_rescan: false,
+            pending_change_at: None,
+            last_project: None,
+            reopen_last_project: false,
+            max_file_bytes: analysis::DEFAULT_MAX_FILE_BYTES,
+            doc_summary_width: DEFAULT_DOC_SUMMARY_WIDTH,
+
+            excluded_files: HashSet::new(),
+            content_selection: ContentSelection::default(),
+            focus_file: None,
+            focus_hops: DEFAULT_FOCUS_HOPS,
+            regenerate_sections: false,
+
+            stale_files: HashSet::new(),
+            missing_file_notice: None,
+
+            corpus_format: corpus::CorpusFormat::Markdown,
+            corpus_include_content: true,
+            corpus_receiver: None,
+            corpus_progress: None,
+            corpus_status: None,
+
+            dot_direction: reporting::DotDirection::LeftRight,
+            dot_phantom_nodes: false,
+            dot_clusters: false,
+
+            review_base_ref: "HEAD".to_string(),
+            review_error: None,
 
             // Initialize modal state
             show_modal: false,
             modal_file_path: None,
             modal_file_content: None,
             modal_copy_include_path: false,
+            modal_target: None,
+            modal_history: Vec::new(),
+            file_links: HashMap::new(),
         }
     }
 }
 
 // --- Funciones Helper para UI ---
 
-fn copy_to_clipboard(text_to_copy: &str, copy_notification: &mut Option<Instant>) {
-    match Clipboard::new() {
-        Ok(mut clipboard) => {
-            if let Err(e) = clipboard.set_text(text_to_copy) {
-                eprintln!("Error al copiar al portapapeles: {}", e);
-                *copy_notification = None; 
+// How report sections are drawn; shared by every display_section call
+struct SectionView<'a> {
+    stale_files: &'a HashSet<PathBuf>,
+    font_size: f32,
+    elide_paths: bool,
+    focus: &'a mut HashMap<&'static str, (usize, egui::Id)>, // Keyboard-focused item per section
+    content_selection: Option<&'a ContentSelection>, // Checkboxes in the structure while contents are included
+}
+
+// A clickable path inside a section: a whole item, or one span of a composite line
+struct SectionLink<'a, 'p> {
+    display: &'a str,
+    label: &'a str, // Full line, for screen readers
+    path: &'p Path,
+    is_file: bool,
+    line: Option<usize>, // Definitions open the file at their line
+}
+
+// Stable id for a report link: section + path (+ occurrence, as a path can repeat in a section)
+fn report_item_id<'p>(section: &str, path: &'p Path, occurrences: &mut HashMap<&'p Path, usize>) -> egui::Id {
+    let occurrence = occurrences.entry(path).or_default();
+    *occurrence += 1;
+    egui::Id::new((section, path, *occurrence))
+}
+
+// Focusable link with a stable id; screen readers get the full (non-elided) line
+fn report_link(ui: &mut egui::Ui, id: egui::Id, text: egui::RichText, label: &str) -> egui::Response {
+    let response = ui.push_id(id, |ui| ui.link(text)).inner;
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Link, label));
+    response
+}
+
+// Elides the path part of a report line, keeping tree-drawing prefixes intact
+fn elide_report_line(line: &str) -> String {
+    let path_start = line
+        .find(|c: char| !matches!(c, '├' | '└' | '│' | '─' | ' ' | '~'))
+        .unwrap_or(line.len());
+    let (prefix, path) = line.split_at(path_start);
+    format!("{}{}", prefix, reporting::elide_path_middle(path, ELIDED_PATH_MAX_CHARS))
+}
+
+// Section label with its estimated size, once the section has been generated
+fn token_label(label: &str, counts: &HashMap<&'static str, usize>, section: &str) -> String {
+    match counts.get(section) {
+        Some(tokens) => format!("{} (~{})", label, format_count(*tokens)),
+        None => label.to_string(),
+    }
+}
+
+fn format_count(n: usize) -> String {
+    if n >= 1_000_000 {
+        format!("{:.1}M", n as f64 / 1_000_000.0)
+    } else if n >= 1_000 {
+        format!("{:.1}k", n as f64 / 1_000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+// Exact count with thousands separators (1,243) for progress labels
+fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+// A path is excluded if it or one of its ancestor directories was excluded
+fn is_path_excluded(path: &Path, excluded: &HashSet<PathBuf>) -> bool {
+    !excluded.is_empty() && excluded.iter().any(|e| path.starts_with(e))
+}
+
+// Structure checkboxes for the content section. Unchecked files are stored rather than checked
+// ones, so everything starts selected, new files join the selection and filters don't reset it.
+#[derive(Default)]
+struct ContentSelection {
+    unchecked: HashSet<PathBuf>,
+    directories: HashMap<PathBuf, (usize, usize)>, // Files below each folder: (total, unchecked)
+}
+
+impl ContentSelection {
+    fn is_selected(&self, path: &Path) -> bool {
+        !self.unchecked.contains(path)
+    }
+
+    // (checked, mixed) for a file or folder checkbox
+    fn state(&self, path: &Path) -> (bool, bool) {
+        match self.directories.get(path) {
+            Some(&(total, unchecked)) => (unchecked == 0, unchecked > 0 && unchecked < total),
+            None => (self.is_selected(path), false),
+        }
+    }
+
+    fn retain_selected(&self, files: Vec<PathBuf>) -> Vec<PathBuf> {
+        files.into_iter().filter(|f| self.is_selected(f)).collect()
+    }
+
+    // Checks or unchecks `path` and, for a folder, every file below it
+    fn set(&mut self, root_path: &Path, files: &[PathBuf], path: &Path, selected: bool) {
+        for file in files.iter().filter(|f| f.starts_with(path)) {
+            if selected {
+                self.unchecked.remove(file);
             } else {
-                *copy_notification = Some(Instant::now());
+                self.unchecked.insert(file.clone());
             }
         }
-        Err(e) => {
-            eprintln!("Error al inicializar el portapapeles: {}", e);
-             *copy_notification = None;
+        self.recount(root_path, files);
+    }
+
+    fn set_all(&mut self, root_path: &Path, files: &[PathBuf], selected: bool) {
+        self.unchecked = if selected { HashSet::new() } else { files.iter().cloned().collect() };
+        self.recount(root_path, files);
+    }
+
+    // Folder tallies for the tri-state checkboxes; after toggling or when the file list changes
+    fn recount(&mut self, root_path: &Path, files: &[PathBuf]) {
+        self.directories.clear();
+        for file in files {
+            let unchecked = usize::from(!self.is_selected(file));
+            for dir in file.ancestors().skip(1).take_while(|d| d.starts_with(root_path) && *d != root_path) {
+                let counts = self.directories.entry(dir.to_path_buf()).or_default();
+                counts.0 += 1;
+                counts.1 += unchecked;
+            }
         }
     }
 }
 
+fn content_order_label(order: reporting::ContentOrder) -> &'static str {
+    match order {
+        reporting::ContentOrder::Alphabetical => "Alfabético",
+        reporting::ContentOrder::DependenciesFirst => "Dependencias primero",
+        reporting::ContentOrder::Importance => "Importancia",
+    }
+}
+
+// Files within `hops` imports of `focus` (dependencies and dependents), ignoring excluded files.
+// None when there is no focus or the focused file is not part of this analysis.
+fn focus_set(analysis: &ProjectAnalysis, excluded: &HashSet<PathBuf>, focus: Option<&PathBuf>, hops: usize) -> Option<HashSet<PathBuf>> {
+    let focus = focus.filter(|f| analysis.files.contains(f))?;
+    let mut adjacency: HashMap<&PathBuf, Vec<&PathBuf>> = HashMap::new();
+    for conn in analysis.connections.iter().filter(|c| !is_path_excluded(&c.source_file, excluded)) {
+        for target in conn.resolved_target.iter().chain(&conn.pattern_matches) {
+            if !is_path_excluded(target, excluded) {
+                adjacency.entry(&conn.source_file).or_default().push(target);
+            }
+        }
+    }
+    Some(graph::within_hops(&adjacency, &focus, hops).into_iter().cloned().collect())
+}
+
+// Clones the visible items whose precomputed search keys pass `matches`, in parallel.
+// `items`, `keys` and `visible` are parallel slices.
+fn select_matching<T: Clone + Send + Sync, K: Sync>(
+    items: &[T],
+    keys: &[K],
+    visible: &[bool],
+    matches: impl Fn(&K) -> bool + Sync,
+) -> Vec<T> {
+    items.par_iter()
+        .zip(keys.par_iter())
+        .zip(visible.par_iter())
+        .filter(|((_, key), is_visible)| **is_visible && matches(key))
+        .map(|((item, _), _)| item.clone())
+        .collect()
+}
+
 impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SETTINGS_KEY, &Settings::from_app(self));
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Release the analysis so extracted archives are cleaned up
+        self.scan_status = ScanStatus::Idle;
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut trigger_section_generation = false;
         let mut trigger_content_generation_only = false;
 
-        if let Some(rx) = &self.scan_receiver {
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok((root_path, files, connections, definitions)) => {
-                        self.scan_status = ScanStatus::Completed(root_path, files, connections, definitions);
+        // Folders and archives dropped onto the window are analyzed like a picked folder;
+        // any other file analyzes its parent folder
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if let Some(first) = dropped.first() {
+            let target = if first.is_dir() || archive::archive_kind(first).is_some() {
+                Some(first.clone())
+            } else {
+                first.parent().map(Path::to_path_buf)
+            };
+            if self.is_scanning() {
+                self.notice = Some((Instant::now(), "Hay un análisis en curso; se ignoró lo soltado.".to_string()));
+            } else if let Some(target) = target {
+                if dropped.len() > 1 {
+                    let name = first.file_name().and_then(|n| n.to_str()).unwrap_or("el primero");
+                    self.notice = Some((
+                        Instant::now(),
+                        format!("Se soltaron {} elementos; solo se analiza {}.", dropped.len(), name),
+                    ));
+                }
+                self.start_scan(target);
+            }
+        }
+
+        // Drop hint while files are dragged over the window
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let screen = ctx.screen_rect();
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_hint")));
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Suelta una carpeta o un archivo comprimido para analizarlo",
+                egui::FontId::proportional(20.0),
+                egui::Color32::WHITE,
+            );
+        }
+
+        if let Some(rx) = self.scan_receiver.take() {
+            let mut scan_finished = false;
+            // Drain every pending message this frame instead of one per frame
+            loop {
+                match rx.try_recv() {
+                    Ok(ScanProgress::FilesDiscovered(total)) => self.scan_progress = Some((0, total)),
+                    Ok(ScanProgress::PartialFiles { root_path, files }) => {
+                        // Show the structure right away; connections and definitions arrive later
+                        self.content_selection.recount(&root_path, &files);
+                        self.scan_status = ScanStatus::Completed(ProjectAnalysis::partial(root_path, files));
                         trigger_section_generation = true;
+                        self.partial_refreshed_at = Instant::now();
                     }
-                    Err(err_msg) => {
-                        self.scan_status = ScanStatus::Error(err_msg);
-                        self.clear_generated_sections();
+                    Ok(ScanProgress::FileAnalyzed { done, total, current_path }) => {
+                        self.scan_progress = Some((done, total));
+                        self.scan_current_file = current_path.file_name().map(|n| n.to_string_lossy().into_owned());
+                    }
+                    Ok(ScanProgress::PartialConnections(connections)) => self.partial_connections.extend(connections),
+                    Ok(ScanProgress::PartialDefinitions(definitions)) => self.partial_definitions.extend(definitions),
+                    Ok(ScanProgress::Finished(result)) => {
+                        match result {
+                            Ok(analysis) => {
+                                // Watch the folder for later edits (an archive is a fixed snapshot)
+                                if analysis.archive.is_none() {
+                                    self.last_project = Some(analysis.root_path.clone());
+                                    let repaint_ctx = ctx.clone();
+                                    let scan = self.active_scan.clone().unwrap_or_default();
+                                    // Usually the inotify watch limit on a large repo: say so instead of silently
+                                    // losing staleness tracking and auto-rescan
+                                    match watcher::watch_project(&analysis.root_path, self.active_respect_gitignore, scan, move || repaint_ctx.request_repaint()) {
+                                        Ok(project_watcher) => self.project_watcher = Some(project_watcher),
+                                        Err(e) => {
+                                            self.notice = Some((Instant::now(), format!("{}. Los cambios en disco no se detectarán hasta re-analizar.", e)));
+                                        }
+                                    }
+                                }
+                                self.content_selection.recount(&analysis.root_path, &analysis.files);
+                                self.file_links = build_file_links(&analysis);
+                                self.scan_status = ScanStatus::Completed(analysis);
+                                trigger_section_generation = true;
+                            }
+                            Err(err_msg) => {
+                                self.scan_status = ScanStatus::Error(err_msg);
+                                self.clear_generated_sections();
+                            }
+                        }
+                        scan_finished = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        // The worker went away without sending a result
+                        if !scan_finished {
+                            self.scan_status = ScanStatus::Error("El análisis terminó sin resultado".to_string());
+                            self.clear_generated_sections();
+                        }
+                        scan_finished = true;
+                        break;
                     }
                 }
-                self.scan_receiver = None;
+            }
+
+            if scan_finished {
+                self.scan_progress = None;
+                self.scan_current_file = None;
+                // The final analysis already contains everything that was streamed
+                self.partial_connections.clear();
+                self.partial_definitions.clear();
             } else {
-                 ctx.request_repaint();
+                let has_partial = !self.partial_connections.is_empty() || !self.partial_definitions.is_empty();
+                if has_partial && self.partial_refreshed_at.elapsed() >= PARTIAL_REFRESH_INTERVAL {
+                    if let ScanStatus::Completed(analysis) = &mut self.scan_status {
+                        analysis.merge_partial(std::mem::take(&mut self.partial_connections), std::mem::take(&mut self.partial_definitions));
+                        trigger_section_generation = true;
+                    }
+                    self.partial_refreshed_at = Instant::now();
+                }
+                self.scan_receiver = Some(rx);
+                // The worker requests a repaint when messages arrive; this only covers a missed one
+                ctx.request_repaint_after(SCAN_FALLBACK_INTERVAL);
             }
         }
 
+        // Watched changes flag the files as stale; with auto-rescan, a rescan follows once they settle
+        if let Some(watcher) = &self.project_watcher {
+            let changes = watcher.drain_changes();
+            if !changes.is_empty() {
+                self.stale_files.extend(changes);
+                self.pending_change_at = Some(Instant::now());
+            }
+        }
+        if let Some(changed_at) = self.pending_change_at {
+            if !self.auto_rescan {
+                self.pending_change_at = None;
+            } else if changed_at.elapsed() < AUTO_RESCAN_DEBOUNCE {
+                ctx.request_repaint_after(AUTO_RESCAN_DEBOUNCE - changed_at.elapsed());
+            } else if !self.is_scanning() {
+                self.pending_change_at = None;
+                if let Some(root_path) = self.rescan_root() {
+                    self.start_scan(root_path);
+                }
+            }
+        }
+
+        // Poll the on-demand duplicate search
+        if let Some(rx) = self.duplicates_receiver.take() {
+            match rx.try_recv() {
+                Ok(groups) => {
+                    if let ScanStatus::Completed(analysis) = &self.scan_status {
+                        self.duplicates_section = Some(reporting::generate_duplicates_section(&analysis.root_path, &groups));
+                        self.show_duplicates = true;
+                        self.refresh_token_counts();
+                    }
+                }
+                Err(TryRecvError::Empty) => {
+                    self.duplicates_receiver = Some(rx);
+                    ctx.request_repaint_after(SCAN_POLL_INTERVAL);
+                }
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+
+        // Poll the content search; matches stream in file by file
+        if let Some(rx) = self.content_search_receiver.take() {
+            let mut finished = None;
+            let mut received = false;
+            loop {
+                match rx.try_recv() {
+                    Ok(content_search::SearchProgress::Matches(matches)) => {
+                        self.content_search_matches.extend(matches);
+                        received = true;
+                    }
+                    Ok(content_search::SearchProgress::Finished { files_searched, truncated }) => {
+                        finished = Some((files_searched, truncated));
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => {
+                        self.content_search_receiver = Some(rx);
+                        ctx.request_repaint_after(SCAN_POLL_INTERVAL);
+                        break;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            }
+            if let ScanStatus::Completed(analysis) = &self.scan_status {
+                if received || finished.is_some() {
+                    let truncated = finished.is_some_and(|(_, truncated)| truncated);
+                    self.content_search_section = Some(reporting::generate_content_search_section(
+                        &analysis.root_path,
+                        &self.content_search_pattern,
+                        &self.content_search_matches,
+                        truncated,
+                    ));
+                }
+            }
+            if let Some((files_searched, _)) = finished {
+                let files_matched = self.content_search_matches.iter().map(|m| &m.path).collect::<HashSet<_>>().len();
+                self.content_search_status = Some(Ok(format!(
+                    "{} coincidencias en {} de {} archivos",
+                    format_count(self.content_search_matches.len()),
+                    format_count(files_matched),
+                    format_count(files_searched),
+                )));
+            }
+        }
+
+        // Poll the file export
+        if let Some(rx) = self.export_receiver.take() {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.export_status = Some(result.map(|report| {
+                        format!("Exportado a {} (~{} tokens)", report.destination, format_count(report.estimated_tokens))
+                    }));
+                }
+                Err(TryRecvError::Empty) => {
+                    self.export_receiver = Some(rx);
+                    ctx.request_repaint_after(SCAN_POLL_INTERVAL);
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.export_status = Some(Err("La exportación terminó sin resultado".to_string()));
+                }
+            }
+        }
+
+        // Poll the HTML export
+        if let Some(rx) = self.html_receiver.take() {
+            match rx.try_recv() {
+                Ok(result) => self.export_status = Some(result.map(|path| format!("Informe HTML exportado a {}", path.display()))),
+                Err(TryRecvError::Empty) => {
+                    self.html_receiver = Some(rx);
+                    ctx.request_repaint_after(SCAN_POLL_INTERVAL);
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.export_status = Some(Err("La exportación terminó sin resultado".to_string()));
+                }
+            }
+        }
+
+        // Poll the corpus export
+        if let Some(rx) = self.corpus_receiver.take() {
+            let mut finished = false;
+            loop {
+                match rx.try_recv() {
+                    Ok(corpus::CorpusProgress::Written { done, total }) => self.corpus_progress = Some((done, total)),
+                    Ok(corpus::CorpusProgress::Finished(result)) => {
+                        self.corpus_status = Some(match result {
+                            Ok(count) => format!("{} documentos exportados", count),
+                            Err(e) => format!("Error: {}", e),
+                        });
+                        finished = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+            if finished {
+                self.corpus_progress = None;
+            } else {
+                self.corpus_receiver = Some(rx);
+                ctx.request_repaint_after(SCAN_POLL_INTERVAL);
+            }
+        }
+
+        if self.copy_notification.is_some() {
+            // Make sure the "¡Copiado!" label disappears even without user input
+            ctx.request_repaint_after(NOTIFICATION_POLL_INTERVAL);
+        }
+
         // --- Panel Superior ---
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                  
-                let analysis_button_enabled = !matches!(self.scan_status, ScanStatus::Scanning);
-                let analysis_button_text = match self.scan_status { ScanStatus::Scanning => "Analizando...", _ => "Analizar Proyecto" };
+                let analysis_button_enabled = !self.is_scanning();
+                let analysis_button_text = if self.is_scanning() { "Analizando..." } else { "Analizar Proyecto" };
                 if ui.add_enabled(analysis_button_enabled, egui::Button::new(analysis_button_text)).clicked() {
                     if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                        self.scan_status = ScanStatus::Scanning;
-                        self.clear_generated_sections();
-                        self.scan_receiver = Some(analysis::start_analysis(path));
+                        self.start_scan(path);
+                    }
+                }
+                if ui.add_enabled(analysis_button_enabled, egui::Button::new("Analizar Archivo...")).clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Archivos comprimidos", &["zip", "gz", "tgz"])
+                        .pick_file()
+                    {
+                        self.start_scan(path);
+                    }
+                }
+                let rescan_root = self.rescan_root().filter(|_| self.analysis_ready());
+                if ui.add_enabled(rescan_root.is_some(), egui::Button::new("Reescanear"))
+                    .on_hover_text("Vuelve a analizar la misma carpeta; solo se re-parsean los archivos cambiados")
+                    .clicked()
+                {
+                    if let Some(root_path) = rescan_root {
+                        self.start_scan(root_path);
                     }
                 }
                 ui.separator();
 
                 
-                let is_completed = matches!(self.scan_status, ScanStatus::Completed(_, _, _, _));
-                let checkbox_changed = ui.add_enabled(is_completed, egui::Checkbox::new(&mut self.include_file_content, "Incluir contenido")).changed();
-                if checkbox_changed && is_completed {
-                    trigger_content_generation_only = true;
-                }
+                // Partial results are browsable while streaming, but copy and export wait for the full analysis
+                let is_completed = self.analysis_ready();
+                ui.add_enabled_ui(is_completed, |ui| {
+                    egui::ComboBox::from_id_source("content_mode")
+                        .selected_text(self.content_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in [ContentMode::None, ContentMode::Skeleton, ContentMode::Full] {
+                                if ui.selectable_value(&mut self.content_mode, mode, mode.label()).changed() {
+                                    trigger_content_generation_only = true;
+                                }
+                            }
+                        });
+                });
+                ui.add_enabled_ui(is_completed && self.content_mode.includes_content(), |ui| {
+                    egui::ComboBox::from_id_source("content_layout")
+                        .selected_text(self.content_layout.label())
+                        .show_ui(ui, |ui| {
+                            for layout in [ContentLayout::Separate, ContentLayout::InlineTree] {
+                                if ui.selectable_value(&mut self.content_layout, layout, layout.label()).changed() {
+                                    trigger_content_generation_only = true;
+                                }
+                            }
+                        });
+                    // Same as ticking every structure checkbox, or none
+                    if ui.button("Todos").on_hover_text("Incluir el contenido de todos los archivos").clicked() {
+                        if let ScanStatus::Completed(analysis) = &self.scan_status {
+                            self.content_selection.set_all(&analysis.root_path, &analysis.files, true);
+                            trigger_content_generation_only = true;
+                        }
+                    }
+                    if ui.button("Ninguno").on_hover_text("Quitar todos los archivos del contenido").clicked() {
+                        if let ScanStatus::Completed(analysis) = &self.scan_status {
+                            self.content_selection.set_all(&analysis.root_path, &analysis.files, false);
+                            trigger_content_generation_only = true;
+                        }
+                    }
+                    // The inline tree always follows tree order
+                    ui.add_enabled_ui(self.content_layout == ContentLayout::Separate, |ui| {
+                        egui::ComboBox::from_label("Orden del contenido")
+                            .selected_text(content_order_label(self.content_order))
+                            .show_ui(ui, |ui| {
+                                for order in [reporting::ContentOrder::Alphabetical, reporting::ContentOrder::DependenciesFirst, reporting::ContentOrder::Importance] {
+                                    if ui.selectable_value(&mut self.content_order, order, content_order_label(order)).changed() {
+                                        trigger_content_generation_only = true;
+                                    }
+                                }
+                            });
+                    });
+                });
+                ui.add_enabled_ui(is_completed, |ui| {
+                    egui::ComboBox::from_id_source("output_format")
+                        .selected_text(self.output_format.label())
+                        .show_ui(ui, |ui| {
+                            for format in [output::OutputFormat::Markdown, output::OutputFormat::XmlTags] {
+                                // File contents are rendered per format, so they are regenerated
+                                if ui.selectable_value(&mut self.output_format, format, format.label()).changed() {
+                                    trigger_content_generation_only = true;
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Formato de copia y exportación");
+                    ui.menu_button("Transformar", |ui| {
+                        ui.checkbox(&mut self.relative_paths, "Rutas relativas")
+                            .on_hover_text("Las rutas absolutas dentro de la raíz se copian relativas a ella");
+                        ui.checkbox(&mut self.anonymize_output, "Anonimizar")
+                            .on_hover_text("Sustituye la carpeta personal por ~ y el nombre de usuario por <usuario>");
+                        ui.checkbox(&mut self.redact_secrets, "Redactar secretos")
+                            .on_hover_text("Claves de API, tokens, contraseñas y claves privadas pasan a [REDACTADO]");
+                    })
+                    .response
+                    .on_hover_text("Transformaciones aplicadas a cada copia y exportación");
+                });
                 ui.separator();
                 
                 
                 let copy_enabled = is_completed;
-                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Estructura")).clicked() {
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Estadísticas", &self.section_tokens, "Estadísticas"))).clicked() {
+                    if let Some(items) = &self.stats_section {
+                        let section = output::OutputSection { name: "Estadísticas", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Estructura", &self.section_tokens, "Estructura"))).clicked() {
                     if let Some(items) = &self.structure_section {
-                        // Convert ReportItems to String before copying
-                        let text_to_copy = Self::report_items_to_string(items);
-                        copy_to_clipboard(&text_to_copy, &mut self.copy_notification);
+                        let section = output::OutputSection { name: "Estructura", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
                     }
                 }
-                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Conexiones")).clicked() {
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Conexiones", &self.section_tokens, "Conexiones"))).clicked() {
                     if let Some(items) = &self.connections_section {
-                        // Convert ReportItems to String before copying
-                        let text_to_copy = Self::report_items_to_string(items);
-                        copy_to_clipboard(&text_to_copy, &mut self.copy_notification);
+                        let section = output::OutputSection { name: "Conexiones", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
                     }
                 }
-                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Definiciones")).clicked() {
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Definiciones", &self.section_tokens, "Definiciones"))).clicked() {
                     if let Some(items) = &self.definitions_section {
-                        // Convert ReportItems to String before copying
-                        let text_to_copy = Self::report_items_to_string(items);
-                        copy_to_clipboard(&text_to_copy, &mut self.copy_notification);
+                        let section = output::OutputSection { name: "Definiciones", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Índice", &self.section_tokens, "Índice"))).clicked() {
+                    if let Some(items) = &self.symbol_index_section {
+                        let section = output::OutputSection { name: "Índice", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
                     }
                 }
-                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Usos")).clicked() {
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Usos", &self.section_tokens, "Usos"))).clicked() {
                     if let Some(items) = &self.inverse_usage_section {
-                        // Convert ReportItems to String before copying
-                        let text_to_copy = Self::report_items_to_string(items);
-                        copy_to_clipboard(&text_to_copy, &mut self.copy_notification);
+                        let section = output::OutputSection { name: "Usos", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Símbolos", &self.section_tokens, "Símbolos"))).clicked() {
+                    if let Some(items) = &self.symbol_usage_section {
+                        let section = output::OutputSection { name: "Símbolos", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
                     }
                 }
-                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Todo")).clicked() {
-                     let full_context = self.rebuild_full_context();
-                    copy_to_clipboard(&full_context, &mut self.copy_notification);
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Ciclos", &self.section_tokens, "Ciclos"))).clicked() {
+                    if let Some(items) = &self.cycles_section {
+                        let section = output::OutputSection { name: "Ciclos", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Huérfanos", &self.section_tokens, "Huérfanos"))).clicked() {
+                    if let Some(items) = &self.orphans_section {
+                        let section = output::OutputSection { name: "Huérfanos", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Hubs", &self.section_tokens, "Hubs"))).clicked() {
+                    if let Some(items) = &self.hubs_section {
+                        let section = output::OutputSection { name: "Hubs", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar TODOs", &self.section_tokens, "TODOs"))).clicked() {
+                    if let Some(items) = &self.todos_section {
+                        let section = output::OutputSection { name: "TODOs", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Referencias", &self.section_tokens, "Referencias"))).clicked() {
+                    if let Some(items) = &self.project_references_section {
+                        let section = output::OutputSection { name: "Referencias", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Tareas", &self.section_tokens, "Tareas"))).clicked() {
+                    if let Some(items) = &self.task_pipeline_section {
+                        let section = output::OutputSection { name: "Tareas", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new(token_label("Copiar Resumen", &self.section_tokens, "Resumen"))).clicked() {
+                    if let Some(items) = &self.directory_summary_section {
+                        let section = output::OutputSection { name: "Resumen", text: Self::report_items_to_string(items) };
+                        self.copy_sections(vec![section]);
+                    }
+                }
+                // Total of the visible sections, in the warning colour above the budget
+                let context_tokens = self.context_tokens();
+                let copy_all = format!("Copiar Todo (~{})", format_count(context_tokens));
+                let (copy_all, budget_hint) = if context_tokens > self.token_budget {
+                    (egui::RichText::new(copy_all).color(egui::Color32::from_rgb(230, 160, 40)), "Supera el presupuesto de tokens")
+                } else {
+                    (egui::RichText::new(copy_all), "Dentro del presupuesto de tokens")
+                };
+                if ui.add_enabled(copy_enabled, egui::Button::new(copy_all)).on_hover_text(budget_hint).clicked() {
+                    let parts = self.full_context_parts();
+                    if let Some(report) = self.copy_sections(parts) {
+                        self.copy_breakdown = Some((Instant::now(), report));
+                    }
+                }
+                let exporting = self.export_receiver.is_some();
+                if ui.add_enabled(copy_enabled && !exporting, egui::Button::new("Exportar a archivo..."))
+                    .on_hover_text("Guarda lo mismo que Copiar Todo en un archivo Markdown")
+                    .clicked()
+                {
+                    if let (ScanStatus::Completed(analysis), Some(path)) = (
+                        &self.scan_status,
+                        rfd::FileDialog::new().add_filter("Markdown", &["md"]).set_file_name("contexto.md").save_file(),
+                    ) {
+                        // Archives are extracted to a temp dir, so name the archive itself
+                        let root = if analysis.archive.is_some() { analysis.root_label.clone() } else { analysis.root_path.display().to_string() };
+                        let header = output::export_header(&root, std::time::SystemTime::now());
+                        let mut sections = vec![output::OutputSection { name: "Cabecera", text: header }];
+                        sections.extend(self.full_context_parts());
+                        self.export_status = None;
+                        let request = self.output_request(sections);
+                        self.export_receiver = Some(output::start_file_export(request, path));
+                    }
+                }
+                if exporting {
+                    ui.spinner();
+                }
+                let exporting_html = self.html_receiver.is_some();
+                if ui.add_enabled(copy_enabled && !exporting_html, egui::Button::new("Exportar HTML..."))
+                    .on_hover_text("Informe navegable en un solo archivo: secciones visibles enlazadas al contenido de cada archivo")
+                    .clicked()
+                {
+                    if let (ScanStatus::Completed(analysis), Some(path)) = (
+                        &self.scan_status,
+                        rfd::FileDialog::new().add_filter("HTML", &["html"]).set_file_name("contexto.html").save_file(),
+                    ) {
+                        let sections: Vec<(&'static str, Vec<reporting::ReportItem>)> = self.report_sections()
+                            .into_iter()
+                            .filter_map(|(name, visible, items)| items.clone().filter(|_| visible).map(|items| (name, items)))
+                            .collect();
+                        let (files, _) = self.visible_scope(analysis);
+                        let files = self.content_selection.retain_selected(files);
+                        self.export_status = None;
+                        self.html_receiver = Some(html::start_html_export(analysis.root_label.clone(), analysis.root_path.clone(), sections, files, path, self.max_file_bytes));
+                    }
+                }
+                if exporting_html {
+                    ui.spinner();
+                }
+                if ui.add_enabled(copy_enabled, egui::Button::new("Exportar JSON..."))
+                    .on_hover_text("Archivos, conexiones y definiciones del análisis completo, con rutas relativas a la raíz")
+                    .clicked()
+                {
+                    if let (ScanStatus::Completed(analysis), Some(path)) = (
+                        &self.scan_status,
+                        rfd::FileDialog::new().add_filter("JSON", &["json"]).set_file_name("analisis.json").save_file(),
+                    ) {
+                        let report = json_report::AnalysisReport::new(&analysis.root_path, &analysis.files, &analysis.connections, &analysis.definitions);
+                        self.export_status = Some(report.write(&path).map(|()| format!("Análisis exportado a {}", path.display())));
+                    }
+                }
+                ui.add(egui::DragValue::new(&mut self.token_budget).speed(1000.0).prefix("Presupuesto: ").suffix(" tokens"))
+                    .on_hover_text("Estimación aproximada (~4 caracteres por token)");
+                ui.checkbox(&mut self.fit_to_budget, "Ajustar al presupuesto")
+                    .on_hover_text("Copiar Todo incluye estructura, conexiones, definiciones y contenidos (los más importados primero) hasta el presupuesto, y anota lo omitido");
+                if ui.add_enabled(copy_enabled, egui::Button::new("Copiar Revisión"))
+                    .on_hover_text("Diff respecto a la ref base, contenido de los cambios, dependientes y tests")
+                    .clicked()
+                {
+                    if let ScanStatus::Completed(analysis) = &self.scan_status {
+                        let git = git::GitCli::new(&analysis.root_path);
+                        match reporting::generate_review_packet(
+                            &analysis.root_path,
+                            &analysis.files,
+                            &analysis.connections,
+                            &analysis.definitions,
+                            &git,
+                            self.review_base_ref.trim(),
+                            self.max_file_bytes,
+                        ) {
+                            Ok(packet) => {
+                                self.review_error = None;
+                                self.copy_sections(vec![output::OutputSection { name: "Revisión", text: packet }]);
+                            }
+                            Err(err) => self.review_error = Some(err),
+                        }
+                    }
                 }
 
-                
+                match &self.export_status {
+                    Some(Ok(message)) => { ui.label(message); }
+                    Some(Err(err)) => { ui.label(egui::RichText::new(err).color(egui::Color32::RED)); }
+                    None => {}
+                }
                 if let Some(copy_time) = self.copy_notification {
                     if copy_time.elapsed() < Duration::from_secs(2) {
                          ui.label(egui::RichText::new("¡Copiado!").color(egui::Color32::GREEN));
@@ -225,142 +1552,665 @@ impl eframe::App for MyApp {
             .show(ctx, |ui| {
                 ui.heading("Mostrar Secciones");
                 ui.separator();
-                ui.checkbox(&mut self.show_structure, "Estructura");
-                ui.checkbox(&mut self.show_connections, "Conexiones");
-                ui.checkbox(&mut self.show_definitions, "Definiciones");
-                ui.checkbox(&mut self.show_inverse_usage, "Usos Inversos");
-                ui.add_enabled(self.include_file_content, egui::Checkbox::new(&mut self.show_file_content, "Contenido Archivos"));
+                ui.checkbox(&mut self.show_stats, token_label("Estadísticas", &self.section_tokens, "Estadísticas"));
+                ui.checkbox(&mut self.show_directory_summary, token_label("Resumen por carpeta", &self.section_tokens, "Resumen"));
+                ui.checkbox(&mut self.show_structure, token_label("Estructura", &self.section_tokens, "Estructura"));
+                ui.checkbox(&mut self.show_connections, token_label("Conexiones", &self.section_tokens, "Conexiones"));
+                ui.checkbox(&mut self.show_definitions, token_label("Definiciones", &self.section_tokens, "Definiciones"));
+                ui.checkbox(&mut self.show_symbol_index, token_label("Índice de símbolos", &self.section_tokens, "Índice"));
+                ui.checkbox(&mut self.show_inverse_usage, token_label("Usos Inversos", &self.section_tokens, "Usos"));
+                ui.checkbox(&mut self.show_symbol_usage, token_label("Usos por símbolo", &self.section_tokens, "Símbolos"));
+                ui.checkbox(&mut self.show_cycles, token_label("Ciclos", &self.section_tokens, "Ciclos"));
+                ui.checkbox(&mut self.show_orphans, token_label("Huérfanos", &self.section_tokens, "Huérfanos"));
+                ui.checkbox(&mut self.show_hubs, token_label("Archivos hub", &self.section_tokens, "Hubs"));
+                ui.checkbox(&mut self.show_todos, token_label("TODOs", &self.section_tokens, "TODOs"));
+                ui.checkbox(&mut self.show_project_references, token_label("Referencias de proyecto", &self.section_tokens, "Referencias"));
+                ui.checkbox(&mut self.show_task_pipeline, token_label("Pipeline de tareas", &self.section_tokens, "Tareas"));
+                ui.add_enabled(self.duplicates_section.is_some(), egui::Checkbox::new(&mut self.show_duplicates, token_label("Duplicaciones", &self.section_tokens, "Duplicados")));
+                ui.add_enabled(self.content_mode.includes_content(), egui::Checkbox::new(&mut self.show_file_content, token_label("Contenido Archivos", &self.section_tokens, CONTENT_TOKENS_KEY)));
                 ui.separator();
 
                 // --- Filter Inputs ---
                 ui.heading("Filtrar");
-                ui.label("Estructura:");
-                ui.text_edit_singleline(&mut self.filter_structure);
-                ui.label("Conexiones:");
-                ui.text_edit_singleline(&mut self.filter_connections);
-                ui.label("Definiciones:");
-                ui.text_edit_singleline(&mut self.filter_definitions);
-                 ui.label("Usos Inversos:");
-                ui.text_edit_singleline(&mut self.filter_inverse_usage);
+                let mut filters_changed = false;
+                for (label, filter) in [
+                    ("Estructura:", &mut self.filter_structure),
+                    ("Conexiones:", &mut self.filter_connections),
+                    ("Definiciones:", &mut self.filter_definitions),
+                    ("Índice (prefijo):", &mut self.filter_symbol_index),
+                    ("Usos Inversos:", &mut self.filter_inverse_usage),
+                    ("Usos por símbolo:", &mut self.filter_symbol_usage),
+                    ("Huérfanos:", &mut self.filter_orphans),
+                    ("TODOs:", &mut self.filter_todos),
+                ] {
+                    ui.label(label);
+                    // Clearing a filter counts as an edit too, so the unfiltered sections come back
+                    filters_changed |= ui.text_edit_singleline(filter).changed();
+                }
+                if filters_changed {
+                    self.filters_edited_at = Some(Instant::now());
+                }
                 // ---------------------
 
                 // Ensure visibility is off if generation is off
-                if !self.include_file_content {
+                if !self.content_mode.includes_content() {
                     self.show_file_content = false;
                 }
 
+                // --- Focus mode ---
+                if let Some(focus) = self.focus_file.clone() {
+                    ui.separator();
+                    ui.heading("Foco");
+                    let display = match &self.scan_status {
+                        ScanStatus::Completed(analysis) => focus.strip_prefix(&analysis.root_path).unwrap_or(&focus).display().to_string(),
+                        _ => focus.display().to_string(),
+                    };
+                    ui.label(display);
+                    if ui.add(egui::Slider::new(&mut self.focus_hops, FOCUS_HOPS_RANGE).text("Saltos"))
+                        .on_hover_text("Imports de distancia, en ambos sentidos (dependencias y dependientes)")
+                        .changed()
+                    {
+                        self.regenerate_sections = true;
+                    }
+                    if ui.button("Quitar foco").clicked() {
+                        self.focus_file = None;
+                        self.regenerate_sections = true;
+                    }
+                }
+
+                // --- Session exclusions ---
+                if !self.excluded_files.is_empty() {
+                    ui.separator();
+                    ui.heading("Excluidos");
+                    let root_path = match &self.scan_status {
+                        ScanStatus::Completed(analysis) => Some(analysis.root_path.clone()),
+                        _ => None,
+                    };
+                    let mut sorted_excluded: Vec<PathBuf> = self.excluded_files.iter().cloned().collect();
+                    sorted_excluded.sort();
+                    for path in sorted_excluded {
+                        let display = root_path.as_ref()
+                            .and_then(|root| path.strip_prefix(root).ok())
+                            .unwrap_or(&path)
+                            .display()
+                            .to_string();
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").on_hover_text("Volver a incluir").clicked() {
+                                self.excluded_files.remove(&path);
+                                self.regenerate_sections = true;
+                            }
+                            ui.label(display);
+                        });
+                    }
+                    if ui.button("Limpiar").clicked() {
+                        self.excluded_files.clear();
+                        self.regenerate_sections = true;
+                    }
+                }
+
+                // --- Scan settings: per-run glob patterns (next scan) and file size limit ---
+                ui.separator();
+                ui.heading("Escaneo");
+                ui.label("Excluir (uno por línea)");
+                let exclude_changed = ui.add(egui::TextEdit::multiline(&mut self.exclude_patterns)
+                    .hint_text("**/*.test.ts\nfixtures/**")
+                    .desired_rows(2))
+                    .changed();
+                ui.label("Incluir solo");
+                let include_changed = ui.add(egui::TextEdit::multiline(&mut self.include_patterns)
+                    .hint_text("src/**")
+                    .desired_rows(2))
+                    .changed();
+                if exclude_changed || include_changed {
+                    self.scan_pattern_error = analysis::ScanOptions::from_patterns(&self.exclude_patterns, &self.include_patterns).err();
+                }
+                if let Some(error) = &self.scan_pattern_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                let mut max_file_kb = self.max_file_bytes / 1024;
+                if ui.add(egui::DragValue::new(&mut max_file_kb).speed(16.0).prefix("Tamaño máximo: ").suffix(" KB"))
+                    .on_hover_text("Los archivos más grandes no se analizan ni se vuelcan en Contenidos (0 = sin límite)")
+                    .changed()
+                {
+                    self.max_file_bytes = max_file_kb * 1024;
+                    self.regenerate_sections = true;
+                }
+                let rescan_root = self.rescan_root();
+                if ui.add_enabled(rescan_root.is_some() && self.scan_pattern_error.is_none() && !self.is_scanning(), egui::Button::new("Re-analizar"))
+                    .on_hover_text("Los patrones se aplican en el próximo análisis")
+                    .clicked()
+                {
+                    if let Some(root_path) = rescan_root.clone() {
+                        self.start_scan(root_path);
+                    }
+                }
+                if ui.add_enabled(rescan_root.is_some() && self.scan_pattern_error.is_none() && !self.is_scanning(), egui::Button::new("Reescanear (completo)"))
+                    .on_hover_text("Vuelve a parsear todos los archivos sin usar la caché")
+                    .clicked()
+                {
+                    if let Some(root_path) = rescan_root {
+                        self.start_scan_with_cache(root_path, cache::CacheMode::Refresh);
+                    }
+                }
+                ui.checkbox(&mut self.auto_rescan, "Re-analizar al cambiar archivos")
+                    .on_hover_text("Sin marcar, los archivos editados solo se señalan como desactualizados");
+                ui.checkbox(&mut self.reopen_last_project, "Reabrir el último proyecto al iniciar");
+
+                // --- Report view ---
+                ui.separator();
+                ui.heading("Vista");
+                ui.add(egui::Slider::new(&mut self.report_font_size, REPORT_FONT_SIZE_RANGE).text("Tamaño"));
+                ui.checkbox(&mut self.elide_paths, "Acortar rutas largas");
+                ui.checkbox(&mut self.syntax_highlighting, "Resaltado de sintaxis")
+                    .on_hover_text("Colorea el código del visor y de Contenido de Archivos; desactívalo si va lento");
+                ui.horizontal(|ui| {
+                    ui.label("Editor:");
+                    ui.text_edit_singleline(&mut self.editor_command)
+                        .on_hover_text("Comando de \"Abrir en editor\"; {path} y {line} se sustituyen por el archivo y la línea");
+                });
+                if ui.checkbox(&mut self.show_sizes, "Mostrar tamaños")
+                    .on_hover_text("Líneas y tamaño de cada archivo y carpeta en Estructura (también al copiar)")
+                    .changed()
+                {
+                    self.regenerate_sections = true;
+                }
+                if ui.checkbox(&mut self.expand_glob_imports, "Expandir imports glob").changed() {
+                    self.regenerate_sections = true;
+                }
+                if ui.checkbox(&mut self.flatten_barrels, "Resolver barrels")
+                    .on_hover_text("Seguir los re-exports de archivos index/barrel hasta los archivos finales")
+                    .changed()
+                {
+                    self.regenerate_sections = true;
+                }
+                if ui.checkbox(&mut self.hubs_pagerank, "PageRank en hubs")
+                    .on_hover_text("Ordenar los archivos hub por PageRank del grafo de imports en vez de por número de importadores")
+                    .changed()
+                {
+                    self.regenerate_sections = true;
+                }
+                if ui.checkbox(&mut self.runtime_only_usage, "Usos sin imports de tipo")
+                    .on_hover_text("Omitir `import type` en Usos Inversos: solo dependencias en tiempo de ejecución")
+                    .changed()
+                {
+                    self.regenerate_sections = true;
+                }
+                let members_changed = ui.checkbox(&mut self.include_class_members, "Incluir miembros de clase")
+                    .on_hover_text("Métodos y propiedades de las clases en Definiciones (re-analiza el proyecto)")
+                    .changed();
+                let docs_changed = ui.checkbox(&mut self.full_doc_comments, "Doc completa")
+                    .on_hover_text("Todo el comentario /** */ en vez de su primera línea (re-analiza el proyecto)")
+                    .changed();
+                let gitignore_changed = ui.checkbox(&mut self.respect_gitignore, "Respetar .gitignore")
+                    .on_hover_text("Omitir lo que excluyen los .gitignore (también los de subcarpetas) y .ignore del proyecto (re-analiza el proyecto)")
+                    .changed();
+                if members_changed || docs_changed || gitignore_changed {
+                    if let Some(root_path) = self.rescan_root() {
+                        self.start_scan(root_path);
+                    }
+                }
+                if ui.add(egui::Slider::new(&mut self.doc_summary_width, 0..=200).text("Ancho doc"))
+                    .on_hover_text("Caracteres del comentario mostrados en Definiciones (0 = sin límite)")
+                    .changed()
+                {
+                    self.regenerate_sections = true;
+                }
+                ui.collapsing("Puntos de entrada (huérfanos)", |ui| {
+                    for (enabled, label) in self.orphan_entry_points.iter_mut().zip(ORPHAN_ENTRY_POINT_LABELS) {
+                        if ui.checkbox(enabled, label).changed() {
+                            self.regenerate_sections = true;
+                        }
+                    }
+                });
+
+                // --- On-demand duplicate module search ---
+                ui.separator();
+                ui.heading("Duplicaciones");
+                let searching = self.duplicates_receiver.is_some();
+                let can_search = self.analysis_ready() && !searching;
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(can_search, egui::Button::new("Buscar duplicaciones"))
+                        .on_hover_text("Compara carpetas por nombres de archivo, contenido y símbolos exportados")
+                        .clicked()
+                    {
+                        if let ScanStatus::Completed(analysis) = &self.scan_status {
+                            let files: Vec<PathBuf> = analysis.files.iter()
+                                .filter(|f| !is_path_excluded(f, &self.excluded_files))
+                                .cloned()
+                                .collect();
+                            self.duplicates_receiver = Some(duplicates::start_duplicate_search(files, analysis.definitions.clone()));
+                        }
+                    }
+                    if searching {
+                        ui.spinner();
+                    }
+                });
+
+                // --- Content search across the scanned files ---
+                ui.separator();
+                ui.heading("Buscar en el contenido");
+                let content_searching = self.content_search_receiver.is_some();
+                let submitted = ui.add(egui::TextEdit::singleline(&mut self.content_search_query).hint_text("Texto o expresión regular"))
+                    .lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.horizontal_wrapped(|ui| {
+                    ui.checkbox(&mut self.content_search_options.regex, "Regex");
+                    ui.checkbox(&mut self.content_search_options.case_insensitive, "Ignorar mayúsculas");
+                    ui.checkbox(&mut self.content_search_options.whole_word, "Palabra completa");
+                });
+                ui.horizontal(|ui| {
+                    let can_search = self.analysis_ready() && !content_searching && !self.content_search_query.is_empty();
+                    let clicked = ui.add_enabled(can_search, egui::Button::new("Buscar")).clicked();
+                    if clicked || (submitted && can_search) {
+                        if let ScanStatus::Completed(analysis) = &self.scan_status {
+                            let (files, _) = self.visible_scope(analysis);
+                            match content_search::build_pattern(&self.content_search_query, self.content_search_options) {
+                                Ok(pattern) => {
+                                    self.content_search_receiver = Some(content_search::start_content_search(files, pattern, self.max_file_bytes));
+                                    self.content_search_pattern = self.content_search_query.clone();
+                                    self.content_search_matches.clear();
+                                    self.content_search_section = None;
+                                    self.content_search_status = None;
+                                }
+                                Err(e) => self.content_search_status = Some(Err(e)),
+                            }
+                        }
+                    }
+                    if content_searching {
+                        ui.spinner();
+                    }
+                    if ui.add_enabled(self.content_search_section.is_some(), egui::Button::new("Copiar resultados"))
+                        .on_hover_text("Copia las coincidencias como `ruta:línea: texto`")
+                        .clicked()
+                    {
+                        if let Some(items) = &self.content_search_section {
+                            let section = output::OutputSection { name: "Búsqueda", text: Self::report_items_to_string(items) };
+                            self.copy_sections(vec![section]);
+                        }
+                    }
+                    if ui.add_enabled(self.content_search_section.is_some() || content_searching, egui::Button::new("Cerrar")).clicked() {
+                        self.clear_content_search();
+                    }
+                });
+                match &self.content_search_status {
+                    Some(Ok(summary)) => { ui.label(summary); }
+                    Some(Err(e)) => { ui.colored_label(egui::Color32::RED, e); }
+                    None => {}
+                }
+
+                // --- Corpus export ---
+                ui.separator();
+                ui.heading("Corpus");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.corpus_format, corpus::CorpusFormat::Markdown, "Markdown");
+                    ui.radio_value(&mut self.corpus_format, corpus::CorpusFormat::Json, "JSON");
+                });
+                ui.checkbox(&mut self.corpus_include_content, "Incluir contenido");
+                let exporting = self.corpus_receiver.is_some();
+                let can_export = self.analysis_ready() && !exporting;
+                if ui.add_enabled(can_export, egui::Button::new("Exportar corpus..."))
+                    .on_hover_text("Un documento por archivo, replicando el árbol del proyecto, más un manifiesto")
+                    .clicked()
+                {
+                    if let (ScanStatus::Completed(analysis), Some(out_dir)) = (&self.scan_status, rfd::FileDialog::new().pick_folder()) {
+                        let files: Vec<PathBuf> = analysis.files.iter()
+                            .filter(|f| !is_path_excluded(f, &self.excluded_files))
+                            .cloned()
+                            .collect();
+                        let options = corpus::CorpusOptions { format: self.corpus_format, include_content: self.corpus_include_content, max_file_bytes: self.max_file_bytes };
+                        self.corpus_status = None;
+                        self.corpus_progress = Some((0, files.len()));
+                        self.corpus_receiver = Some(corpus::start_corpus_export(
+                            analysis.root_path.clone(),
+                            files,
+                            analysis.connections.clone(),
+                            analysis.definitions.clone(),
+                            out_dir,
+                            options,
+                        ));
+                    }
+                }
+                if let Some((done, total)) = self.corpus_progress {
+                    let fraction = if total == 0 { 1.0 } else { done as f32 / total as f32 };
+                    ui.add(egui::ProgressBar::new(fraction).text(format!("{}/{}", done, total)));
+                }
+                if let Some(status) = &self.corpus_status {
+                    ui.label(status);
+                }
+
+                // --- Graphviz DOT export ---
+                ui.separator();
+                ui.heading("Grafo DOT");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.dot_direction, reporting::DotDirection::LeftRight, "Izq. a der.");
+                    ui.radio_value(&mut self.dot_direction, reporting::DotDirection::TopBottom, "Arriba a abajo");
+                });
+                ui.checkbox(&mut self.dot_phantom_nodes, "Imports sin resolver");
+                ui.checkbox(&mut self.dot_clusters, "Agrupar por carpeta");
+                if ui.add_enabled(self.analysis_ready(), egui::Button::new("Exportar DOT..."))
+                    .on_hover_text("Grafo de imports para Graphviz (dot -Tsvg)")
+                    .clicked()
+                {
+                    if let (ScanStatus::Completed(analysis), Some(path)) = (
+                        &self.scan_status,
+                        rfd::FileDialog::new().add_filter("Graphviz", &["dot", "gv"]).set_file_name("imports.dot").save_file(),
+                    ) {
+                        // Excluded files drop out of the graph along with their edges
+                        let connections: Vec<analysis::ResolvedConnection> = analysis.connections.iter()
+                            .filter(|c| !is_path_excluded(&c.source_file, &self.excluded_files))
+                            .filter(|c| c.resolved_target.as_ref().is_none_or(|t| !is_path_excluded(t, &self.excluded_files)))
+                            .cloned()
+                            .collect();
+                        let options = reporting::DotOptions::default()
+                            .direction(self.dot_direction)
+                            .phantom_nodes(self.dot_phantom_nodes)
+                            .cluster_by_directory(self.dot_clusters);
+                        let dot = reporting::generate_dot_graph(&analysis.root_path, &connections, &options);
+                        self.export_status = Some(
+                            output::write_atomically(&path, dot.as_bytes())
+                                .map(|()| format!("Grafo exportado a {}", path.display()))
+                                .map_err(|e| format!("Error al escribir {}: {}", path.display(), e)),
+                        );
+                    }
+                }
+
+                // --- Code review packet ---
+                ui.separator();
+                ui.heading("Revisión");
+                ui.label("Ref base (git):");
+                ui.text_edit_singleline(&mut self.review_base_ref);
+                if let Some(err) = &self.review_error {
+                    ui.label(egui::RichText::new(err).color(egui::Color32::RED));
+                }
+
                 // TODO: Add filtering controls here in the future?
             });
 
         
         // --- Section Generation Logic (Applying Filters) ---
-        if trigger_section_generation || 
-           // Regenerate sections if filters change and we have data
-           (matches!(self.scan_status, ScanStatus::Completed(_,_,_,_)) && 
-            (self.filter_structure.len() > 0 || self.filter_connections.len() > 0 || 
-             self.filter_definitions.len() > 0 || self.filter_inverse_usage.len() > 0))
-         {
-             if let ScanStatus::Completed(root_path, files, connections, definitions) = &self.scan_status {
-                // Apply filters BEFORE generating sections
-                
-                // Filter Files for Structure Section
-                let filtered_files: Vec<PathBuf> = files.iter()
-                    .filter(|path| {
-                        if self.filter_structure.is_empty() { return true; }
-                        path.strip_prefix(root_path).unwrap_or(path)
-                           .to_string_lossy().to_lowercase()
-                           .contains(&self.filter_structure.to_lowercase())
+        if self.regenerate_sections && matches!(self.scan_status, ScanStatus::Completed(_)) {
+            trigger_section_generation = true;
+        }
+        self.regenerate_sections = false;
+
+        // Filters apply once typing pauses; sections are otherwise kept between frames
+        if let Some(edited_at) = self.filters_edited_at {
+            let elapsed = edited_at.elapsed();
+            if elapsed >= FILTER_DEBOUNCE {
+                self.filters_edited_at = None;
+                trigger_section_generation |= matches!(self.scan_status, ScanStatus::Completed(_));
+            } else {
+                ctx.request_repaint_after(FILTER_DEBOUNCE - elapsed);
+            }
+        }
+
+        let mut sections_rebuilt = false;
+        if trigger_section_generation {
+             if let ScanStatus::Completed(analysis) = &self.scan_status {
+                sections_rebuilt = true;
+                let ProjectAnalysis { root_path, files, connections, definitions, ts_projects, .. } = analysis;
+
+                let keys = &analysis.search_keys;
+                let excluded = &self.excluded_files;
+                let focus = focus_set(analysis, excluded, self.focus_file.as_ref(), self.focus_hops);
+                let in_focus = |path: &Path| focus.as_ref().is_none_or(|f| f.contains(path));
+
+                // Drop session exclusions first; connections touching an excluded file are hidden too.
+                // In focus mode, everything outside the focused neighbourhood is hidden the same way.
+                let file_visible: Vec<bool> = files.par_iter()
+                    .map(|f| !is_path_excluded(f, excluded) && in_focus(f))
+                    .collect();
+                let connection_visible: Vec<bool> = connections.par_iter()
+                    .map(|c| {
+                        !is_path_excluded(&c.source_file, excluded)
+                            && in_focus(&c.source_file)
+                            && c.resolved_target.as_ref().map_or(true, |t| !is_path_excluded(t, excluded) && in_focus(t))
                     })
-                    .cloned()
                     .collect();
-                self.structure_section = Some(reporting::generate_structure_section(root_path, &filtered_files));
+                let definition_visible: Vec<bool> = definitions.par_iter()
+                    .map(|d| !is_path_excluded(&d.source_file, excluded) && in_focus(&d.source_file))
+                    .collect();
+                let hidden_edges = connection_visible.iter().filter(|v| !**v).count();
+
+                let visible_files = select_matching(files, &keys.files, &file_visible, |_| true);
+                let visible_connections = select_matching(connections, &keys.connections, &connection_visible, |_| true);
+                let visible_definitions = select_matching(definitions, &keys.definitions, &definition_visible, |_| true);
+
+                // Apply filters BEFORE generating sections (keys are already lowercased)
+                let structure_filter = self.filter_structure.to_lowercase();
+                let connections_filter = self.filter_connections.to_lowercase();
+                let definitions_filter = self.filter_definitions.to_lowercase();
+                let symbol_index_filter = self.filter_symbol_index.to_lowercase();
+                let inverse_filter = self.filter_inverse_usage.to_lowercase();
+                let symbol_usage_filter = self.filter_symbol_usage.to_lowercase();
+                let orphans_filter = self.filter_orphans.to_lowercase();
+                let todos_filter = self.filter_todos.to_lowercase();
+
+                // Filter Files for Structure Section
+                let filtered_files = select_matching(files, &keys.files, &file_visible, |key| key.contains(&structure_filter));
+                self.structure_section = Some(reporting::generate_structure_section(root_path, &analysis.root_label, &filtered_files, self.show_sizes.then_some(&*analysis.file_metrics)));
+
+                // Project scale, from the line counts taken during analysis
+                self.stats_section = Some(reporting::generate_stats_section(root_path, &visible_files, &visible_connections, &visible_definitions, &analysis.file_metrics, &analysis.skipped_files, LARGEST_FILES_TOP_N));
+
+                // Directory summary is an orientation overview: exclusions and focus apply, the text filters do not
+                self.directory_summary_section = Some(reporting::generate_directory_summary_section(root_path, &visible_files, &visible_connections, &visible_definitions, &analysis.package_versions));
 
                 // Filter Connections for Connections Section
-                let filtered_connections: Vec<ResolvedConnection> = connections.iter()
-                    .filter(|conn| {
-                        if self.filter_connections.is_empty() { return true; }
-                        let filter_lower = self.filter_connections.to_lowercase();
-                        let source_match = conn.source_file.strip_prefix(root_path).unwrap_or(&conn.source_file)
-                                           .to_string_lossy().to_lowercase().contains(&filter_lower);
-                        let import_match = conn.imported_string.to_lowercase().contains(&filter_lower);
-                        let target_match = conn.resolved_target.as_ref().map_or(false, |target| {
-                            target.strip_prefix(root_path).unwrap_or(target)
-                                  .to_string_lossy().to_lowercase().contains(&filter_lower)
-                        });
-                        source_match || import_match || target_match
-                    })
-                    .cloned()
-                    .collect();
-                 self.connections_section = Some(reporting::generate_connections_section(root_path, &filtered_connections));
-
-                 // Filter Definitions for Definitions Section
-                 let filtered_definitions: Vec<DetectedDefinition> = definitions.iter()
-                     .filter(|def| {
-                         if self.filter_definitions.is_empty() { return true; }
-                         let filter_lower = self.filter_definitions.to_lowercase();
-                         let source_match = def.source_file.strip_prefix(root_path).unwrap_or(&def.source_file)
-                                            .to_string_lossy().to_lowercase().contains(&filter_lower);
-                         let symbol_match = def.symbol_name.to_lowercase().contains(&filter_lower);
-                         let kind_match = def.kind.to_lowercase().contains(&filter_lower);
-                         source_match || symbol_match || kind_match
-                     })
-                     .cloned()
-                     .collect();
-                 self.definitions_section = Some(reporting::generate_definitions_section(root_path, &filtered_definitions));
+                let filtered_connections = select_matching(connections, &keys.connections, &connection_visible, |key| key.matches(&connections_filter));
+                 let mut connections_items = reporting::generate_connections_section(root_path, &filtered_connections, &analysis.package_versions, self.expand_glob_imports, self.flatten_barrels);
+                 if hidden_edges > 0 {
+                     connections_items.push(reporting::ReportItem::PlainText(format!(
+                         "_{} conexiones ocultas por archivos excluidos._\n", hidden_edges
+                     )));
+                 }
+                 self.connections_section = Some(connections_items);
+
+                 // Filter Definitions for Definitions Section; route handlers resolve through every visible connection
+                 let filtered_definitions = select_matching(definitions, &keys.definitions, &definition_visible, |key| key.matches(&definitions_filter));
+                 self.definitions_section = Some(reporting::generate_definitions_section(root_path, &filtered_definitions, &visible_connections, self.doc_summary_width));
+
+                 // Symbol index: prefix match so typing `use` narrows to hooks
+                 let indexed_definitions = select_matching(definitions, &keys.definitions, &definition_visible, |key| key.symbol.starts_with(&symbol_index_filter));
+                 self.symbol_index_section = Some(reporting::generate_symbol_index_section(root_path, &indexed_definitions));
 
                  // Filter Connections for Inverse Usage Section
-                 let filtered_connections_for_inverse: Vec<ResolvedConnection> = connections.iter()
-                     .filter(|conn| {
-                         if self.filter_inverse_usage.is_empty() { return true; }
-                         let filter_lower = self.filter_inverse_usage.to_lowercase();
-                         let source_match = conn.source_file.strip_prefix(root_path).unwrap_or(&conn.source_file)
-                                            .to_string_lossy().to_lowercase().contains(&filter_lower);
-                         let target_match = conn.resolved_target.as_ref().map_or(false, |target| {
-                            target.strip_prefix(root_path).unwrap_or(target)
-                                  .to_string_lossy().to_lowercase().contains(&filter_lower)
-                        });
-                         source_match || target_match
-                     })
-                     .cloned()
+                 let filtered_connections_for_inverse = select_matching(connections, &keys.connections, &connection_visible, |key| {
+                     key.source.contains(&inverse_filter) || key.matches_target(&inverse_filter)
+                 });
+                 self.inverse_usage_section = Some(reporting::generate_inverse_usage_section(root_path, &filtered_connections_for_inverse, self.flatten_barrels, self.runtime_only_usage));
+
+                 // Symbol usage filters the exports; importers come from every visible connection
+                 let filtered_exports = select_matching(definitions, &keys.definitions, &definition_visible, |key| {
+                     key.source.contains(&symbol_usage_filter) || key.symbol.contains(&symbol_usage_filter)
+                 });
+                 self.symbol_usage_section = Some(reporting::generate_symbol_usage_section(root_path, &visible_connections, &filtered_exports));
+
+                 // Cycles need the whole graph: a filter would only hide edges and break cycles
+                 self.cycles_section = Some(reporting::generate_cycles_section(root_path, &visible_connections));
+
+                 // Orphans: the filter narrows the candidate files; importers come from every visible connection
+                 let orphan_candidates = select_matching(files, &keys.files, &file_visible, |key| key.contains(&orphans_filter));
+                 let [index_files, main_files, config_files, routes, tests] = self.orphan_entry_points;
+                 let entry_points = reporting::EntryPointRules::default()
+                     .index_files(index_files)
+                     .main_files(main_files)
+                     .config_files(config_files)
+                     .routes(routes)
+                     .tests(tests);
+                 self.orphans_section = Some(reporting::generate_orphans_section(root_path, &orphan_candidates, &visible_connections, &entry_points));
+
+                 // Hub ranking needs every visible edge, like the cycles
+                 self.hubs_section = Some(reporting::generate_hubs_section(root_path, &visible_connections, HUB_FILES_TOP_N, self.hubs_pagerank));
+
+                 // TODO/FIXME inventory, keyed by file, marker and comment text
+                 let todo_visible: Vec<bool> = analysis.todos.par_iter()
+                     .map(|t| !is_path_excluded(&t.source_file, excluded) && in_focus(&t.source_file))
                      .collect();
-                 self.inverse_usage_section = Some(reporting::generate_inverse_usage_section(root_path, &filtered_connections_for_inverse));
+                 let filtered_todos = select_matching(&analysis.todos, &keys.todos, &todo_visible, |key| key.contains(&todos_filter));
+                 self.todos_section = Some(reporting::generate_todos_section(root_path, &filtered_todos));
+
+                 // Project references check for missing references against every visible connection, ignoring the connections filter
+                 self.project_references_section = Some(reporting::generate_project_references_section(root_path, ts_projects, &visible_connections));
+
+                 // Task pipeline cross-checks workspace imports, so it also ignores the connections filter
+                 self.task_pipeline_section = Some(reporting::generate_task_pipeline_section(root_path, &analysis.monorepo, &visible_connections));
                  
                  // File content generation remains unchanged (not filtered currently)
-                 if self.include_file_content {
-                     self.file_content_section = Some(reporting::generate_file_content_section(root_path, files));
+                 if self.content_mode.includes_content() {
+                     let skeleton = self.content_mode == ContentMode::Skeleton;
+                     let content_files = self.content_selection.retain_selected(visible_files);
+                     self.file_content_section = Some(match self.content_layout {
+                         ContentLayout::Separate => reporting::generate_file_content_section(root_path, &content_files, skeleton, &visible_connections, self.content_order, self.output_format, self.max_file_bytes),
+                         // Inline tree follows the structure filter, like the tree it replaces
+                         ContentLayout::InlineTree => reporting::generate_tree_with_contents_section(root_path, &analysis.root_label, &self.content_selection.retain_selected(filtered_files), skeleton, self.output_format, self.max_file_bytes),
+                     });
+                     // Cheap metadata check so the banner reflects content that no longer matches the scan
+                     self.stale_files.extend(analysis.files_changed_since_scan(&content_files));
                  } else {
                      self.file_content_section = None;
                  }
             }
         } else if trigger_content_generation_only {
-            if let ScanStatus::Completed(root_path, files, _, _) = &self.scan_status {
-                 if self.include_file_content {
-                     self.file_content_section = Some(reporting::generate_file_content_section(root_path, files));
+            if let ScanStatus::Completed(analysis) = &self.scan_status {
+                 sections_rebuilt = true;
+                 if self.content_mode.includes_content() {
+                     let skeleton = self.content_mode == ContentMode::Skeleton;
+                     let focus = focus_set(analysis, &self.excluded_files, self.focus_file.as_ref(), self.focus_hops);
+                     let is_visible = |f: &PathBuf| !is_path_excluded(f, &self.excluded_files) && focus.as_ref().is_none_or(|set| set.contains(f));
+                     let visible_files: Vec<PathBuf> = analysis.files.iter()
+                         .filter(|f| is_visible(f) && self.content_selection.is_selected(f))
+                         .cloned()
+                         .collect();
+                     self.file_content_section = Some(match self.content_layout {
+                         ContentLayout::Separate => {
+                             let visible_connections: Vec<analysis::ResolvedConnection> = analysis.connections.iter()
+                                 .filter(|c| is_visible(&c.source_file))
+                                 .cloned()
+                                 .collect();
+                             reporting::generate_file_content_section(&analysis.root_path, &visible_files, skeleton, &visible_connections, self.content_order, self.output_format, self.max_file_bytes)
+                         }
+                         ContentLayout::InlineTree => {
+                             let structure_filter = self.filter_structure.to_lowercase();
+                             let file_visible: Vec<bool> = analysis.files.iter().map(is_visible).collect();
+                             let filtered_files = select_matching(&analysis.files, &analysis.search_keys.files, &file_visible, |key| key.contains(&structure_filter));
+                             reporting::generate_tree_with_contents_section(&analysis.root_path, &analysis.root_label, &self.content_selection.retain_selected(filtered_files), skeleton, self.output_format, self.max_file_bytes)
+                         }
+                     });
+                     self.stale_files.extend(analysis.files_changed_since_scan(&visible_files));
                  } else {
                      self.file_content_section = None;
                  }
             }
         }
+        // Counts follow filters, exclusions and the content mode because all of them rebuild sections
+        if sections_rebuilt {
+            self.refresh_token_counts();
+        }
 
         
+        let mut pending_structure_filter: Option<String> = None;
+        let mut rescan_requested = false;
         egui::CentralPanel::default().show(ctx, |ui| {
            ui.heading("Project Context Extractor"); ui.separator();
              match &self.scan_status {
                 ScanStatus::Idle => { ui.label("Selecciona una carpeta de proyecto para analizar."); }
-                ScanStatus::Scanning => { ui.horizontal(|ui| { ui.spinner(); ui.label("Analizando archivos..."); }); }
-                ScanStatus::Completed(root_path, _, _, _) => {
-                    ui.label(format!("Carpeta analizada: {}", root_path.display()));
+                ScanStatus::Scanning => self.show_scan_progress(ui),
+                ScanStatus::Completed(analysis) => {
+                    if self.scan_receiver.is_some() {
+                        // Streaming: partial sections below, progress on top
+                        self.show_scan_progress(ui);
+                    } else if analysis.archive.is_some() {
+                        ui.label(format!("Archivo analizado: {}", analysis.root_label));
+                    } else {
+                        ui.label(format!("Carpeta analizada: {}", analysis.root_path.display()));
+                    }
+                    if !analysis.warnings.is_empty() {
+                        egui::CollapsingHeader::new(
+                            egui::RichText::new(format!("⚠ {} archivos no se pudieron analizar", analysis.warnings.len()))
+                                .color(egui::Color32::from_rgb(230, 160, 40)),
+                        )
+                        .id_source("analysis_warnings")
+                        .show(ui, |ui| {
+                            for warning in analysis.warnings.iter() {
+                                let relative = warning.file.strip_prefix(&analysis.root_path).unwrap_or(&warning.file);
+                                ui.label(format!("{}: {}", relative.display(), warning.message));
+                            }
+                        });
+                    }
+                    if !analysis.skipped_files.is_empty() {
+                        egui::CollapsingHeader::new(format!("{} archivos omitidos (grandes, binarios o ilegibles)", analysis.skipped_files.len()))
+                            .id_source("skipped_files")
+                            .show(ui, |ui| {
+                                for skipped in analysis.skipped_files.iter() {
+                                    let relative = skipped.file.strip_prefix(&analysis.root_path).unwrap_or(&skipped.file);
+                                    ui.label(format!("{}: {}", relative.display(), skipped.reason.describe()));
+                                }
+                            });
+                    }
+                    if !self.stale_files.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(230, 160, 40),
+                                format!("⚠ {} archivos han cambiado desde el análisis", self.stale_files.len()),
+                            );
+                            if analysis.archive.is_none() && ui.button("Re-analizar").clicked() {
+                                rescan_requested = true;
+                            }
+                        });
+                    }
                     ui.separator();
-                    let mut clicked_path_in_scroll: Option<PathBuf> = None;
-                    egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut clicked_path_in_scroll: Option<ReportClick> = None;
+                    // Moved out so sections can update it while self is borrowed immutably
+                    let mut report_focus = std::mem::take(&mut self.report_focus);
+                    egui::ScrollArea::both().show(ui, |ui| {
+                        // PageUp/PageDown scroll the report by one visible page
+                        let page = ui.clip_rect().height() * 0.9;
+                        if ui.input(|i| i.key_pressed(egui::Key::PageDown)) {
+                            ui.scroll_with_delta(egui::vec2(0.0, -page));
+                        }
+                        if ui.input(|i| i.key_pressed(egui::Key::PageUp)) {
+                            ui.scroll_with_delta(egui::vec2(0.0, page));
+                        }
+
                         // Borrow self immutably within the scroll area
                         let app_state = &*self; // Use immutable borrow inside closure
+                        let mut view = SectionView {
+                            stale_files: &app_state.stale_files,
+                            font_size: app_state.report_font_size,
+                            elide_paths: app_state.elide_paths,
+                            focus: &mut report_focus,
+                            content_selection: app_state.content_mode.includes_content().then_some(&app_state.content_selection),
+                        };
+
+                        // Search results go first: they are what the user is looking at right now
+                        if let Some(results) = &app_state.content_search_section {
+                            if let Some(click) = Self::display_section(ui, &mut view, "content_search_section", results) {
+                                clicked_path_in_scroll = Some(click);
+                            }
+                            ui.separator();
+                        }
                         
+                        if app_state.show_stats {
+                            if let Some(stats) = &app_state.stats_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "stats_section", stats) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_directory_summary {
+                            if let Some(summary) = &app_state.directory_summary_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "directory_summary_section", summary) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
                         if app_state.show_structure {
                             if let Some(structure) = &app_state.structure_section {
                                 // Display section and capture potential click
-                                if let Some(path) = Self::display_section(ui, "structure_section", structure) {
-                                    clicked_path_in_scroll = Some(path);
+                                if let Some(click) = Self::display_section(ui, &mut view, "structure_section", structure) {
+                                    clicked_path_in_scroll = Some(click);
                                 }
                                 ui.separator();
                             }
@@ -368,8 +2218,8 @@ impl eframe::App for MyApp {
                         if app_state.show_connections {
                             if let Some(connections) = &app_state.connections_section {
                                 // Pass the &[ReportItem] slice directly
-                                if let Some(path) = Self::display_section(ui, "connections_section", connections) {
-                                     clicked_path_in_scroll = Some(path);
+                                if let Some(click) = Self::display_section(ui, &mut view, "connections_section", connections) {
+                                     clicked_path_in_scroll = Some(click);
                                 }
                                 ui.separator();
                             }
@@ -377,8 +2227,16 @@ impl eframe::App for MyApp {
                         if app_state.show_definitions {
                             if let Some(definitions) = &app_state.definitions_section {
                                 // Actualizado: ahora usa ReportItem
-                                if let Some(path) = Self::display_section(ui, "definitions_section", definitions) {
-                                    clicked_path_in_scroll = Some(path);
+                                if let Some(click) = Self::display_section(ui, &mut view, "definitions_section", definitions) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_symbol_index {
+                            if let Some(index) = &app_state.symbol_index_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "symbol_index_section", index) {
+                                    clicked_path_in_scroll = Some(click);
                                 }
                                 ui.separator();
                             }
@@ -386,37 +2244,249 @@ impl eframe::App for MyApp {
                         if app_state.show_inverse_usage {
                             if let Some(inverse_usage) = &app_state.inverse_usage_section {
                                 // Actualizado: ahora usa ReportItem
-                                if let Some(path) = Self::display_section(ui, "inverse_usage_section", inverse_usage) {
-                                    clicked_path_in_scroll = Some(path);
+                                if let Some(click) = Self::display_section(ui, &mut view, "inverse_usage_section", inverse_usage) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_symbol_usage {
+                            if let Some(symbol_usage) = &app_state.symbol_usage_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "symbol_usage_section", symbol_usage) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_cycles {
+                            if let Some(cycles) = &app_state.cycles_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "cycles_section", cycles) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_orphans {
+                            if let Some(orphans) = &app_state.orphans_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "orphans_section", orphans) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_hubs {
+                            if let Some(hubs) = &app_state.hubs_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "hubs_section", hubs) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_todos {
+                            if let Some(todos) = &app_state.todos_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "todos_section", todos) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_project_references {
+                            if let Some(references) = &app_state.project_references_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "project_references_section", references) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_task_pipeline {
+                            if let Some(pipeline) = &app_state.task_pipeline_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "task_pipeline_section", pipeline) {
+                                    clicked_path_in_scroll = Some(click);
+                                }
+                                ui.separator();
+                            }
+                        }
+                        if app_state.show_duplicates {
+                            if let Some(duplicates) = &app_state.duplicates_section {
+                                if let Some(click) = Self::display_section(ui, &mut view, "duplicates_section", duplicates) {
+                                    clicked_path_in_scroll = Some(click);
                                 }
                                 ui.separator();
                             }
                         }
                         // File content display remains the same for now
-                        if app_state.include_file_content && app_state.show_file_content {
+                        if app_state.content_mode.includes_content() && app_state.show_file_content {
                             if let Some(content) = &app_state.file_content_section {
-                                ui.strong("Contenido de Archivos"); // Temporary heading
+                                ui.strong(match app_state.content_layout {
+                                    ContentLayout::Separate => "Contenido de Archivos",
+                                    ContentLayout::InlineTree => "Árbol con contenidos",
+                                });
                                 ui.add_space(2.0);
                                 let mut text = content.clone();
-                                ui.add(egui::TextEdit::multiline(&mut text).code_editor().desired_width(f32::INFINITY));
+                                let font_size = app_state.report_font_size;
+                                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                                    let mut job = highlight::highlight(ui.ctx(), font_size, highlight::Source::Report, text);
+                                    job.wrap.max_width = wrap_width;
+                                    ui.fonts(|f| f.layout_job(job))
+                                };
+                                let mut editor = egui::TextEdit::multiline(&mut text)
+                                    .code_editor()
+                                    .font(egui::FontId::monospace(font_size))
+                                    .desired_width(f32::INFINITY);
+                                if app_state.syntax_highlighting {
+                                    editor = editor.layouter(&mut layouter);
+                                }
+                                ui.add(editor);
                             }
                         }
                     }); // End of ScrollArea
+                    self.report_focus = report_focus;
 
                     // -- Handle click AFTER ScrollArea --
-                    if let Some(path) = clicked_path_in_scroll {
-                        self.show_modal = true;
-                        self.modal_file_path = Some(path.clone());
-                        match std::fs::read_to_string(&path) {
-                            Ok(content) => self.modal_file_content = Some(content),
-                            Err(e) => self.modal_file_content = Some(format!("[Error al leer el archivo: {}]", e)),
+                    match clicked_path_in_scroll {
+                        Some(ReportClick::File(path, _)) if !path.exists() => {
+                            // Deleted or moved since the scan: offer a rescan instead of a raw read error
+                            self.stale_files.insert(path.clone());
+                            self.missing_file_notice = Some(path);
+                        }
+                        Some(ReportClick::File(path, line)) => {
+                            // A click in the report starts a new browsing session
+                            self.modal_history.clear();
+                            self.open_modal(path, line);
+                        }
+                        Some(ReportClick::Exclude(path)) => {
+                            self.excluded_files.insert(path);
+                            self.regenerate_sections = true;
+                            ctx.request_repaint();
+                        }
+                        Some(ReportClick::Focus(path)) => {
+                            self.focus_file = Some(path);
+                            self.regenerate_sections = true;
+                            ctx.request_repaint();
+                        }
+                        Some(ReportClick::OpenInEditor(path, line)) => self.open_in_editor(&path, line),
+                        Some(ReportClick::CopyPath(path)) => {
+                            match output::OutputSink::deliver(&mut output::ClipboardSink, &path.display().to_string()) {
+                                Ok(()) => self.copy_notification = Some(Instant::now()),
+                                Err(e) => self.notice = Some((Instant::now(), e)),
+                            }
+                        }
+                        Some(ReportClick::Reveal(path)) => {
+                            if let Err(e) = editor::reveal(&path) {
+                                self.notice = Some((Instant::now(), e));
+                            }
                         }
+                        Some(ReportClick::SelectContent(path, selected)) => {
+                            self.content_selection.set(&analysis.root_path, &analysis.files, &path, selected);
+                            self.regenerate_sections = true;
+                            ctx.request_repaint();
+                        }
+                        Some(ReportClick::Directory(path)) => {
+                            // Narrow the structure tree down to the clicked directory
+                            let relative = path.strip_prefix(&analysis.root_path).unwrap_or(&path);
+                            pending_structure_filter = Some(relative.to_string_lossy().to_string());
+                        }
+                        None => {}
                     }
                 }
                 ScanStatus::Error(msg) => { ui.colored_label(egui::Color32::RED, format!("Error: {}", msg)); }
             }
         });
 
+        if let Some(filter) = pending_structure_filter {
+            self.filter_structure = filter;
+            self.show_structure = true;
+            self.regenerate_sections = true;
+            ctx.request_repaint();
+        }
+
+        // --- Missing file toast (clicked a file deleted since the scan) ---
+        if let Some(path) = &self.missing_file_notice {
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("Archivo").to_string();
+            let can_rescan = !self.is_scanning() && matches!(&self.scan_status, ScanStatus::Completed(a) if a.archive.is_none());
+            let mut dismissed = false;
+            egui::Window::new("Archivo no encontrado")
+                .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("'{}' ya no existe en disco; el análisis está desactualizado.", file_name));
+                    ui.horizontal(|ui| {
+                        if can_rescan && ui.button("Re-analizar").clicked() {
+                            rescan_requested = true;
+                        }
+                        if ui.button("Cerrar").clicked() {
+                            dismissed = true;
+                        }
+                    });
+                });
+            if dismissed {
+                self.missing_file_notice = None;
+            }
+        }
+
+        if rescan_requested {
+            if let ScanStatus::Completed(analysis) = &self.scan_status {
+                let root_path = analysis.root_path.clone();
+                self.start_scan(root_path);
+            }
+        }
+
+        // --- Copy breakdown toast (after "Copiar Todo") ---
+        if let Some((shown_at, breakdown)) = &self.copy_breakdown {
+            if shown_at.elapsed() < COPY_BREAKDOWN_DURATION {
+                egui::Window::new("Resumen de copia")
+                    .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+                    .collapsible(false)
+                    .resizable(false)
+                    .title_bar(false)
+                    .show(ctx, |ui| {
+                        ui.strong(format!("Copiado al {}:", breakdown.destination));
+                        egui::Grid::new("copy_breakdown_grid").striped(true).show(ui, |ui| {
+                            for (name, chars) in &breakdown.sections {
+                                ui.label(*name);
+                                ui.label(format!("{} chars", format_count(*chars)));
+                                ui.label(format!("~{} tokens", format_count(tokens::estimate_tokens(*chars))));
+                                ui.end_row();
+                            }
+                            ui.strong("Total");
+                            ui.strong(format!("{} chars", format_count(breakdown.total_chars)));
+                            ui.strong(format!("~{} tokens", format_count(breakdown.estimated_tokens)));
+                            ui.end_row();
+                        });
+                        if breakdown.truncated {
+                            ui.label("Truncado al límite configurado.");
+                        }
+                        if breakdown.redactions > 0 {
+                            ui.label(format!("{} secretos redactados.", format_count(breakdown.redactions)));
+                        }
+                        if !breakdown.truncated && breakdown.redactions == 0 {
+                            ui.label("Sin truncado ni redacción.");
+                        }
+                    });
+                ctx.request_repaint_after(NOTIFICATION_POLL_INTERVAL);
+            } else {
+                self.copy_breakdown = None;
+            }
+        }
+
+        // --- Transient notice ---
+        if let Some((shown_at, notice)) = &self.notice {
+            if shown_at.elapsed() < NOTICE_DURATION {
+                egui::Window::new("Aviso")
+                    .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+                    .collapsible(false)
+                    .resizable(false)
+                    .title_bar(false)
+                    .show(ctx, |ui| {
+                        ui.label(notice.as_str());
+                    });
+                ctx.request_repaint_after(NOTIFICATION_POLL_INTERVAL);
+            } else {
+                self.notice = None;
+            }
+        }
+
         // --- Modal Window Logic ---
         if self.show_modal {
             let mut is_open = true; // Control variable for the window
@@ -424,8 +2494,29 @@ impl eframe::App for MyApp {
                               .and_then(|p| p.file_name())
                               .and_then(|n| n.to_str())
                               .unwrap_or("Archivo");
-            
+            // Definitions of the open file, offered for "Copiar contexto mínimo", and the header links
+            let (modal_definitions, modal_links): (Vec<analysis::DetectedDefinition>, ModalLinkGroups) = match (&self.scan_status, &self.modal_file_path) {
+                (ScanStatus::Completed(analysis), Some(path)) => match self.file_links.get(path) {
+                    Some(links) => (
+                        links.definitions.iter().map(|&i| analysis.definitions[i].clone()).collect(),
+                        modal_link_groups(analysis, links),
+                    ),
+                    None => (Vec::new(), Vec::new()),
+                },
+                _ => (Vec::new(), Vec::new()),
+            };
+            let modal_path_label = match (&self.scan_status, &self.modal_file_path) {
+                (ScanStatus::Completed(analysis), Some(path)) => path.strip_prefix(&analysis.root_path).unwrap_or(path).display().to_string(),
+                (_, Some(path)) => path.display().to_string(),
+                _ => String::new(),
+            };
+            let mut minimal_context_request: Option<analysis::DetectedDefinition> = None;
+            let mut modal_nav: Option<ModalNav> = None;
+            let mut open_in_editor_request = false;
+
+            // Fixed id: following a link changes the title but keeps the window in place
             egui::Window::new(format!("Contenido: {}", file_name))
+                .id(egui::Id::new("content_modal"))
                 .open(&mut is_open)
                 .default_width(600.0)
                 .default_height(400.0)
@@ -441,15 +2532,32 @@ impl eframe::App for MyApp {
                                 if self.modal_copy_include_path {
                                     if let Some(path) = &self.modal_file_path {
                                         let path_str = path.display().to_string();
-                                        // Use a common comment style (adjust if needed for specific languages later)
-                                        text_to_copy = format!("// File: {}\n\n{}", path_str, content);
+                                        text_to_copy = match self.output_format {
+                                            // Use a common comment style (adjust if needed for specific languages later)
+                                            output::OutputFormat::Markdown => format!("// File: {}\n\n{}", path_str, content),
+                                            output::OutputFormat::XmlTags => reporting::xml_file_block(&path_str, content),
+                                        };
                                     }
                                 }
-                                copy_to_clipboard(&text_to_copy, &mut self.copy_notification);
+                                self.copy_sections(vec![output::OutputSection { name: "Archivo", text: text_to_copy }]);
                             }
                         }
                         // Checkbox to include path
                         ui.checkbox(&mut self.modal_copy_include_path, "Incluir path");
+                        if ui.button("Abrir en editor").on_hover_text(&self.editor_command).clicked() {
+                            open_in_editor_request = true;
+                        }
+
+                        if !modal_definitions.is_empty() {
+                            ui.menu_button("Copiar contexto mínimo", |ui| {
+                                for def in &modal_definitions {
+                                    if ui.button(format!("L{} {} {}", def.line_number, def.kind, def.symbol_name)).clicked() {
+                                        minimal_context_request = Some(def.clone());
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        }
                         
                         // Display copy notification within the modal as well
                          if let Some(copy_time) = self.copy_notification {
@@ -458,33 +2566,237 @@ impl eframe::App for MyApp {
                             } // Resetting happens in the main UI update
                         }
                     });
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.modal_history.is_empty(), egui::Button::new("← Atrás")).clicked() {
+                            modal_nav = Some(ModalNav::Back);
+                        }
+                        ui.monospace(&modal_path_label);
+                    });
+                    // Mini browser: definitions jump to their line, imports and importers open the other file
+                    for (title, entries) in &modal_links {
+                        egui::CollapsingHeader::new(format!("{} ({})", title, entries.len()))
+                            .id_source(("modal_links", *title))
+                            .show(ui, |ui| {
+                                egui::ScrollArea::vertical()
+                                    .id_source(("modal_links_scroll", *title))
+                                    .max_height(MODAL_LINKS_MAX_HEIGHT)
+                                    .show(ui, |ui| {
+                                        for (label, nav) in entries {
+                                            match nav {
+                                                Some(nav) => {
+                                                    if ui.link(label).clicked() {
+                                                        modal_nav = Some(nav.clone());
+                                                    }
+                                                }
+                                                None => {
+                                                    ui.label(label);
+                                                }
+                                            }
+                                        }
+                                    });
+                            });
+                    }
                     ui.separator();
 
                     if let Some(content) = &self.modal_file_content {
                          // Use a text edit for selection and copying, but make it read-only
                          let mut content_display = content.clone();
-                         ui.add_sized(ui.available_size(), 
-                            egui::TextEdit::multiline(&mut content_display)
+                         let extension = self.modal_file_path.as_ref()
+                             .and_then(|p| p.extension())
+                             .and_then(|e| e.to_str())
+                             .unwrap_or_default();
+                         let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                             let font_size = egui::TextStyle::Monospace.resolve(ui.style()).size;
+                             let mut job = highlight::highlight(ui.ctx(), font_size, highlight::Source::File(extension), text);
+                             job.wrap.max_width = wrap_width;
+                             ui.fonts(|f| f.layout_job(job))
+                         };
+                         let mut editor = egui::TextEdit::multiline(&mut content_display)
                                 .code_editor()
                                 .desired_width(f32::INFINITY)
-                                .lock_focus(true) // Prevent accidental edits
-                         );
+                                .min_size(ui.available_size())
+                                .lock_focus(true); // Prevent accidental edits
+                         if self.syntax_highlighting {
+                             editor = editor.layouter(&mut layouter);
+                         }
+                         let output = editor.show(ui);
+                         if let Some(target) = &mut self.modal_target {
+                             Self::highlight_modal_line(ui, &output, content, target);
+                         }
                     } else {
                         ui.label("No se pudo cargar el contenido.");
                     }
             });
 
+            if let Some(def) = minimal_context_request {
+                if let ScanStatus::Completed(analysis) = &self.scan_status {
+                    let packet = reporting::generate_minimal_context(&analysis.root_path, &def, &analysis.definitions, &analysis.connections);
+                    match packet {
+                        Ok(text) => {
+                            self.copy_sections(vec![output::OutputSection { name: "Contexto mínimo", text }]);
+                        }
+                        // Deleted since the scan: same rescan offer as clicking the file in the report
+                        Err(_) if !def.source_file.exists() => {
+                            self.stale_files.insert(def.source_file.clone());
+                            self.missing_file_notice = Some(def.source_file);
+                        }
+                        Err(e) => self.notice = Some((Instant::now(), e)),
+                    }
+                    ctx.request_repaint();
+                }
+            }
+
+            if open_in_editor_request {
+                if let Some(path) = self.modal_file_path.clone() {
+                    let line = self.modal_target.as_ref().map(|target| target.line);
+                    self.open_in_editor(&path, line);
+                }
+            }
+
+            match modal_nav {
+                Some(ModalNav::Line(line)) => self.modal_target = Some(ModalTarget { line, shown_at: None }),
+                Some(ModalNav::File(path, line)) => {
+                    if let Some(current) = self.modal_file_path.take() {
+                        self.modal_history.push(current);
+                    }
+                    self.open_modal(path, line);
+                }
+                Some(ModalNav::Back) => {
+                    if let Some(previous) = self.modal_history.pop() {
+                        self.open_modal(previous, None);
+                    }
+                }
+                None => {}
+            }
+
             // If the window was closed (by clicking 'x'), update the state
             if !is_open {
                 self.show_modal = false;
                 self.modal_file_path = None;
                 self.modal_file_content = None;
+                self.modal_target = None;
+                self.modal_history.clear();
             }
         }
     }
 }
 
 impl MyApp {
+    // Restores the persisted settings (missing or unparsable ones keep their defaults)
+    // and reopens the last project if asked to
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self { repaint_ctx: Some(cc.egui_ctx.clone()), ..Self::default() };
+        // Unparsable (corrupt or incompatible) settings are dropped as a whole
+        if let Some(settings) = cc.storage.and_then(|storage| eframe::get_value::<Settings>(storage, SETTINGS_KEY)) {
+            settings.apply(&mut app);
+        }
+        app.scan_pattern_error = analysis::ScanOptions::from_patterns(&app.exclude_patterns, &app.include_patterns).err();
+        if app.reopen_last_project {
+            if let Some(path) = app.last_project.clone().filter(|p| p.is_dir()) {
+                app.start_scan(path);
+            }
+        }
+        app
+    }
+
+    // Starts a scan of a folder or of a .zip/.tar.gz archive, reusing cached per-file results
+    fn start_scan(&mut self, path: PathBuf) {
+        self.start_scan_with_cache(path, cache::CacheMode::Reuse);
+    }
+
+    fn start_scan_with_cache(&mut self, path: PathBuf, cache_mode: cache::CacheMode) {
+        // Invalid patterns stop the scan instead of being silently dropped
+        let scan = match analysis::ScanOptions::from_patterns(&self.exclude_patterns, &self.include_patterns) {
+            Ok(scan) => scan,
+            Err(e) => {
+                self.scan_pattern_error = Some(e);
+                return;
+            }
+        };
+        // Dropping the previous analysis also removes any extracted temp directory
+        self.scan_status = ScanStatus::Scanning;
+        self.project_watcher = None;
+        self.pending_change_at = None;
+        self.active_scan = Some(scan.clone());
+        self.active_respect_gitignore = self.respect_gitignore;
+        self.scan_progress = None;
+        self.scan_current_file = None;
+        self.partial_connections.clear();
+        self.partial_definitions.clear();
+        self.clear_generated_sections();
+        self.stale_files.clear();
+        self.missing_file_notice = None;
+        self.file_links.clear();
+        let options = analysis::AnalysisOptions {
+            include_class_members: self.include_class_members,
+            full_doc_comments: self.full_doc_comments,
+            respect_gitignore: self.respect_gitignore,
+            max_file_bytes: self.max_file_bytes,
+        };
+        let repaint_ctx = self.repaint_ctx.clone();
+        let on_progress = move || {
+            if let Some(ctx) = &repaint_ctx {
+                ctx.request_repaint();
+            }
+        };
+        if archive::archive_kind(&path).is_some() {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("archivo");
+            self.scanning_message = format!("Extrayendo y analizando {}...", name);
+            self.scan_receiver = Some(analysis::start_archive_analysis(path, options, scan, on_progress));
+        } else {
+            self.scanning_message = "Analizando archivos...".to_string();
+            self.scan_receiver = Some(analysis::start_analysis(path, options, scan, cache_mode, on_progress));
+        }
+    }
+
+    // Scrolls the modal to the target line once it is visible, then fades a highlight over it
+    // Launch failures (editor not installed, typo in the template) become a notice
+    fn open_in_editor(&mut self, path: &Path, line: Option<usize>) {
+        if let Err(e) = editor::open_in_editor(&self.editor_command, path, line) {
+            self.notice = Some((Instant::now(), e));
+        }
+    }
+
+    // Shows `path` in the content modal, scrolled to `line` if given
+    fn open_modal(&mut self, path: PathBuf, line: Option<usize>) {
+        if let ScanStatus::Completed(analysis) = &self.scan_status {
+            if !analysis.files_changed_since_scan(std::slice::from_ref(&path)).is_empty() {
+                self.stale_files.insert(path.clone());
+            }
+        }
+        self.show_modal = true;
+        self.modal_target = line.map(|line| ModalTarget { line, shown_at: None });
+        self.modal_file_content = Some(match analysis::read_text_file(&path, self.max_file_bytes) {
+            Ok(content) => content,
+            Err(reason) => format!("[Archivo omitido: {}]", reason.describe()),
+        });
+        self.modal_file_path = Some(path);
+    }
+
+    fn highlight_modal_line(ui: &mut egui::Ui, output: &egui::text_edit::TextEditOutput, content: &str, target: &mut ModalTarget) {
+        let Some(line_text) = content.split('\n').nth(target.line.saturating_sub(1)) else { return };
+        let start: usize = content.split('\n').take(target.line.saturating_sub(1)).map(|l| l.chars().count() + 1).sum();
+        let end = start + line_text.chars().count();
+        let rows = output.galley.pos_from_ccursor(egui::text::CCursor::new(start))
+            .union(output.galley.pos_from_ccursor(egui::text::CCursor::new(end)))
+            .translate(output.galley_pos.to_vec2());
+        let rect = egui::Rect::from_x_y_ranges(output.response.rect.x_range(), rows.y_range());
+
+        // The first frame of a new window is an invisible sizing pass
+        if target.shown_at.is_none() && ui.is_visible() {
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+            target.shown_at = Some(Instant::now());
+        }
+        let Some(shown_at) = target.shown_at else { return };
+        let elapsed = shown_at.elapsed();
+        if elapsed < MODAL_HIGHLIGHT_DURATION {
+            let fade = 1.0 - elapsed.as_secs_f32() / MODAL_HIGHLIGHT_DURATION.as_secs_f32();
+            let color = egui::Color32::from_rgba_unmultiplied(255, 210, 0, (70.0 * fade) as u8);
+            ui.painter().rect_filled(rect, 2.0, color);
+            ui.ctx().request_repaint();
+        }
+    }
+
     // --- NEW Helper function ---
     fn report_items_to_string(items: &[reporting::ReportItem]) -> String {
         let mut result = String::new();
@@ -493,62 +2805,237 @@ impl MyApp {
                 reporting::ReportItem::PlainText(text) => result.push_str(text),
                 // For FilePath, just use the display string for copying/full context
                 reporting::ReportItem::FilePath { display, .. } => result.push_str(display),
+                reporting::ReportItem::Directory { display, .. } => result.push_str(display),
+                reporting::ReportItem::DefinitionLink { display, .. } => result.push_str(display),
+                reporting::ReportItem::Line(spans) => result.push_str(&reporting::spans_to_string(spans)),
             }
             result.push('\n'); // Add newline between items for readability
         }
         result.trim_end().to_string() // Remove trailing newline if any
     }
 
+    fn show_scan_progress(&self, ui: &mut egui::Ui) {
+        // A spinner repaints every frame; once the bar exists, frames come only from scan messages
+        ui.horizontal(|ui| {
+            if self.scan_progress.is_none() {
+                ui.spinner();
+            }
+            ui.label(&self.scanning_message);
+        });
+        if let Some((done, total)) = self.scan_progress {
+            let fraction = if total == 0 { 1.0 } else { done as f32 / total as f32 };
+            ui.add(egui::ProgressBar::new(fraction)
+                .text(format!("{} / {} archivos", format_thousands(done), format_thousands(total))));
+            if let Some(name) = &self.scan_current_file {
+                ui.label(egui::RichText::new(name).weak());
+            }
+        }
+    }
+
+    // Folder of the finished analysis, for rescans (archives are not rescanned)
+    fn rescan_root(&self) -> Option<PathBuf> {
+        match &self.scan_status {
+            ScanStatus::Completed(analysis) if analysis.archive.is_none() => Some(analysis.root_path.clone()),
+            _ => None,
+        }
+    }
+
+    // Scanning, including while partial results are already shown
+    fn is_scanning(&self) -> bool {
+        matches!(self.scan_status, ScanStatus::Scanning) || self.scan_receiver.is_some()
+    }
+
+    // A finished analysis, not the partial one shown while streaming
+    fn analysis_ready(&self) -> bool {
+        matches!(self.scan_status, ScanStatus::Completed(_)) && self.scan_receiver.is_none()
+    }
+
     fn clear_generated_sections(&mut self) {
+        self.stats_section = None;
         self.structure_section = None;
         self.connections_section = None;
         self.file_content_section = None;
         self.definitions_section = None;
+        self.symbol_index_section = None;
         self.inverse_usage_section = None;
+        self.symbol_usage_section = None;
+        self.cycles_section = None;
+        self.orphans_section = None;
+        self.todos_section = None;
+        self.hubs_section = None;
+        self.project_references_section = None;
+        self.task_pipeline_section = None;
+        self.directory_summary_section = None;
+        self.duplicates_section = None;
+        self.duplicates_receiver = None;
+        self.clear_content_search();
+        self.section_tokens.clear();
+    }
+
+    fn clear_content_search(&mut self) {
+        self.content_search_receiver = None;
+        self.content_search_matches.clear();
+        self.content_search_section = None;
+        self.content_search_status = None;
     }
 
-    fn rebuild_full_context(&self) -> String {
-        let mut full_context = String::new();
-        if let Some(items) = &self.structure_section {
-             // Convert ReportItems to String for full context
-            let structure_text = Self::report_items_to_string(items);
-            full_context.push_str(&structure_text);
-            full_context.push_str("\n\n");
+    // Report sections of the full context, in output order: (name, visible, generated items)
+    fn report_sections(&self) -> [ReportSection<'_>; 15] {
+        [
+            ("Estadísticas", self.show_stats, &self.stats_section),
+            ("Resumen", self.show_directory_summary, &self.directory_summary_section),
+            ("Estructura", self.show_structure, &self.structure_section),
+            ("Conexiones", self.show_connections, &self.connections_section),
+            ("Definiciones", self.show_definitions, &self.definitions_section),
+            ("Índice", self.show_symbol_index, &self.symbol_index_section),
+            ("Usos", self.show_inverse_usage, &self.inverse_usage_section),
+            ("Símbolos", self.show_symbol_usage, &self.symbol_usage_section),
+            ("Ciclos", self.show_cycles, &self.cycles_section),
+            ("Huérfanos", self.show_orphans, &self.orphans_section),
+            ("Hubs", self.show_hubs, &self.hubs_section),
+            ("TODOs", self.show_todos, &self.todos_section),
+            ("Referencias", self.show_project_references, &self.project_references_section),
+            ("Tareas", self.show_task_pipeline, &self.task_pipeline_section),
+            ("Duplicados", self.show_duplicates, &self.duplicates_section),
+        ]
+    }
+
+    // Named pieces of the full context, in output order
+    fn assemble_context_parts(&self) -> Vec<output::OutputSection> {
+        let contents = if self.content_mode.includes_content() && self.show_file_content { self.file_content_section.as_deref() } else { None };
+        // The inline tree already carries the structure, so it takes the plain tree's place
+        let inline_tree = contents.is_some() && self.content_layout == ContentLayout::InlineTree;
+        assemble_parts(&self.report_sections(), contents, inline_tree)
+    }
+
+    // What "Copiar Todo" and "Exportar a archivo..." deliver
+    fn full_context_parts(&self) -> Vec<output::OutputSection> {
+        if self.fit_to_budget { self.budgeted_context_parts() } else { self.assemble_context_parts() }
+    }
+
+    // Files outside the session exclusions and the focus, with the connections they start
+    fn visible_scope(&self, analysis: &ProjectAnalysis) -> (Vec<PathBuf>, Vec<analysis::ResolvedConnection>) {
+        let focus = focus_set(analysis, &self.excluded_files, self.focus_file.as_ref(), self.focus_hops);
+        let is_visible = |f: &PathBuf| !is_path_excluded(f, &self.excluded_files) && focus.as_ref().is_none_or(|set| set.contains(f));
+        let visible_files: Vec<PathBuf> = analysis.files.iter().filter(|f| is_visible(f)).cloned().collect();
+        let visible_connections: Vec<analysis::ResolvedConnection> = analysis.connections.iter()
+            .filter(|c| is_visible(&c.source_file))
+            .cloned()
+            .collect();
+        (visible_files, visible_connections)
+    }
+
+    // "Copiar Todo" within token_budget: sections in priority order, file contents most-imported first
+    // and cut between files. Contents always use the separate layout so they can be cut per file.
+    fn budgeted_context_parts(&self) -> Vec<output::OutputSection> {
+        let ScanStatus::Completed(analysis) = &self.scan_status else { return Vec::new() };
+        let report_sections = self.report_sections();
+        let section = |name: &'static str| report_sections.iter().find(|(n, ..)| *n == name).copied();
+        let candidate = |(name, visible, section): ReportSection| {
+            section.as_ref().filter(|_| visible).map(|items| output::BudgetCandidate {
+                name,
+                text: Self::report_items_to_string(items),
+                blocks: Vec::new(),
+            })
+        };
+
+        let mut candidates: Vec<output::BudgetCandidate> = BUDGET_PRIORITY_SECTIONS.iter()
+            .filter_map(|name| section(name).and_then(candidate))
+            .collect();
+        if self.content_mode.includes_content() && self.show_file_content {
+            let (visible_files, visible_connections) = self.visible_scope(analysis);
+            let visible_files = self.content_selection.retain_selected(visible_files);
+            let skeleton = self.content_mode == ContentMode::Skeleton;
+            let blocks = reporting::file_content_blocks(&analysis.root_path, &visible_files, skeleton, &visible_connections, reporting::ContentOrder::Importance, self.output_format, self.max_file_bytes)
+                .into_iter()
+                .map(|(path, block)| (path.strip_prefix(&analysis.root_path).unwrap_or(&path).display().to_string(), block))
+                .collect();
+            candidates.push(output::BudgetCandidate { name: CONTENT_TOKENS_KEY, text: reporting::file_content_heading(skeleton).to_string(), blocks });
         }
-        if let Some(items) = &self.connections_section {
-            let connections_text = Self::report_items_to_string(items);
-            full_context.push_str(&connections_text);
-             full_context.push_str("\n\n");
+        candidates.extend(report_sections.into_iter()
+            .filter(|(name, ..)| !BUDGET_PRIORITY_SECTIONS.contains(name))
+            .filter_map(candidate));
+
+        output::fit_to_budget(candidates, self.token_budget, self.token_estimator.as_ref())
+    }
+
+    // Recounts every generated section; called whenever sections are rebuilt, not every frame
+    fn refresh_token_counts(&mut self) {
+        let mut counts = HashMap::new();
+        for (name, _, section) in self.report_sections() {
+            if let Some(items) = section {
+                counts.insert(name, self.token_estimator.estimate(&Self::report_items_to_string(items)));
+            }
         }
-        if let Some(items) = &self.definitions_section {
-            let definitions_text = Self::report_items_to_string(items);
-            full_context.push_str(&definitions_text);
-            full_context.push_str("\n\n");
+        if let Some(fc) = &self.file_content_section {
+            counts.insert(CONTENT_TOKENS_KEY, self.token_estimator.estimate(fc.trim_end()));
         }
-        if let Some(items) = &self.inverse_usage_section {
-            let inverse_usage_text = Self::report_items_to_string(items);
-            full_context.push_str(&inverse_usage_text);
-            full_context.push_str("\n\n");
+        self.section_tokens = counts;
+    }
+
+    // Estimated size of "Copiar Todo" with the current visibility (same rules as assemble_context_parts)
+    fn context_tokens(&self) -> usize {
+        let contents = self.content_mode.includes_content() && self.show_file_content && self.file_content_section.is_some();
+        let inline_tree = contents && self.content_layout == ContentLayout::InlineTree;
+        let sections: usize = self.report_sections()
+            .into_iter()
+            .filter(|(name, visible, _)| *visible && !(inline_tree && *name == "Estructura"))
+            .filter_map(|(name, ..)| self.section_tokens.get(name))
+            .sum();
+        let content = if contents { self.section_tokens.get(CONTENT_TOKENS_KEY).copied().unwrap_or(0) } else { 0 };
+        sections + content
+    }
+
+    // Format and transformations chosen in the top panel, shared by every copy and export
+    fn output_request(&self, sections: Vec<output::OutputSection>) -> output::OutputRequest {
+        let mut request = output::OutputRequest { format: self.output_format, ..output::OutputRequest::new(sections) };
+        if let (true, ScanStatus::Completed(analysis)) = (self.relative_paths, &self.scan_status) {
+            request.options.path_root = Some(analysis.root_path.clone());
         }
-        if self.include_file_content {
-            if let Some(fc) = &self.file_content_section {
-                 full_context.push_str(fc);
+        request.options.anonymize = self.anonymize_output.then(output::Anonymization::current_user);
+        request.options.redact_secrets = self.redact_secrets;
+        request
+    }
+
+    // Sends sections to the clipboard through the shared output pipeline
+    fn copy_sections(&mut self, sections: Vec<output::OutputSection>) -> Option<output::DeliveryReport> {
+        let request = self.output_request(sections);
+        match output::render_and_deliver(&request, &mut output::ClipboardSink) {
+            Ok(report) => {
+                self.copy_notification = Some(Instant::now());
+                Some(report)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                self.copy_notification = None;
+                None
             }
         }
-        full_context.trim_end().to_string()
     }
 
-    // UPDATED: Returns Option<PathBuf> on click instead of modifying state directly
-    fn display_section(ui: &mut egui::Ui, id_source: &str, items: &[reporting::ReportItem]) -> Option<PathBuf> {
-        let mut clicked_path: Option<PathBuf> = None;
+    // UPDATED: Returns Option<ReportClick> on click instead of modifying state directly
+    fn display_section(ui: &mut egui::Ui, view: &mut SectionView, id_source: &'static str, items: &[reporting::ReportItem]) -> Option<ReportClick> {
+        let mut clicked_path: Option<ReportClick> = None;
 
         // Add a heading before each section
         let heading = match id_source {
+            "stats_section" => "Estadísticas del proyecto",
             "structure_section" => "Estructura del Proyecto",
             "connections_section" => "Conexiones Detectadas", // TODO: Update when these use ReportItem
             "definitions_section" => "Definiciones y Exportaciones", // TODO: Update when these use ReportItem
+            "symbol_index_section" => "Índice de símbolos",
             "inverse_usage_section" => "Usos Inversos", // TODO: Update when these use ReportItem
+            "symbol_usage_section" => "Usos por símbolo",
+            "cycles_section" => "Ciclos de imports",
+            "orphans_section" => "Archivos huérfanos",
+            "hubs_section" => "Archivos más importados",
+            "todos_section" => "TODO / FIXME",
+            "project_references_section" => "Referencias de proyecto",
+            "task_pipeline_section" => "Pipeline de tareas",
+            "directory_summary_section" => "Resumen por carpeta",
+            "duplicates_section" => "Posibles duplicaciones de módulo",
             "content_section" => "Contenido de Archivos",
+            "content_search_section" => "Búsqueda en el contenido",
             _ => "Sección", // Fallback heading
         };
         ui.strong(heading);
@@ -558,24 +3045,280 @@ impl MyApp {
         // Using a code block style for consistent spacing
         egui::Frame::none().show(ui, |ui| { // Use a frame for potential background/styling
             ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+            ui.style_mut().text_styles.insert(egui::TextStyle::Monospace, egui::FontId::monospace(view.font_size));
+            // Keep tree lines intact; the surrounding ScrollArea scrolls horizontally instead
+            ui.style_mut().wrap = Some(false);
             ui.vertical(|ui|{
+                // Interactive items in display order, for arrow-key navigation
+                let mut links: Vec<(egui::Id, egui::Rect)> = Vec::new();
+                let mut occurrences: HashMap<&Path, usize> = HashMap::new();
                 for item in items {
-                    match item {
+                    let link = match item {
                         reporting::ReportItem::PlainText(text) => {
                             ui.label(text);
+                            continue;
                         }
-                        reporting::ReportItem::FilePath { display, path } => {
-                            // Use a button that looks like a link for click detection
-                             if ui.link(display).clicked() {
-                                // Signal that this path was clicked
-                                clicked_path = Some(path.clone());
-                            }
+                        reporting::ReportItem::FilePath { display, path } => SectionLink { display, label: display, path, is_file: true, line: None },
+                        reporting::ReportItem::Directory { display, path } => SectionLink { display, label: display, path, is_file: false, line: None },
+                        reporting::ReportItem::DefinitionLink { display, file, line } => SectionLink { display, label: display, path: file, is_file: true, line: Some(*line) },
+                        reporting::ReportItem::Line(spans) => {
+                            // One tree line: text and links side by side, no gaps between spans
+                            let label = reporting::spans_to_string(spans);
+                            ui.horizontal(|ui| {
+                                ui.spacing_mut().item_spacing.x = 0.0;
+                                for span in spans {
+                                    let link = match span {
+                                        reporting::ReportSpan::Text(text) => {
+                                            ui.label(text);
+                                            continue;
+                                        }
+                                        reporting::ReportSpan::FileLink { display, path } => {
+                                            if let Some(click) = Self::content_checkbox(ui, view, id_source, path) {
+                                                clicked_path = Some(click);
+                                            }
+                                            SectionLink { display, label: &label, path, is_file: true, line: None }
+                                        }
+                                        reporting::ReportSpan::DirectoryLink { display, path } => SectionLink { display, label: &label, path, is_file: false, line: None },
+                                        reporting::ReportSpan::FileLineLink { display, path, line } => SectionLink { display, label: &label, path, is_file: true, line: Some(*line) },
+                                    };
+                                    let (response, click) = Self::section_link(ui, view, id_source, &mut occurrences, link);
+                                    clicked_path = click.or(clicked_path.take());
+                                    links.push((response.id, response.rect));
+                                }
+                            });
+                            continue;
                         }
-                    }
+                    };
+                    let (response, click) = Self::section_link(ui, view, id_source, &mut occurrences, link);
+                    clicked_path = click.or(clicked_path.take());
+                    links.push((response.id, response.rect));
                 }
+
+                Self::navigate_section_links(ui, view, id_source, &links);
             });
         });
 
         clicked_path // Return the path if a link was clicked
     }
+
+    // Structure checkbox putting a file (or every file in a folder) in or out of the content section
+    fn content_checkbox(ui: &mut egui::Ui, view: &SectionView, id_source: &'static str, path: &Path) -> Option<ReportClick> {
+        let selection = view.content_selection.filter(|_| id_source == "structure_section")?;
+        let (mut checked, mixed) = selection.state(path);
+        let response = ui.add(egui::Checkbox::without_text(&mut checked).indeterminate(mixed))
+            .on_hover_text("Incluir en el contenido de archivos");
+        ui.add_space(4.0);
+        response.clicked().then(|| ReportClick::SelectContent(path.to_path_buf(), checked))
+    }
+
+    // Draws one clickable path (whole item or span of a line) with its keyboard and context-menu actions
+    fn section_link<'p>(
+        ui: &mut egui::Ui,
+        view: &SectionView,
+        id_source: &'static str,
+        occurrences: &mut HashMap<&'p Path, usize>,
+        link: SectionLink<'_, 'p>,
+    ) -> (egui::Response, Option<ReportClick>) {
+        let SectionLink { display, label, path, is_file, line } = link;
+        let item_id = report_item_id(id_source, path, occurrences);
+        let response = if is_file {
+            // Elision only affects what is drawn; clicks and copies keep the full path
+            let shown = if view.elide_paths { elide_report_line(display) } else { display.to_string() };
+            let text = if view.stale_files.contains(path) {
+                egui::RichText::new(shown).italics().color(egui::Color32::from_rgb(230, 160, 40))
+            } else {
+                egui::RichText::new(shown)
+            };
+            let response = report_link(ui, item_id, text, label);
+            if view.stale_files.contains(path) {
+                response.on_hover_text("Cambiado o eliminado desde el análisis")
+            } else if view.elide_paths {
+                response.on_hover_text(display)
+            } else {
+                response
+            }
+        } else {
+            report_link(ui, item_id, egui::RichText::new(display).strong(), label)
+        };
+
+        // Enter on a focused link counts as a click
+        let mut clicked_path = None;
+        if response.clicked() {
+            clicked_path = Some(if is_file { ReportClick::File(path.to_path_buf(), line) } else { ReportClick::Directory(path.to_path_buf()) });
+        }
+        if response.has_focus() {
+            ui.painter().rect_stroke(response.rect.expand(1.0), 2.0, ui.visuals().selection.stroke);
+        }
+
+        // Actions: right click, or Shift+F10 on the focused link
+        let actions_popup = response.id.with("actions");
+        if response.has_focus() && ui.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::F10)) {
+            ui.memory_mut(|m| m.open_popup(actions_popup));
+        }
+        let actions = |ui: &mut egui::Ui| -> Option<ReportClick> {
+            let path = path.to_path_buf();
+            if ui.button("Excluir del contexto").clicked() {
+                return Some(ReportClick::Exclude(path));
+            }
+            if is_file && ui.button("Enfocar en este archivo").clicked() {
+                return Some(ReportClick::Focus(path));
+            }
+            if is_file && ui.button("Abrir en editor").clicked() {
+                return Some(ReportClick::OpenInEditor(path, line));
+            }
+            if ui.button("Copiar ruta").clicked() {
+                return Some(ReportClick::CopyPath(path));
+            }
+            if ui.button("Mostrar en el explorador").clicked() {
+                return Some(ReportClick::Reveal(path));
+            }
+            None
+        };
+        let mut action = None;
+        egui::popup_below_widget(ui, actions_popup, &response, |ui| {
+            action = actions(ui);
+        });
+        response.context_menu(|ui| {
+            if let Some(chosen) = actions(ui) {
+                action = Some(chosen);
+                ui.close_menu();
+            }
+        });
+        if action.is_some() {
+            clicked_path = action;
+            ui.memory_mut(|m| m.close_popup());
+        }
+
+        (response, clicked_path)
+    }
+
+    // Arrow keys move focus between a section's links; the focused index survives regeneration
+    fn navigate_section_links(ui: &mut egui::Ui, view: &mut SectionView, id_source: &'static str, links: &[(egui::Id, egui::Rect)]) {
+        let focused = ui.memory(|m| m.focused());
+        match links.iter().position(|(id, _)| Some(*id) == focused) {
+            Some(index) => {
+                // Keep egui's spatial arrow navigation from also moving the focus
+                let filter = egui::EventFilter { vertical_arrows: true, ..Default::default() };
+                ui.memory_mut(|m| m.set_focus_lock_filter(links[index].0, filter));
+                let target = if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)) {
+                    Some((index + 1).min(links.len() - 1))
+                } else if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp)) {
+                    Some(index.saturating_sub(1))
+                } else {
+                    None
+                };
+                let index = match target {
+                    Some(target) => {
+                        ui.memory_mut(|m| m.request_focus(links[target].0));
+                        ui.scroll_to_rect(links[target].1, None);
+                        target
+                    }
+                    None => index,
+                };
+                view.focus.insert(id_source, (index, links[index].0));
+            }
+            None => {
+                // Item vanished (filter, exclusion, rescan) while focused: focus its neighbour
+                if let Some((index, id)) = view.focus.get(id_source).copied() {
+                    let vanished = !links.iter().any(|(link_id, _)| *link_id == id);
+                    if vanished && focused.is_none() && !links.is_empty() {
+                        let index = index.min(links.len() - 1);
+                        ui.memory_mut(|m| m.request_focus(links[index].0));
+                        view.focus.insert(id_source, (index, links[index].0));
+                    } else {
+                        view.focus.remove(id_source);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(text: &str) -> Option<Vec<reporting::ReportItem>> {
+        Some(vec![reporting::ReportItem::PlainText(text.to_string())])
+    }
+
+    struct Discard;
+
+    impl output::OutputSink for Discard {
+        fn deliver(&mut self, _text: &str) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn destination(&self) -> String {
+            String::new()
+        }
+    }
+
+    fn names(parts: &[output::OutputSection]) -> Vec<&'static str> {
+        parts.iter().map(|part| part.name).collect()
+    }
+
+    #[test]
+    fn context_parts_keep_only_visible_generated_sections() {
+        let (stats, structure, connections, hidden) = (items("stats\n"), items("tree\n"), None, items("todos\n"));
+        let sections: [ReportSection; 4] = [
+            ("Estadísticas", true, &stats),
+            ("Estructura", true, &structure),
+            ("Conexiones", true, &connections), // visible but not generated
+            ("TODOs", false, &hidden),
+        ];
+        let parts = assemble_parts(&sections, Some("## Contents\n\n"), false);
+        assert_eq!(names(&parts), ["Estadísticas", "Estructura", "Contenidos"]);
+        assert_eq!(parts[0].text, "stats");
+        assert_eq!(parts[2].text, "## Contents");
+
+        let report = output::render_and_deliver(&output::OutputRequest::new(parts), &mut Discard).unwrap();
+        // One entry per part; the sizes plus the separators add up to the total
+        assert_eq!(report.sections, vec![("Estadísticas", 5), ("Estructura", 4), ("Contenidos", 11)]);
+        assert_eq!(report.sections.iter().map(|(_, size)| size).sum::<usize>() + 2 * 2, report.total_chars);
+    }
+
+    #[test]
+    fn inline_tree_takes_the_place_of_the_structure() {
+        let (stats, structure) = (items("stats\n"), items("tree\n"));
+        let sections: [ReportSection; 2] = [("Estadísticas", true, &stats), ("Estructura", false, &structure)];
+        // The inline tree takes the structure's place even when the structure is hidden
+        let parts = assemble_parts(&sections, Some("tree + code\n"), true);
+        assert_eq!(names(&parts), ["Estadísticas", "Árbol con contenidos"]);
+        assert_eq!(parts[1].text, "tree + code");
+        assert_eq!(names(&assemble_parts(&sections, None, false)), ["Estadísticas"]);
+    }
+
+    // Keystroke-to-section filtering cost on a large synthetic project (target: 500k connections
+    // under 50 ms). Timing depends on the machine, so it only runs on demand:
+    // `cargo test --release -- --ignored filter_500k_connections --nocapture`
+    #[test]
+    #[ignore]
+    fn filter_500k_connections() {
+        let root = PathBuf::from("/proyecto");
+        let connections: Vec<analysis::ResolvedConnection> = (0..500_000)
+            .map(|i| {
+                let source = root.join(format!("src/module_{}/file_{}.ts", i % 1_000, i));
+                let target = root.join(format!("src/shared/util_{}.ts", i % 5_000));
+                test_support::connection(&source, &target)
+            })
+            .collect();
+        let keys = search::SearchKeys::build(&root, &[], &connections, &[], &[]);
+        let visible = vec![true; connections.len()];
+
+        // Best of five runs per filter, so one-off scheduling noise doesn't count
+        let mut slowest = Duration::ZERO;
+        for filter in ["util_42", "module_7/", "zzz-no-match"] {
+            let mut best = Duration::MAX;
+            let mut matched = 0;
+            for _ in 0..5 {
+                let started = Instant::now();
+                matched = select_matching(&connections, &keys.connections, &visible, |key| key.matches(filter)).len();
+                best = best.min(started.elapsed());
+            }
+            println!("{:>14}: {:>6} matches in {:?}", filter, matched, best);
+            slowest = slowest.max(best);
+        }
+        assert!(slowest < Duration::from_millis(50), "filtering 500k connections took {:?}", slowest);
+    }
 }
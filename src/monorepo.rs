@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::analysis::ResolvedConnection;
+use crate::reporting::package_name;
+use crate::tsconfig::read_jsonc_file;
+
+// --- Monorepos: paquetes del workspace y pipelines de tareas (Turborepo / Nx) ---
+
+#[derive(Clone, Debug)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub manifest_path: PathBuf,      // package.json del paquete
+    pub scripts: BTreeSet<String>,   // Scripts de package.json (tareas que turbo puede ejecutar)
+    pub targets: BTreeSet<String>,   // Targets de project.json (Nx)
+    pub dependencies: BTreeSet<String>, // Dependencias declaradas que son paquetes del workspace
+}
+
+impl WorkspacePackage {
+    pub fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new(""))
+    }
+
+    pub fn defines_task(&self, task: &str) -> bool {
+        self.scripts.contains(task) || self.targets.contains(task)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineTool {
+    Turbo,
+    Nx,
+}
+
+impl PipelineTool {
+    pub fn label(self) -> &'static str {
+        match self {
+            PipelineTool::Turbo => "Turborepo",
+            PipelineTool::Nx => "Nx",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PipelineTask {
+    pub name: String,            // `build` o `pkg#build`
+    pub depends_on: Vec<String>, // `^build` (mismo task en dependencias), `lint`, `pkg#build`
+}
+
+#[derive(Clone, Debug)]
+pub struct TaskPipeline {
+    pub tool: PipelineTool,
+    pub config_path: PathBuf,
+    pub tasks: Vec<PipelineTask>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MonorepoInfo {
+    pub packages: Vec<WorkspacePackage>,
+    pub pipelines: Vec<TaskPipeline>,
+}
+
+impl MonorepoInfo {
+    /// Paquete más profundo que contiene a `file`.
+    pub fn owning_package(&self, file: &Path) -> Option<&WorkspacePackage> {
+        self.packages
+            .iter()
+            .filter(|p| file.starts_with(p.dir()))
+            .max_by_key(|p| p.dir().components().count())
+    }
+}
+
+fn string_array(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn object_keys(value: Option<&Value>) -> BTreeSet<String> {
+    value
+        .and_then(Value::as_object)
+        .map(|obj| obj.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Tareas de un objeto `{ "build": { "dependsOn": [...] }, ... }`
+fn parse_tasks(tasks: Option<&Value>) -> Vec<PipelineTask> {
+    let Some(tasks) = tasks.and_then(Value::as_object) else { return Vec::new() };
+    tasks
+        .iter()
+        .map(|(name, config)| PipelineTask { name: name.clone(), depends_on: string_array(config.get("dependsOn")) })
+        .collect()
+}
+
+fn discover_packages(root_path: &Path, project_files: &HashSet<PathBuf>) -> Vec<WorkspacePackage> {
+    let manifests: Vec<(PathBuf, Value)> = project_files
+        .iter()
+        .filter(|p| p.file_name().and_then(|n| n.to_str()) == Some("package.json"))
+        .filter(|p| p.parent() != Some(root_path))
+        .filter_map(|p| read_jsonc_file(p).map(|json| (p.clone(), json)))
+        .collect();
+    let names: HashSet<String> = manifests
+        .iter()
+        .filter_map(|(_, json)| json.get("name").and_then(Value::as_str).map(str::to_string))
+        .collect();
+
+    let mut packages: Vec<WorkspacePackage> = manifests
+        .into_iter()
+        .filter_map(|(manifest_path, json)| {
+            let name = json.get("name").and_then(Value::as_str)?.to_string();
+            let dependencies = ["dependencies", "devDependencies", "peerDependencies"]
+                .iter()
+                .flat_map(|field| object_keys(json.get(*field)))
+                .filter(|dep| names.contains(dep) && *dep != name)
+                .collect();
+            // Nx: project.json junto al package.json
+            let project_json = manifest_path.with_file_name("project.json");
+            let targets = if project_files.contains(&project_json) {
+                read_jsonc_file(&project_json).map(|p| object_keys(p.get("targets"))).unwrap_or_default()
+            } else {
+                BTreeSet::new()
+            };
+            Some(WorkspacePackage {
+                name,
+                scripts: object_keys(json.get("scripts")),
+                targets,
+                dependencies,
+                manifest_path,
+            })
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+fn discover_pipelines(root_path: &Path, project_files: &HashSet<PathBuf>) -> Vec<TaskPipeline> {
+    let mut pipelines = Vec::new();
+
+    let turbo_path = root_path.join("turbo.json");
+    if project_files.contains(&turbo_path) && let Some(json) = read_jsonc_file(&turbo_path) {
+        // Turborepo 2 usa `tasks`; la 1.x usaba `pipeline`
+        let tasks = parse_tasks(json.get("tasks").or_else(|| json.get("pipeline")));
+        pipelines.push(TaskPipeline { tool: PipelineTool::Turbo, config_path: turbo_path, tasks });
+    }
+
+    let nx_path = root_path.join("nx.json");
+    if project_files.contains(&nx_path) && let Some(json) = read_jsonc_file(&nx_path) {
+        let tasks = parse_tasks(json.get("targetDefaults"));
+        pipelines.push(TaskPipeline { tool: PipelineTool::Nx, config_path: nx_path, tasks });
+    }
+
+    for pipeline in &mut pipelines {
+        pipeline.tasks.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    pipelines
+}
+
+pub fn discover_monorepo(root_path: &Path, project_files: &HashSet<PathBuf>) -> MonorepoInfo {
+    MonorepoInfo {
+        packages: discover_packages(root_path, project_files),
+        pipelines: discover_pipelines(root_path, project_files),
+    }
+}
+
+/// Paquetes del workspace que cada paquete importa en código (por ruta resuelta o por nombre).
+pub fn code_dependencies(info: &MonorepoInfo, connections: &[ResolvedConnection]) -> BTreeMap<String, BTreeSet<String>> {
+    let names: HashSet<&str> = info.packages.iter().map(|p| p.name.as_str()).collect();
+    let mut deps: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for conn in connections {
+        let Some(source_package) = info.owning_package(&conn.source_file) else { continue };
+        let target_package = match &conn.resolved_target {
+            Some(target) => info.owning_package(target).map(|p| p.name.clone()),
+            None => package_name(&conn.imported_string).filter(|n| names.contains(n.as_str())),
+        };
+        if let Some(target_package) = target_package.filter(|t| *t != source_package.name) {
+            deps.entry(source_package.name.clone()).or_default().insert(target_package);
+        }
+    }
+    deps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{connection, two_package_monorepo};
+
+    fn project_files(root: &Path, relative: &[&str]) -> HashSet<PathBuf> {
+        relative.iter().map(|r| root.join(r)).collect()
+    }
+
+    #[test]
+    fn discovers_packages_tasks_and_workspace_dependencies() {
+        let tree = two_package_monorepo();
+        let files = project_files(&tree.root, &[
+            "package.json", "turbo.json", "nx.json",
+            "packages/ui/package.json", "packages/web/package.json", "packages/web/project.json",
+        ]);
+        let info = discover_monorepo(&tree.root, &files);
+
+        // El package.json de la raíz no es un paquete del workspace
+        let names: Vec<&str> = info.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["@acme/ui", "@acme/web"]);
+        let web = &info.packages[1];
+        assert_eq!(web.dependencies, BTreeSet::from(["@acme/ui".to_string()]));
+        assert!(web.defines_task("build") && web.defines_task("e2e") && !web.defines_task("lint"));
+
+        assert_eq!(info.pipelines.len(), 2);
+        let turbo = &info.pipelines[0];
+        assert_eq!(turbo.tool, PipelineTool::Turbo);
+        let tasks: Vec<(&str, Vec<&str>)> = turbo.tasks.iter()
+            .map(|t| (t.name.as_str(), t.depends_on.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(tasks, [
+            ("build", vec!["^build"]),
+            ("deploy", vec!["@acme/missing#build"]),
+            ("test", vec!["build", "@acme/ui#lint"]),
+        ]);
+        assert_eq!(info.pipelines[1].tool, PipelineTool::Nx);
+        assert_eq!(info.pipelines[1].tasks[0].name, "e2e");
+    }
+
+    #[test]
+    fn code_dependencies_follow_resolved_paths_and_package_names() {
+        let tree = two_package_monorepo();
+        let files = project_files(&tree.root, &["packages/ui/package.json", "packages/web/package.json"]);
+        let info = discover_monorepo(&tree.root, &files);
+        let (button, main) = (tree.path("packages/ui/src/Button.ts"), tree.path("packages/web/src/main.ts"));
+
+        let mut by_name = connection(&main, &button);
+        by_name.imported_string = "@acme/ui/button".to_string();
+        by_name.resolved_target = None;
+        let mut package = by_name.clone();
+        package.imported_string = "react".to_string();
+        let deps = code_dependencies(&info, &[by_name, package, connection(&button, &main), connection(&main, &main)]);
+        assert_eq!(deps, BTreeMap::from([
+            ("@acme/ui".to_string(), BTreeSet::from(["@acme/web".to_string()])),
+            ("@acme/web".to_string(), BTreeSet::from(["@acme/ui".to_string()])),
+        ]));
+    }
+}
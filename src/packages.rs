@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::analysis::{ImportClass, ResolvedConnection};
+use crate::languages;
+use crate::reporting::package_name;
+
+// --- Versiones de paquetes externos (node_modules, lockfiles, package.json) ---
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionSource {
+    Installed, // node_modules/<pkg>/package.json (incluye el store de pnpm)
+    Lockfile,  // package-lock.json, pnpm-lock.yaml o yarn.lock
+    Declared,  // Rango declarado en el package.json más cercano
+}
+
+#[derive(Clone, Debug)]
+pub struct PackageVersion {
+    pub version: String,
+    pub source: VersionSource,
+    pub installed_versions: Vec<String>, // Todas las versiones que aparecen en el lockfile/store
+}
+
+impl PackageVersion {
+    pub fn has_duplicates(&self) -> bool {
+        self.installed_versions.len() > 1
+    }
+
+    /// Texto corto para los informes: `1.6.0`, `^1.0.0 (declarado)`, `... [varias versiones: a, b]`.
+    pub fn describe(&self) -> String {
+        let mut text = match self.source {
+            VersionSource::Installed => self.version.clone(),
+            VersionSource::Lockfile => format!("{} (lockfile)", self.version),
+            VersionSource::Declared => format!("{} (declarado)", self.version),
+        };
+        if self.has_duplicates() {
+            text.push_str(&format!(" [varias versiones: {}]", self.installed_versions.join(", ")));
+        }
+        text
+    }
+}
+
+pub type PackageVersions = HashMap<String, PackageVersion>;
+
+// Versiones por paquete según el lockfile de la raíz
+#[derive(Default)]
+struct LockfileIndex {
+    versions: HashMap<String, BTreeSet<String>>,
+}
+
+impl LockfileIndex {
+    fn load(root_path: &Path) -> Self {
+        let mut index = Self::default();
+        if let Ok(content) = fs::read_to_string(root_path.join("package-lock.json")) {
+            index.parse_package_lock(&content);
+        } else if let Ok(content) = fs::read_to_string(root_path.join("pnpm-lock.yaml")) {
+            index.parse_pnpm_lock(&content);
+        } else if let Ok(content) = fs::read_to_string(root_path.join("yarn.lock")) {
+            index.parse_yarn_lock(&content);
+        }
+        index
+    }
+
+    fn insert(&mut self, package: &str, version: &str) {
+        self.versions.entry(package.to_string()).or_default().insert(version.to_string());
+    }
+
+    // lockfileVersion 2/3: "packages": { "node_modules/a/node_modules/@s/b": { "version": ... } }
+    // lockfileVersion 1: "dependencies": { "a": { "version": ... } }
+    fn parse_package_lock(&mut self, content: &str) {
+        let Ok(json) = serde_json::from_str::<Value>(content) else { return };
+        if let Some(packages) = json.get("packages").and_then(Value::as_object) {
+            for (key, entry) in packages {
+                let Some(idx) = key.rfind("node_modules/") else { continue };
+                let name = &key[idx + "node_modules/".len()..];
+                if let Some(version) = entry.get("version").and_then(Value::as_str) {
+                    self.insert(name, version);
+                }
+            }
+        } else if let Some(dependencies) = json.get("dependencies").and_then(Value::as_object) {
+            for (name, entry) in dependencies {
+                if let Some(version) = entry.get("version").and_then(Value::as_str) {
+                    self.insert(name, version);
+                }
+            }
+        }
+    }
+
+    // Claves de `packages:` en sus variantes: `/a/1.0.0:` (v5), `/a@1.0.0:` (v6), `a@1.0.0:` / `'@s/b@1.0.0':` (v9)
+    fn parse_pnpm_lock(&mut self, content: &str) {
+        let entry = Regex::new(r#"^ {2}['"]?/?((?:@[^/\s'"]+/)?[^@/\s'"]+)[@/](\d[^(:'"\s/]*)"#).unwrap();
+        let mut in_packages = false;
+        for line in content.lines() {
+            if !line.starts_with(' ') && !line.is_empty() {
+                in_packages = line.trim_end() == "packages:";
+                continue;
+            }
+            if !in_packages {
+                continue;
+            }
+            if let Some(caps) = entry.captures(line) {
+                self.insert(&caps[1], &caps[2]);
+            }
+        }
+    }
+
+    // Cabeceras `"a@^1", a@^1.2:` seguidas de `  version "1.6.0"`
+    fn parse_yarn_lock(&mut self, content: &str) {
+        let mut current: Option<String> = None;
+        for line in content.lines() {
+            if !line.starts_with(' ') && line.ends_with(':') {
+                let first_spec = line.trim_end_matches(':').split(',').next().unwrap_or("").trim().trim_matches('"');
+                // El separador de versión es la última '@' que no inicia un scope
+                current = first_spec.rfind('@').filter(|&i| i > 0).map(|i| first_spec[..i].to_string());
+                continue;
+            }
+            let trimmed = line.trim();
+            if let (Some(name), Some(version)) = (&current, trimmed.strip_prefix("version ")) {
+                self.insert(name, version.trim_matches('"'));
+            }
+        }
+    }
+}
+
+// Resolución perezosa con caché: un paquete se busca una sola vez por análisis
+struct VersionResolver<'a> {
+    root_path: &'a Path,
+    lockfile: Option<LockfileIndex>,
+    manifests: HashMap<PathBuf, Option<Value>>,
+    cache: PackageVersions,
+    missing: BTreeSet<String>,
+}
+
+impl<'a> VersionResolver<'a> {
+    fn new(root_path: &'a Path) -> Self {
+        Self { root_path, lockfile: None, manifests: HashMap::new(), cache: HashMap::new(), missing: BTreeSet::new() }
+    }
+
+    fn lockfile(&mut self) -> &LockfileIndex {
+        self.lockfile.get_or_insert_with(|| LockfileIndex::load(self.root_path))
+    }
+
+    // Directorios desde el del importador hasta la raíz (monorepos: node_modules anidados)
+    fn ancestors(&self, importer: &Path) -> Vec<PathBuf> {
+        importer
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(self.root_path))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    fn read_version(package_json: &Path) -> Option<String> {
+        let content = fs::read_to_string(package_json).ok()?;
+        let json: Value = serde_json::from_str(&content).ok()?;
+        json.get("version").and_then(Value::as_str).map(str::to_string)
+    }
+
+    // pnpm: node_modules/.pnpm/<scope+name>@<versión>[_peers]/node_modules/<name>
+    fn pnpm_store_versions(node_modules: &Path, package: &str) -> Vec<String> {
+        let prefix = format!("{}@", package.replace('/', "+"));
+        let Ok(entries) = fs::read_dir(node_modules.join(".pnpm")) else { return Vec::new() };
+        let versions: BTreeSet<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter_map(|name| name.strip_prefix(&prefix).map(|rest| rest.split(['_', '(']).next().unwrap_or(rest).to_string()))
+            .filter(|v| !v.is_empty())
+            .collect();
+        versions.into_iter().collect()
+    }
+
+    fn installed_version(&self, package: &str, importer: &Path) -> Option<(String, Vec<String>)> {
+        for dir in self.ancestors(importer) {
+            let node_modules = dir.join("node_modules");
+            if !node_modules.is_dir() {
+                continue;
+            }
+            if let Some(version) = Self::read_version(&node_modules.join(package).join("package.json")) {
+                return Some((version, Self::pnpm_store_versions(&node_modules, package)));
+            }
+            let store_versions = Self::pnpm_store_versions(&node_modules, package);
+            if let Some(latest) = store_versions.last() {
+                return Some((latest.clone(), store_versions));
+            }
+        }
+        None
+    }
+
+    fn declared_range(&mut self, package: &str, importer: &Path) -> Option<String> {
+        for dir in self.ancestors(importer) {
+            let manifest = self
+                .manifests
+                .entry(dir.clone())
+                .or_insert_with(|| {
+                    fs::read_to_string(dir.join("package.json")).ok().and_then(|c| serde_json::from_str(&c).ok())
+                });
+            let Some(manifest) = manifest else { continue };
+            for field in ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"] {
+                if let Some(range) = manifest.get(field).and_then(|deps| deps.get(package)).and_then(Value::as_str) {
+                    return Some(range.to_string());
+                }
+            }
+        }
+        None
+    }
+
+    fn resolve(&mut self, package: &str, importer: &Path) {
+        if self.cache.contains_key(package) || self.missing.contains(package) {
+            return;
+        }
+        let lockfile_versions: Vec<String> = self
+            .lockfile()
+            .versions
+            .get(package)
+            .map(|v| v.iter().cloned().collect())
+            .unwrap_or_default();
+
+        let resolved = if let Some((version, store_versions)) = self.installed_version(package, importer) {
+            let installed_versions = if lockfile_versions.len() > store_versions.len() { lockfile_versions } else { store_versions };
+            Some(PackageVersion { version, source: VersionSource::Installed, installed_versions })
+        } else if lockfile_versions.len() == 1 {
+            Some(PackageVersion { version: lockfile_versions[0].clone(), source: VersionSource::Lockfile, installed_versions: lockfile_versions })
+        } else {
+            // Sin node_modules (o ignorado) y lockfile ambiguo: rango declarado
+            self.declared_range(package, importer).map(|range| PackageVersion {
+                version: range,
+                source: VersionSource::Declared,
+                installed_versions: lockfile_versions,
+            })
+        };
+
+        match resolved {
+            Some(version) => {
+                self.cache.insert(package.to_string(), version);
+            }
+            None => {
+                self.missing.insert(package.to_string());
+            }
+        }
+    }
+}
+
+/// Versiones de los paquetes externos realmente importados (solo se consultan esos).
+pub fn resolve_package_versions(root_path: &Path, connections: &[ResolvedConnection]) -> PackageVersions {
+    let mut resolver = VersionResolver::new(root_path);
+    for conn in connections {
+        if conn.resolved_target.is_some() || conn.ambient_module.is_some() || conn.dynamic_pattern {
+            continue;
+        }
+        // Rust y otros lenguajes no importan paquetes npm
+        if languages::handles(&conn.source_file) {
+            continue;
+        }
+        if let Some(package) = package_name(&conn.imported_string) {
+            resolver.resolve(&package, &conn.source_file);
+        }
+    }
+    resolver.cache
+}
+
+// Módulos del núcleo de Node (`require('fs')`, `import 'node:path'`); `fs/promises` cuenta por `fs`
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "async_hooks", "buffer", "child_process", "cluster", "console", "constants", "crypto",
+    "dgram", "diagnostics_channel", "dns", "domain", "events", "fs", "http", "http2", "https", "inspector",
+    "module", "net", "os", "path", "perf_hooks", "process", "punycode", "querystring", "readline", "repl",
+    "stream", "string_decoder", "sys", "timers", "tls", "trace_events", "tty", "url", "util", "v8", "vm",
+    "wasi", "worker_threads", "zlib",
+];
+
+pub fn is_node_builtin(import_str: &str) -> bool {
+    if import_str.starts_with("node:") {
+        return true;
+    }
+    let first = import_str.split('/').next().unwrap_or(import_str);
+    NODE_BUILTINS.contains(&first)
+}
+
+/// Clase de un import ya resuelto (o no): local, paquete npm, builtin de Node, archivo
+/// local que falta, fuera de la raíz o externo sin identificar.
+pub fn classify_import(conn: &ResolvedConnection, package_versions: &PackageVersions) -> ImportClass {
+    if conn.outside_target.is_some() {
+        return ImportClass::OutsideRoot;
+    }
+    if conn.resolved_target.is_some() || conn.ambient_module.is_some() || conn.dynamic_pattern || conn.glob.is_some() {
+        return ImportClass::Local;
+    }
+    let import_str = conn.imported_string.as_str();
+    if import_str.starts_with('.') || import_str.starts_with('/') {
+        return ImportClass::Missing;
+    }
+    // Rust y otros lenguajes no importan paquetes npm ni módulos de Node
+    if languages::handles(&conn.source_file) {
+        return ImportClass::External;
+    }
+    if is_node_builtin(import_str) {
+        return ImportClass::NodeBuiltin;
+    }
+    match package_name(import_str) {
+        Some(package) if package_versions.contains_key(&package) => ImportClass::NpmPackage,
+        _ => ImportClass::External,
+    }
+}
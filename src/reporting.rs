@@ -1,16 +1,131 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::cmp::Ordering;
 
-use crate::analysis::{DetectedDefinition, ResolvedConnection}; // DetectedConnection eliminado
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{self, CapturePattern, DetectedDefinition, FileMetrics, ImportClass, ResolvedConnection, SkippedFile, TodoComment}; // DetectedConnection eliminado
+use crate::content_search::{self, ContentMatch};
+use crate::duplicates::{self, DuplicateGroup};
+use crate::git::GitSource;
+use crate::graph;
+use crate::monorepo::{self, MonorepoInfo, WorkspacePackage};
+use crate::output::OutputFormat;
+use crate::packages::PackageVersions;
+use crate::tsconfig::{self, TsProject};
+use crate::tokens;
 
 // --- NEW: Structured Report Item --- 
 #[derive(Clone, Debug)]
 pub enum ReportItem {
     PlainText(String),
     FilePath { display: String, path: PathBuf },
-    // Future: DefinitionLink { display: String, file: PathBuf, line: usize }, etc.
+    Directory { display: String, path: PathBuf },
+    // Una línea lógica con tramos de texto y enlaces (`├── ./utils -> src/utils.ts`)
+    Line(Vec<ReportSpan>),
+    // Definición: el clic abre el archivo en su línea
+    DefinitionLink { display: String, file: PathBuf, line: usize },
+}
+
+#[derive(Clone, Debug)]
+pub enum ReportSpan {
+    Text(String),
+    FileLink { display: String, path: PathBuf },
+    DirectoryLink { display: String, path: PathBuf },
+    FileLineLink { display: String, path: PathBuf, line: usize }, // Abre el archivo en `line`
+}
+
+// Texto de la línea tal como se ve en pantalla (copias, tokens, exportaciones)
+pub fn spans_to_string(spans: &[ReportSpan]) -> String {
+    spans
+        .iter()
+        .map(|span| match span {
+            ReportSpan::Text(text) => text.as_str(),
+            ReportSpan::FileLink { display, .. } | ReportSpan::DirectoryLink { display, .. } | ReportSpan::FileLineLink { display, .. } => {
+                display.as_str()
+            }
+        })
+        .collect()
+}
+
+// Enlace al archivo o, para paquetes Go (resueltos a su carpeta), a la carpeta
+fn target_link(display: String, path: &Path) -> ReportSpan {
+    if path.is_dir() {
+        ReportSpan::DirectoryLink { display: format!("{}/", display), path: path.to_path_buf() }
+    } else {
+        ReportSpan::FileLink { display, path: path.to_path_buf() }
+    }
+}
+
+// --- Helpers de clasificación ---
+
+pub fn language_for_extension(ext: &str) -> &'static str {
+    match ext {
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+        "rs" => "Rust",
+        "go" => "Go",
+        "vue" => "Vue",
+        "astro" => "Astro",
+        "php" => "PHP",
+        "kt" | "kts" => "Kotlin",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "sql" => "SQL",
+        "sh" | "bash" => "Shell",
+        "proto" => "Protobuf",
+        "ex" | "exs" => "Elixir",
+        "json" => "JSON",
+        "css" | "scss" | "sass" | "less" => "CSS",
+        "md" | "mdx" => "Markdown",
+        "html" => "HTML",
+        _ => "Other",
+    }
+}
+
+/// Acorta el centro de una ruta larga manteniendo el primer segmento y el mayor número
+/// posible de segmentos finales (`src/…/components/Button.tsx`). Opera por caracteres,
+/// nunca por bytes, y siempre conserva al menos el primer y el último segmento.
+pub fn elide_path_middle(path: &str, max_chars: usize) -> String {
+    if path.chars().count() <= max_chars {
+        return path.to_string();
+    }
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() < 3 {
+        return path.to_string();
+    }
+    let head = segments[0];
+    let mut tail: Vec<&str> = vec![segments[segments.len() - 1]];
+    let mut length = head.chars().count() + "/…/".chars().count() + tail[0].chars().count();
+    for segment in segments[1..segments.len() - 1].iter().rev() {
+        let extra = segment.chars().count() + 1;
+        if length + extra > max_chars {
+            break;
+        }
+        length += extra;
+        tail.insert(0, segment);
+    }
+    // Elidir un solo segmento corto (`a/b/c.ts`) no ahorra nada y esconde información
+    if length >= path.chars().count() {
+        return path.to_string();
+    }
+    format!("{}/…/{}", head, tail.join("/"))
+}
+
+// Nombre del paquete npm para un especificador "bare" (`@scope/pkg/sub` -> `@scope/pkg`)
+pub fn package_name(import_str: &str) -> Option<String> {
+    if import_str.starts_with('.') || import_str.starts_with('/') || import_str.contains(':') || import_str.is_empty() {
+        return None;
+    }
+    let mut segments = import_str.split('/');
+    let first = segments.next()?;
+    if first.starts_with('@') {
+        let second = segments.next()?;
+        Some(format!("{}/{}", first, second))
+    } else {
+        Some(first.to_string())
+    }
 }
 
 // --- Funciones auxiliares para ordenación natural ---
@@ -107,11 +222,18 @@ fn compare_paths_naturally(a: &Path, b: &Path) -> Ordering {
             }
             (Some(_), None) => return Ordering::Greater, // a es más largo (más profundo)
             (None, Some(_)) => return Ordering::Less,    // b es más largo (más profundo)
-            (None, None) => return Ordering::Equal,      // Ambas rutas son equivalentes
+            // Equivalentes en orden natural (`file01` y `file1`): desempate byte a byte, para
+            // que el orden no dependa del de entrada y los informes se puedan comparar entre ejecuciones
+            (None, None) => return a.cmp(b),
         }
     }
 }
 
+// Mismo criterio para cadenas sueltas (imported_string, rutas ya relativizadas)
+fn compare_strs_naturally(a: &str, b: &str) -> Ordering {
+    natural_lexical_cmp_revised(a, b).then_with(|| a.cmp(b))
+}
+
 // --- Funciones Movidas desde analysis.rs ---
 
 // Helper interno para generar árbol de estructura (podría permanecer aquí o moverse si se reutiliza)
@@ -170,8 +292,63 @@ fn generate_tree_structure_string(root_path: &Path, files: &[PathBuf]) -> String
     tree
 }
 
+// `512 B`, `9.4 KB`, `1.2 MB`
+fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f < KB {
+        format!("{} B", bytes)
+    } else if bytes_f < KB * KB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{:.1} MB", bytes_f / (KB * KB))
+    }
+}
+
+// `  (312 lines, 9.4 KB)`; sin líneas para archivos binarios o no UTF-8
+fn size_annotation(lines: Option<usize>, bytes: u64) -> String {
+    match lines {
+        Some(lines) => format!("  ({} lines, {})", lines, format_size(bytes)),
+        None => format!("  ({})", format_size(bytes)),
+    }
+}
+
+// Totales de cada carpeta del árbol, sumando solo los archivos listados (cuadran con el filtro)
+fn directory_totals(root_path: &Path, files: &[PathBuf], metrics: &HashMap<PathBuf, FileMetrics>) -> HashMap<PathBuf, FileMetrics> {
+    let mut totals: HashMap<PathBuf, FileMetrics> = HashMap::new();
+    for file in files {
+        let Some(file_metrics) = metrics.get(file) else { continue };
+        for dir in file.ancestors().skip(1).take_while(|dir| dir.starts_with(root_path) && *dir != root_path) {
+            let total = totals.entry(dir.to_path_buf()).or_default();
+            total.bytes += file_metrics.bytes;
+            if let Some(lines) = file_metrics.lines {
+                *total.lines.get_or_insert(0) += lines;
+            }
+        }
+    }
+    totals
+}
+
+// Línea del árbol: prefijo de dibujo + nombre clickable + anotación de tamaño
+fn tree_line(prefix: String, name: String, path: &Path, annotation: String) -> ReportItem {
+    let mut spans = vec![ReportSpan::Text(prefix), ReportSpan::FileLink { display: name, path: path.to_path_buf() }];
+    if !annotation.is_empty() {
+        spans.push(ReportSpan::Text(annotation));
+    }
+    ReportItem::Line(spans)
+}
+
 // Helper interno para generar árbol de estructura (AHORA DEVUELVE Vec<ReportItem>)
-fn generate_tree_structure_items(root_path: &Path, files: &[PathBuf]) -> Vec<ReportItem> {
+// Con `metrics`, cada archivo y carpeta lleva su tamaño (`(312 lines, 9.4 KB)`).
+fn generate_tree_structure_items(root_path: &Path, files: &[PathBuf], metrics: Option<&HashMap<PathBuf, FileMetrics>>) -> Vec<ReportItem> {
+    let totals = metrics.map(|metrics| directory_totals(root_path, files, metrics)).unwrap_or_default();
+    let annotation = |path: &Path| -> String {
+        let Some(metrics) = metrics else { return String::new() };
+        match metrics.get(path).or_else(|| totals.get(path)) {
+            Some(m) => size_annotation(m.lines, m.bytes),
+            None => String::new(),
+        }
+    };
     let mut items = Vec::new();
     let mut sorted_files = files.to_vec();
     sorted_files.sort_by(|a, b| compare_paths_naturally(a.as_path(), b.as_path()));
@@ -183,7 +360,7 @@ fn generate_tree_structure_items(root_path: &Path, files: &[PathBuf]) -> Vec<Rep
              // Evitar imprimir la raíz dos veces si solo hay archivos en ella
             if components.is_empty() || (components.len() == 1 && components[0].as_os_str() == relative_path.as_os_str()) {
                  if let Some(name) = relative_path.file_name().and_then(|n| n.to_str()) {
-                    items.push(ReportItem::FilePath { display: format!("├── {}", name), path: file_path.clone() });
+                    items.push(tree_line("├── ".to_string(), name.to_string(), &file_path, annotation(&file_path)));
                 }
                 continue;
             }
@@ -201,14 +378,14 @@ fn generate_tree_structure_items(root_path: &Path, files: &[PathBuf]) -> Vec<Rep
                             continue;
                         } else {
                             printed_dirs.insert(component_path.clone());
-                            items.push(ReportItem::FilePath { display: format!("{}├── {}/", current_prefix, name), path: component_path });
+                            items.push(tree_line(format!("{}├── ", current_prefix), format!("{}/", name), &component_path, annotation(&component_path)));
                             current_prefix.push_str("│   ");
                         }
                     } else {
-                        items.push(ReportItem::FilePath { display: format!("{}└── {}", current_prefix, name), path: file_path.clone() });
+                        items.push(tree_line(format!("{}└── ", current_prefix), name.to_string(), &file_path, annotation(&file_path)));
                     }
                  } else {
-                    items.push(ReportItem::FilePath { display: format!("{}└── [Nombre no UTF-8]", current_prefix), path: file_path.clone() });
+                    items.push(tree_line(format!("{}└── ", current_prefix), "[Nombre no UTF-8]".to_string(), &file_path, String::new()));
                     break;
                  }
             }
@@ -218,13 +395,13 @@ fn generate_tree_structure_items(root_path: &Path, files: &[PathBuf]) -> Vec<Rep
 }
 
 // --- Generadores de Secciones (Públicos) ---
-pub fn generate_structure_section(root_path: &Path, files: &[PathBuf]) -> Vec<ReportItem> {
+pub fn generate_structure_section(root_path: &Path, root_label: &str, files: &[PathBuf], metrics: Option<&HashMap<PathBuf, FileMetrics>>) -> Vec<ReportItem> {
     let mut section_items = Vec::new();
     section_items.push(ReportItem::PlainText("## Project Structure\n\n```".to_string()));
-    section_items.push(ReportItem::PlainText(format!("{}", root_path.file_name().unwrap_or_default().to_str().unwrap_or("ROOT"))));
+    section_items.push(ReportItem::PlainText(root_label.to_string()));
     
     // Get the tree structure items
-    section_items.extend(generate_tree_structure_items(root_path, files));
+    section_items.extend(generate_tree_structure_items(root_path, files, metrics));
     
     section_items.push(ReportItem::PlainText("```\n".to_string()));
     section_items
@@ -232,7 +409,34 @@ pub fn generate_structure_section(root_path: &Path, files: &[PathBuf]) -> Vec<Re
 
 
 // ACTUALIZADO: generate_connections_section ahora usa ResolvedConnection y devuelve Vec<ReportItem>
-pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedConnection]) -> Vec<ReportItem> {
+// `["default as React", "useState", "b as c"]` -> `React, { useState, b as c }`; `*` solo para
+// `export * from`. None si la sentencia no importa nada con nombre.
+fn import_symbols_clause(symbols: &[String]) -> Option<String> {
+    if symbols.is_empty() {
+        return None;
+    }
+    let mut parts: Vec<String> = Vec::new();
+    let mut named: Vec<&str> = Vec::new();
+    for symbol in symbols {
+        match symbol.strip_prefix("default as ") {
+            Some(local) => parts.push(local.to_string()),
+            None if symbol.starts_with('*') => parts.push(symbol.clone()),
+            None => named.push(symbol),
+        }
+    }
+    if !named.is_empty() {
+        parts.push(format!("{{ {} }}", named.join(", ")));
+    }
+    Some(parts.join(", "))
+}
+
+pub fn generate_connections_section(
+    root_path: &Path,
+    connections: &[ResolvedConnection],
+    package_versions: &PackageVersions,
+    expand_globs: bool, // false = solo el número de archivos de cada require.context / import.meta.glob
+    flatten_barrels: bool, // true = listar también los archivos finales detrás de cada barrel
+) -> Vec<ReportItem> {
     let mut section_items = Vec::new();
     section_items.push(ReportItem::PlainText("## Detected Connections (Resolved)\n\n```".to_string()));
 
@@ -253,7 +457,7 @@ pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedCon
 
     // 2. Get sorted source files
     let mut sorted_files: Vec<PathBuf> = grouped_connections.keys().cloned().collect();
-    sorted_files.sort();
+    sorted_files.sort_by(|a, b| compare_paths_naturally(a, b));
 
     // 3. Build the item list
     let num_files = sorted_files.len();
@@ -268,14 +472,14 @@ pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedCon
             .to_string();
         
         // Add source file path as clickable item
-        section_items.push(ReportItem::FilePath { 
-            display: format!("{}{}", file_prefix, display_path_str),
-            path: file_path.clone()
-        });
+        section_items.push(ReportItem::Line(vec![
+            ReportSpan::Text(file_prefix.to_string()),
+            ReportSpan::FileLink { display: display_path_str, path: file_path.clone() },
+        ]));
 
         // Get and sort imports for this file (by imported_string)
         if let Some(imports) = grouped_connections.get_mut(file_path) {
-            imports.sort_by(|a, b| a.imported_string.cmp(&b.imported_string));
+            imports.sort_by(|a, b| compare_strs_naturally(&a.imported_string, &b.imported_string));
             let num_imports = imports.len();
             let base_indent = if is_last_file { "    " } else { "│   " };
 
@@ -284,7 +488,42 @@ pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedCon
                 let import_prefix = if is_last_import { "└── " } else { "├── " };
                 
                 // Start the line with indent and prefix as plain text
-                let mut line_items = vec![ReportItem::PlainText(format!("{}{}{}", base_indent, import_prefix, import_conn.imported_string))];
+                let attribute_tag = import_conn.import_attribute
+                    .as_ref()
+                    .map(|attr| format!(" ({})", attr))
+                    .unwrap_or_default();
+                // Tipo de conexión salvo el import normal: [re-export], [side-effect], [dynamic], ...
+                let kind_tag = match import_conn.kind {
+                    CapturePattern::Import => String::new(),
+                    kind => format!(" [{}]", kind.label()),
+                };
+                // `import type`: no existe en tiempo de ejecución
+                let kind_tag = if import_conn.type_only { format!(" [type]{}", kind_tag) } else { kind_tag };
+                // El mismo módulo importado en varias sentencias del archivo
+                let kind_tag = if import_conn.occurrences > 1 { format!("{} (x{})", kind_tag, import_conn.occurrences) } else { kind_tag };
+                let symbols = match import_symbols_clause(&import_conn.imported_symbols) {
+                    Some(clause) => format!("{} from ", clause),
+                    None => String::new(),
+                };
+                // La sentencia enlaza a su línea en el archivo de origen: `./utils:L12`
+                let mut line = vec![
+                    ReportSpan::Text(format!("{}{}", base_indent, import_prefix)),
+                    ReportSpan::FileLineLink {
+                        display: format!("{}{}:L{}", symbols, import_conn.imported_string, import_conn.line_number),
+                        path: import_conn.source_file.clone(),
+                        line: import_conn.line_number,
+                    },
+                    ReportSpan::Text(format!("{}{}", attribute_tag, kind_tag)),
+                ];
+                // Líneas colgando del import (archivos tras un barrel, coincidencias de un glob)
+                let mut sub_items = Vec::new();
+                let sub_indent = format!("{}{}", base_indent, if j == num_imports - 1 { "    " } else { "│   " });
+                let sub_line = |marker: &str, path: &PathBuf| {
+                    ReportItem::Line(vec![
+                        ReportSpan::Text(format!("{}  {} ", sub_indent, marker)),
+                        ReportSpan::FileLink { display: path.strip_prefix(root_path).unwrap_or(path).display().to_string(), path: path.clone() },
+                    ])
+                };
 
                 // Add target info, potentially clickable
                 match &import_conn.resolved_target {
@@ -294,19 +533,66 @@ pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedCon
                             .unwrap_or(target_path)
                             .display()
                             .to_string();
-                        // Add arrow as plain text, then clickable target path
-                        line_items.push(ReportItem::PlainText(" -> ".to_string()));
-                        line_items.push(ReportItem::FilePath { 
-                            display: relative_target_str, 
-                            path: target_path.clone() 
-                        });
+                        line.push(ReportSpan::Text(" -> ".to_string()));
+                        line.push(target_link(relative_target_str, target_path));
+                        if flatten_barrels {
+                            sub_items.extend(import_conn.barrel_targets.iter().map(|source_path| sub_line("=>", source_path)));
+                        }
                     }
-                    None => {
-                        line_items.push(ReportItem::PlainText(" (External or Unresolved)".to_string()));
+                    None if import_conn.glob.is_some() => {
+                        let glob = import_conn.glob.as_ref().map(|g| g.describe()).unwrap_or_default();
+                        line.push(ReportSpan::Text(format!(
+                            " (glob: {} -> {} archivos)",
+                            glob,
+                            import_conn.pattern_matches.len()
+                        )));
+                        if expand_globs {
+                            sub_items.extend(import_conn.pattern_matches.iter().map(|target_path| sub_line("*", target_path)));
+                        }
+                    }
+                    None if import_conn.dynamic_pattern => {
+                        line.push(ReportSpan::Text(format!(
+                            " (dynamic import matching {} -> {} archivos)",
+                            import_conn.imported_string,
+                            import_conn.pattern_matches.len()
+                        )));
+                        sub_items.extend(import_conn.pattern_matches.iter().map(|target_path| sub_line("~", target_path)));
                     }
+                    None => match &import_conn.ambient_module {
+                        Some(ambient) => {
+                            // Enlazar al .d.ts que declara el módulo
+                            let declaring_file = ambient.source_file
+                                .strip_prefix(root_path)
+                                .unwrap_or(&ambient.source_file)
+                                .display()
+                                .to_string();
+                            line.push(ReportSpan::Text(" (declared ambient module (sin archivo físico)) ".to_string()));
+                            line.push(ReportSpan::FileLink {
+                                display: format!("declare module '{}' @ {}:L{}", ambient.pattern, declaring_file, ambient.line_number),
+                                path: ambient.source_file.clone(),
+                            });
+                        }
+                        None => {
+                            let tag = match import_conn.import_class {
+                                ImportClass::Missing => " (Missing local file)".to_string(),
+                                ImportClass::OutsideRoot => format!(
+                                    " (outside project) {}",
+                                    import_conn.outside_target.as_ref().map(|t| t.display().to_string()).unwrap_or_default()
+                                ),
+                                ImportClass::NodeBuiltin => " (Node builtin)".to_string(),
+                                ImportClass::NpmPackage => package_name(&import_conn.imported_string)
+                                    .and_then(|package| package_versions.get(&package).map(|v| (package, v)))
+                                    .map(|(package, version)| format!(" (npm: {}@{})", package, version.describe()))
+                                    .unwrap_or_else(|| " (npm)".to_string()),
+                                ImportClass::Local | ImportClass::External => " (External or Unresolved)".to_string(),
+                            };
+                            line.push(ReportSpan::Text(tag));
+                        }
+                    },
                 };
 
-                section_items.extend(line_items);
+                section_items.push(ReportItem::Line(line));
+                section_items.extend(sub_items);
             }
         }
     }
@@ -315,8 +601,52 @@ pub fn generate_connections_section(root_path: &Path, connections: &[ResolvedCon
     section_items
 }
 
+// `texto largo` -> `texto l…` (por caracteres); 0 = sin límite
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
 // --- Nueva Función para Generar Sección de Definiciones ---
-pub fn generate_definitions_section(root_path: &Path, definitions: &[DetectedDefinition]) -> Vec<ReportItem> {
+// `doc_width`: ancho máximo del comentario /** ... */ que se muestra tras el nombre (0 = completo)
+// Firmas de funciones y métodos (`getUser(id: string): Promise<User>`) ya incluyen el nombre
+fn is_call_signature(def: &DetectedDefinition) -> bool {
+    def.signature
+        .strip_prefix(def.symbol_name.as_str())
+        .is_some_and(|rest| rest.starts_with(['(', '<']))
+}
+
+// `getUser(id: string): Promise<User>` o `Config  interface Config` según el tipo de firma,
+// seguido de los decoradores (`@Get(':id')`)
+fn definition_label(def: &DetectedDefinition) -> String {
+    let label = if is_call_signature(def) {
+        def.signature.clone()
+    } else {
+        format!("{}  {}", def.symbol_name, def.signature)
+    };
+    match def.decorators.is_empty() {
+        true => label,
+        false => format!("{}  {}", label, def.decorators.join(" ")),
+    }
+}
+
+// Archivo del manejador de una ruta importado por su archivo: `getUser` o `users.create`
+// (namespace `* as users`) contra los bindings locales de sus imports
+fn route_handler_target<'a>(def: &DetectedDefinition, connections: &'a [ResolvedConnection]) -> Option<&'a PathBuf> {
+    let handler = def.handler.as_deref()?;
+    let binding = handler.split('.').next().unwrap_or(handler);
+    connections
+        .iter()
+        .filter(|conn| conn.source_file == def.source_file)
+        .find(|conn| conn.imported_symbols.iter().any(|symbol| symbol.rsplit(" as ").next().map(str::trim) == Some(binding)))
+        .and_then(|conn| conn.resolved_target.as_ref())
+}
+
+pub fn generate_definitions_section(root_path: &Path, definitions: &[DetectedDefinition], connections: &[ResolvedConnection], doc_width: usize) -> Vec<ReportItem> {
     let mut section_items = Vec::new();
     section_items.push(ReportItem::PlainText("## Detected Definitions & Exports\n\n".to_string()));
 
@@ -333,7 +663,7 @@ pub fn generate_definitions_section(root_path: &Path, definitions: &[DetectedDef
 
     // 2. Obtener archivos fuente ordenados
     let mut sorted_files: Vec<PathBuf> = grouped_definitions.keys().cloned().collect();
-    sorted_files.sort();
+    sorted_files.sort_by(|a, b| compare_paths_naturally(a, b));
 
     // 3. Construir los items de la sección
     for file_path in sorted_files {
@@ -353,27 +683,59 @@ pub fn generate_definitions_section(root_path: &Path, definitions: &[DetectedDef
             let max_line_num = defs_in_file.last().map_or(0, |d| d.line_number);
             let line_width = if max_line_num == 0 { 1 } else { max_line_num.to_string().len() };
 
-            // Calcular padding para el tipo (Kind)
-            let max_kind_len = defs_in_file.iter().map(|d| d.kind.len()).max().unwrap_or(0);
+            // Calcular padding para el tipo (Kind) y el nombre; los miembros de clase van sangrados
+            let kind_label = |d: &DetectedDefinition| if d.parent.is_some() { format!("  {}", d.kind) } else { d.kind.clone() };
+            let max_kind_len = defs_in_file.iter().map(|d| kind_label(d).chars().count()).max().unwrap_or(0);
+            // Las firmas invocables ocupan la columna del nombre y no cuentan para su ancho
+            let max_name_len = defs_in_file.iter().filter(|d| !is_call_signature(d)).map(|d| d.symbol_name.chars().count()).max().unwrap_or(0);
+
+            // Columna de documentación solo si algún símbolo del archivo la tiene
+            let doc_label = |d: &DetectedDefinition| d.doc_summary.as_deref().map(|doc| format!("/** {} */", truncate_chars(doc, doc_width)));
+            let max_doc_len = defs_in_file.iter().filter_map(|d| doc_label(d)).map(|doc| doc.chars().count()).max();
 
             for def in defs_in_file {
-                // Añadir la definición como texto
-                section_items.push(ReportItem::PlainText(format!(
-                    "L{:<line_width$} {:<kind_width$} {}\n", 
-                    def.line_number, 
-                    def.kind, 
-                    def.symbol_name, 
-                    line_width = line_width, 
-                    kind_width = max_kind_len
-                )));
-                
-                // Opcionalmente podríamos hacer que cada símbolo sea clickable usando:
-                // section_items.push(ReportItem::FilePath { 
-                //    display: format!("L{:<line_width$} {:<kind_width$} {}", 
-                //    def.line_number, def.kind, def.symbol_name, 
-                //    line_width = line_width, kind_width = max_kind_len),
-                //    path: def.source_file.clone() 
-                // });
+                // Añadir la definición como texto (firma en una columna adicional)
+                let doc_column = match max_doc_len {
+                    Some(doc_width) => format!("{:<doc_width$}  ", doc_label(def).unwrap_or_default(), doc_width = doc_width),
+                    None => String::new(),
+                };
+                let (name_column, signature) = if is_call_signature(def) {
+                    (def.signature.as_str(), "")
+                } else {
+                    (def.symbol_name.as_str(), def.signature.as_str())
+                };
+                // Decoradores tras la firma: `export class UsersController  @Controller('users')`
+                let signature_column = match (signature, def.decorators.join(" ")) {
+                    ("", decorators) => decorators,
+                    (signature, decorators) if decorators.is_empty() => signature.to_string(),
+                    (signature, decorators) => format!("{}  {}", signature, decorators),
+                };
+                let line = format!(
+                    "L{:<line_width$} {:<kind_width$} {:<name_width$}  {}{}",
+                    def.line_number,
+                    kind_label(def),
+                    name_column,
+                    doc_column,
+                    signature_column,
+                    line_width = line_width,
+                    kind_width = max_kind_len,
+                    name_width = max_name_len
+                );
+                section_items.push(ReportItem::DefinitionLink {
+                    display: line.trim_end().to_string(),
+                    file: def.source_file.clone(),
+                    line: def.line_number,
+                });
+
+                // Rutas con manejador importado: enlace al archivo que lo define
+                if let Some(target) = route_handler_target(def, connections) {
+                    let indent = " ".repeat(line_width + max_kind_len + 3);
+                    section_items.push(ReportItem::FilePath {
+                        display: format!("{}↳ {} -> {}", indent, def.handler.as_deref().unwrap_or(""), target.strip_prefix(root_path).unwrap_or(target).display()),
+                        path: target.clone(),
+                    });
+                }
+
             }
             section_items.push(ReportItem::PlainText("```\n\n".to_string()));
         }
@@ -382,21 +744,80 @@ pub fn generate_definitions_section(root_path: &Path, definitions: &[DetectedDef
     section_items
 }
 
+// --- Índice alfabético de símbolos ---
+
+const SYMBOL_INDEX_MAX_NAME_WIDTH: usize = 40;
+
+/// Lista plana de símbolos (nombre y tipo) con todas sus ubicaciones, en orden natural.
+/// Cada fila es "símbolo (tipo)  ruta:línea" para poder pegarla como inventario de API.
+pub fn generate_symbol_index_section(root_path: &Path, definitions: &[DetectedDefinition]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Índice de símbolos\n\n```".to_string()));
+
+    // Las declaraciones exportadas se detectan dos veces (declaración y export): deduplicar
+    let mut entries: Vec<&DetectedDefinition> = definitions.iter().collect();
+    entries.sort_by(|a, b| {
+        natural_lexical_cmp_revised(&a.symbol_name.to_lowercase(), &b.symbol_name.to_lowercase())
+            .then_with(|| a.symbol_name.cmp(&b.symbol_name))
+            .then_with(|| compare_paths_naturally(&a.source_file, &b.source_file))
+            .then_with(|| a.line_number.cmp(&b.line_number))
+    });
+    entries.dedup_by(|a, b| a.symbol_name == b.symbol_name && a.source_file == b.source_file && a.line_number == b.line_number);
+
+    if entries.is_empty() {
+        section_items.push(ReportItem::PlainText("_No symbols found._".to_string()));
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+        return section_items;
+    }
+
+    let labels: Vec<String> = entries.iter().map(|d| format!("{} ({})", d.symbol_name, d.kind)).collect();
+    let name_width = labels
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(SYMBOL_INDEX_MAX_NAME_WIDTH);
+
+    for (def, label) in entries.iter().zip(labels) {
+        let display_path = def.source_file.strip_prefix(root_path).unwrap_or(&def.source_file).display();
+        section_items.push(ReportItem::FilePath {
+            display: format!("{:<name_width$}  {}:{}", label, display_path, def.line_number, name_width = name_width),
+            path: def.source_file.clone(),
+        });
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
 // --- NUEVA FUNCIÓN: Generar Sección de Usos Inversos ---
-pub fn generate_inverse_usage_section(root_path: &Path, connections: &[ResolvedConnection]) -> Vec<ReportItem> {
+pub fn generate_inverse_usage_section(
+    root_path: &Path,
+    connections: &[ResolvedConnection],
+    flatten_barrels: bool,
+    exclude_type_only: bool, // true = solo el grafo de dependencias en tiempo de ejecución
+) -> Vec<ReportItem> {
     let mut section_items = Vec::new();
     section_items.push(ReportItem::PlainText("## Inverse Usage (Who Imports What)\n\n".to_string()));
 
-    // 1. Construir mapa inverso: Target -> Vec<Source>
-    let mut inverse_map: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    // 1. Construir mapa inverso: Target -> Vec<(Source, línea del import)>
+    let mut inverse_map: HashMap<PathBuf, Vec<(PathBuf, usize)>> = HashMap::new();
     let mut files_with_imports: HashSet<PathBuf> = HashSet::new(); // Para rastrear archivos que *tienen* importaciones
 
-    for conn in connections {
-        if let Some(target_path) = &conn.resolved_target {
+    for conn in connections.iter().filter(|c| !(exclude_type_only && c.type_only)) {
+        // Los imports por glob son dependencias reales en tiempo de ejecución de cada archivo expandido
+        let glob_targets = conn.glob.as_ref().map(|_| conn.pattern_matches.as_slice()).unwrap_or_default();
+        // Con barrels aplanados, quien importa el index.ts depende de los archivos finales
+        let direct_targets = match conn.resolved_target.as_ref() {
+            Some(_) if flatten_barrels && !conn.barrel_targets.is_empty() => conn.barrel_targets.as_slice(),
+            Some(target) => std::slice::from_ref(target),
+            None => &[],
+        };
+        for target_path in direct_targets.iter().chain(glob_targets) {
             inverse_map
                 .entry(target_path.clone()) // El archivo importado es la clave
                 .or_default()
-                .push(conn.source_file.clone()); // El archivo que importa es el valor
+                .push((conn.source_file.clone(), conn.line_number)); // El archivo que importa es el valor
             files_with_imports.insert(target_path.clone()); // Marcar que este archivo fue importado
         }
     }
@@ -408,7 +829,7 @@ pub fn generate_inverse_usage_section(root_path: &Path, connections: &[ResolvedC
 
     // 2. Obtener lista ordenada de archivos que fueron importados
     let mut sorted_target_files: Vec<PathBuf> = inverse_map.keys().cloned().collect();
-    sorted_target_files.sort();
+    sorted_target_files.sort_by(|a, b| compare_paths_naturally(a, b));
 
     // 3. Construir los items de reporte
     section_items.push(ReportItem::PlainText("```\n".to_string()));
@@ -422,18 +843,21 @@ pub fn generate_inverse_usage_section(root_path: &Path, connections: &[ResolvedC
             .unwrap_or(target_file)
             .display();
 
-        // Agregar como FilePath para que sea clickable
-        section_items.push(ReportItem::FilePath { 
-            display: format!("{}{}", target_prefix, display_target_path),
-            path: target_file.clone() 
-        });
+        // Clickable: enlace al archivo (a la carpeta para paquetes Go)
+        section_items.push(ReportItem::Line(vec![
+            ReportSpan::Text(target_prefix.to_string()),
+            target_link(display_target_path.to_string(), target_file),
+        ]));
 
         if let Some(source_files) = inverse_map.get_mut(target_file) {
-            source_files.sort(); // Ordenar los archivos que lo importan
+            // Ordenar los archivos que lo importan; cada uno cuenta una vez (su primer import)
+            // aunque llegue por varios especificadores
+            source_files.sort_by(|a, b| compare_paths_naturally(&a.0, &b.0).then(a.1.cmp(&b.1)));
+            source_files.dedup_by(|a, b| a.0 == b.0);
             let num_sources = source_files.len();
             let base_indent = if is_last_target { "    " } else { "│   " };
 
-            for (j, source_file) in source_files.iter().enumerate() {
+            for (j, (source_file, line)) in source_files.iter().enumerate() {
                 let is_last_source = j == num_sources - 1;
                 let source_prefix = if is_last_source { "└── " } else { "├── " };
                 
@@ -442,10 +866,136 @@ pub fn generate_inverse_usage_section(root_path: &Path, connections: &[ResolvedC
                     .unwrap_or(source_file)
                     .display();
 
-                // Agregar como FilePath para que sea clickable
-                section_items.push(ReportItem::FilePath { 
-                    display: format!("{}{}{}", base_indent, source_prefix, display_source_path),
-                    path: source_file.clone() 
+                // Enlace a la línea del import en el archivo que importa
+                section_items.push(ReportItem::Line(vec![
+                    ReportSpan::Text(format!("{}{}", base_indent, source_prefix)),
+                    ReportSpan::FileLineLink { display: format!("{}:L{}", display_source_path, line), path: source_file.clone(), line: *line },
+                ]));
+            }
+        }
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
+// --- Usos por símbolo exportado ---
+
+// Nombre exportado que pide cada entrada de imported_symbols: `a as b` -> `a`, `default as X` ->
+// `default`. None para `* as ns` y `*`: la sentencia trae el módulo entero.
+fn requested_export(symbol: &str) -> Option<&str> {
+    if symbol.starts_with('*') {
+        return None;
+    }
+    Some(symbol.split(" as ").next().unwrap_or(symbol).trim())
+}
+
+/// Para cada símbolo exportado (JS/TS), los archivos que lo importan por su nombre.
+/// Los imports de namespace (`* as ns`, `export *`) cuentan para todos los exports del
+/// destino; los imports a través de un barrel se atribuyen al archivo que define el símbolo.
+pub fn generate_symbol_usage_section(root_path: &Path, connections: &[ResolvedConnection], definitions: &[DetectedDefinition]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Symbol Usage (Who Imports Each Export)\n\n".to_string()));
+
+    // 1. Exports por archivo: nombre exportado -> definición (las exportadas se detectan dos veces)
+    let mut exports: HashMap<&Path, Vec<(&str, &DetectedDefinition)>> = HashMap::new();
+    for def in definitions {
+        if let Some(exported_as) = &def.exported_as {
+            let file_exports = exports.entry(def.source_file.as_path()).or_default();
+            if !file_exports.iter().any(|(name, _)| *name == exported_as.as_str()) {
+                file_exports.push((exported_as.as_str(), def));
+            }
+        }
+    }
+
+    if exports.is_empty() {
+        section_items.push(ReportItem::PlainText("_No exported symbols detected._\n".to_string()));
+        return section_items;
+    }
+
+    // 2. (archivo, símbolo) -> importadores; `None` como símbolo = import del módulo completo
+    let mut importers: HashMap<(&Path, Option<&str>), Vec<&Path>> = HashMap::new();
+    for conn in connections {
+        let Some(target) = conn.resolved_target.as_deref() else { continue };
+        for symbol in &conn.imported_symbols {
+            match requested_export(symbol) {
+                Some(name) => {
+                    if exports.get(target).is_some_and(|e| e.iter().any(|(n, _)| *n == name)) {
+                        importers.entry((target, Some(name))).or_default().push(conn.source_file.as_path());
+                    }
+                    // Un barrel no define el símbolo: buscarlo entre los archivos que re-exporta,
+                    // también por nombre local (`export { default as Button } from './Button'`)
+                    let barrel_owners = if name == "default" { &[][..] } else { conn.barrel_targets.as_slice() };
+                    for owner in barrel_owners.iter().map(PathBuf::as_path) {
+                        let found = exports.get(owner).and_then(|e| e.iter().find(|(n, def)| *n == name || def.symbol_name == name));
+                        if let Some((exported, _)) = found {
+                            importers.entry((owner, Some(*exported))).or_default().push(conn.source_file.as_path());
+                        }
+                    }
+                }
+                None => importers.entry((target, None)).or_default().push(conn.source_file.as_path()),
+            }
+        }
+    }
+
+    // 3. Archivo que define -> símbolo -> importadores
+    let mut sorted_files: Vec<&Path> = exports.keys().copied().collect();
+    sorted_files.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+    let num_files = sorted_files.len();
+    for (i, file_path) in sorted_files.iter().enumerate() {
+        let is_last_file = i == num_files - 1;
+        let file_prefix = if is_last_file { "└── " } else { "├── " };
+        let base_indent = if is_last_file { "    " } else { "│   " };
+
+        section_items.push(ReportItem::FilePath {
+            display: format!("{}{}", file_prefix, file_path.strip_prefix(root_path).unwrap_or(file_path).display()),
+            path: file_path.to_path_buf(),
+        });
+
+        let mut file_exports = exports[file_path].clone();
+        file_exports.sort_by_key(|(_, def)| def.line_number);
+        let namespace_importers = importers.get(&(*file_path, None)).cloned().unwrap_or_default();
+
+        let num_exports = file_exports.len();
+        for (j, (name, def)) in file_exports.iter().enumerate() {
+            let is_last_export = j == num_exports - 1;
+            let export_prefix = if is_last_export { "└── " } else { "├── " };
+            let export_indent = if is_last_export { "    " } else { "│   " };
+
+            // `default` se muestra con el nombre local de la declaración
+            let label = if *name == "default" { format!("default ({})", def.symbol_name) } else { name.to_string() };
+            let mut symbol_importers: Vec<(&Path, bool)> = importers
+                .get(&(*file_path, Some(*name)))
+                .into_iter()
+                .flatten()
+                .map(|source| (*source, false))
+                .chain(namespace_importers.iter().map(|source| (*source, true)))
+                .collect();
+            symbol_importers.sort_by(|a, b| compare_paths_naturally(a.0, b.0).then(a.1.cmp(&b.1)));
+            symbol_importers.dedup_by(|a, b| a.0 == b.0);
+
+            let unused_tag = if symbol_importers.is_empty() { "  (unused export?)" } else { "" };
+            section_items.push(ReportItem::PlainText(format!(
+                "{}{}{} ({}){}",
+                base_indent, export_prefix, label, def.kind, unused_tag
+            )));
+
+            let num_importers = symbol_importers.len();
+            for (k, (source, via_namespace)) in symbol_importers.iter().enumerate() {
+                let importer_prefix = if k == num_importers - 1 { "└── " } else { "├── " };
+                let namespace_tag = if *via_namespace { " (*)" } else { "" };
+                section_items.push(ReportItem::FilePath {
+                    display: format!(
+                        "{}{}{}{}{}",
+                        base_indent,
+                        export_indent,
+                        importer_prefix,
+                        source.strip_prefix(root_path).unwrap_or(source).display(),
+                        namespace_tag
+                    ),
+                    path: source.to_path_buf(),
                 });
             }
         }
@@ -455,50 +1005,2410 @@ pub fn generate_inverse_usage_section(root_path: &Path, connections: &[ResolvedC
     section_items
 }
 
-pub fn generate_file_content_section(root_path: &Path, files: &[PathBuf]) -> String {
-     let mut section = String::new();
-    section.push_str("## File Contents\n\n");
-    let mut sorted_files = files.to_vec();
-    sorted_files.sort();
+// --- Ciclos de imports ---
 
-    for file_path in sorted_files {
-        let relative_path_display = match file_path.strip_prefix(root_path) {
-            Ok(relative_path) => relative_path.display().to_string(),
-            Err(_) => file_path.display().to_string(), // Use full path if strip fails
-        };
+/// Ciclos de dependencias entre archivos (componentes fuertemente conexos de más de un nodo),
+/// de mayor a menor. Cada ciclo se muestra como la cadena más corta que vuelve a su primer
+/// archivo; los demás miembros de la componente se listan debajo. Los ciclos a través de
+/// barrels (`index.ts`) suelen ser inofensivos, pero se informan igualmente y se marcan.
+pub fn generate_cycles_section(root_path: &Path, connections: &[ResolvedConnection]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Import Cycles\n\n".to_string()));
 
-        section.push_str(&format!("### `{}`\n\n", relative_path_display));
-        section.push_str("```");
-        if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
-            section.push_str(ext);
+    let mut import_graph: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut barrels: HashSet<&Path> = HashSet::new();
+    for conn in connections {
+        if let Some(target) = &conn.resolved_target {
+            let targets = import_graph.entry(conn.source_file.clone()).or_default();
+            if !targets.contains(target) {
+                targets.push(target.clone());
+            }
+            if !conn.barrel_targets.is_empty() {
+                barrels.insert(target.as_path());
+            }
         }
-        section.push('\n');
+    }
+
+    let mut cycles: Vec<Vec<PathBuf>> = graph::strongly_connected_components(&import_graph)
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .collect();
+    cycles.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| compare_paths_naturally(&a[0], &b[0])));
+
+    if cycles.is_empty() {
+        section_items.push(ReportItem::PlainText("_No import cycles detected._\n".to_string()));
+        return section_items;
+    }
+
+    let relative = |p: &Path| {
+        let barrel_note = if barrels.contains(p) { " (barrel)" } else { "" };
+        format!("{}{}", p.strip_prefix(root_path).unwrap_or(p).display(), barrel_note)
+    };
+
+    section_items.push(ReportItem::PlainText("```".to_string()));
+    for (i, component) in cycles.iter().enumerate() {
+        section_items.push(ReportItem::PlainText(format!("Ciclo {} ({} archivos)", i + 1, component.len())));
+
+        let members: HashSet<PathBuf> = component.iter().cloned().collect();
+        let chain = graph::shortest_cycle_through(&import_graph, &component[0], &members);
+        for (j, file) in chain.iter().enumerate() {
+            let arrow = if j == 0 { "    " } else { "  -> " };
+            section_items.push(ReportItem::FilePath { display: format!("{}{}", arrow, relative(file)), path: file.clone() });
+        }
+        // Cerrar la cadena en el archivo inicial
+        section_items.push(ReportItem::FilePath { display: format!("  -> {}", relative(&component[0])), path: component[0].clone() });
+
+        let rest: Vec<&PathBuf> = component.iter().filter(|f| !chain.contains(*f)).collect();
+        if !rest.is_empty() {
+            section_items.push(ReportItem::PlainText("  también en el ciclo:".to_string()));
+            for file in rest {
+                section_items.push(ReportItem::FilePath { display: format!("    · {}", relative(file)), path: file.clone() });
+            }
+        }
+        section_items.push(ReportItem::PlainText(String::new()));
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
+// --- Archivos huérfanos (nadie los importa) ---
+
+/// Qué archivos se consideran puntos de entrada y por tanto nunca huérfanos.
+/// Por defecto se aplican todas las reglas; cada método las activa o desactiva:
+/// `EntryPointRules::default().routes(false)`.
+#[derive(Clone, Copy, Debug)]
+pub struct EntryPointRules {
+    index_files: bool,  // index.*
+    main_files: bool,   // main.*
+    config_files: bool, // *.config.* (vite.config.ts, tailwind.config.js, ...)
+    routes: bool,       // Cualquier archivo bajo pages/ o app/ (rutas por sistema de archivos)
+    tests: bool,        // is_test_file
+}
+
+impl Default for EntryPointRules {
+    fn default() -> Self {
+        Self { index_files: true, main_files: true, config_files: true, routes: true, tests: true }
+    }
+}
+
+impl EntryPointRules {
+    pub fn index_files(mut self, enabled: bool) -> Self {
+        self.index_files = enabled;
+        self
+    }
+
+    pub fn main_files(mut self, enabled: bool) -> Self {
+        self.main_files = enabled;
+        self
+    }
+
+    pub fn config_files(mut self, enabled: bool) -> Self {
+        self.config_files = enabled;
+        self
+    }
+
+    pub fn routes(mut self, enabled: bool) -> Self {
+        self.routes = enabled;
+        self
+    }
+
+    pub fn tests(mut self, enabled: bool) -> Self {
+        self.tests = enabled;
+        self
+    }
+
+    pub fn is_entry_point(&self, root_path: &Path, file: &Path) -> bool {
+        let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let stem = name.split('.').next().unwrap_or("");
+        let relative = file.strip_prefix(root_path).unwrap_or(file);
+        let in_route_dir = relative
+            .parent()
+            .is_some_and(|dir| dir.components().any(|c| c.as_os_str() == "pages" || c.as_os_str() == "app"));
+        (self.index_files && stem == "index")
+            || (self.main_files && stem == "main")
+            || (self.config_files && name.contains(".config."))
+            || (self.routes && in_route_dir)
+            || (self.tests && is_test_file(file))
+    }
+}
+
+// Lenguajes cuyos imports se resuelven a archivos concretos: en el resto (Go resuelve a
+// carpetas, SQL no tiene imports) todos los archivos parecerían huérfanos.
+fn orphan_candidate(file: &Path) -> bool {
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let is_declaration = file.to_string_lossy().ends_with(".d.ts"); // Tipos ambientales: nunca se importan
+    !is_declaration && matches!(language_for_extension(ext), "JavaScript" | "TypeScript" | "Vue" | "Astro" | "CSS")
+}
+
+/// Candidatos a borrar: archivos que no son destino de ninguna conexión y no parecen puntos
+/// de entrada. Un archivo importado por cualquiera, aunque sea por un huérfano, cuenta como
+/// usado; esos archivos se listan aparte para que se vean las cadenas de código muerto.
+pub fn generate_orphans_section(
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    rules: &EntryPointRules,
+) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Orphan Files (Nobody Imports Them)\n\n".to_string()));
+
+    // 1. Importadores de cada archivo (incluye los expandidos por glob y patrones dinámicos)
+    let mut importers: HashMap<&Path, Vec<&Path>> = HashMap::new();
+    for conn in connections {
+        for target in conn.resolved_target.iter().chain(&conn.pattern_matches) {
+            if *target != conn.source_file {
+                importers.entry(target.as_path()).or_default().push(conn.source_file.as_path());
+            }
+        }
+    }
+
+    let candidates: Vec<&Path> = files
+        .iter()
+        .map(PathBuf::as_path)
+        .filter(|f| orphan_candidate(f) && !rules.is_entry_point(root_path, f))
+        .collect();
+    let mut orphans: Vec<&Path> = candidates.iter().copied().filter(|f| !importers.contains_key(f)).collect();
+    orphans.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    if orphans.is_empty() {
+        section_items.push(ReportItem::PlainText("_No orphan files found._\n".to_string()));
+        return section_items;
+    }
+
+    // 2. Usados solo por código muerto: todos sus importadores son huérfanos o, a su vez,
+    //    usados solo por código muerto (punto fijo; los ciclos entre ellos no se detectan)
+    let mut dead: HashSet<&Path> = orphans.iter().copied().collect();
+    let mut dead_chain: Vec<&Path> = Vec::new();
+    loop {
+        let newly_dead: Vec<&Path> = candidates
+            .iter()
+            .copied()
+            .filter(|f| !dead.contains(f))
+            .filter(|f| importers.get(f).is_some_and(|sources| sources.iter().all(|s| dead.contains(s))))
+            .collect();
+        if newly_dead.is_empty() {
+            break;
+        }
+        dead.extend(newly_dead.iter().copied());
+        dead_chain.extend(newly_dead);
+    }
+    dead_chain.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+
+    section_items.push(ReportItem::PlainText("```".to_string()));
+    section_items.push(ReportItem::PlainText(format!("Nadie los importa ({}):", orphans.len())));
+    let num_orphans = orphans.len();
+    for (i, file) in orphans.iter().enumerate() {
+        let prefix = if i == num_orphans - 1 { "└── " } else { "├── " };
+        section_items.push(ReportItem::FilePath { display: format!("{}{}", prefix, relative(file)), path: file.to_path_buf() });
+    }
+
+    if !dead_chain.is_empty() {
+        section_items.push(ReportItem::PlainText(String::new()));
+        section_items.push(ReportItem::PlainText(format!(
+            "Usados solo por archivos huérfanos ({}) (cuentan como usados, pero forman cadenas de código muerto):",
+            dead_chain.len()
+        )));
+        let num_dead = dead_chain.len();
+        for (i, file) in dead_chain.iter().enumerate() {
+            let prefix = if i == num_dead - 1 { "└── " } else { "├── " };
+            let mut sources: Vec<String> = importers[file].iter().map(|s| relative(s)).collect();
+            sources.sort_by(|a, b| compare_strs_naturally(a, b));
+            sources.dedup();
+            section_items.push(ReportItem::FilePath {
+                display: format!("{}{}  <- {}", prefix, relative(file), sources.join(", ")),
+                path: file.to_path_buf(),
+            });
+        }
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
+// --- Inventario de TODO/FIXME ---
+
+/// Marcadores TODO/FIXME/HACK/XXX agrupados por archivo, con el número de marcadores de cada
+/// archivo en su cabecera para priorizar.
+pub fn generate_todos_section(root_path: &Path, todos: &[TodoComment]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## TODO / FIXME Comments\n\n".to_string()));
+
+    if todos.is_empty() {
+        section_items.push(ReportItem::PlainText("_No TODO/FIXME/HACK/XXX comments found._\n".to_string()));
+        return section_items;
+    }
+
+    let mut by_file: HashMap<&Path, Vec<&TodoComment>> = HashMap::new();
+    let mut by_marker: HashMap<&str, usize> = HashMap::new();
+    for todo in todos {
+        by_file.entry(todo.source_file.as_path()).or_default().push(todo);
+        *by_marker.entry(todo.marker.as_str()).or_default() += 1;
+    }
+    let mut files: Vec<&Path> = by_file.keys().copied().collect();
+    files.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    let mut marker_counts: Vec<(&str, usize)> = by_marker.into_iter().collect();
+    marker_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let summary: Vec<String> = marker_counts.iter().map(|(marker, count)| format!("{} {}", marker, count)).collect();
+    section_items.push(ReportItem::PlainText(format!(
+        "{} marcadores en {} archivos ({})\n",
+        todos.len(),
+        files.len(),
+        summary.join(", ")
+    )));
+
+    section_items.push(ReportItem::PlainText("```".to_string()));
+    for file in files {
+        let mut file_todos = by_file.remove(file).unwrap_or_default();
+        file_todos.sort_by_key(|todo| todo.line_number);
+        section_items.push(ReportItem::FilePath {
+            display: format!("{} ({})", file.strip_prefix(root_path).unwrap_or(file).display(), file_todos.len()),
+            path: file.to_path_buf(),
+        });
+        let line_width = file_todos.last().map_or(1, |todo| todo.line_number.to_string().len());
+        let num_todos = file_todos.len();
+        for (i, todo) in file_todos.iter().enumerate() {
+            let prefix = if i == num_todos - 1 { "└── " } else { "├── " };
+            let line = format!("{}L{:<line_width$} {:<5} {}", prefix, todo.line_number, todo.marker, todo.text, line_width = line_width);
+            section_items.push(ReportItem::FilePath { display: line.trim_end().to_string(), path: file.to_path_buf() });
+        }
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
+// --- Sección de Referencias de Proyecto (tsconfig `references`) ---
+pub fn generate_project_references_section(root_path: &Path, projects: &[TsProject], connections: &[ResolvedConnection]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Project References\n\n".to_string()));
+
+    if projects.is_empty() {
+        section_items.push(ReportItem::PlainText("_No tsconfig files found._\n".to_string()));
+        return section_items;
+    }
+
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let projects_by_config: HashMap<&PathBuf, &TsProject> = projects.iter().map(|p| (&p.config_path, p)).collect();
+
+    // 1. Grafo de referencias resueltas y detección de ciclos
+    let mut reference_graph: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for project in projects {
+        let targets = project.references.iter().filter_map(|r| r.resolved_config.clone()).collect();
+        reference_graph.insert(project.config_path.clone(), targets);
+    }
+    let mut cycle_of: HashMap<PathBuf, usize> = HashMap::new();
+    for (i, component) in graph::nodes_in_cycles(&reference_graph).into_iter().enumerate() {
+        for node in component {
+            cycle_of.insert(node, i);
+        }
+    }
 
-        match fs::read_to_string(&file_path) {
-            Ok(content) => {
-                let lines: Vec<&str> = content.lines().collect();
-                let num_lines = lines.len();
-                // Calculate padding width based on the largest line number
-                let width = if num_lines == 0 { 1 } else { num_lines.to_string().len() };
+    // 2. DAG de referencias
+    section_items.push(ReportItem::PlainText("```".to_string()));
+    let num_projects = projects.len();
+    for (i, project) in projects.iter().enumerate() {
+        let is_last_project = i == num_projects - 1;
+        let project_prefix = if is_last_project { "└── " } else { "├── " };
+        let composite_note = if project.composite { " (composite)" } else { "" };
 
-                for (i, line) in lines.iter().enumerate() {
-                    let line_number = i + 1;
-                    section.push_str(&format!("{:<width$} | {}\n", line_number, line, width = width)); // Use left alignment for line numbers
+        section_items.push(ReportItem::FilePath {
+            display: format!("{}{}{}", project_prefix, relative(&project.config_path), composite_note),
+            path: project.config_path.clone(),
+        });
+
+        let base_indent = if is_last_project { "    " } else { "│   " };
+        let num_refs = project.references.len();
+        for (j, reference) in project.references.iter().enumerate() {
+            let ref_prefix = if j == num_refs - 1 { "└── " } else { "├── " };
+            match &reference.resolved_config {
+                Some(target) => {
+                    let mut flags = String::new();
+                    if projects_by_config.get(target).is_some_and(|t| !t.composite) {
+                        flags.push_str(" [sin composite: true]");
+                    }
+                    let same_cycle = cycle_of.get(&project.config_path).is_some()
+                        && cycle_of.get(&project.config_path) == cycle_of.get(target);
+                    if same_cycle {
+                        flags.push_str(" [ciclo]");
+                    }
+                    section_items.push(ReportItem::FilePath {
+                        display: format!("{}{}{} -> {}{}", base_indent, ref_prefix, reference.raw_path, relative(target), flags),
+                        path: target.clone(),
+                    });
+                }
+                None => {
+                    section_items.push(ReportItem::PlainText(format!(
+                        "{}{}{} (Unresolved)", base_indent, ref_prefix, reference.raw_path
+                    )));
                 }
-                 // Handle trailing newline correctly after loop
-                 if content.ends_with('\n') && !content.is_empty() {
-                    // If content ends with newline AND is not empty, the loop added the last line's \n. We are good.
-                 } else if content.is_empty() {
-                    // Empty file, do nothing extra.
-                 } else if !content.ends_with('\n') && !lines.is_empty() {
-                     // Content does not end with newline, but we added one for the last line. Remove it.
-                     if section.ends_with('\n') { section.pop(); }
-                 }
             }
-            Err(e) => section.push_str(&format!("[Error reading file: {}]", e)),
         }
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
 
-        section.push_str("\n```\n\n"); // Ensure newline before closing backticks
+    // 3. Imports entre paquetes sin la referencia correspondiente
+    let mut missing: HashMap<(PathBuf, PathBuf), Vec<&ResolvedConnection>> = HashMap::new();
+    for conn in connections {
+        let Some(target) = &conn.resolved_target else { continue };
+        let (Some(source_project), Some(target_project)) = (
+            tsconfig::owning_project(&conn.source_file, projects),
+            tsconfig::owning_project(target, projects),
+        ) else {
+            continue;
+        };
+        if source_project.config_path == target_project.config_path {
+            continue;
+        }
+        let has_reference = source_project
+            .references
+            .iter()
+            .any(|r| r.resolved_config.as_ref() == Some(&target_project.config_path));
+        if !has_reference {
+            missing
+                .entry((source_project.config_path.clone(), target_project.config_path.clone()))
+                .or_default()
+                .push(conn);
+        }
     }
-    section
-} 
\ No newline at end of file
+
+    if !missing.is_empty() {
+        section_items.push(ReportItem::PlainText("### Missing References\n".to_string()));
+        section_items.push(ReportItem::PlainText("```".to_string()));
+        let mut sorted_pairs: Vec<(PathBuf, PathBuf)> = missing.keys().cloned().collect();
+        sorted_pairs.sort_by(|a, b| compare_paths_naturally(&a.0, &b.0).then_with(|| compare_paths_naturally(&a.1, &b.1)));
+        let num_pairs = sorted_pairs.len();
+        for (i, pair) in sorted_pairs.iter().enumerate() {
+            let is_last_pair = i == num_pairs - 1;
+            let pair_prefix = if is_last_pair { "└── " } else { "├── " };
+            section_items.push(ReportItem::FilePath {
+                display: format!("{}{} -> {} (referencia faltante)", pair_prefix, relative(&pair.0), relative(&pair.1)),
+                path: pair.0.clone(),
+            });
+
+            if let Some(conns) = missing.get_mut(pair) {
+                conns.sort_by(|a, b| compare_paths_naturally(&a.source_file, &b.source_file).then_with(|| compare_strs_naturally(&a.imported_string, &b.imported_string)));
+                let base_indent = if is_last_pair { "    " } else { "│   " };
+                let num_conns = conns.len();
+                for (j, conn) in conns.iter().enumerate() {
+                    let conn_prefix = if j == num_conns - 1 { "└── " } else { "├── " };
+                    section_items.push(ReportItem::FilePath {
+                        display: format!("{}{}{}: {}", base_indent, conn_prefix, relative(&conn.source_file), conn.imported_string),
+                        path: conn.source_file.clone(),
+                    });
+                }
+            }
+        }
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    section_items
+}
+
+// --- Sección de pipeline de tareas (turbo.json / nx.json) ---
+
+// `pkg#build` -> (Some("pkg"), "build"); `build` -> (None, "build")
+fn split_task_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('#') {
+        Some((package, task)) => (Some(package), task),
+        None => (None, name),
+    }
+}
+
+pub fn generate_task_pipeline_section(root_path: &Path, info: &MonorepoInfo, connections: &[ResolvedConnection]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Task Pipeline\n\n".to_string()));
+
+    if info.pipelines.is_empty() {
+        section_items.push(ReportItem::PlainText("_No turbo.json / nx.json found._\n".to_string()));
+        return section_items;
+    }
+
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let packages_by_name: HashMap<&str, &WorkspacePackage> = info.packages.iter().map(|p| (p.name.as_str(), p)).collect();
+    // Paquetes que definen una tarea (script o target); `pkg#task` restringe a ese paquete
+    let packages_for = |task_name: &str| -> Vec<&WorkspacePackage> {
+        let (package, task) = split_task_name(task_name);
+        info.packages
+            .iter()
+            .filter(|p| package.is_none_or(|name| p.name == name) && p.defines_task(task))
+            .collect()
+    };
+
+    // 1. Tareas de cada configuración y paquetes que las definen
+    for pipeline in &info.pipelines {
+        section_items.push(ReportItem::FilePath {
+            display: format!("### {}: {}", pipeline.tool.label(), relative(&pipeline.config_path)),
+            path: pipeline.config_path.clone(),
+        });
+        if pipeline.tasks.is_empty() {
+            section_items.push(ReportItem::PlainText("_Sin tareas declaradas._\n".to_string()));
+            continue;
+        }
+        section_items.push(ReportItem::PlainText("```".to_string()));
+        let num_tasks = pipeline.tasks.len();
+        for (i, task) in pipeline.tasks.iter().enumerate() {
+            let is_last_task = i == num_tasks - 1;
+            let task_prefix = if is_last_task { "└── " } else { "├── " };
+            let depends_note = if task.depends_on.is_empty() {
+                String::new()
+            } else {
+                format!(" (dependsOn: {})", task.depends_on.join(", "))
+            };
+            section_items.push(ReportItem::PlainText(format!("{}{}{}", task_prefix, task.name, depends_note)));
+
+            let base_indent = if is_last_task { "    " } else { "│   " };
+            let defining = packages_for(&task.name);
+            let num_defining = defining.len();
+            for (j, package) in defining.iter().enumerate() {
+                let package_prefix = if j == num_defining - 1 { "└── " } else { "├── " };
+                section_items.push(ReportItem::FilePath {
+                    display: format!("{}{}{} ({})", base_indent, package_prefix, package.name, relative(&package.manifest_path)),
+                    path: package.manifest_path.clone(),
+                });
+            }
+        }
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    // 2. Dependencias de tareas entre paquetes: `^build` = el build de cada dependencia declarada
+    let mut task_edges: Vec<(String, String, PathBuf)> = Vec::new(); // (origen, destino, manifiesto origen)
+    let mut unknown_targets: Vec<(String, String, PathBuf)> = Vec::new(); // (tarea, `pkg#task` desconocido, config)
+    for pipeline in &info.pipelines {
+        for task in &pipeline.tasks {
+            let (_, task_base) = split_task_name(&task.name);
+            // `pkg#task` de un paquete inexistente, aunque ningún paquete defina la tarea
+            for dependency in task.depends_on.iter().filter(|d| !d.starts_with('^')) {
+                if let (Some(dep_package), _) = split_task_name(dependency) && !packages_by_name.contains_key(dep_package) {
+                    unknown_targets.push((task.name.clone(), dependency.clone(), pipeline.config_path.clone()));
+                }
+            }
+            for package in packages_for(&task.name) {
+                for dependency in &task.depends_on {
+                    if let Some(upstream_task) = dependency.strip_prefix('^') {
+                        for dep_name in &package.dependencies {
+                            if packages_by_name.get(dep_name.as_str()).is_some_and(|d| d.defines_task(upstream_task)) {
+                                task_edges.push((
+                                    format!("{} {}", package.name, task_base),
+                                    format!("{} {}", dep_name, upstream_task),
+                                    package.manifest_path.clone(),
+                                ));
+                            }
+                        }
+                    } else if let (Some(dep_package), dep_task) = split_task_name(dependency)
+                        && packages_by_name.contains_key(dep_package)
+                        && dep_package != package.name
+                    {
+                        task_edges.push((
+                            format!("{} {}", package.name, task_base),
+                            format!("{} {}", dep_package, dep_task),
+                            package.manifest_path.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    task_edges.sort();
+    task_edges.dedup();
+    unknown_targets.sort();
+    unknown_targets.dedup();
+
+    if !task_edges.is_empty() {
+        section_items.push(ReportItem::PlainText("### Package Task Dependencies\n".to_string()));
+        section_items.push(ReportItem::PlainText("```".to_string()));
+        for (from, to, manifest) in &task_edges {
+            section_items.push(ReportItem::FilePath {
+                display: format!("{} depends on {}", from, to),
+                path: manifest.clone(),
+            });
+        }
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    // 3. Imports en código vs dependencias declaradas (de las que salen las aristas `^task`)
+    let code_dependencies = monorepo::code_dependencies(info, connections);
+    let mut warnings: Vec<ReportItem> = Vec::new();
+    for package in &info.packages {
+        let imported = code_dependencies.get(&package.name);
+        for target in imported.into_iter().flatten() {
+            if !package.dependencies.contains(target) {
+                warnings.push(ReportItem::FilePath {
+                    display: format!(
+                        "- {} imports {} in code, but does not declare it as a dependency (its tasks won't wait for {})",
+                        package.name, target, target
+                    ),
+                    path: package.manifest_path.clone(),
+                });
+            }
+        }
+        for dependency in &package.dependencies {
+            if !imported.is_some_and(|targets| targets.contains(dependency)) {
+                warnings.push(ReportItem::FilePath {
+                    display: format!(
+                        "- {} declares {} as a dependency, but no code imports it (task dependency may be unnecessary)",
+                        package.name, dependency
+                    ),
+                    path: package.manifest_path.clone(),
+                });
+            }
+        }
+    }
+    for (task, dependency, config_path) in &unknown_targets {
+        warnings.push(ReportItem::FilePath {
+            display: format!("- {} depends on {}, which is not a workspace package", task, dependency),
+            path: config_path.clone(),
+        });
+    }
+
+    if !warnings.is_empty() {
+        section_items.push(ReportItem::PlainText("### Potential Misconfigurations\n".to_string()));
+        section_items.extend(warnings);
+        section_items.push(ReportItem::PlainText(String::new()));
+    }
+
+    section_items
+}
+
+// --- Estadísticas del proyecto ---
+
+// Filas "clave  valor" alineadas en árbol, como el resto de secciones
+fn push_stat_rows(section_items: &mut Vec<ReportItem>, rows: &[(String, String)]) {
+    let key_width = rows.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    for (i, (key, value)) in rows.iter().enumerate() {
+        let prefix = if i == rows.len() - 1 { "└── " } else { "├── " };
+        section_items.push(ReportItem::PlainText(format!("{}{:<key_width$}  {}", prefix, key, value, key_width = key_width)));
+    }
+}
+
+/// Escala del proyecto: archivos y líneas (por extensión), conexiones resueltas y sin resolver,
+/// definiciones por tipo y los `top_n` archivos más largos. Las líneas vienen del análisis.
+pub fn generate_stats_section(
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    definitions: &[DetectedDefinition],
+    metrics: &HashMap<PathBuf, FileMetrics>,
+    skipped: &[SkippedFile],
+    top_n: usize,
+) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Project Statistics\n\n".to_string()));
+
+    let lines_of = |file: &PathBuf| metrics.get(file).and_then(|m| m.lines).unwrap_or(0);
+    let total_lines: usize = files.iter().map(lines_of).sum();
+    let resolved = connections.iter().filter(|c| c.resolved_target.is_some() || !c.pattern_matches.is_empty()).count();
+    let mut unresolved_by_class: BTreeMap<&str, usize> = BTreeMap::new();
+    for conn in connections.iter().filter(|c| c.resolved_target.is_none() && c.pattern_matches.is_empty()) {
+        *unresolved_by_class.entry(conn.import_class.label()).or_default() += 1;
+    }
+    let unresolved_detail: Vec<String> = unresolved_by_class.iter().map(|(class, count)| format!("{} {}", class, count)).collect();
+    let unresolved = connections.len() - resolved;
+
+    section_items.push(ReportItem::PlainText(format!("- Files: {} ({} lines)", files.len(), total_lines)));
+    section_items.push(ReportItem::PlainText(if unresolved_detail.is_empty() {
+        format!("- Connections: {} resolved, {} unresolved", resolved, unresolved)
+    } else {
+        format!("- Connections: {} resolved, {} unresolved ({})", resolved, unresolved, unresolved_detail.join(", "))
+    }));
+    section_items.push(ReportItem::PlainText(format!("- Definitions: {}\n", definitions.len())));
+
+    // Por extensión: más líneas primero
+    let mut by_extension: HashMap<String, (usize, usize)> = HashMap::new();
+    for file in files {
+        let ext = file.extension().and_then(|e| e.to_str()).map_or_else(|| "(sin extensión)".to_string(), |e| format!(".{}", e));
+        let entry = by_extension.entry(ext).or_default();
+        entry.0 += 1;
+        entry.1 += lines_of(file);
+    }
+    if !by_extension.is_empty() {
+        let mut extensions: Vec<(String, (usize, usize))> = by_extension.into_iter().collect();
+        extensions.sort_by(|(a, (_, a_lines)), (b, (_, b_lines))| b_lines.cmp(a_lines).then_with(|| a.cmp(b)));
+        let rows: Vec<(String, String)> = extensions
+            .into_iter()
+            .map(|(ext, (count, lines))| (ext, format!("{} files, {} lines", count, lines)))
+            .collect();
+        section_items.push(ReportItem::PlainText("### By extension\n\n```".to_string()));
+        push_stat_rows(&mut section_items, &rows);
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    let mut by_kind: HashMap<&str, usize> = HashMap::new();
+    for def in definitions {
+        *by_kind.entry(def.kind.as_str()).or_default() += 1;
+    }
+    if !by_kind.is_empty() {
+        let mut kinds: Vec<(&str, usize)> = by_kind.into_iter().collect();
+        kinds.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        let rows: Vec<(String, String)> = kinds.into_iter().map(|(kind, count)| (kind.to_string(), count.to_string())).collect();
+        section_items.push(ReportItem::PlainText("### Definitions by kind\n\n```".to_string()));
+        push_stat_rows(&mut section_items, &rows);
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    let mut largest: Vec<&PathBuf> = files.iter().filter(|f| lines_of(f) > 0).collect();
+    largest.sort_by(|a, b| lines_of(b).cmp(&lines_of(a)).then_with(|| compare_paths_naturally(a, b)));
+    largest.truncate(top_n);
+    if !largest.is_empty() {
+        section_items.push(ReportItem::PlainText("### Largest files\n\n```".to_string()));
+        let num_largest = largest.len();
+        for (i, file) in largest.into_iter().enumerate() {
+            let prefix = if i == num_largest - 1 { "└── " } else { "├── " };
+            section_items.push(ReportItem::FilePath {
+                display: format!("{}{}  ({} lines)", prefix, file.strip_prefix(root_path).unwrap_or(file).display(), lines_of(file)),
+                path: file.clone(),
+            });
+        }
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    // Archivos listados en la estructura pero sin analizar: que el lector sepa que existen
+    let listed: HashSet<&PathBuf> = files.iter().collect();
+    let mut skipped: Vec<&SkippedFile> = skipped.iter().filter(|s| listed.contains(&s.file)).collect();
+    skipped.sort_by(|a, b| compare_paths_naturally(&a.file, &b.file));
+    if !skipped.is_empty() {
+        section_items.push(ReportItem::PlainText("### Skipped files\n\n```".to_string()));
+        let num_skipped = skipped.len();
+        for (i, skipped_file) in skipped.into_iter().enumerate() {
+            let prefix = if i == num_skipped - 1 { "└── " } else { "├── " };
+            section_items.push(ReportItem::FilePath {
+                display: format!("{}{}  ({})", prefix, skipped_file.file.strip_prefix(root_path).unwrap_or(&skipped_file.file).display(), skipped_file.reason.describe()),
+                path: skipped_file.file.clone(),
+            });
+        }
+        section_items.push(ReportItem::PlainText("```\n".to_string()));
+    }
+
+    section_items
+}
+
+// --- Archivos hub (los más importados) ---
+
+const PAGERANK_DAMPING: f64 = 0.85;
+const PAGERANK_ITERATIONS: usize = 30;
+
+// Importancia de cada archivo importado: fan-in o, con `use_pagerank`, su PageRank en el grafo
+// de imports resueltos. Los archivos que nadie importa no aparecen.
+fn file_importance(connections: &[ResolvedConnection], use_pagerank: bool) -> HashMap<PathBuf, f64> {
+    let fan_in = compute_fan_in(connections);
+    if !use_pagerank {
+        return fan_in.into_iter().map(|(file, count)| (file, count as f64)).collect();
+    }
+    let mut adjacency: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for conn in connections {
+        if let Some(target) = conn.resolved_target.as_ref().filter(|t| **t != conn.source_file) {
+            adjacency.entry(conn.source_file.clone()).or_default().push(target.clone());
+        }
+    }
+    graph::pagerank(&adjacency, PAGERANK_DAMPING, PAGERANK_ITERATIONS)
+        .into_iter()
+        .filter(|(file, _)| fan_in.contains_key(file))
+        .collect()
+}
+
+// Más importante primero; empates por orden natural de ruta para una salida estable
+fn by_importance(importance: &HashMap<PathBuf, f64>, a: &Path, b: &Path) -> Ordering {
+    let score = |p: &Path| importance.get(p).copied().unwrap_or(0.0);
+    score(b).total_cmp(&score(a)).then_with(|| compare_paths_naturally(a, b))
+}
+
+/// Los `top_n` archivos con más importadores (o mayor PageRank), con su número de importadores.
+pub fn generate_hubs_section(root_path: &Path, connections: &[ResolvedConnection], top_n: usize, use_pagerank: bool) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Hub Files (Most Imported)\n\n".to_string()));
+
+    let fan_in = compute_fan_in(connections);
+    let importance = file_importance(connections, use_pagerank);
+    if importance.is_empty() {
+        section_items.push(ReportItem::PlainText("_No resolved local imports found._\n".to_string()));
+        return section_items;
+    }
+    let mut ranked: Vec<&PathBuf> = importance.keys().collect();
+    ranked.sort_by(|a, b| by_importance(&importance, a, b));
+    ranked.truncate(top_n);
+
+    section_items.push(ReportItem::PlainText("```".to_string()));
+    let rank_width = ranked.len().to_string().len();
+    let num_ranked = ranked.len();
+    for (i, file) in ranked.iter().enumerate() {
+        let prefix = if i == num_ranked - 1 { "└── " } else { "├── " };
+        let importers = fan_in.get(*file).copied().unwrap_or(0);
+        let score = if use_pagerank { format!(", PageRank {:.4}", importance[*file]) } else { String::new() };
+        section_items.push(ReportItem::FilePath {
+            display: format!(
+                "{}{:>rank_width$}. {}  ({} importadores{})",
+                prefix,
+                i + 1,
+                file.strip_prefix(root_path).unwrap_or(file).display(),
+                importers,
+                score,
+                rank_width = rank_width
+            ),
+            path: (*file).clone(),
+        });
+    }
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+
+    section_items
+}
+
+// --- Grafo de imports en formato Graphviz DOT (`dot -Tsvg imports.dot`) ---
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotDirection {
+    TopBottom,
+    LeftRight,
+}
+
+/// Opciones del grafo DOT; como `EntryPointRules`, se ajustan encadenando métodos:
+/// `DotOptions::default().direction(DotDirection::TopBottom).phantom_nodes(true)`.
+#[derive(Clone, Copy, Debug)]
+pub struct DotOptions {
+    direction: DotDirection,
+    phantom_nodes: bool,        // Nodo discontinuo por cada import sin resolver (paquetes, rotos...)
+    cluster_by_directory: bool, // `subgraph cluster_N` por carpeta contenedora
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self { direction: DotDirection::LeftRight, phantom_nodes: false, cluster_by_directory: false }
+    }
+}
+
+impl DotOptions {
+    pub fn direction(mut self, direction: DotDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn phantom_nodes(mut self, enabled: bool) -> Self {
+        self.phantom_nodes = enabled;
+        self
+    }
+
+    pub fn cluster_by_directory(mut self, enabled: bool) -> Self {
+        self.cluster_by_directory = enabled;
+        self
+    }
+}
+
+// Identificador DOT entre comillas: solo `"` y `\` necesitan escape
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Grafo dirigido importador -> importado. Los nodos son rutas relativas y el tooltip de
+/// cada arista lista los especificadores que la producen (varios imports = una arista).
+pub fn generate_dot_graph(root_path: &Path, connections: &[ResolvedConnection], options: &DotOptions) -> String {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+
+    // BTreeMap/BTreeSet: la misma entrada produce siempre el mismo archivo
+    let mut files: BTreeSet<String> = BTreeSet::new();
+    let mut phantoms: BTreeSet<String> = BTreeSet::new();
+    let mut edges: BTreeMap<(String, String), BTreeSet<&str>> = BTreeMap::new();
+    for conn in connections {
+        let source = relative(&conn.source_file);
+        let target = match &conn.resolved_target {
+            Some(target) => {
+                let target = relative(target);
+                files.insert(target.clone());
+                target
+            }
+            None if options.phantom_nodes => {
+                let phantom = format!("?{}", conn.imported_string);
+                phantoms.insert(phantom.clone());
+                phantom
+            }
+            None => continue,
+        };
+        files.insert(source.clone());
+        edges.entry((source, target)).or_default().insert(&conn.imported_string);
+    }
+
+    let mut dot = String::from("digraph imports {\n");
+    let rankdir = match options.direction {
+        DotDirection::TopBottom => "TB",
+        DotDirection::LeftRight => "LR",
+    };
+    dot.push_str(&format!("    rankdir={};\n", rankdir));
+    dot.push_str("    node [shape=box, fontname=\"monospace\", fontsize=10];\n\n");
+
+    if options.cluster_by_directory {
+        let mut by_directory: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for file in &files {
+            let directory = Path::new(file).parent().map(|d| d.display().to_string()).unwrap_or_default();
+            by_directory.entry(directory).or_default().push(file);
+        }
+        for (i, (directory, members)) in by_directory.iter().enumerate() {
+            // Los archivos de la raíz no forman cluster
+            if directory.is_empty() {
+                for file in members {
+                    dot.push_str(&format!("    {};\n", dot_quote(file)));
+                }
+                continue;
+            }
+            dot.push_str(&format!("    subgraph cluster_{} {{\n        label={};\n", i, dot_quote(directory)));
+            for file in members {
+                dot.push_str(&format!("        {};\n", dot_quote(file)));
+            }
+            dot.push_str("    }\n");
+        }
+    } else {
+        for file in &files {
+            dot.push_str(&format!("    {};\n", dot_quote(file)));
+        }
+    }
+    for phantom in &phantoms {
+        dot.push_str(&format!("    {} [label={}, style=dashed];\n", dot_quote(phantom), dot_quote(&phantom[1..])));
+    }
+    dot.push('\n');
+
+    for ((source, target), specifiers) in &edges {
+        let specifiers: Vec<&str> = specifiers.iter().copied().collect();
+        let style = if phantoms.contains(target) { ", style=dashed" } else { "" };
+        dot.push_str(&format!(
+            "    {} -> {} [tooltip={}{}];\n",
+            dot_quote(source),
+            dot_quote(target),
+            dot_quote(&specifiers.join(", ")),
+            style
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// --- Resumen por carpeta de primer nivel ---
+
+#[derive(Clone, Debug)]
+pub struct DirectorySummary {
+    pub directory: PathBuf,
+    pub file_count: usize,
+    pub dominant_language: Option<(&'static str, usize)>,
+    pub top_symbols: Vec<(String, usize)>,       // (símbolo exportado, fan-in de su archivo)
+    pub external_packages: Vec<(String, usize)>, // (paquete, nº de imports)
+    pub depends_on: Vec<PathBuf>,                // otras carpetas de primer nivel importadas
+}
+
+const SUMMARY_TOP_SYMBOLS: usize = 5;
+const SUMMARY_TOP_PACKAGES: usize = 5;
+
+// Carpeta de primer nivel (absoluta) a la que pertenece un archivo; None para archivos en la raíz
+fn top_level_directory(root_path: &Path, file: &Path) -> Option<PathBuf> {
+    let relative = file.strip_prefix(root_path).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?; // Debe haber algo más (el archivo) debajo
+    Some(root_path.join(first.as_os_str()))
+}
+
+// Fan-in por archivo: número de archivos distintos que lo importan
+pub fn compute_fan_in(connections: &[ResolvedConnection]) -> HashMap<PathBuf, usize> {
+    let mut importers: HashMap<&PathBuf, HashSet<&PathBuf>> = HashMap::new();
+    for conn in connections {
+        if let Some(target) = &conn.resolved_target {
+            importers.entry(target).or_default().insert(&conn.source_file);
+        }
+    }
+    importers.into_iter().map(|(target, sources)| (target.clone(), sources.len())).collect()
+}
+
+fn top_counts(counts: HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(limit);
+    sorted
+}
+
+pub fn summarize_directories(
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    definitions: &[DetectedDefinition],
+) -> Vec<DirectorySummary> {
+    let fan_in = compute_fan_in(connections);
+
+    let mut files_by_dir: HashMap<PathBuf, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        if let Some(dir) = top_level_directory(root_path, file) {
+            files_by_dir.entry(dir).or_default().push(file);
+        }
+    }
+
+    let mut summaries: Vec<DirectorySummary> = files_by_dir
+        .into_iter()
+        .map(|(directory, dir_files)| {
+            // Lenguaje dominante por extensión
+            let mut languages: HashMap<&'static str, usize> = HashMap::new();
+            for file in &dir_files {
+                let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+                *languages.entry(language_for_extension(ext)).or_default() += 1;
+            }
+            let dominant_language = languages
+                .into_iter()
+                .filter(|(lang, _)| *lang != "Other")
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)));
+
+            // Símbolos exportados cuyo archivo tiene más fan-in (lo interno no orienta a quien llega de fuera)
+            let mut symbol_scores: HashMap<String, usize> = HashMap::new();
+            for def in definitions.iter().filter(|d| d.exported_as.is_some() && d.source_file.starts_with(&directory)) {
+                let score = fan_in.get(&def.source_file).copied().unwrap_or(0);
+                let entry = symbol_scores.entry(def.symbol_name.clone()).or_default();
+                *entry = (*entry).max(score);
+            }
+            let top_symbols = top_counts(symbol_scores, SUMMARY_TOP_SYMBOLS);
+
+            // Paquetes externos y dependencias entre carpetas
+            let mut packages: HashMap<String, usize> = HashMap::new();
+            let mut depends_on: HashSet<PathBuf> = HashSet::new();
+            for conn in connections.iter().filter(|c| c.source_file.starts_with(&directory)) {
+                match &conn.resolved_target {
+                    Some(target) => {
+                        if let Some(target_dir) = top_level_directory(root_path, target) {
+                            if target_dir != directory {
+                                depends_on.insert(target_dir);
+                            }
+                        }
+                    }
+                    None => {
+                        if let Some(package) = package_name(&conn.imported_string) {
+                            *packages.entry(package).or_default() += 1;
+                        }
+                    }
+                }
+            }
+            let mut depends_on: Vec<PathBuf> = depends_on.into_iter().collect();
+            depends_on.sort_by(|a, b| compare_paths_naturally(a, b));
+
+            DirectorySummary {
+                directory,
+                file_count: dir_files.len(),
+                dominant_language,
+                top_symbols,
+                external_packages: top_counts(packages, SUMMARY_TOP_PACKAGES),
+                depends_on,
+            }
+        })
+        .collect();
+
+    summaries.sort_by(|a, b| compare_paths_naturally(&a.directory, &b.directory));
+    summaries
+}
+
+pub fn generate_directory_summary_section(
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    definitions: &[DetectedDefinition],
+    package_versions: &PackageVersions,
+) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Directory Summary\n\n".to_string()));
+
+    let summaries = summarize_directories(root_path, files, connections, definitions);
+    if summaries.is_empty() {
+        section_items.push(ReportItem::PlainText("_No top-level directories found._\n".to_string()));
+        return section_items;
+    }
+
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+
+    for summary in summaries {
+        section_items.push(ReportItem::Directory {
+            display: format!("### `{}/`", relative(&summary.directory)),
+            path: summary.directory.clone(),
+        });
+
+        let language = match summary.dominant_language {
+            Some((lang, count)) => format!(", mostly {} ({} files)", lang, count),
+            None => String::new(),
+        };
+        section_items.push(ReportItem::PlainText(format!("- {} files{}", summary.file_count, language)));
+
+        if !summary.top_symbols.is_empty() {
+            let symbols: Vec<String> = summary.top_symbols
+                .iter()
+                .map(|(name, fan_in)| format!("{} (fan-in {})", name, fan_in))
+                .collect();
+            section_items.push(ReportItem::PlainText(format!("- Key symbols: {}", symbols.join(", "))));
+        }
+        if !summary.external_packages.is_empty() {
+            let packages: Vec<String> = summary.external_packages
+                .iter()
+                .map(|(name, count)| match package_versions.get(name) {
+                    Some(version) => format!("{}@{} ({})", name, version.describe(), count),
+                    None => format!("{} ({})", name, count),
+                })
+                .collect();
+            section_items.push(ReportItem::PlainText(format!("- External packages: {}", packages.join(", "))));
+        }
+        if !summary.depends_on.is_empty() {
+            let dirs: Vec<String> = summary.depends_on.iter().map(|d| format!("{}/", relative(d))).collect();
+            section_items.push(ReportItem::PlainText(format!("- Depends on: {}", dirs.join(", "))));
+        }
+        section_items.push(ReportItem::PlainText(String::new()));
+    }
+
+    section_items
+}
+
+pub fn generate_duplicates_section(root_path: &Path, groups: &[DuplicateGroup]) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText("## Posibles duplicaciones de módulo\n\n".to_string()));
+
+    if groups.is_empty() {
+        section_items.push(ReportItem::PlainText("_No se encontraron carpetas con estructura duplicada._\n".to_string()));
+        return section_items;
+    }
+
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+
+    for group in groups.iter().take(duplicates::MAX_REPORTED_GROUPS) {
+        section_items.push(ReportItem::Directory {
+            display: format!("### `{}/`", relative(&group.dir_a)),
+            path: group.dir_a.clone(),
+        });
+        section_items.push(ReportItem::Directory {
+            display: format!("    ≈ `{}/` ({:.0}% similar)", relative(&group.dir_b), group.similarity * 100.0),
+            path: group.dir_b.clone(),
+        });
+        for pair in &group.pairs {
+            let marker = if pair.identical { "=" } else { "~" };
+            section_items.push(ReportItem::FilePath {
+                display: format!("  {} {}", marker, relative(&pair.file_a)),
+                path: pair.file_a.clone(),
+            });
+            section_items.push(ReportItem::FilePath {
+                display: format!("    ↔ {}", relative(&pair.file_b)),
+                path: pair.file_b.clone(),
+            });
+        }
+        section_items.push(ReportItem::PlainText(String::new()));
+    }
+    if groups.len() > duplicates::MAX_REPORTED_GROUPS {
+        section_items.push(ReportItem::PlainText(format!(
+            "_{} grupos más con menor similitud._\n",
+            groups.len() - duplicates::MAX_REPORTED_GROUPS
+        )));
+    }
+    section_items.push(ReportItem::PlainText("_= contenido idéntico, ~ mismos símbolos exportados._\n".to_string()));
+
+    section_items
+}
+
+// Resultados de la búsqueda en el contenido, una línea `ruta:línea: texto` por coincidencia (como grep).
+// Cada línea abre el archivo en la coincidencia.
+pub fn generate_content_search_section(root_path: &Path, query: &str, matches: &[ContentMatch], truncated: bool) -> Vec<ReportItem> {
+    let mut section_items = Vec::new();
+    section_items.push(ReportItem::PlainText(format!("## Búsqueda en el contenido: `{}`
+
+```", query)));
+    if matches.is_empty() {
+        section_items.push(ReportItem::PlainText("_Sin coincidencias._".to_string()));
+    }
+
+    let mut sorted: Vec<&ContentMatch> = matches.iter().collect();
+    sorted.sort_by(|a, b| compare_paths_naturally(&a.path, &b.path).then(a.line.cmp(&b.line)));
+    for m in sorted {
+        section_items.push(ReportItem::DefinitionLink {
+            display: format!("{}:{}: {}", m.path.strip_prefix(root_path).unwrap_or(&m.path).display(), m.line, m.text),
+            file: m.path.clone(),
+            line: m.line,
+        });
+    }
+
+    section_items.push(ReportItem::PlainText("```\n".to_string()));
+    if truncated {
+        section_items.push(ReportItem::PlainText(format!("_Solo se muestran las primeras {} coincidencias._\n", content_search::MAX_MATCHES)));
+    }
+    section_items
+}
+
+// Orden de los archivos en la sección de contenidos
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentOrder {
+    Alphabetical,
+    DependenciesFirst, // Orden topológico: cada archivo después de lo que importa
+    Importance, // Los más importados primero: si el contexto se trunca, se pierde la cola
+}
+
+// Orden topológico de `files` con las dependencias primero. Los ciclos se agrupan (componentes
+// fuertemente conexas) y dentro de ellos, como entre archivos sin relación, manda el orden
+// natural de ruta. Cada archivo aparece exactamente una vez.
+fn dependency_order(files: &[PathBuf], connections: &[ResolvedConnection]) -> Vec<PathBuf> {
+    let mut sorted: Vec<&PathBuf> = files.iter().collect();
+    sorted.sort_by(|a, b| compare_paths_naturally(a, b));
+    sorted.dedup();
+    let index_of: HashMap<&PathBuf, usize> = sorted.iter().enumerate().map(|(i, f)| (*f, i)).collect();
+
+    // Aristas importador -> importado entre archivos de la lista (índices en orden natural)
+    let mut adjacency: HashMap<usize, Vec<usize>> = (0..sorted.len()).map(|i| (i, Vec::new())).collect();
+    for conn in connections {
+        let (Some(&source), Some(&target)) = (index_of.get(&conn.source_file), conn.resolved_target.as_ref().and_then(|t| index_of.get(t))) else { continue };
+        if source != target {
+            adjacency.entry(source).or_default().push(target);
+        }
+    }
+
+    let mut components = graph::strongly_connected_components(&adjacency);
+    for component in &mut components {
+        component.sort_unstable();
+    }
+    let mut component_of = vec![0; sorted.len()];
+    for (c, component) in components.iter().enumerate() {
+        for &file in component {
+            component_of[file] = c;
+        }
+    }
+
+    // Kahn sobre el grafo de componentes: lista una componente cuando ya salieron todas las que
+    // importa; entre las listas, la de menor ruta primero
+    let mut pending_dependencies = vec![0usize; components.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); components.len()];
+    let mut seen_edges = HashSet::new();
+    for (&source, targets) in &adjacency {
+        for &target in targets {
+            let (from, to) = (component_of[source], component_of[target]);
+            if from != to && seen_edges.insert((from, to)) {
+                pending_dependencies[from] += 1;
+                dependents[to].push(from);
+            }
+        }
+    }
+    let mut ready: BTreeSet<(usize, usize)> = (0..components.len())
+        .filter(|&c| pending_dependencies[c] == 0)
+        .map(|c| (components[c][0], c))
+        .collect();
+
+    let mut ordered = Vec::with_capacity(sorted.len());
+    while let Some((_, c)) = ready.pop_first() {
+        ordered.extend(components[c].iter().map(|&i| sorted[i].clone()));
+        for &dependent in &dependents[c] {
+            pending_dependencies[dependent] -= 1;
+            if pending_dependencies[dependent] == 0 {
+                ready.insert((components[dependent][0], dependent));
+            }
+        }
+    }
+    ordered
+}
+
+pub fn generate_file_content_section(
+    root_path: &Path,
+    files: &[PathBuf],
+    skeleton: bool,
+    connections: &[ResolvedConnection],
+    order: ContentOrder,
+    format: OutputFormat,
+    max_file_bytes: u64, // Los archivos más grandes (o binarios) quedan como una nota
+) -> String {
+     let mut section = file_content_heading(skeleton).to_string();
+    for (_, block) in file_content_blocks(root_path, files, skeleton, connections, order, format, max_file_bytes) {
+        section.push_str(&block);
+    }
+    section
+}
+
+pub fn file_content_heading(skeleton: bool) -> &'static str {
+    if skeleton { "## File Skeletons\n\n" } else { "## File Contents\n\n" }
+}
+
+/// Un bloque por archivo, ya ordenado; permite recortar el contenido sin partir archivos.
+pub fn file_content_blocks(
+    root_path: &Path,
+    files: &[PathBuf],
+    skeleton: bool,
+    connections: &[ResolvedConnection],
+    order: ContentOrder,
+    format: OutputFormat,
+    max_file_bytes: u64,
+) -> Vec<(PathBuf, String)> {
+    let mut sorted_files = files.to_vec();
+    match order {
+        ContentOrder::Alphabetical => sorted_files.sort_by(|a, b| compare_paths_naturally(a, b)),
+        ContentOrder::DependenciesFirst => sorted_files = dependency_order(&sorted_files, connections),
+        ContentOrder::Importance => {
+            let importance = file_importance(connections, false);
+            sorted_files.sort_by(|a, b| by_importance(&importance, a, b));
+        }
+    }
+
+    sorted_files
+        .into_iter()
+        .map(|file_path| {
+            let mut block = String::new();
+            match (format, skeleton) {
+                (OutputFormat::XmlTags, _) => push_file_xml_block(&mut block, root_path, &file_path, skeleton, max_file_bytes),
+                (OutputFormat::Markdown, true) => push_file_skeleton_block(&mut block, root_path, &file_path, max_file_bytes),
+                (OutputFormat::Markdown, false) => push_file_content_block(&mut block, root_path, &file_path, max_file_bytes),
+            }
+            (file_path, block)
+        })
+        .collect()
+}
+
+// --- Formato XML: `<file path="...">` con el texto tal cual ---
+// Sin números de línea ni vallas; el contenido no se escapa (los consumidores esperan el
+// texto original) y solo el atributo `path` sí.
+
+fn escape_xml_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn xml_file_block(path: &str, content: &str) -> String {
+    format!("<file path=\"{}\">\n{}\n</file>\n", escape_xml_attribute(path), content.trim_end_matches('\n'))
+}
+
+fn push_file_xml_block(section: &mut String, root_path: &Path, file_path: &Path, skeleton: bool, max_file_bytes: u64) {
+    let relative = file_path.strip_prefix(root_path).unwrap_or(file_path).display().to_string();
+    let skeleton_lines = if skeleton { analysis::file_skeleton(file_path, max_file_bytes) } else { None };
+    let content = match skeleton_lines {
+        Some(lines) => lines.into_iter().map(|(_, line)| line).collect::<Vec<_>>().join("\n"),
+        None => analysis::read_text_file(file_path, max_file_bytes).unwrap_or_else(|reason| skipped_note(&reason)),
+    };
+    section.push_str(&xml_file_block(&relative, &content));
+}
+
+// Como `push_file_content_block`, pero con los cuerpos de funciones elididos
+fn push_file_skeleton_block(section: &mut String, root_path: &Path, file_path: &Path, max_file_bytes: u64) {
+    let relative_path_display = file_path.strip_prefix(root_path).unwrap_or(file_path).display();
+    section.push_str(&format!("### `{}`\n\n", relative_path_display));
+    push_skeleton_content(section, file_path, max_file_bytes);
+    section.push('\n');
+}
+
+// Esqueleto numerado con las líneas del archivo original; los archivos que no son JS/TS
+// (CSS, JSON, Markdown...) no tienen cuerpos que elidir y van completos
+fn push_skeleton_content(section: &mut String, file_path: &Path, max_file_bytes: u64) {
+    let Some(lines) = analysis::file_skeleton(file_path, max_file_bytes) else {
+        push_numbered_content(section, file_path, max_file_bytes);
+        return;
+    };
+    section.push_str("```");
+    if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
+        section.push_str(ext);
+    }
+    section.push('\n');
+    let width = lines.last().map_or(1, |(line_number, _)| line_number.to_string().len());
+    for (line_number, line) in &lines {
+        section.push_str(&format!("{:<width$} | {}\n", line_number, line, width = width));
+    }
+    section.push_str("```\n");
+}
+
+// Bloque "### `ruta`" con el contenido numerado del archivo
+pub fn push_file_content_block(section: &mut String, root_path: &Path, file_path: &Path, max_file_bytes: u64) {
+    let relative_path_display = match file_path.strip_prefix(root_path) {
+        Ok(relative_path) => relative_path.display().to_string(),
+        Err(_) => file_path.display().to_string(), // Use full path if strip fails
+    };
+
+    section.push_str(&format!("### `{}`\n\n", relative_path_display));
+    push_numbered_content(section, file_path, max_file_bytes);
+    section.push('\n');
+}
+
+// Bloque cercado con el contenido numerado del archivo (sin cabecera)
+// Nota en lugar del contenido de un archivo que no se vuelca (enorme, binario, ...)
+fn skipped_note(reason: &analysis::SkipReason) -> String {
+    format!("[Omitido: {}]", reason.describe())
+}
+
+fn push_numbered_content(section: &mut String, file_path: &Path, max_file_bytes: u64) {
+    section.push_str("```");
+    if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
+        section.push_str(ext);
+    }
+    section.push('\n');
+
+    match analysis::read_text_file(file_path, max_file_bytes) {
+        Ok(content) => {
+            let lines: Vec<&str> = content.lines().collect();
+            let num_lines = lines.len();
+            // Calculate padding width based on the largest line number
+            let width = if num_lines == 0 { 1 } else { num_lines.to_string().len() };
+
+            for (i, line) in lines.iter().enumerate() {
+                let line_number = i + 1;
+                section.push_str(&format!("{:<width$} | {}\n", line_number, line, width = width)); // Use left alignment for line numbers
+            }
+             // Handle trailing newline correctly after loop
+             if content.ends_with('\n') && !content.is_empty() {
+                // If content ends with newline AND is not empty, the loop added the last line's \n. We are good.
+             } else if content.is_empty() {
+                // Empty file, do nothing extra.
+             } else if !content.ends_with('\n') && !lines.is_empty() {
+                 // Content does not end with newline, but we added one for the last line. Remove it.
+                 if section.ends_with('\n') { section.pop(); }
+             }
+        }
+        Err(reason) => section.push_str(&skipped_note(&reason)),
+    }
+
+    section.push_str("\n```\n"); // Ensure newline before closing backticks
+}
+
+/// Estructura y contenidos intercalados: cada archivo del árbol va seguido de su contenido;
+/// las carpetas quedan como cabeceras. Solo aparecen las carpetas con algún archivo incluido.
+pub fn generate_tree_with_contents_section(
+    root_path: &Path,
+    root_label: &str,
+    files: &[PathBuf],
+    skeleton: bool,
+    format: OutputFormat,
+    max_file_bytes: u64,
+) -> String {
+    let mut section = String::new();
+    section.push_str("## Project Structure with Contents\n\n");
+    section.push_str(root_label);
+    section.push('\n');
+
+    let included: HashSet<&PathBuf> = files.iter().collect();
+    for item in generate_tree_structure_items(root_path, files, None) {
+        let ReportItem::Line(spans) = item else { continue };
+        let Some(path) = spans.iter().find_map(|span| match span {
+            ReportSpan::FileLink { path, .. } => Some(path.clone()),
+            _ => None,
+        }) else {
+            continue;
+        };
+        section.push_str(&spans_to_string(&spans));
+        section.push('\n');
+        if included.contains(&path) {
+            match (format, skeleton) {
+                (OutputFormat::XmlTags, _) => push_file_xml_block(&mut section, root_path, &path, skeleton, max_file_bytes),
+                (OutputFormat::Markdown, true) => push_skeleton_content(&mut section, &path, max_file_bytes),
+                (OutputFormat::Markdown, false) => push_numbered_content(&mut section, &path, max_file_bytes),
+            }
+        }
+    }
+    section
+}
+
+// --- Documento por archivo (corpus para embeddings/RAG) ---
+
+/// Documento markdown autocontenido de un archivo: metadatos, imports, definiciones con
+/// firma y, opcionalmente, el contenido numerado.
+pub fn generate_file_document(
+    root_path: &Path,
+    file: &Path,
+    connections: &[&ResolvedConnection],
+    definitions: &[&DetectedDefinition],
+    include_content: bool,
+    max_file_bytes: u64,
+) -> String {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let mut document = format!("# `{}`\n\n", relative(file));
+    document.push_str(&format!("- Language: {}\n", language_for_extension(ext)));
+    if let Ok(metadata) = fs::metadata(file) {
+        document.push_str(&format!("- Size: {} bytes\n", metadata.len()));
+    }
+    document.push('\n');
+
+    document.push_str("## Imports\n\n");
+    if connections.is_empty() {
+        document.push_str("_None._\n");
+    }
+    for conn in connections {
+        match &conn.resolved_target {
+            Some(target) => document.push_str(&format!("- `{}` -> `{}`\n", conn.imported_string, relative(target))),
+            None => document.push_str(&format!("- `{}` (external or unresolved)\n", conn.imported_string)),
+        }
+    }
+    document.push('\n');
+
+    document.push_str("## Definitions\n\n");
+    if definitions.is_empty() {
+        document.push_str("_None._\n\n");
+    } else {
+        document.push_str("```\n");
+        for def in definitions {
+            let line = format!("L{} {} {}", def.line_number, def.kind, definition_label(def));
+            document.push_str(line.trim_end());
+            document.push('\n');
+        }
+        document.push_str("```\n\n");
+    }
+
+    if include_content {
+        document.push_str("## Content\n\n");
+        push_file_content_block(&mut document, root_path, file, max_file_bytes);
+    }
+    document
+}
+
+// --- Paquete de revisión de código (diff + dependientes + tests) ---
+
+// Heurística de archivos de test: *.test.*, *.spec.* o dentro de __tests__/
+pub fn is_test_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.contains(".test.")
+        || name.contains(".spec.")
+        || path.components().any(|c| c.as_os_str() == "__tests__")
+}
+
+// Nombre base sin extensión ni sufijo .test/.spec (`Button.test.tsx` -> `Button`)
+fn test_subject_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    name.split('.').next().filter(|stem| !stem.is_empty())
+}
+
+/// Tests asociados a `file`: los que lo importan directamente o comparten su nombre base.
+pub fn tests_for_file(file: &Path, files: &[PathBuf], connections: &[ResolvedConnection]) -> Vec<PathBuf> {
+    let mut tests: HashSet<PathBuf> = connections
+        .iter()
+        .filter(|c| c.resolved_target.as_deref() == Some(file) && is_test_file(&c.source_file))
+        .map(|c| c.source_file.clone())
+        .collect();
+    if let Some(stem) = test_subject_stem(file) {
+        tests.extend(
+            files
+                .iter()
+                .filter(|f| f.as_path() != file && is_test_file(f) && test_subject_stem(f) == Some(stem))
+                .cloned(),
+        );
+    }
+    let mut tests: Vec<PathBuf> = tests.into_iter().collect();
+    tests.sort_by(|a, b| compare_paths_naturally(a, b));
+    tests
+}
+
+/// Ensambla en un único markdown: archivos cambiados con su diff, su contenido actual,
+/// los dependientes directos (solo firmas) y los tests asociados.
+pub fn generate_review_packet(
+    root_path: &Path,
+    files: &[PathBuf],
+    connections: &[ResolvedConnection],
+    definitions: &[DetectedDefinition],
+    git: &dyn GitSource,
+    base_ref: &str,
+    max_file_bytes: u64,
+) -> Result<String, String> {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+
+    let mut changed = git.changed_files(base_ref)?;
+    changed.sort_by(|a, b| compare_paths_naturally(a, b));
+    if changed.is_empty() {
+        return Err(format!("No hay cambios respecto a '{}'", base_ref));
+    }
+    let changed_set: HashSet<&PathBuf> = changed.iter().collect();
+
+    let mut body = String::new();
+
+    // 1. Archivos cambiados y sus diffs
+    body.push_str(&format!("## Changed Files ({})\n\n", changed.len()));
+    for file in &changed {
+        let status = if file.exists() { "" } else { " (eliminado)" };
+        body.push_str(&format!("- `{}`{}\n", relative(file), status));
+    }
+    body.push('\n');
+    for file in &changed {
+        let diff = git.file_diff(base_ref, file)?;
+        body.push_str(&format!("### Diff: `{}`\n\n```diff\n{}", relative(file), diff));
+        if !diff.ends_with('\n') {
+            body.push('\n');
+        }
+        body.push_str("```\n\n");
+    }
+
+    // 2. Contenido actual de los archivos cambiados que siguen existiendo
+    body.push_str("## Changed File Contents\n\n");
+    for file in changed.iter().filter(|f| f.exists()) {
+        push_file_content_block(&mut body, root_path, file, max_file_bytes);
+    }
+
+    // 3. Dependientes directos (un salto) que no forman parte del cambio
+    let mut dependents: Vec<&PathBuf> = connections
+        .iter()
+        .filter(|c| c.resolved_target.as_ref().is_some_and(|t| changed_set.contains(t)))
+        .map(|c| &c.source_file)
+        .filter(|source| !changed_set.contains(source) && !is_test_file(source))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    dependents.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    body.push_str(&format!("## Impacted Dependents ({})\n\n", dependents.len()));
+    if dependents.is_empty() {
+        body.push_str("_No direct dependents._\n\n");
+    }
+    for dependent in dependents {
+        body.push_str(&format!("### `{}`\n", relative(dependent)));
+        let mut defs: Vec<&DetectedDefinition> = definitions.iter().filter(|d| &d.source_file == dependent).collect();
+        defs.sort_by_key(|d| d.line_number);
+        if defs.is_empty() {
+            body.push_str("_No definitions detected._\n\n");
+            continue;
+        }
+        body.push_str("```\n");
+        for def in defs {
+            let line = format!("{} {}", def.kind, definition_label(def));
+            body.push_str(line.trim_end());
+            body.push('\n');
+        }
+        body.push_str("```\n\n");
+    }
+
+    // 4. Tests asociados a los archivos cambiados
+    let mut tests: Vec<PathBuf> = changed
+        .iter()
+        .flat_map(|file| tests_for_file(file, files, connections))
+        .filter(|t| !changed_set.contains(t))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    tests.sort_by(|a, b| compare_paths_naturally(a, b));
+
+    body.push_str(&format!("## Related Tests ({})\n\n", tests.len()));
+    if tests.is_empty() {
+        body.push_str("_No associated tests found._\n\n");
+    }
+    for test in &tests {
+        push_file_content_block(&mut body, root_path, test, max_file_bytes);
+    }
+
+    let mut packet = format!("# Code Review Packet (base: `{}`)\n\n", base_ref);
+    packet.push_str(&format!("_Estimated tokens: ~{}_\n\n", tokens::estimate_tokens(body.chars().count())));
+    packet.push_str(&body);
+    Ok(packet)
+}
+
+// --- Contexto mínimo de una definición (para issues / preguntas puntuales) ---
+
+/// Fuente de la definición (por su span), los imports de su archivo que el cuerpo usa y las
+/// firmas de las definiciones locales referenciadas (mismo archivo o archivos importados).
+pub fn generate_minimal_context(
+    root_path: &Path,
+    def: &DetectedDefinition,
+    definitions: &[DetectedDefinition],
+    connections: &[ResolvedConnection],
+) -> Result<String, String> {
+    let relative = |p: &Path| p.strip_prefix(root_path).unwrap_or(p).display().to_string();
+    let content = fs::read_to_string(&def.source_file)
+        .map_err(|e| format!("No se pudo leer {}: {}", def.source_file.display(), e))?;
+
+    let start = def.line_number.max(1);
+    let end = def.end_line_number.max(start);
+    let snippet: Vec<&str> = content.lines().skip(start - 1).take(end - start + 1).collect();
+    let references = analysis::span_references(&def.source_file, start, end).unwrap_or_default();
+
+    let mut packet = format!("# Contexto mínimo: `{}` ({})\n\n", def.symbol_name, def.kind);
+    packet.push_str(&format!("_{}:L{}-L{}_\n\n", relative(&def.source_file), start, end));
+
+    // 1. Fuente de la definición
+    packet.push_str("## Source\n\n```");
+    if let Some(ext) = def.source_file.extension().and_then(|e| e.to_str()) {
+        packet.push_str(ext);
+    }
+    packet.push('\n');
+    for line in &snippet {
+        packet.push_str(line);
+        packet.push('\n');
+    }
+    packet.push_str("```\n\n");
+
+    // 2. Imports del archivo cuyos bindings aparecen en el cuerpo (sin repetir sentencias)
+    let mut used_imports: Vec<&str> = Vec::new();
+    for binding in &references.imports {
+        if references.identifiers.contains(&binding.local_name) && !used_imports.contains(&binding.statement.as_str()) {
+            used_imports.push(&binding.statement);
+        }
+    }
+    packet.push_str("## Imports used\n\n");
+    if used_imports.is_empty() {
+        packet.push_str("_None._\n\n");
+    } else {
+        packet.push_str("```\n");
+        for statement in used_imports {
+            packet.push_str(statement);
+            packet.push('\n');
+        }
+        packet.push_str("```\n\n");
+    }
+
+    // 3. Definiciones referenciadas: del mismo archivo o de archivos que este importa
+    let imported_files: HashSet<&PathBuf> = connections
+        .iter()
+        .filter(|c| c.source_file == def.source_file)
+        .filter_map(|c| c.resolved_target.as_ref())
+        .collect();
+    let mut referenced: Vec<&DetectedDefinition> = definitions
+        .iter()
+        .filter(|d| references.identifiers.contains(&d.symbol_name))
+        .filter(|d| d.source_file == def.source_file || imported_files.contains(&d.source_file))
+        .filter(|d| !(d.source_file == def.source_file && d.line_number == def.line_number))
+        .collect();
+    referenced.sort_by(|a, b| {
+        compare_paths_naturally(&a.source_file, &b.source_file).then_with(|| a.line_number.cmp(&b.line_number))
+    });
+    referenced.dedup_by(|a, b| a.source_file == b.source_file && a.line_number == b.line_number && a.symbol_name == b.symbol_name);
+
+    packet.push_str("## Referenced definitions\n\n");
+    if referenced.is_empty() {
+        packet.push_str("_None._\n");
+    } else {
+        packet.push_str("```\n");
+        for d in referenced {
+            let line = format!("{} {}  {}", d.kind, d.symbol_name, d.signature);
+            packet.push_str(&format!("{}  // {}:L{}\n", line.trim_end(), relative(&d.source_file), d.line_number));
+        }
+        packet.push_str("```\n");
+    }
+
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{connection, definition, TempTree};
+
+    // Texto de los elementos tal como se copian al portapapeles
+    fn render(items: &[ReportItem]) -> String {
+        items.iter()
+            .map(|item| match item {
+                ReportItem::PlainText(text) => text.clone(),
+                ReportItem::FilePath { display, .. } | ReportItem::Directory { display, .. } => display.clone(),
+                ReportItem::DefinitionLink { display, .. } => display.clone(),
+                ReportItem::Line(spans) => spans_to_string(spans),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // --- Elisión de rutas ---
+
+    #[test]
+    fn elision_keeps_the_head_and_as_many_trailing_segments_as_fit() {
+        let path = "src/features/dashboard/components/Button.tsx";
+        assert_eq!(elide_path_middle(path, 60), path);
+        assert_eq!(elide_path_middle(path, 30), "src/…/components/Button.tsx");
+        assert_eq!(elide_path_middle(path, 40), "src/…/dashboard/components/Button.tsx");
+    }
+
+    #[test]
+    fn elision_never_drops_the_first_or_last_segment() {
+        assert_eq!(elide_path_middle("src/features/dashboard/Button.tsx", 5), "src/…/Button.tsx");
+        // Con dos segmentos no hay nada que elidir, aunque no quepa
+        assert_eq!(elide_path_middle("very-long-folder-name/very-long-file-name.ts", 10), "very-long-folder-name/very-long-file-name.ts");
+        // Si elidir un único segmento no acorta la ruta, se deja igual
+        assert_eq!(elide_path_middle("a/b/c.ts", 7), "a/b/c.ts");
+    }
+
+    #[test]
+    fn elision_counts_characters_not_bytes() {
+        let path = "ñ/ááááá/éééé/índice.ts"; // 22 caracteres, 29 bytes
+        assert_eq!(elide_path_middle(path, 22), path);
+        assert_eq!(elide_path_middle(path, 21), "ñ/…/éééé/índice.ts");
+        assert_eq!(elide_path_middle("日本/語の/長い/パス/ファイル.ts", 12), "日本/…/ファイル.ts");
+    }
+
+    // --- Resumen por carpeta ---
+
+    fn package_import(source: &Path, specifier: &str) -> ResolvedConnection {
+        let mut conn = connection(source, source);
+        conn.imported_string = specifier.to_string();
+        conn.resolved_target = None;
+        conn
+    }
+
+    #[test]
+    fn directory_rollups_aggregate_per_top_level_folder() {
+        let root = PathBuf::from("/proyecto");
+        let button = root.join("ui/Button.tsx");
+        let theme = root.join("ui/theme.ts");
+        let styles = root.join("ui/styles.css");
+        let api = root.join("api/client.ts");
+        let main = root.join("main.ts"); // En la raíz: no pertenece a ninguna carpeta
+        let files = vec![button.clone(), theme.clone(), styles.clone(), api.clone(), main.clone()];
+        let connections = vec![
+            connection(&api, &button),
+            connection(&main, &button),
+            connection(&button, &theme),
+            connection(&api, &theme),
+            package_import(&api, "axios"),
+            package_import(&api, "axios/retry"),
+            package_import(&button, "@emotion/react"),
+        ];
+        let definitions = vec![
+            definition(&button, "Button", "Component", 3, true),
+            definition(&button, "useRipple", "Hook", 9, false), // Interno: no cuenta
+            definition(&theme, "theme", "Const", 1, true),
+            definition(&api, "request", "Function", 4, true),
+        ];
+
+        let summaries = summarize_directories(&root, &files, &connections, &definitions);
+        let dirs: Vec<&Path> = summaries.iter().map(|s| s.directory.as_path()).collect();
+        assert_eq!(dirs, [root.join("api"), root.join("ui")]);
+
+        let (api_summary, ui_summary) = (&summaries[0], &summaries[1]);
+        assert_eq!(ui_summary.file_count, 3);
+        assert_eq!(ui_summary.dominant_language, Some(("TypeScript", 2)));
+        assert_eq!(ui_summary.top_symbols, [("Button".to_string(), 2), ("theme".to_string(), 2)]);
+        assert_eq!(ui_summary.external_packages, [("@emotion/react".to_string(), 1)]);
+        assert!(ui_summary.depends_on.is_empty());
+
+        assert_eq!(api_summary.top_symbols, [("request".to_string(), 0)]);
+        assert_eq!(api_summary.external_packages, [("axios".to_string(), 2)]);
+        assert_eq!(api_summary.depends_on, [root.join("ui")]);
+    }
+
+    #[test]
+    fn directory_rollups_skip_unexported_symbols_entirely() {
+        let root = PathBuf::from("/proyecto");
+        let helper = root.join("lib/helper.rs");
+        let definitions = vec![definition(&helper, "internal", "Function", 1, false)];
+        let summaries = summarize_directories(&root, std::slice::from_ref(&helper), &[], &definitions);
+        assert_eq!(summaries.len(), 1);
+        assert!(summaries[0].top_symbols.is_empty());
+        assert_eq!(summaries[0].dominant_language, Some(("Rust", 1)));
+    }
+
+    // --- Paquete de revisión ---
+
+    struct FakeGit {
+        changed: Vec<PathBuf>,
+    }
+
+    impl GitSource for FakeGit {
+        fn changed_files(&self, _base_ref: &str) -> Result<Vec<PathBuf>, String> {
+            Ok(self.changed.clone())
+        }
+
+        fn file_diff(&self, base_ref: &str, file: &Path) -> Result<String, String> {
+            Ok(format!("--- {} ({})\n+cambio", file.file_name().unwrap().to_string_lossy(), base_ref))
+        }
+    }
+
+    #[test]
+    fn review_packet_combines_diffs_dependents_and_tests() {
+        let tree = TempTree::new();
+        let button = tree.file("src/Button.tsx", "export function Button() {}\n");
+        let form = tree.file("src/Form.tsx", "import { Button } from './Button';\n");
+        let test = tree.file("src/Button.test.tsx", "import { Button } from './Button';\n");
+        let unrelated = tree.file("src/Other.ts", "export const other = 1;\n");
+        let files = vec![button.clone(), form.clone(), test.clone(), unrelated.clone()];
+        let connections = vec![connection(&form, &button), connection(&test, &button)];
+        let definitions = vec![definition(&form, "Form", "Function", 3, true)];
+        let git = FakeGit { changed: vec![button.clone()] };
+
+        let packet = generate_review_packet(&tree.root, &files, &connections, &definitions, &git, "main", 1 << 20).unwrap();
+
+        assert!(packet.starts_with("# Code Review Packet (base: `main`)"));
+        assert!(packet.contains("_Estimated tokens: ~"));
+        assert!(packet.contains("## Changed Files (1)\n\n- `src/Button.tsx`\n"));
+        assert!(packet.contains("### Diff: `src/Button.tsx`\n\n```diff\n--- Button.tsx (main)\n+cambio\n```"));
+        assert!(packet.contains("export function Button() {}"));
+        // El test no cuenta como dependiente; el archivo sin relación no aparece
+        assert!(packet.contains("## Impacted Dependents (1)\n\n### `src/Form.tsx`\n```\nFunction Form()\n```"));
+        assert!(packet.contains("## Related Tests (1)\n\n### `src/Button.test.tsx`"));
+        assert!(!packet.contains("Other.ts"));
+
+        let order: Vec<usize> = ["## Changed Files", "## Changed File Contents", "## Impacted Dependents", "## Related Tests"]
+            .iter()
+            .map(|heading| packet.find(heading).unwrap())
+            .collect();
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn review_packet_marks_deleted_files_and_fails_without_changes() {
+        let tree = TempTree::new();
+        let deleted = tree.path("src/gone.ts");
+        let git = FakeGit { changed: vec![deleted] };
+        let packet = generate_review_packet(&tree.root, &[], &[], &[], &git, "HEAD", 1 << 20).unwrap();
+        assert!(packet.contains("- `src/gone.ts` (eliminado)"));
+        assert!(packet.contains("_No direct dependents._"));
+        assert!(packet.contains("_No associated tests found._"));
+
+        let git = FakeGit { changed: Vec::new() };
+        let error = generate_review_packet(&tree.root, &[], &[], &[], &git, "HEAD", 1 << 20).unwrap_err();
+        assert!(error.contains("No hay cambios respecto a 'HEAD'"));
+    }
+
+    // --- Conexiones ---
+
+    #[test]
+    fn ambient_module_imports_link_to_their_declaration() {
+        let root = PathBuf::from("/proyecto");
+        let main = root.join("src/main.ts");
+        let mut conn = package_import(&main, "@legacy/forms");
+        conn.import_class = ImportClass::External;
+        conn.ambient_module = Some(analysis::AmbientModule {
+            source_file: root.join("types/shims.d.ts"),
+            pattern: "@legacy/*".to_string(),
+            line_number: 2,
+        });
+        let text = render(&generate_connections_section(&root, &[conn], &PackageVersions::default(), false, false));
+        assert!(text.contains("└── @legacy/forms:L1 (declared ambient module (sin archivo físico)) declare module '@legacy/*' @ types/shims.d.ts:L2"));
+    }
+
+    #[test]
+    fn json_module_imports_are_tagged_and_fenced_as_json() {
+        let tree = TempTree::new();
+        let main = tree.file("src/main.ts", "import config from './config.json' with { type: 'json' };\n");
+        let config = tree.file("src/config.json", "{ \"debug\": true }\n");
+        let mut conn = connection(&main, &config);
+        conn.imported_string = "./config.json".to_string();
+        conn.import_attribute = Some("json".to_string());
+        let text = render(&generate_connections_section(&tree.root, &[conn], &PackageVersions::default(), false, false));
+        assert!(text.contains("└── ./config.json:L1 (json) -> src/config.json"));
+
+        let mut section = String::new();
+        push_file_content_block(&mut section, &tree.root, &config, 1 << 20);
+        assert_eq!(section, "### `src/config.json`\n\n```json\n1 | { \"debug\": true }\n\n```\n\n");
+    }
+
+    // --- Duplicaciones de módulo ---
+
+    #[test]
+    fn duplicates_section_lists_only_the_top_groups() {
+        let root = PathBuf::from("/proyecto");
+        let groups: Vec<DuplicateGroup> = (0..duplicates::MAX_REPORTED_GROUPS + 2)
+            .map(|i| DuplicateGroup {
+                dir_a: root.join(format!("a{}", i)),
+                dir_b: root.join(format!("b{}", i)),
+                similarity: 0.9,
+                pairs: vec![duplicates::DuplicatePair {
+                    file_a: root.join(format!("a{}/x.ts", i)),
+                    file_b: root.join(format!("b{}/x.ts", i)),
+                    identical: true,
+                }],
+            })
+            .collect();
+        let text = render(&generate_duplicates_section(&root, &groups));
+        assert!(text.contains("### `a0/`\n    ≈ `b0/` (90% similar)\n  = a0/x.ts\n    ↔ b0/x.ts\n"));
+        assert!(text.contains(&format!("a{}/", duplicates::MAX_REPORTED_GROUPS - 1)));
+        assert!(!text.contains(&format!("a{}/", duplicates::MAX_REPORTED_GROUPS)));
+        assert!(text.contains("_2 grupos más con menor similitud._"));
+    }
+
+    // --- Pipeline de tareas ---
+
+    #[test]
+    fn task_pipeline_states_package_edges_and_misconfigurations() {
+        let tree = crate::test_support::two_package_monorepo();
+        let analysis = crate::test_support::analyze(&tree);
+        let info = &analysis.monorepo;
+        let (button, main) = (tree.path("packages/ui/src/Button.ts"), tree.path("packages/web/src/main.ts"));
+        // `ui` importa `web` sin declararlo; `web` importa `ui` (declarado)
+        let connections: Vec<ResolvedConnection> = analysis.connections.iter().cloned().chain([connection(&button, &main)]).collect();
+
+        let text = render(&generate_task_pipeline_section(&tree.root, info, &connections));
+        assert!(text.contains("### Turborepo: turbo.json\n```\n├── build (dependsOn: ^build)\n│   ├── @acme/ui (packages/ui/package.json)\n│   └── @acme/web (packages/web/package.json)\n"));
+        assert!(text.contains("### Nx: nx.json\n```\n└── e2e (dependsOn: ^build)\n    └── @acme/web (packages/web/package.json)\n"));
+        assert!(text.contains("@acme/web build depends on @acme/ui build\n"));
+        assert!(text.contains("@acme/web test depends on @acme/ui lint\n"));
+        assert!(text.contains("@acme/web e2e depends on @acme/ui build\n"));
+        assert!(text.contains("- @acme/ui imports @acme/web in code, but does not declare it as a dependency"));
+        assert!(!text.contains("- @acme/web declares @acme/ui as a dependency, but no code imports it"));
+        assert!(text.contains("- deploy depends on @acme/missing#build, which is not a workspace package"));
+    }
+
+    // --- Árbol con contenidos ---
+
+    #[test]
+    fn tree_with_contents_interleaves_included_files_under_their_tree_lines() {
+        let tree = TempTree::new();
+        let readme = tree.file("README.md", "# Demo\n");
+        let main = tree.file("src/main.ts", "import { a } from './lib/a';\n");
+        let a = tree.file("src/lib/a.ts", "export const a = 1;");
+        let a10 = tree.file("src/lib/a10.ts", "export const a10 = 10;\n");
+        let a2 = tree.file("src/lib/a2.ts", "export const a2 = 2;\n");
+        tree.file("src/lib/skip.ts", "excluido\n"); // Carpeta con hijos incluidos y excluidos
+        tree.file("docs/guide.md", "excluido\n"); // Carpeta sin archivos incluidos
+        // Orden de entrada arbitrario: el árbol usa orden natural (a, a2, a10)
+        let files = vec![main.clone(), a10, readme, a2, a];
+
+        let text = generate_tree_with_contents_section(&tree.root, "demo", &files, false, OutputFormat::Markdown, 1 << 20);
+        assert_eq!(text, concat!(
+            "## Project Structure with Contents\n\n",
+            "demo\n",
+            "├── README.md\n",
+            "```md\n1 | # Demo\n\n```\n",
+            "├── src/\n",
+            "│   ├── lib/\n",
+            "│   │   └── a.ts\n",
+            "```ts\n1 | export const a = 1;\n```\n",
+            "│   │   └── a2.ts\n",
+            "```ts\n1 | export const a2 = 2;\n\n```\n",
+            "│   │   └── a10.ts\n",
+            "```ts\n1 | export const a10 = 10;\n\n```\n",
+            "│   └── main.ts\n",
+            "```ts\n1 | import { a } from './lib/a';\n\n```\n",
+        ));
+
+        let xml = generate_tree_with_contents_section(&tree.root, "demo", &[main], false, OutputFormat::XmlTags, 1 << 20);
+        assert_eq!(xml, concat!(
+            "## Project Structure with Contents\n\n",
+            "demo\n",
+            "├── src/\n",
+            "│   └── main.ts\n",
+            "<file path=\"src/main.ts\">\nimport { a } from './lib/a';\n</file>\n",
+        ));
+    }
+
+    #[test]
+    fn glob_imports_show_counts_and_count_as_inverse_usage() {
+        let tree = crate::test_support::glob_import_tree();
+        let analysis = crate::test_support::analyze(&tree);
+        let collapsed = render(&generate_connections_section(&tree.root, &analysis.connections, &analysis.package_versions, false, false));
+        assert!(collapsed.contains("./pages/**/*.tsx:L3 [glob] (glob: import.meta.glob -> 3 archivos)"), "{}", collapsed);
+        assert!(collapsed.contains("./modules:L1 [glob] (glob: require.context, recursivo, /\\.js$/i -> 4 archivos)"), "{}", collapsed);
+        assert!(collapsed.contains("./modules:L2 [glob] (glob: require.context, /\\.js$/ -> 1 archivo"), "{}", collapsed);
+        assert!(!collapsed.contains("src/pages/index.tsx"));
+        let expanded = render(&generate_connections_section(&tree.root, &analysis.connections, &analysis.package_versions, true, false));
+        assert!(expanded.contains("* src/pages/index.tsx"));
+
+        let inverse = render(&generate_inverse_usage_section(&tree.root, &analysis.connections, false, false));
+        assert!(inverse.contains("├── src/modules/nested/deep/c.js\n│   └── src/main.js:L1\n"));
+        assert!(inverse.contains("├── src/pages/about.vue\n│   └── src/main.js:L4\n"));
+        // Varias expansiones del mismo archivo cuentan una vez, con su primera línea
+        assert!(inverse.contains("├── src/modules/a.js\n│   └── src/main.js:L1\n"));
+    }
+
+    // --- Archivos huérfanos ---
+
+    #[test]
+    fn files_under_pages_or_app_folders_are_entry_points() {
+        let tree = TempTree::new();
+        let files: Vec<PathBuf> = [
+            "src/pages/about.tsx", "src/pages/blog/[slug].tsx", "app/dashboard/page.tsx", "src/app/layout.tsx",
+            // Nombres parecidos que no son carpetas de rutas
+            "src/pages.ts", "src/app.ts", "src/apps/admin.ts", "src/components/pagesHeader.tsx", "src/mypages/list.ts",
+        ]
+        .into_iter()
+        .map(|file| tree.file(file, "export const x = 1;\n"))
+        .collect();
+        let orphans = render(&generate_orphans_section(&tree.root, &files, &[], &EntryPointRules::default()));
+        assert_eq!(orphans, concat!(
+            "## Orphan Files (Nobody Imports Them)\n\n\n```\nNadie los importa (5):\n",
+            "├── src/app.ts\n├── src/apps/admin.ts\n├── src/components/pagesHeader.tsx\n├── src/mypages/list.ts\n└── src/pages.ts\n```\n",
+        ));
+
+        // Sin la regla de rutas todos son candidatos
+        let without_routes = generate_orphans_section(&tree.root, &files, &[], &EntryPointRules::default().routes(false));
+        assert!(render(&without_routes).contains("Nadie los importa (9):"));
+    }
+
+    #[test]
+    fn route_folders_count_only_below_the_project_root() {
+        // Un proyecto que vive en una carpeta `app/` no convierte todos sus archivos en rutas
+        let tree = TempTree::new();
+        let root = tree.root.join("app");
+        let helper = tree.file("app/src/helpers.ts", "");
+        let page = tree.file("app/src/pages/home.tsx", "");
+        let rules = EntryPointRules::default();
+        assert!(!rules.is_entry_point(&root, &helper));
+        assert!(rules.is_entry_point(&root, &page));
+        // La propia carpeta del archivo también cuenta: `pages/index` y `pages/_app` son rutas
+        assert!(rules.index_files(false).is_entry_point(&root, &tree.path("app/pages/index.tsx")));
+    }
+
+    // --- Orden de los archivos ---
+
+    fn names(root: &Path, files: &[PathBuf]) -> Vec<String> {
+        files.iter().map(|f| f.strip_prefix(root).unwrap().display().to_string()).collect()
+    }
+
+    #[test]
+    fn dependency_order_puts_a_chain_backwards() {
+        let root = Path::new("/project");
+        let [a, b, c] = ["a.ts", "b.ts", "c.ts"].map(|f| root.join(f));
+        let connections = vec![connection(&a, &b), connection(&b, &c)];
+        assert_eq!(names(root, &dependency_order(&[a.clone(), b.clone(), c.clone()], &connections)), ["c.ts", "b.ts", "a.ts"]);
+        // Las conexiones hacia archivos fuera de la lista y los autoimports no cuentan
+        let outside = root.join("z.ts");
+        let connections = vec![connection(&a, &outside), connection(&a, &a), connection(&c, &a)];
+        assert_eq!(names(root, &dependency_order(&[c, b, a], &connections)), ["a.ts", "b.ts", "c.ts"]);
+    }
+
+    #[test]
+    fn dependency_order_keeps_a_cycle_together_after_its_dependencies() {
+        let root = Path::new("/project");
+        let [f1, f2, f10, z] = ["f1.ts", "f2.ts", "f10.ts", "z.ts"].map(|f| root.join(f));
+        let connections = vec![connection(&f1, &f10), connection(&f10, &f1), connection(&f10, &z)];
+        let files = [f10.clone(), z.clone(), f2.clone(), f1.clone(), f1.clone()];
+        // `f2` y `z` no dependen de nada; el ciclo sale entero, en orden natural, detrás de `z`
+        assert_eq!(names(root, &dependency_order(&files, &connections)), ["f2.ts", "z.ts", "f1.ts", "f10.ts"]);
+    }
+
+    #[test]
+    fn dependency_order_sorts_unrelated_files_naturally() {
+        let root = Path::new("/project");
+        let files = ["src/file10.ts", "src/file2.ts", "lib/file1.ts", "src/file1.ts"].map(|f| root.join(f));
+        assert_eq!(names(root, &dependency_order(&files, &[])), ["lib/file1.ts", "src/file1.ts", "src/file2.ts", "src/file10.ts"]);
+    }
+
+    // `needles` en el orden en que aparecen por primera vez en `text`
+    fn appearance_order<'a>(text: &str, needles: &[&'a str]) -> Vec<&'a str> {
+        let mut found: Vec<(usize, &str)> = needles.iter().map(|n| (text.find(n).unwrap_or_else(|| panic!("falta {} en:\n{}", n, text)), *n)).collect();
+        found.sort();
+        found.into_iter().map(|(_, n)| n).collect()
+    }
+
+    #[test]
+    fn every_generator_lists_numbered_files_in_natural_order() {
+        let tree = TempTree::new();
+        tree.file("src/shared.ts", "export const s = 1;\n");
+        for n in [10, 2, 1] {
+            tree.file(&format!("src/file{}.ts", n), &format!("import {{ s }} from './shared';\nexport const v{} = s;\n", n));
+        }
+        tree.file("src/main.ts", "import { v10 } from './file10';\nimport { v2 } from './file2';\nimport { v1 } from './file1';\n");
+        let analysis = crate::test_support::analyze(&tree);
+        let files = ["file1.ts", "file2.ts", "file10.ts"];
+
+        let outputs = [
+            ("estructura", render(&generate_structure_section(&tree.root, "demo", &analysis.files, None))),
+            ("conexiones", render(&generate_connections_section(&tree.root, &analysis.connections, &analysis.package_versions, false, false))),
+            ("definiciones", render(&generate_definitions_section(&tree.root, &analysis.definitions, &analysis.connections, 80))),
+            ("uso inverso", render(&generate_inverse_usage_section(&tree.root, &analysis.connections, false, false))),
+            ("contenidos", generate_file_content_section(&tree.root, &analysis.files, false, &[], ContentOrder::Alphabetical, OutputFormat::Markdown, 1 << 20)),
+            ("dependencias", generate_file_content_section(&tree.root, &analysis.files, false, &analysis.connections, ContentOrder::DependenciesFirst, OutputFormat::Markdown, 1 << 20)),
+            ("árbol con contenidos", generate_tree_with_contents_section(&tree.root, "demo", &analysis.files, false, OutputFormat::Markdown, 1 << 20)),
+        ];
+        for (name, output) in &outputs {
+            assert_eq!(appearance_order(output, &files), files, "{}", name);
+        }
+        // Dentro de un archivo, los imports también van en orden natural de especificador
+        assert_eq!(appearance_order(&outputs[1].1, &["./file1:", "./file2:", "./file10:"]), ["./file1:", "./file2:", "./file10:"]);
+    }
+
+    // --- Líneas de los imports ---
+
+    #[test]
+    fn connections_and_inverse_usage_show_import_lines() {
+        let tree = crate::test_support::import_lines_tree();
+        let analysis = crate::test_support::analyze(&tree);
+        let connections = render(&generate_connections_section(&tree.root, &analysis.connections, &analysis.package_versions, false, false));
+        for line in ["./a:L3", "./b:L4", "./c:L8 [re-export]", "./d:L10", "./e:L12", "./f:L12"] {
+            assert!(connections.contains(line), "falta {} en:\n{}", line, connections);
+        }
+        let inverse = render(&generate_inverse_usage_section(&tree.root, &analysis.connections, false, false));
+        assert!(inverse.contains("├── src/b.ts\n│   └── src/main.ts:L4\n"), "{}", inverse);
+        assert!(inverse.contains("└── src/f.ts\n    └── src/main.ts:L12\n"), "{}", inverse);
+    }
+
+    // --- Grafo DOT ---
+
+    // Lo que interesa de un documento DOT válido; `parse_dot` falla ante cualquier otra cosa
+    type DotAttributes = Vec<(String, String)>;
+
+    #[derive(Default)]
+    struct DotGraph {
+        attributes: DotAttributes,
+        nodes: Vec<String>,
+        edges: Vec<(String, String, DotAttributes)>,
+        clusters: Vec<String>,
+    }
+
+    // (entre comillas, texto sin escapes)
+    fn dot_tokens(dot: &str) -> Vec<(bool, String)> {
+        let mut tokens = Vec::new();
+        let mut chars = dot.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {}
+                '"' => {
+                    let mut text = String::new();
+                    loop {
+                        match chars.next().expect("cadena sin cerrar") {
+                            '"' => break,
+                            '\\' => text.push(chars.next().expect("escape al final")),
+                            c => text.push(c),
+                        }
+                    }
+                    tokens.push((true, text));
+                }
+                '-' if chars.peek() == Some(&'>') => {
+                    chars.next();
+                    tokens.push((false, "->".to_string()));
+                }
+                '{' | '}' | '[' | ']' | ';' | '=' | ',' => tokens.push((false, c.to_string())),
+                c if c.is_ascii_alphanumeric() || c == '_' => {
+                    let mut word = c.to_string();
+                    while let Some(&next) = chars.peek().filter(|n| n.is_ascii_alphanumeric() || **n == '_') {
+                        word.push(next);
+                        chars.next();
+                    }
+                    tokens.push((false, word));
+                }
+                c => panic!("carácter inesperado fuera de comillas: {:?}", c),
+            }
+        }
+        tokens
+    }
+
+    fn parse_dot(dot: &str) -> DotGraph {
+        fn id(tokens: &mut std::slice::Iter<(bool, String)>) -> String {
+            let (quoted, text) = tokens.next().expect("falta un identificador");
+            assert!(*quoted || !matches!(text.as_str(), "{" | "}" | "[" | "]" | ";" | "=" | "," | "->"), "se esperaba un identificador: {}", text);
+            text.clone()
+        }
+        fn expect(tokens: &mut std::slice::Iter<(bool, String)>, punct: &str) {
+            assert_eq!(tokens.next().map(|(_, t)| t.as_str()), Some(punct));
+        }
+        fn attributes(tokens: &mut std::slice::Iter<(bool, String)>) -> DotAttributes {
+            let mut list = Vec::new();
+            loop {
+                if tokens.as_slice().first().is_some_and(|(q, t)| !q && t == "]") {
+                    tokens.next();
+                    return list;
+                }
+                let key = id(tokens);
+                expect(tokens, "=");
+                list.push((key, id(tokens)));
+                if tokens.as_slice().first().is_some_and(|(q, t)| !q && t == ",") {
+                    tokens.next();
+                }
+            }
+        }
+        fn statements(tokens: &mut std::slice::Iter<(bool, String)>, graph: &mut DotGraph) {
+            let is = |tokens: &std::slice::Iter<(bool, String)>, punct: &str| tokens.as_slice().first().is_some_and(|(q, t)| !q && t == punct);
+            loop {
+                if is(tokens, "}") {
+                    tokens.next();
+                    return;
+                }
+                let first = id(tokens);
+                if first == "subgraph" {
+                    graph.clusters.push(id(tokens));
+                    expect(tokens, "{");
+                    statements(tokens, graph);
+                    continue;
+                }
+                if is(tokens, "=") {
+                    tokens.next();
+                    graph.attributes.push((first, id(tokens)));
+                } else if is(tokens, "->") {
+                    tokens.next();
+                    let target = id(tokens);
+                    let attrs = if is(tokens, "[") { tokens.next(); attributes(tokens) } else { Vec::new() };
+                    graph.edges.push((first, target, attrs));
+                } else {
+                    if is(tokens, "[") {
+                        tokens.next();
+                        attributes(tokens);
+                    }
+                    if first != "node" {
+                        graph.nodes.push(first);
+                    }
+                }
+                expect(tokens, ";");
+            }
+        }
+        let tokens = dot_tokens(dot);
+        let mut tokens = tokens.iter();
+        expect(&mut tokens, "digraph");
+        id(&mut tokens);
+        expect(&mut tokens, "{");
+        let mut graph = DotGraph::default();
+        statements(&mut tokens, &mut graph);
+        assert!(tokens.next().is_none(), "texto después del grafo");
+        graph
+    }
+
+    #[test]
+    fn dot_output_escapes_quotes_and_backslashes() {
+        let root = Path::new("/project");
+        let quoted = root.join("src/say \"hi\".ts");
+        let backslash = root.join("src/back\\slash.ts");
+        let mut unresolved = connection(&quoted, &backslash);
+        unresolved.imported_string = "we\"ird\\pkg".to_string();
+        unresolved.resolved_target = None;
+        let connections = vec![connection(&quoted, &backslash), unresolved];
+
+        let dot = generate_dot_graph(root, &connections, &DotOptions::default().phantom_nodes(true));
+        assert!(dot.contains(r#""src/say \"hi\".ts" -> "src/back\\slash.ts""#), "{}", dot);
+        let graph = parse_dot(&dot);
+        assert_eq!(graph.nodes, ["src/back\\slash.ts", "src/say \"hi\".ts", "?we\"ird\\pkg"]);
+        let edges: Vec<(&str, &str)> = graph.edges.iter().map(|(s, t, _)| (s.as_str(), t.as_str())).collect();
+        assert_eq!(edges, [("src/say \"hi\".ts", "?we\"ird\\pkg"), ("src/say \"hi\".ts", "src/back\\slash.ts")]);
+        assert!(graph.edges[0].2.contains(&("style".to_string(), "dashed".to_string())));
+        assert!(graph.edges[1].2.contains(&("tooltip".to_string(), "./back\\slash".to_string())));
+
+        // Sin nodos fantasma el import sin resolver no aparece
+        let graph = parse_dot(&generate_dot_graph(root, &connections, &DotOptions::default()));
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn dot_direction_and_clusters_stay_valid() {
+        let root = Path::new("/project");
+        let main = root.join("main.ts");
+        let button = root.join("src/ui/Button.tsx");
+        let format = root.join("src/lib/format.ts");
+        let connections = vec![connection(&main, &button), connection(&button, &format)];
+
+        let left_right = parse_dot(&generate_dot_graph(root, &connections, &DotOptions::default()));
+        assert_eq!(left_right.attributes, [("rankdir".to_string(), "LR".to_string())]);
+        assert!(left_right.clusters.is_empty());
+
+        let options = DotOptions::default().direction(DotDirection::TopBottom).cluster_by_directory(true);
+        let clustered = parse_dot(&generate_dot_graph(root, &connections, &options));
+        assert_eq!(clustered.attributes, [
+            ("rankdir".to_string(), "TB".to_string()),
+            ("label".to_string(), "src/lib".to_string()),
+            ("label".to_string(), "src/ui".to_string()),
+        ]);
+        assert_eq!(clustered.clusters.len(), 2);
+        // Los archivos de la raíz quedan fuera de los clusters
+        assert_eq!(clustered.nodes, ["main.ts", "src/lib/format.ts", "src/ui/Button.tsx"]);
+        assert_eq!(clustered.edges.len(), 2);
+    }
+
+    // --- Referencias de proyecto ---
+
+    #[test]
+    fn project_references_flag_missing_references_between_packages() {
+        let tree = TempTree::new();
+        let core_config = tree.file("packages/core/tsconfig.json", "{ \"compilerOptions\": { \"composite\": true } }");
+        let app_config = tree.file("packages/app/tsconfig.json", "{ \"references\": [{ \"path\": \"../core\" }] }");
+        let web_config = tree.file("packages/web/tsconfig.json", "{}");
+        let core = tree.file("packages/core/index.ts", "");
+        let app = tree.file("packages/app/main.ts", "");
+        let web = tree.file("packages/web/main.ts", "");
+        let files: HashSet<PathBuf> = [core_config, app_config, web_config, core.clone(), app.clone(), web.clone()].into_iter().collect();
+        let projects = tsconfig::discover_ts_projects(&files);
+        // `app` importa `core` con referencia; `web` lo importa sin ella
+        let connections = vec![connection(&app, &core), connection(&web, &core), connection(&web, &web)];
+
+        let text = render(&generate_project_references_section(&tree.root, &projects, &connections));
+        assert!(text.contains("├── packages/app/tsconfig.json\n│   └── ../core -> packages/core/tsconfig.json\n"));
+        assert!(text.contains("├── packages/core/tsconfig.json (composite)\n"));
+        assert!(text.contains("### Missing References\n\n```\n└── packages/web/tsconfig.json -> packages/core/tsconfig.json (referencia faltante)\n    └── packages/web/main.ts: ./index"));
+        assert!(!text.contains("packages/app/tsconfig.json -> packages/core/tsconfig.json (referencia faltante)"));
+    }
+
+    #[test]
+    fn project_references_flag_non_composite_targets_and_cycles() {
+        let tree = TempTree::new();
+        let a = tree.file("a/tsconfig.json", "{ \"references\": [{ \"path\": \"../b\" }] }");
+        let b = tree.file("b/tsconfig.json", "{ \"references\": [{ \"path\": \"../a\" }] }");
+        let files: HashSet<PathBuf> = [a, b].into_iter().collect();
+        let projects = tsconfig::discover_ts_projects(&files);
+        let text = render(&generate_project_references_section(&tree.root, &projects, &[]));
+        assert!(text.contains("../b -> b/tsconfig.json [sin composite: true] [ciclo]"));
+        assert!(text.contains("../a -> a/tsconfig.json [sin composite: true] [ciclo]"));
+        assert!(!text.contains("Missing References"));
+    }
+
+    // --- Definiciones ---
+
+    #[test]
+    fn definitions_render_signatures_in_the_name_column() {
+        let root = PathBuf::from("/proyecto");
+        let users = root.join("src/users.ts");
+        let signed = |name: &str, kind: &str, line: usize, signature: &str| DetectedDefinition {
+            signature: signature.to_string(),
+            ..definition(&users, name, kind, line, true)
+        };
+        let definitions = vec![
+            signed("Cache", "Class", 12, "export class Cache<K, V = unknown> extends Map<K, V>"),
+            signed("fetchUser", "Function", 1, "fetchUser<T extends User = User>(id: string, retries = 3): Promise<T>"),
+            signed("formatName", "Function", 4, "formatName(user: User, separator = ' '): string"),
+            signed("LIMIT", "Variable", 9, "export const LIMIT = 3"),
+        ];
+        let text = render(&generate_definitions_section(&root, &definitions, &[], 80));
+        assert_eq!(text, concat!(
+            "## Detected Definitions & Exports\n\n\n",
+            "### `src/users.ts`\n\n",
+            "```\n\n",
+            "L1  Function fetchUser<T extends User = User>(id: string, retries = 3): Promise<T>\n",
+            "L4  Function formatName(user: User, separator = ' '): string\n",
+            "L9  Variable LIMIT  export const LIMIT = 3\n",
+            "L12 Class    Cache  export class Cache<K, V = unknown> extends Map<K, V>\n",
+            "```\n\n",
+        ));
+    }
+
+    // --- Contexto mínimo ---
+
+    fn minimal_context_tree() -> TempTree {
+        let tree = TempTree::new();
+        tree.file("src/math.ts", concat!(
+            "export function clamp(v: number, lo = 0, hi = 1): number {\n",
+            "  return Math.min(hi, Math.max(lo, v));\n",
+            "}\n",
+            "export const unused = 1;\n",
+        ));
+        tree.file("src/format.ts", concat!(
+            "import { clamp } from './math';\n",
+            "import { unused } from './math';\n",
+            "import * as path from 'path';\n",
+            "import lodash from 'lodash';\n",
+            "\n",
+            "export function formatRatio(value: number): string {\n",
+            "  const ratio = clamp(value);\n",
+            "  return `${path.sep}${ratio}`;\n",
+            "}\n",
+            "\n",
+            "export function other() { return lodash; }\n",
+        ));
+        tree
+    }
+
+    #[test]
+    fn minimal_context_collects_used_imports_and_referenced_signatures() {
+        let tree = minimal_context_tree();
+        let analysis = crate::test_support::analyze(&tree);
+        let def = analysis.definitions.iter().find(|d| d.symbol_name == "formatRatio").unwrap();
+        let packet = generate_minimal_context(&tree.root, def, &analysis.definitions, &analysis.connections).unwrap();
+        assert_eq!(packet, concat!(
+            "# Contexto mínimo: `formatRatio` (Function)\n\n",
+            "_src/format.ts:L6-L9_\n\n",
+            "## Source\n\n```ts\n",
+            "export function formatRatio(value: number): string {\n",
+            "  const ratio = clamp(value);\n",
+            "  return `${path.sep}${ratio}`;\n",
+            "}\n",
+            "```\n\n",
+            "## Imports used\n\n```\n",
+            "import { clamp } from './math';\n",
+            "import * as path from 'path';\n",
+            "```\n\n",
+            "## Referenced definitions\n\n```\n",
+            "Function clamp  clamp(v: number, lo = 0, hi = 1): number  // src/math.ts:L1\n",
+            "```\n",
+        ));
+        // Determinista: mismo resultado en cada llamada
+        assert_eq!(packet, generate_minimal_context(&tree.root, def, &analysis.definitions, &analysis.connections).unwrap());
+    }
+
+    #[test]
+    fn minimal_context_survives_stale_spans_and_broken_code() {
+        let tree = minimal_context_tree();
+        let broken = tree.file("src/broken.ts", "export function half(a: number {
+  return a / ;
+");
+        // Span que sale del archivo (p. ej. el archivo se acortó tras el análisis)
+        let mut stale = definition(&broken, "half", "Function", 1, true);
+        stale.end_line_number = 40;
+        let packet = generate_minimal_context(&tree.root, &stale, &[], &[]).unwrap();
+        assert!(packet.contains("_src/broken.ts:L1-L40_"));
+        assert!(packet.contains("  return a / ;\n```"));
+        assert!(packet.ends_with("## Referenced definitions\n\n_None._\n"));
+
+        let past_end = DetectedDefinition { line_number: 90, end_line_number: 95, ..stale.clone() };
+        assert!(generate_minimal_context(&tree.root, &past_end, &[], &[]).unwrap().contains("## Source\n\n```ts\n```"));
+        let missing = definition(&tree.path("src/gone.ts"), "gone", "Function", 1, true);
+        assert!(generate_minimal_context(&tree.root, &missing, &[], &[]).unwrap_err().starts_with("No se pudo leer"));
+    }
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::analysis::{DetectedDefinition, ResolvedConnection, TodoComment};
+
+// --- Claves de búsqueda precalculadas (minúsculas, rutas relativas a la raíz) ---
+// Se construyen una vez por análisis para que cada pulsación en los filtros solo haga
+// comparaciones de subcadenas, sin to_lowercase ni strip_prefix repetidos.
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionKeys {
+    pub source: String,
+    pub import: String,
+    pub target: Option<String>,
+    pub class: &'static str, // `missing`, `npm`, `builtin`, ... (ImportClass::label)
+    pub kind: &'static str,  // `re-export`, `side-effect`, `dynamic`, ... (CapturePattern::label)
+    pub symbols: String,     // Símbolos importados separados por comas
+    pub type_only: bool,     // `import type`: el filtro `type` lo encuentra, como la etiqueta [type]
+}
+
+impl ConnectionKeys {
+    pub fn matches(&self, filter_lower: &str) -> bool {
+        self.source.contains(filter_lower)
+            || self.import.contains(filter_lower)
+            || self.matches_target(filter_lower)
+            || self.class.contains(filter_lower)
+            || self.kind.contains(filter_lower)
+            || self.symbols.contains(filter_lower)
+            || (self.type_only && "type".contains(filter_lower))
+    }
+
+    pub fn matches_target(&self, filter_lower: &str) -> bool {
+        self.target.as_ref().is_some_and(|t| t.contains(filter_lower))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DefinitionKeys {
+    pub source: String,
+    pub symbol: String,
+    pub kind: String,
+    pub signature: String,
+}
+
+impl DefinitionKeys {
+    pub fn matches(&self, filter_lower: &str) -> bool {
+        self.source.contains(filter_lower)
+            || self.symbol.contains(filter_lower)
+            || self.kind.contains(filter_lower)
+            || self.signature.contains(filter_lower)
+    }
+}
+
+// Vectores paralelos a files / connections / definitions / todos de ProjectAnalysis
+#[derive(Clone, Debug, Default)]
+pub struct SearchKeys {
+    pub files: Vec<String>,
+    pub connections: Vec<ConnectionKeys>,
+    pub definitions: Vec<DefinitionKeys>,
+    pub todos: Vec<String>, // `ruta marcador texto`
+}
+
+// Misma normalización que usan los generadores para mostrar rutas
+pub fn relative_key(root_path: &Path, path: &Path) -> String {
+    path.strip_prefix(root_path).unwrap_or(path).to_string_lossy().to_lowercase()
+}
+
+fn connection_keys(root_path: &Path, connections: &[ResolvedConnection]) -> Vec<ConnectionKeys> {
+    connections
+        .par_iter()
+        .map(|conn| ConnectionKeys {
+            source: relative_key(root_path, &conn.source_file),
+            import: conn.imported_string.to_lowercase(),
+            target: conn.resolved_target.as_ref().map(|t| relative_key(root_path, t)),
+            class: conn.import_class.label(),
+            kind: conn.kind.label(),
+            symbols: conn.imported_symbols.join(", ").to_lowercase(),
+            type_only: conn.type_only,
+        })
+        .collect()
+}
+
+fn definition_keys(root_path: &Path, definitions: &[DetectedDefinition]) -> Vec<DefinitionKeys> {
+    definitions
+        .par_iter()
+        .map(|def| DefinitionKeys {
+            source: relative_key(root_path, &def.source_file),
+            symbol: def.symbol_name.to_lowercase(),
+            kind: def.kind.to_lowercase(),
+            signature: def.signature.to_lowercase(),
+        })
+        .collect()
+}
+
+impl SearchKeys {
+    pub fn build(
+        root_path: &Path,
+        files: &[std::path::PathBuf],
+        connections: &[ResolvedConnection],
+        definitions: &[DetectedDefinition],
+        todos: &[TodoComment],
+    ) -> Self {
+        let files = files.par_iter().map(|f| relative_key(root_path, f)).collect();
+        let todos = todos
+            .par_iter()
+            .map(|todo| format!("{} {} {}", relative_key(root_path, &todo.source_file), todo.marker, todo.text).to_lowercase())
+            .collect();
+        Self {
+            files,
+            connections: connection_keys(root_path, connections),
+            definitions: definition_keys(root_path, definitions),
+            todos,
+        }
+    }
+
+    /// Añade las claves de conexiones y definiciones nuevas (lotes parciales del escaneo),
+    /// sin recalcular las que ya había.
+    pub fn extend(&mut self, root_path: &Path, connections: &[ResolvedConnection], definitions: &[DetectedDefinition]) {
+        self.connections.extend(connection_keys(root_path, connections));
+        self.definitions.extend(definition_keys(root_path, definitions));
+    }
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::analysis::{self, AnalysisOptions, CapturePattern, DetectedDefinition, ImportClass, ProjectAnalysis, ResolvedConnection, ScanOptions, ScanProgress};
+use crate::cache::CacheMode;
+
+// --- Utilidades compartidas por los tests (árboles de fixtures y datos de análisis) ---
+
+/// Directorio temporal único; se borra al salir del test.
+pub struct TempTree {
+    pub root: PathBuf,
+}
+
+impl TempTree {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "context-lens-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+
+    /// Crea `relative` (y sus carpetas) con `content`; devuelve la ruta absoluta.
+    pub fn file(&self, relative: &str, content: &str) -> PathBuf {
+        let path = self.root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+}
+
+impl Drop for TempTree {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.root).ok();
+    }
+}
+
+/// Análisis completo del árbol, sin caché; espera al resultado final.
+pub fn analyze(tree: &TempTree) -> ProjectAnalysis {
+    let progress = analysis::start_analysis(tree.root.clone(), AnalysisOptions::default(), ScanOptions::default(), CacheMode::Disabled, || {});
+    progress.iter()
+        .find_map(|message| match message {
+            ScanProgress::Finished(result) => Some(result.unwrap()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+/// Import local `source -> target` resuelto, tal como lo deja el análisis.
+pub fn connection(source: &Path, target: &Path) -> ResolvedConnection {
+    ResolvedConnection {
+        source_file: source.to_path_buf(),
+        imported_string: format!("./{}", target.file_stem().unwrap().to_string_lossy()),
+        line_number: 1,
+        resolved_target: Some(target.to_path_buf()),
+        outside_target: None,
+        ambient_module: None,
+        dynamic_pattern: false,
+        pattern_matches: Vec::new(),
+        import_attribute: None,
+        glob: None,
+        barrel_targets: Vec::new(),
+        import_class: ImportClass::Local,
+        kind: CapturePattern::Import,
+        imported_symbols: Vec::new(),
+        type_only: false,
+        occurrences: 1,
+    }
+}
+
+/// Definición de una línea; `exported` rellena `exported_as` con su propio nombre.
+pub fn definition(file: &Path, name: &str, kind: &str, line: usize, exported: bool) -> DetectedDefinition {
+    DetectedDefinition {
+        source_file: file.to_path_buf(),
+        symbol_name: name.to_string(),
+        kind: kind.to_string(),
+        line_number: line,
+        end_line_number: line,
+        signature: format!("{}()", name),
+        exported_as: exported.then(|| name.to_string()),
+        parent: None,
+        doc_summary: None,
+        decorators: Vec::new(),
+        handler: None,
+    }
+}
+
+/// Monorepo Turborepo + Nx con dos paquetes: `@acme/web` depende de `@acme/ui`.
+pub fn two_package_monorepo() -> TempTree {
+    let tree = TempTree::new();
+    tree.file("package.json", r#"{ "name": "acme", "private": true }"#);
+    tree.file("turbo.json", r#"{
+        // Turborepo 2
+        "tasks": {
+            "build": { "dependsOn": ["^build"] },
+            "test": { "dependsOn": ["build", "@acme/ui#lint"] },
+            "deploy": { "dependsOn": ["@acme/missing#build"] },
+        }
+    }"#);
+    tree.file("nx.json", r#"{ "targetDefaults": { "e2e": { "dependsOn": ["^build"] } } }"#);
+    tree.file("packages/ui/package.json", r#"{ "name": "@acme/ui", "scripts": { "build": "tsc", "lint": "eslint ." } }"#);
+    tree.file("packages/web/package.json", r#"{
+        "name": "@acme/web",
+        "scripts": { "build": "vite build", "test": "vitest" },
+        "dependencies": { "@acme/ui": "workspace:*", "react": "^18.0.0" }
+    }"#);
+    tree.file("packages/web/project.json", r#"{ "targets": { "e2e": {} } }"#);
+    tree.file("packages/ui/src/Button.ts", "export const Button = 1;\n");
+    tree.file("packages/web/src/main.ts", "import { Button } from '@acme/ui';\n");
+    tree
+}
+
+/// Árbol con `require.context` e `import.meta.glob`, con coincidencias anidadas
+pub fn glob_import_tree() -> TempTree {
+    let tree = TempTree::new();
+    for file in [
+        "src/modules/a.js", "src/modules/nested/b.js", "src/modules/nested/deep/c.js", "src/modules/d.ts", "src/modules/E.JS",
+        "src/pages/index.tsx", "src/pages/blog/[slug].tsx", "src/pages/blog/skip.tsx", "src/pages/about.vue", "src/pages/notes.md",
+    ] {
+        tree.file(file, "export default 1;\n");
+    }
+    tree.file("src/main.js", concat!(
+        "const all = require.context('./modules', true, /\\.js$/i);\n",
+        "const flat = require.context('./modules', false, /\\.js$/);\n",
+        "const pages = import.meta.glob('./pages/**/*.tsx');\n",
+        "const views = import.meta.glob(['./pages/**/*.{tsx,vue}', '!./pages/blog/skip.tsx'], { eager: true });\n",
+    ));
+    tree
+}
+
+/// `src/main.ts` con imports en líneas conocidas (ver el comentario de cada uno)
+pub fn import_lines_tree() -> TempTree {
+    let tree = TempTree::new();
+    for file in ["src/a.ts", "src/b.ts", "src/c.ts", "src/d.ts", "src/e.ts", "src/f.ts"] {
+        tree.file(file, "export const x = 1;\n");
+    }
+    tree.file("src/main.ts", concat!(
+        "// Cabecera\n",
+        "\n",
+        "import { x } from './a'; // L3\n",
+        "import {\n",
+        "  x as y,\n",
+        "} from './b'; // L4: línea de la sentencia, no del especificador\n",
+        "/* import { z } from './f'; */\n",
+        "export * from './c'; // L8\n",
+        "function load() {\n",
+        "  return import('./d'); // L10\n",
+        "}\n",
+        "const e = require('./e'); import './f'; // L12 los dos\n",
+    ));
+    tree
+}
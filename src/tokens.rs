@@ -0,0 +1,25 @@
+// --- Estimación de tokens ---
+// La interfaz solo cuenta tokens de un texto; la heurística actual (~4 caracteres
+// por token, cercana a cl100k en código y texto en inglés) puede sustituirse por un
+// tokenizador BPE real implementando `TokenEstimator`, sin tocar la UI ni la salida.
+
+const CHARS_PER_TOKEN: usize = 4;
+
+pub trait TokenEstimator {
+    fn estimate(&self, text: &str) -> usize;
+}
+
+/// Estimación por longitud: no tokeniza, solo divide los caracteres entre 4.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CharHeuristic;
+
+impl TokenEstimator for CharHeuristic {
+    fn estimate(&self, text: &str) -> usize {
+        estimate_tokens(text.chars().count())
+    }
+}
+
+// Para cuando ya se conoce el número de caracteres (p. ej. el resumen de copia)
+pub fn estimate_tokens(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
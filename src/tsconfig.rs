@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use path_clean::PathClean;
+use serde_json::Value;
+
+// --- Configuración de proyectos TypeScript (tsconfig*.json) ---
+
+#[derive(Clone, Debug)]
+pub struct TsProjectReference {
+    pub raw_path: String,                  // Valor literal de `references[].path`
+    pub resolved_config: Option<PathBuf>,  // tsconfig representativo del destino, si existe
+}
+
+#[derive(Clone, Debug)]
+pub struct TsProject {
+    pub config_path: PathBuf,
+    pub composite: bool,
+    pub references: Vec<TsProjectReference>,
+}
+
+impl TsProject {
+    /// Directorio que contiene el tsconfig (raíz del paquete/proyecto).
+    pub fn project_dir(&self) -> &Path {
+        self.config_path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+pub fn is_tsconfig_file(path: &Path) -> bool {
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name == "tsconfig.json" || (name.starts_with("tsconfig.") && name.ends_with(".json")),
+        None => false,
+    }
+}
+
+// tsconfig admite comentarios y comas finales (JSONC), que serde_json rechaza.
+// Se eliminan respetando el contenido de las cadenas.
+pub fn strip_jsonc(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::with_capacity(input.len());
+    let mut i = 0;
+    let mut in_string = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            output.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                output.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                output.push(c);
+                i += 1;
+            }
+            '/' if i + 1 < chars.len() && chars[i + 1] == '/' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if i + 1 < chars.len() && chars[i + 1] == '*' => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+            }
+            ',' => {
+                // Coma final: el siguiente carácter significativo cierra el objeto/array
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                if j < chars.len() && (chars[j] == '}' || chars[j] == ']') {
+                    i += 1;
+                } else {
+                    output.push(c);
+                    i += 1;
+                }
+            }
+            _ => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+pub fn read_jsonc_file(path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&strip_jsonc(&content)).ok()
+}
+
+// Resuelve `references[].path`: puede apuntar a un directorio (se usa su tsconfig.json)
+// o directamente a un archivo tsconfig.
+fn resolve_reference(config_dir: &Path, raw_path: &str, project_files: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let candidate = config_dir.join(raw_path).clean();
+    if raw_path.ends_with(".json") {
+        return project_files.contains(&candidate).then_some(candidate);
+    }
+    let dir_config = candidate.join("tsconfig.json").clean();
+    if project_files.contains(&dir_config) {
+        return Some(dir_config);
+    }
+    None
+}
+
+fn parse_ts_project(config_path: &Path, project_files: &HashSet<PathBuf>) -> Option<TsProject> {
+    let json = read_jsonc_file(config_path)?;
+    let config_dir = config_path.parent()?;
+
+    let composite = json
+        .get("compilerOptions")
+        .and_then(|opts| opts.get("composite"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let references = json
+        .get("references")
+        .and_then(Value::as_array)
+        .map(|refs| {
+            refs.iter()
+                .filter_map(|r| r.get("path").and_then(Value::as_str))
+                .map(|raw| TsProjectReference {
+                    raw_path: raw.to_string(),
+                    resolved_config: resolve_reference(config_dir, raw, project_files),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(TsProject { config_path: config_path.to_path_buf(), composite, references })
+}
+
+/// Localiza y parsea todos los tsconfig*.json del proyecto (monorepos incluidos).
+pub fn discover_ts_projects(project_files: &HashSet<PathBuf>) -> Vec<TsProject> {
+    let mut projects: Vec<TsProject> = project_files
+        .iter()
+        .filter(|p| is_tsconfig_file(p))
+        .filter_map(|p| parse_ts_project(p, project_files))
+        .collect();
+    projects.sort_by(|a, b| a.config_path.cmp(&b.config_path));
+    projects
+}
+
+/// Devuelve el proyecto (tsconfig.json) más profundo que contiene a `file`.
+pub fn owning_project<'a>(file: &Path, projects: &'a [TsProject]) -> Option<&'a TsProject> {
+    projects
+        .iter()
+        .filter(|p| p.config_path.file_name().and_then(|n| n.to_str()) == Some("tsconfig.json"))
+        .filter(|p| file.starts_with(p.project_dir()))
+        .max_by_key(|p| p.project_dir().components().count())
+}
+
+// --- Alias de módulos: `compilerOptions.baseUrl` y `compilerOptions.paths` ---
+
+#[derive(Clone, Debug)]
+pub struct PathMapping {
+    pub pattern: String,      // `@/*`, `~lib`, ... (como mucho un `*`)
+    pub targets: Vec<String>, // Alternativas en orden (`["./src/*", "./generated/*"]`)
+}
+
+#[derive(Clone, Debug)]
+pub struct PathAliases {
+    pub config_path: PathBuf,
+    pub base_url: Option<PathBuf>, // Absoluto, ya resuelto contra el tsconfig que lo define
+    pub paths_base: PathBuf,       // Base de los destinos de `paths`: baseUrl o la carpeta de su tsconfig
+    pub paths: Vec<PathMapping>,
+}
+
+// Máximo de saltos en cadenas `extends` (cortar ciclos y configuraciones absurdas)
+const MAX_EXTENDS_DEPTH: usize = 16;
+
+// `extends` relativo (`./tsconfig.base.json`, `../tsconfig`) o de un paquete
+// (`@tsconfig/next/tsconfig.json`, buscado en los node_modules de los ancestros)
+fn resolve_extends(config_dir: &Path, raw: &str) -> Option<PathBuf> {
+    let with_json = |path: PathBuf| if path.extension().is_some_and(|e| e == "json") { path } else { path.with_extension("json") };
+    if raw.starts_with('.') || Path::new(raw).is_absolute() {
+        let candidate = with_json(config_dir.join(raw).clean());
+        return candidate.is_file().then_some(candidate);
+    }
+    config_dir.ancestors().find_map(|dir| {
+        let package = dir.join("node_modules").join(raw);
+        [package.join("tsconfig.json"), with_json(package.clone()), package]
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+// Recorre la cadena `extends` (del tsconfig más cercano hacia arriba) quedándose con el primer
+// valor de cada opción, como hace tsc. Devuelve (baseUrl, paths, carpeta del tsconfig con paths).
+fn effective_compiler_paths(config_path: &Path) -> (Option<PathBuf>, Option<(Value, PathBuf)>) {
+    let mut base_url: Option<PathBuf> = None;
+    let mut paths: Option<(Value, PathBuf)> = None;
+    let mut pending = vec![(config_path.to_path_buf(), 0)];
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+
+    while let Some((current, depth)) = pending.pop() {
+        if depth > MAX_EXTENDS_DEPTH || !visited.insert(current.clone()) {
+            continue;
+        }
+        let Some(json) = read_jsonc_file(&current) else { continue };
+        let Some(config_dir) = current.parent() else { continue };
+        let options = json.get("compilerOptions");
+        if base_url.is_none() {
+            base_url = options
+                .and_then(|o| o.get("baseUrl"))
+                .and_then(Value::as_str)
+                .map(|raw| config_dir.join(raw).clean());
+        }
+        if paths.is_none() {
+            paths = options
+                .and_then(|o| o.get("paths"))
+                .filter(|p| p.is_object())
+                .map(|p| (p.clone(), config_dir.to_path_buf()));
+        }
+        // TS 5: `extends` puede ser una lista; las últimas entradas tienen prioridad
+        let parents: Vec<&str> = match json.get("extends") {
+            Some(Value::String(raw)) => vec![raw.as_str()],
+            Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        for raw in parents {
+            if let Some(parent) = resolve_extends(config_dir, raw) {
+                pending.push((parent, depth + 1));
+            }
+        }
+    }
+    (base_url, paths)
+}
+
+fn parse_path_aliases(config_path: &Path) -> Option<PathAliases> {
+    let (base_url, paths) = effective_compiler_paths(config_path);
+    if base_url.is_none() && paths.is_none() {
+        return None;
+    }
+    let (paths_value, paths_dir) = paths.unzip();
+    let paths = paths_value
+        .as_ref()
+        .and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .map(|(pattern, targets)| PathMapping {
+                    pattern: pattern.clone(),
+                    targets: targets
+                        .as_array()
+                        .map(|items| items.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                        .unwrap_or_default(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let paths_base = base_url.clone().or(paths_dir).unwrap_or_else(|| config_path.parent().unwrap_or(Path::new("")).to_path_buf());
+    Some(PathAliases { config_path: config_path.to_path_buf(), base_url, paths_base, paths })
+}
+
+// jsconfig.json (CRA, Vite en JS) admite las mismas opciones; tsconfig.json gana si conviven
+fn is_tsconfig_json(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()) == Some("tsconfig.json")
+}
+
+fn is_jsconfig_json(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()) == Some("jsconfig.json")
+}
+
+/// `baseUrl`/`paths` efectivos (con `extends`) de cada tsconfig.json/jsconfig.json del proyecto
+/// que los tenga.
+pub fn discover_path_aliases(project_files: &HashSet<PathBuf>) -> Vec<PathAliases> {
+    let mut aliases: Vec<PathAliases> = project_files
+        .iter()
+        .filter(|p| is_tsconfig_json(p) || is_jsconfig_json(p))
+        .filter_map(|p| parse_path_aliases(p))
+        .collect();
+    aliases.sort_by(|a, b| a.config_path.cmp(&b.config_path));
+    aliases
+}
+
+/// Configuración que aplica a `file`: la del tsconfig/jsconfig más profundo que lo contiene.
+pub fn aliases_for_file<'a>(file: &Path, aliases: &'a [PathAliases]) -> Option<&'a PathAliases> {
+    aliases
+        .iter()
+        .filter(|a| a.config_path.parent().is_some_and(|dir| file.starts_with(dir)))
+        .max_by_key(|a| (a.config_path.components().count(), is_tsconfig_json(&a.config_path)))
+}
+
+impl PathAliases {
+    /// Rutas base candidatas para un especificador "bare", en el orden en que tsc las prueba:
+    /// el patrón de `paths` que coincide (exacto, o el de prefijo más largo) con cada uno de sus
+    /// destinos y, por último, `baseUrl`. Sin extensión: quien llama prueba extensiones e índices.
+    pub fn candidates(&self, import_str: &str) -> Vec<PathBuf> {
+        let exact = self.paths.iter().find(|m| m.pattern == import_str).map(|m| (m, ""));
+        let wildcard = || {
+            self.paths
+                .iter()
+                .filter_map(|m| {
+                    let (prefix, suffix) = m.pattern.split_once('*')?;
+                    let matched = import_str.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                    Some((m, matched, prefix.len()))
+                })
+                .max_by_key(|(_, _, prefix_len)| *prefix_len)
+                .map(|(m, matched, _)| (m, matched))
+        };
+
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        if let Some((mapping, matched)) = exact.or_else(wildcard) {
+            for target in &mapping.targets {
+                let substituted = target.replacen('*', matched, 1);
+                candidates.push(self.paths_base.join(&substituted).clean());
+            }
+        }
+        // `baseUrl` (`"baseUrl": "src"` -> `components/Button`): nunca para paquetes con scope,
+        // que casi siempre son npm; y quien llama solo acepta archivos que existen
+        if let Some(base_url) = &self.base_url
+            && !import_str.starts_with('@')
+        {
+            candidates.push(base_url.join(import_str).clean());
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempTree;
+
+    #[test]
+    fn strip_jsonc_removes_comments_and_trailing_commas() {
+        let input = r#"{
+            // Comentario de línea
+            "compilerOptions": { /* en bloque */ "composite": true, },
+            "include": ["src/**/*", "http://x.dev/*/y", ],
+            "note": "// no es comentario, /* tampoco */ \" , }",
+        }"#;
+        let json: Value = serde_json::from_str(&strip_jsonc(input)).unwrap();
+        assert_eq!(json["compilerOptions"]["composite"], true);
+        assert_eq!(json["include"][1], "http://x.dev/*/y");
+        assert_eq!(json["note"], "// no es comentario, /* tampoco */ \" , }");
+    }
+
+    // Dos paquetes: `app` referencia a `core` (composite); `legacy` no tiene referencias
+    fn two_package_tree() -> (TempTree, HashSet<PathBuf>) {
+        let tree = TempTree::new();
+        let files = [
+            tree.file("packages/core/tsconfig.json", "{\n  // núcleo\n  \"compilerOptions\": { \"composite\": true, },\n}\n"),
+            tree.file("packages/app/tsconfig.json", "{ \"references\": [{ \"path\": \"../core\" }, { \"path\": \"../missing\" },], }"),
+            tree.file("packages/app/tsconfig.build.json", "{ \"references\": [{ \"path\": \"../core/tsconfig.json\" }] }"),
+            tree.file("packages/legacy/tsconfig.json", "{}"),
+            tree.file("packages/app/src/main.ts", ""),
+        ];
+        (tree, files.into_iter().collect())
+    }
+
+    #[test]
+    fn discovers_projects_and_resolves_references() {
+        let (tree, files) = two_package_tree();
+        let projects = discover_ts_projects(&files);
+        let configs: Vec<&Path> = projects.iter().map(|p| p.config_path.as_path()).collect();
+        assert_eq!(configs, [
+            tree.path("packages/app/tsconfig.build.json"),
+            tree.path("packages/app/tsconfig.json"),
+            tree.path("packages/core/tsconfig.json"),
+            tree.path("packages/legacy/tsconfig.json"),
+        ]);
+
+        let core_config = tree.path("packages/core/tsconfig.json");
+        let app = &projects[1];
+        assert!(!app.composite);
+        assert!(projects[2].composite);
+        let references: Vec<(&str, Option<&Path>)> = app.references.iter()
+            .map(|r| (r.raw_path.as_str(), r.resolved_config.as_deref()))
+            .collect();
+        assert_eq!(references, [("../core", Some(core_config.as_path())), ("../missing", None)]);
+        // Una referencia directa a un archivo tsconfig también se resuelve
+        assert_eq!(projects[0].references[0].resolved_config.as_deref(), Some(core_config.as_path()));
+    }
+
+    #[test]
+    fn owning_project_is_the_deepest_tsconfig_json() {
+        let (tree, mut files) = two_package_tree();
+        files.insert(tree.file("tsconfig.json", "{}"));
+        let projects = discover_ts_projects(&files);
+        let owner = |relative: &str| owning_project(&tree.path(relative), &projects).map(|p| p.config_path.clone());
+        // tsconfig.build.json no cuenta como dueño de los archivos de `app`
+        assert_eq!(owner("packages/app/src/main.ts"), Some(tree.path("packages/app/tsconfig.json")));
+        assert_eq!(owner("packages/core/index.ts"), Some(tree.path("packages/core/tsconfig.json")));
+        assert_eq!(owner("scripts/build.ts"), Some(tree.path("tsconfig.json")));
+    }
+}
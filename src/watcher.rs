@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::analysis::{self, ScanOptions};
+
+// --- Vigilancia de la carpeta analizada ---
+// Tras un análisis correcto se vigila la raíz y solo se avisa de cambios en rutas que el escáner
+// habría recorrido: nada de node_modules, .git, patrones excluidos ni lo que ignoran las reglas
+// de git, para que instalar paquetes o compilar no marque el análisis como desactualizado.
+
+pub struct ProjectWatcher {
+    _watcher: RecommendedWatcher, // Deja de vigilar al soltarse
+    changes: Receiver<PathBuf>,
+}
+
+impl ProjectWatcher {
+    /// Rutas cambiadas (creadas, modificadas o borradas) desde la última llamada.
+    pub fn drain_changes(&self) -> Vec<PathBuf> {
+        self.changes.try_iter().collect()
+    }
+}
+
+// Reglas de ignorado de una carpeta
+struct DirRules {
+    ignore: Gitignore,    // .ignore (también fuera de un repositorio)
+    gitignore: Gitignore, // .gitignore
+    exclude: Gitignore,   // .git/info/exclude, si la carpeta es la raíz de un repositorio
+    has_git: bool,
+}
+
+fn load_ignore_file(dir: &Path, file: PathBuf) -> Gitignore {
+    if !file.is_file() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    builder.add(file);
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+// Las mismas reglas que aplica el WalkBuilder del escáner (`analysis::project_files`), que no
+// expone su matcher: .gitignore/.ignore de cada carpeta y de las superiores, .git/info/exclude y
+// el gitignore global. Como en git, la carpeta más profunda manda, `.ignore` gana a `.gitignore`
+// y las reglas de git solo valen dentro de un repositorio (y no por encima de su raíz).
+struct IgnoreRules {
+    root: PathBuf,
+    dirs: HashMap<PathBuf, DirRules>, // Se cargan al consultarlas; `forget` las invalida
+    global: Gitignore,
+}
+
+impl IgnoreRules {
+    fn new(root: &Path) -> Self {
+        Self { root: root.to_path_buf(), dirs: HashMap::new(), global: Gitignore::global().0 }
+    }
+
+    fn load_dir_rules(&mut self, dir: &Path) {
+        self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+            let git_dir = dir.join(".git");
+            DirRules {
+                ignore: load_ignore_file(dir, dir.join(".ignore")),
+                gitignore: load_ignore_file(dir, dir.join(".gitignore")),
+                exclude: load_ignore_file(dir, git_dir.join("info").join("exclude")),
+                has_git: git_dir.exists(),
+            }
+        });
+    }
+
+    // Un cambio en un archivo de reglas invalida las de su carpeta
+    fn forget(&mut self, changed: &Path) {
+        let name = changed.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if matches!(name, ".gitignore" | ".ignore") {
+            if let Some(dir) = changed.parent() {
+                self.dirs.remove(dir);
+            }
+        } else if changed.ends_with(".git/info/exclude") {
+            if let Some(dir) = changed.ancestors().nth(3) {
+                self.dirs.remove(dir);
+            }
+        }
+    }
+
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+        for dir in &dirs {
+            self.load_dir_rules(dir);
+        }
+        let any_git = dirs.iter().any(|dir| self.dirs[*dir].has_git);
+        // Some(true) = ignorado, Some(false) = re-incluido con `!`; la primera coincidencia manda
+        let decided = |m: Match<_>| if m.is_none() { None } else { Some(m.is_ignore()) };
+        let (mut ignore, mut gitignore, mut exclude) = (None, None, None);
+        let mut saw_git = false;
+        for dir in &dirs {
+            let rules = &self.dirs[*dir];
+            ignore = ignore.or_else(|| decided(rules.ignore.matched(path, is_dir)));
+            if any_git && !saw_git {
+                gitignore = gitignore.or_else(|| decided(rules.gitignore.matched(path, is_dir)));
+                exclude = exclude.or_else(|| decided(rules.exclude.matched(path, is_dir)));
+            }
+            saw_git |= rules.has_git;
+        }
+        let global = if any_git { decided(self.global.matched(path, is_dir)) } else { None };
+        ignore.or(gitignore).or(exclude).or(global).unwrap_or(false)
+    }
+
+    // El escáner poda las carpetas ignoradas: se comprueba cada tramo de la ruta bajo la raíz
+    fn is_visible(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else { return false };
+        let mut current = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component);
+            let is_dir = components.peek().is_some() || current.is_dir();
+            if self.is_ignored(&current, is_dir) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Vigila `root_path` recursivamente; `on_change` se llama (desde el hilo del watcher) cada vez
+/// que llega algún cambio relevante, p. ej. para repintar la interfaz.
+pub fn watch_project(
+    root_path: &Path,
+    respect_gitignore: bool,
+    scan: ScanOptions,
+    on_change: impl Fn() + Send + 'static,
+) -> Result<ProjectWatcher, String> {
+    let root = root_path.to_path_buf();
+    let mut rules = respect_gitignore.then(|| IgnoreRules::new(root_path));
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        let mut relevant = false;
+        for path in event.paths {
+            if let Some(rules) = rules.as_mut() {
+                rules.forget(&path);
+            }
+            let visible = analysis::scanner_would_visit(&root, &path, &scan)
+                && rules.as_mut().is_none_or(|rules| rules.is_visible(&path));
+            if visible && tx.send(path).is_ok() {
+                relevant = true;
+            }
+        }
+        if relevant {
+            on_change();
+        }
+    })
+    .map_err(|e| format!("No se pudo vigilar {}: {}", root_path.display(), e))?;
+    watcher
+        .watch(root_path, RecursiveMode::Recursive)
+        .map_err(|e| format!("No se pudo vigilar {}: {}", root_path.display(), e))?;
+    Ok(ProjectWatcher { _watcher: watcher, changes: rx })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use walkdir::WalkDir;
+
+    use super::*;
+    use crate::test_support::TempTree;
+
+    fn nested_ignore_tree(with_git: bool) -> TempTree {
+        let tree = TempTree::new();
+        if with_git {
+            tree.file(".git/info/exclude", "secret.ts\n");
+        }
+        tree.file(".gitignore", "*.log\n");
+        tree.file("packages/web/.gitignore", "dist/\n!keep.log\n");
+        tree.file("packages/web/.ignore", "generated.ts\n");
+        for file in [
+            "src/main.ts", "debug.log", "secret.ts",
+            "packages/web/src/app.ts", "packages/web/dist/out.js", "packages/web/dist/nested/chunk.js",
+            "packages/web/keep.log", "packages/web/debug.log", "packages/web/generated.ts",
+        ] {
+            tree.file(file, "");
+        }
+        tree
+    }
+
+    // Cada archivo del árbol es visible para el watcher si y solo si el escáner lo recorre
+    fn assert_matches_scanner(tree: &TempTree) {
+        let scanned: HashSet<PathBuf> = analysis::project_files(&tree.root, true, &ScanOptions::default()).into_iter().collect();
+        let mut rules = IgnoreRules::new(&tree.root);
+        let paths = WalkDir::new(&tree.root).min_depth(1).into_iter().filter_map(|e| e.ok()).map(|e| e.into_path());
+        for path in paths.filter(|p| p.is_file() && analysis::scanner_would_visit(&tree.root, p, &ScanOptions::default())) {
+            assert_eq!(rules.is_visible(&path), scanned.contains(&path), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn nested_and_exclude_rules_match_the_scanner() {
+        let tree = nested_ignore_tree(true);
+        assert_matches_scanner(&tree);
+        let mut rules = IgnoreRules::new(&tree.root);
+        assert!(!rules.is_visible(&tree.path("packages/web/dist/out.js")));
+        assert!(!rules.is_visible(&tree.path("secret.ts")));
+        assert!(rules.is_visible(&tree.path("packages/web/keep.log")));
+    }
+
+    #[test]
+    fn git_rules_only_apply_inside_a_repository() {
+        let tree = nested_ignore_tree(false);
+        assert_matches_scanner(&tree);
+        let mut rules = IgnoreRules::new(&tree.root);
+        assert!(rules.is_visible(&tree.path("packages/web/dist/out.js")));
+        // `.ignore` vale también sin git
+        assert!(!rules.is_visible(&tree.path("packages/web/generated.ts")));
+    }
+
+    #[test]
+    fn editing_an_ignore_file_reloads_its_rules() {
+        let tree = nested_ignore_tree(true);
+        let mut rules = IgnoreRules::new(&tree.root);
+        let out = tree.path("packages/web/dist/out.js");
+        assert!(!rules.is_visible(&out));
+        let gitignore = tree.file("packages/web/.gitignore", "");
+        rules.forget(&gitignore);
+        assert!(rules.is_visible(&out));
+    }
+}